@@ -0,0 +1,167 @@
+## Navigation
+
+site-title = Nomen Explorer
+nav-home = Home
+nav-explorer = Explorer
+nav-new-name = New Name
+nav-update-records = Update Records
+nav-faqs = FAQs
+nav-fees = Fees
+nav-activity = Activity
+
+## Index
+
+index-intro = Nomen is an open protocol for global names, like a decentralized DNS, built with Bitcoin and Nostr. The goals of the Nomen protocol are decentralization and self-sovereignty.
+index-indexer = Nomen Explorer is an indexer. It catalogues the Bitcoin blockchain and associated Nomen events on Nostr.
+index-more-info = More Information
+index-more-info-body =
+    Check out some more information below, starting with the FAQ if you want details. Check out the Explorer if you want to see
+    what names already exist out there.
+index-specification = Specification
+index-howto = How to get a name
+
+## Explorer
+
+explorer-title = Explorer
+explorer-last-indexed = Last indexed at { $time }.
+explorer-never-indexed = Last indexed: never.
+explorer-search = Search...
+explorer-select = Select a name to see its current record set.
+explorer-no-names = No names found!
+
+## Fees
+
+fees-title = Name Claim Fees
+fees-intro = Fee rates paid by name claim transactions, so you can pick a rate that confirms quickly.
+fees-window = Claims confirmed in the last { $days } days.
+fees-none = No name claims with known fees in this period.
+fees-percentile = Percentile
+fees-rate = Fee rate (sat/vB)
+fees-chart = Fee rates over time
+fees-chart-legend = Shaded bands show the 10th to 90th and 25th to 75th percentiles, and the line the median. The top of the chart is { $max } sat/vB.
+fees-period = Period starting
+fees-claims = Claims
+
+## Activity
+
+activity-title = Recent activity
+activity-none = Nothing has been indexed yet.
+activity-when = When
+activity-event = Event
+activity-name = Name
+activity-owner = Owner
+activity-registration = Registered
+activity-transfer = Transferred
+activity-records = Records updated
+time-just-now = just now
+time-minutes-ago = { $count ->
+    [one] 1 minute ago
+   *[other] { $count } minutes ago
+}
+time-hours-ago = { $count ->
+    [one] 1 hour ago
+   *[other] { $count } hours ago
+}
+time-days-ago = { $count ->
+    [one] 1 day ago
+   *[other] { $count } days ago
+}
+
+## Name page
+
+nsid-blockchain-info = Blockchain Info
+nsid-blockhash = Blockhash
+nsid-block-height = Block Height
+nsid-confirmations = Confirmations
+nsid-confirmed = confirmed
+nsid-unconfirmed = not yet confirmed, treat with caution
+nsid-txid = Txid
+nsid-vout = Vout
+nsid-tx-position = Position in Block
+nsid-tx-size = Tx Size
+nsid-tx-fee = Tx Fee
+nsid-blocktime = Blocktime
+nsid-nsid = NSID
+nsid-owner = Owner (pubkey)
+nsid-records = Records
+nsid-update-records = Update Records
+nsid-no-records = No records found.
+nsid-last-updated = Last updated at { $time }.
+nsid-sources = Records event seen on { $relays }.
+nsid-record-type = Record Type
+nsid-value = Value
+nsid-children = Names below
+nsid-unapproved = not approved by the owner of the name above it
+nsid-deactivated = deactivated by its owner; its records are not served
+nsid-similar = This name is easily mistaken for:
+nsid-raw = Raw JSON
+nsid-copy = Copy
+nsid-copied = Copied
+nsid-download-event = Download the signed records event
+nsid-meta-description = A Nomen name, registered on Bitcoin at block { $height }.
+pagination-prev = Previous
+pagination-next = Next
+pagination-page = Page
+
+## Redirect
+
+redirect-title = { $name }
+redirect-notice = This name links to the address below. Nomen Explorer does not check the sites names link to, so only continue if you trust it.
+
+## Owner page
+
+owner-title = Names by owner
+owner-no-names = This key does not own any names.
+owner-name = Name
+owner-status = Status
+owner-records-updated = Records updated
+owner-confirmed = Confirmed
+owner-unconfirmed = Not yet confirmed
+
+## Errors
+
+error-title = Error
+error-message = Error: { $message }
+error-request-id = Request ID: { $id }
+
+## FAQs
+
+faq-title = FAQs
+faq-what = What is Nomen?
+faq-what-body =
+    Nomen is an open-protocol that aims to solve the "ICANN problem" by providing a registry for global names and identities without any central authority making decisions.
+    The goals of this project are to promote decentralization, censorship resistance and self sovereignty. Controlling your own digital identity without having to ask anyone's
+    permission is a huge, important part of that goal.
+faq-how = How does it work?
+faq-how-body =
+    Nomen is an open protocol with a few, extremely simple rules, built as a layer on top of two established permissionless technologies: Bitcoin and Nostr. Bitcoin provides the
+    decentralized timestamps needed to establish first-come ownership over global names, and Nostr provides the transport method for name data.
+faq-how-steps = In short, to establish a name:
+faq-how-step-1 = Create a hash, representing a new global name. This hash can uniquely and provably represent that name, and that name <b><i>only</i></b>. This hash is published to the Bitcoin blockchain, as a permanent record of the claim.
+faq-how-step-2 =
+    Publish metadata for the name as a Nostr event. This contains all of the data necessary to reconstruct the hash, and prove the ownership of that name. That hash includes the public key of the owner, and the Nostr event
+    must be signed by the associated private key, thus creating a cryptographically proven link between the owner and on-chain claim.
+faq-how-summary = That is all, in a nutshell! Publish a recognizable hash on the Bitcoin timechain, then broadcast your provable identity to the world. For more technical details, check out the <a href="https://github.com/ursuscamp/nomen/blob/master/docs/SPEC.md">spec</a> (it's very simple and not difficult to understand).
+faq-scale = How can this possibly scale?
+faq-scale-body-1 = Scalability was an important goal of this protocol. Bitcoin transactions won't be affordable forever, and not everyone will be able to make an on-chain transaction just to prove ownership. So how can this possibly scale?
+faq-scale-body-2 =
+    The answer is <b>namespaces</b>. Each on-chain transaction represents a TOP-LEVEL root to a nested namespace. Each namespace can contain potentially infinite children, grandchildren, great grandchildren, etc. Think of it like <code>com</code>
+    being a root name on chain, and <code>amazon</code> and <code>google</code> being child names. Top level names belong to the root owner of the namespace, and can be operated like a business if they wish, or a charity. They can aggregate namespace updates to the blockchain
+    periodically.
+faq-scale-body-3 =
+    If you create a name, you can keep it just to yourself forever, or give away names to friends and family, sell them, whatever you want. And it scales because the only thing that gets updated on the blockchain are things that affect ownership. Everything else happens
+    off-chain, always provably linked back to an on-chain fingerprint.
+faq-censorship = If someone else owns a namespace, how is it censorship resistant?
+faq-censorship-body =
+    After a child namespace is published (as part of a merkle tree in the on-chain hash), it can <b><i>NEVER</i></b> be unpublished.  Namespace can only ever be added, not deleted. Part of that hash includes the public key of the child's owner! Which means that,
+    once a namespace owner provides you with a name, it is associated only with <b>your</b> private key, and only <b>you</b> control it. You can continue to use it forever and the original namespace provider has no say in it.
+faq-nostr = How exactly does Nostr come into this?
+faq-nostr-body-1 =
+    Because on-chain data is limited and we don't want to pollute it unnecesasrily, we need a secondary protocol for data transport. Rather than create some brand new P2P protocol, we can use the established Nostr network, which already has
+    the necessary cryptographic primitives (and a healthy dose of users that respect a mission of controlling your digital identity).
+faq-nostr-body-2 =
+    Nostr's role in this is quite simple: When a root-level namespace is created, the namespace owner broadcasts a Nostr event referencing that on-chain hash, with all of the data necessary to reproduce the hash. This proves ownership.
+    A second Nostr event is used to update records for any namespaces, or descendants. The records associated with a name just keys and values, similar to DNS records, as a JSON object. For instance:
+faq-nostr-body-3 =
+    Your name can represent you in many different contexts, such as your website, your Nostr identity, or whatever you choose to include. While standards will no doubt exist, this is an open protocol. When you want to update it, just sign and broadcast
+    an updated event using the owner key of the namespace!
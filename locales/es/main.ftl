@@ -0,0 +1,167 @@
+## Navigation
+
+site-title = Explorador Nomen
+nav-home = Inicio
+nav-explorer = Explorador
+nav-new-name = Nuevo nombre
+nav-update-records = Actualizar registros
+nav-faqs = Preguntas frecuentes
+nav-fees = Comisiones
+nav-activity = Actividad
+
+## Index
+
+index-intro = Nomen es un protocolo abierto para nombres globales, como un DNS descentralizado, construido con Bitcoin y Nostr. Los objetivos del protocolo Nomen son la descentralización y la autosoberanía.
+index-indexer = Nomen Explorer es un indexador. Cataloga la cadena de bloques de Bitcoin y los eventos de Nomen asociados en Nostr.
+index-more-info = Más información
+index-more-info-body =
+    Consulta más información a continuación, empezando por las preguntas frecuentes si quieres detalles. Visita el Explorador si quieres ver
+    qué nombres existen ya.
+index-specification = Especificación
+index-howto = Cómo obtener un nombre
+
+## Explorer
+
+explorer-title = Explorador
+explorer-last-indexed = Última indexación: { $time }.
+explorer-never-indexed = Última indexación: nunca.
+explorer-search = Buscar...
+explorer-select = Selecciona un nombre para ver sus registros actuales.
+explorer-no-names = ¡No se encontraron nombres!
+
+## Fees
+
+fees-title = Comisiones de reclamación de nombres
+fees-intro = Tasas de comisión pagadas por las transacciones de reclamación de nombres, para que puedas elegir una tasa que se confirme rápido.
+fees-window = Reclamaciones confirmadas en los últimos { $days } días.
+fees-none = No hay reclamaciones de nombres con comisiones conocidas en este periodo.
+fees-percentile = Percentil
+fees-rate = Tasa de comisión (sat/vB)
+fees-chart = Tasas de comisión a lo largo del tiempo
+fees-chart-legend = Las bandas sombreadas muestran los percentiles 10 a 90 y 25 a 75, y la línea la mediana. La parte superior del gráfico es { $max } sat/vB.
+fees-period = Periodo desde
+fees-claims = Reclamaciones
+
+## Activity
+
+activity-title = Actividad reciente
+activity-none = Todavía no se ha indexado nada.
+activity-when = Cuándo
+activity-event = Evento
+activity-name = Nombre
+activity-owner = Propietario
+activity-registration = Registrado
+activity-transfer = Transferido
+activity-records = Registros actualizados
+time-just-now = justo ahora
+time-minutes-ago = { $count ->
+    [one] hace 1 minuto
+   *[other] hace { $count } minutos
+}
+time-hours-ago = { $count ->
+    [one] hace 1 hora
+   *[other] hace { $count } horas
+}
+time-days-ago = { $count ->
+    [one] hace 1 día
+   *[other] hace { $count } días
+}
+
+## Name page
+
+nsid-blockchain-info = Información de la cadena de bloques
+nsid-blockhash = Hash del bloque
+nsid-block-height = Altura del bloque
+nsid-confirmations = Confirmaciones
+nsid-confirmed = confirmado
+nsid-unconfirmed = aún sin confirmar, úsalo con precaución
+nsid-txid = Txid
+nsid-vout = Vout
+nsid-tx-position = Posición en el bloque
+nsid-tx-size = Tamaño de la tx
+nsid-tx-fee = Comisión de la tx
+nsid-blocktime = Fecha del bloque
+nsid-nsid = NSID
+nsid-owner = Propietario (clave pública)
+nsid-records = Registros
+nsid-update-records = Actualizar registros
+nsid-no-records = No se encontraron registros.
+nsid-last-updated = Última actualización: { $time }.
+nsid-sources = Evento de registros visto en { $relays }.
+nsid-record-type = Tipo de registro
+nsid-value = Valor
+nsid-children = Nombres debajo
+nsid-similar = Este nombre se confunde fácilmente con:
+nsid-unapproved = no aprobado por el dueño del nombre superior
+nsid-deactivated = desactivado por su dueño; sus registros no se sirven
+nsid-raw = JSON sin procesar
+nsid-copy = Copiar
+nsid-copied = Copiado
+nsid-download-event = Descargar el evento de registros firmado
+nsid-meta-description = Un nombre Nomen, registrado en Bitcoin en el bloque { $height }.
+pagination-prev = Anterior
+pagination-next = Siguiente
+pagination-page = Página
+
+## Redirect
+
+redirect-title = { $name }
+redirect-notice = Este nombre enlaza a la dirección de abajo. Nomen Explorer no revisa los sitios a los que enlazan los nombres, así que continúa solo si confías en él.
+
+## Owner page
+
+owner-title = Nombres por propietario
+owner-no-names = Esta clave no es propietaria de ningún nombre.
+owner-name = Nombre
+owner-status = Estado
+owner-records-updated = Registros actualizados
+owner-confirmed = Confirmado
+owner-unconfirmed = Aún sin confirmar
+
+## Errors
+
+error-title = Error
+error-message = Error: { $message }
+error-request-id = ID de la solicitud: { $id }
+
+## FAQs
+
+faq-title = Preguntas frecuentes
+faq-what = ¿Qué es Nomen?
+faq-what-body =
+    Nomen es un protocolo abierto que pretende resolver el "problema ICANN" proporcionando un registro de nombres e identidades globales sin que ninguna autoridad central tome decisiones.
+    Los objetivos de este proyecto son promover la descentralización, la resistencia a la censura y la autosoberanía. Controlar tu propia identidad digital sin tener que pedir
+    permiso a nadie es una parte enorme e importante de ese objetivo.
+faq-how = ¿Cómo funciona?
+faq-how-body =
+    Nomen es un protocolo abierto con unas pocas reglas extremadamente simples, construido como una capa sobre dos tecnologías sin permisos ya establecidas: Bitcoin y Nostr. Bitcoin proporciona
+    las marcas de tiempo descentralizadas necesarias para establecer la propiedad por orden de llegada de los nombres globales, y Nostr proporciona el medio de transporte de los datos de los nombres.
+faq-how-steps = En resumen, para establecer un nombre:
+faq-how-step-1 = Crea un hash que represente un nuevo nombre global. Este hash puede representar de forma única y demostrable ese nombre, y <b><i>solo</i></b> ese nombre. Este hash se publica en la cadena de bloques de Bitcoin, como registro permanente de la reclamación.
+faq-how-step-2 =
+    Publica los metadatos del nombre como un evento de Nostr. Este contiene todos los datos necesarios para reconstruir el hash y demostrar la propiedad del nombre. Ese hash incluye la clave pública del propietario, y el evento de Nostr
+    debe estar firmado con la clave privada asociada, creando así un vínculo criptográficamente demostrado entre el propietario y la reclamación en la cadena.
+faq-how-summary = ¡Eso es todo, en pocas palabras! Publica un hash reconocible en la cadena de bloques de Bitcoin y luego difunde tu identidad demostrable al mundo. Para más detalles técnicos, consulta la <a href="https://github.com/ursuscamp/nomen/blob/master/docs/SPEC.md">especificación</a> (es muy sencilla y fácil de entender).
+faq-scale = ¿Cómo puede esto escalar?
+faq-scale-body-1 = La escalabilidad fue un objetivo importante de este protocolo. Las transacciones de Bitcoin no serán asequibles para siempre, y no todo el mundo podrá hacer una transacción en la cadena solo para demostrar la propiedad. Entonces, ¿cómo puede escalar?
+faq-scale-body-2 =
+    La respuesta son los <b>espacios de nombres</b>. Cada transacción en la cadena representa la raíz de PRIMER NIVEL de un espacio de nombres anidado. Cada espacio de nombres puede contener potencialmente infinitos hijos, nietos, bisnietos, etc. Piensa en <code>com</code>
+    como un nombre raíz en la cadena, y en <code>amazon</code> y <code>google</code> como nombres hijos. Los nombres de primer nivel pertenecen al propietario raíz del espacio de nombres, que puede gestionarlo como un negocio si lo desea, o como una organización benéfica. Puede agregar las actualizaciones del espacio de nombres a la cadena de bloques
+    periódicamente.
+faq-scale-body-3 =
+    Si creas un nombre, puedes quedártelo para siempre, o regalar nombres a amigos y familiares, venderlos, lo que quieras. Y escala porque lo único que se actualiza en la cadena de bloques son las cosas que afectan a la propiedad. Todo lo demás ocurre
+    fuera de la cadena, siempre vinculado de forma demostrable a una huella en la cadena.
+faq-censorship = Si otra persona es dueña de un espacio de nombres, ¿cómo resiste la censura?
+faq-censorship-body =
+    Después de que se publica un espacio de nombres hijo (como parte de un árbol de Merkle en el hash en la cadena), <b><i>NUNCA</i></b> se puede despublicar. Los espacios de nombres solo se pueden añadir, no eliminar. ¡Parte de ese hash incluye la clave pública del propietario del hijo! Lo que significa que,
+    una vez que el propietario de un espacio de nombres te proporciona un nombre, este queda asociado únicamente a <b>tu</b> clave privada, y solo <b>tú</b> lo controlas. Puedes seguir usándolo para siempre y el proveedor original del espacio de nombres no tiene nada que decir.
+faq-nostr = ¿Qué papel juega exactamente Nostr?
+faq-nostr-body-1 =
+    Como los datos en la cadena son limitados y no queremos contaminarla innecesariamente, necesitamos un protocolo secundario para el transporte de datos. En lugar de crear un protocolo P2P completamente nuevo, podemos usar la red Nostr ya establecida, que ya tiene
+    las primitivas criptográficas necesarias (y una buena dosis de usuarios que respetan la misión de controlar tu identidad digital).
+faq-nostr-body-2 =
+    El papel de Nostr es bastante sencillo: cuando se crea un espacio de nombres raíz, su propietario difunde un evento de Nostr que hace referencia a ese hash en la cadena, con todos los datos necesarios para reproducir el hash. Esto demuestra la propiedad.
+    Un segundo evento de Nostr se usa para actualizar los registros de cualquier espacio de nombres o descendiente. Los registros asociados a un nombre son simplemente claves y valores, similares a los registros DNS, como un objeto JSON. Por ejemplo:
+faq-nostr-body-3 =
+    Tu nombre puede representarte en muchos contextos diferentes, como tu sitio web, tu identidad de Nostr o lo que decidas incluir. Aunque sin duda existirán estándares, este es un protocolo abierto. Cuando quieras actualizarlo, simplemente firma y difunde
+    un evento actualizado usando la clave del propietario del espacio de nombres.
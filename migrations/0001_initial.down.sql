@@ -0,0 +1,13 @@
+DROP VIEW detail_vw;
+DROP VIEW records_vw;
+DROP TABLE name_owners;
+DROP VIEW owners_vw;
+DROP VIEW ownership_chain_vw;
+DROP VIEW name_vw;
+DROP VIEW ranked_name_vw;
+DROP VIEW ordered_blockchain_vw;
+DROP TABLE event_log;
+DROP TABLE transfer_events;
+DROP TABLE name_events;
+DROP TABLE blockchain;
+DROP TABLE index_height;
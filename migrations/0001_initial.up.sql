@@ -0,0 +1,71 @@
+CREATE TABLE index_height (blockheight INTEGER PRIMARY KEY, blockhash);
+CREATE TABLE blockchain (id INTEGER PRIMARY KEY, fingerprint, nsid, blockhash, txid, blocktime, blockheight, txheight, vout, kind, indexed_at);
+CREATE TABLE name_events (name, fingerprint, nsid, pubkey, created_at, event_id, records, indexed_at, raw_event);
+CREATE UNIQUE INDEX name_events_unique_idx ON name_events(name, pubkey);
+CREATE INDEX name_events_created_at_idx ON name_events(created_at);
+CREATE TABLE transfer_events (nsid, name, fingerprint, pubkey, created_at, event_id, content, indexed_at, raw_event);
+CREATE UNIQUE INDEX transfer_events_unique_idx ON transfer_events(nsid);
+
+-- We order by blockheight -> txheight (height of tx inside block) and then vout (output inside tx)
+-- to make sure we are always looking in exact blockchain order
+CREATE VIEW ordered_blockchain_vw AS
+    SELECT b.* FROM blockchain b
+    ORDER BY b.blockheight, b.txheight, b.vout;
+
+-- Someone could theoretically try to claim a name a second time, we want to rank each blockchain event
+-- in order, partitioned by name. So if Person A claims 'domain-name' first, then Person B also claims 'domain-name'
+-- second, then Person A will be ranked 1, and Person B will be ranked 2.
+CREATE VIEW ranked_name_vw AS
+    SELECT ne.*, ROW_NUMBER() OVER (PARTITION BY ne.name) as row
+    FROM ordered_blockchain_vw b
+    JOIN name_events ne on b.fingerprint = ne.fingerprint AND b.nsid = ne.nsid
+    WHERE b.kind = 'create';
+
+-- We select everyone that has rank 1. This is always going to be first claimed on blockchain.
+CREATE VIEW name_vw AS
+    SELECT * FROM ranked_name_vw WHERE row = 1;
+
+-- Starting with a valid name event, follow the graph recursively to each successive transfer_event (if such exists),
+-- connecting pubkey -> content (next pubkey) -> pubkey -> content (next pubkey), etc. The resulting query returns
+-- the successive owners of each name
+CREATE VIEW ownership_chain_vw AS
+    WITH RECURSIVE owners(name, pk) as (
+        SELECT name, pubkey FROM name_vw
+        UNION ALL
+        SELECT te.name, te.content
+            FROM transfer_events te
+            JOIN owners ON te.pubkey = owners.pk AND te.name = owners.name
+            JOIN blockchain b on te.nsid = b.nsid AND te.fingerprint = b.fingerprint
+            WHERE b.kind = 'transfer'
+    )
+    SELECT name, pk FROM owners;
+
+-- Partition over the names, and only return the final value (the latest owner)
+CREATE VIEW owners_vw AS
+    SELECT DISTINCT name, last_value(pk) OVER (PARTITION BY name) AS pubkey
+    FROM ownership_chain_vw;
+
+-- This table is used to cache the owners_vw results, to avoid a full graph traversal every time.
+CREATE TABLE name_owners (name, pubkey);
+
+CREATE VIEW records_vw AS
+    SELECT ne.* FROM name_owners no
+    JOIN name_events ne on no.name = ne.name AND no.pubkey = ne.pubkey
+    ORDER BY ne.created_at DESC;
+
+CREATE VIEW detail_vw AS
+    SELECT
+        b.nsid,
+        b.blockhash,
+        b.blocktime,
+        b.txid,
+        b.vout,
+        b.blockheight,
+        r.name,
+        COALESCE(r.records, '{}') as records,
+        r.pubkey,
+        r.created_at as records_created_at
+    FROM records_vw r
+    JOIN ordered_blockchain_vw b ON r.fingerprint = b.fingerprint AND r.nsid = b.nsid;
+
+CREATE TABLE event_log (created_at, type, data);
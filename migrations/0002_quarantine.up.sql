@@ -0,0 +1,3 @@
+-- Rows removed by `db verify --fix`, kept for inspection.
+-- IF NOT EXISTS, because this table briefly existed before the move to versioned migrations.
+CREATE TABLE IF NOT EXISTS quarantine (table_name, row_data, reason, quarantined_at);
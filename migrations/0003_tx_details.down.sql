@@ -0,0 +1,19 @@
+DROP VIEW detail_vw;
+CREATE VIEW detail_vw AS
+    SELECT
+        b.nsid,
+        b.blockhash,
+        b.blocktime,
+        b.txid,
+        b.vout,
+        b.blockheight,
+        r.name,
+        COALESCE(r.records, '{}') as records,
+        r.pubkey,
+        r.created_at as records_created_at
+    FROM records_vw r
+    JOIN ordered_blockchain_vw b ON r.fingerprint = b.fingerprint AND r.nsid = b.nsid;
+
+ALTER TABLE blockchain DROP COLUMN vsize;
+ALTER TABLE blockchain DROP COLUMN size;
+ALTER TABLE blockchain DROP COLUMN fee;
@@ -0,0 +1,25 @@
+-- Details of the transaction carrying each Nomen output, recorded at index time so they don't need
+-- another RPC lookup later. fee is NULL when the inputs could not be looked up (e.g. no txindex).
+ALTER TABLE blockchain ADD COLUMN fee;
+ALTER TABLE blockchain ADD COLUMN size;
+ALTER TABLE blockchain ADD COLUMN vsize;
+
+DROP VIEW detail_vw;
+CREATE VIEW detail_vw AS
+    SELECT
+        b.nsid,
+        b.blockhash,
+        b.blocktime,
+        b.txid,
+        b.vout,
+        b.blockheight,
+        b.txheight,
+        b.fee,
+        b.size,
+        b.vsize,
+        r.name,
+        COALESCE(r.records, '{}') as records,
+        r.pubkey,
+        r.created_at as records_created_at
+    FROM records_vw r
+    JOIN ordered_blockchain_vw b ON r.fingerprint = b.fingerprint AND r.nsid = b.nsid;
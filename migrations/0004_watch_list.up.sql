@@ -0,0 +1,3 @@
+-- Names added with `watch add`. The indexer sends an alert when one of them changes.
+CREATE TABLE watch_list (name PRIMARY KEY, fingerprint, added_at);
+CREATE INDEX watch_list_fingerprint_idx ON watch_list(fingerprint);
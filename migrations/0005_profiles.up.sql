@@ -0,0 +1,2 @@
+-- Kind 0 (metadata) events of name owners, fetched when `indexer.profiles` is enabled.
+CREATE TABLE profiles (pubkey PRIMARY KEY, name, display_name, picture, about, created_at, indexed_at);
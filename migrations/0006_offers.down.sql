@@ -0,0 +1,2 @@
+DROP VIEW open_offers_vw;
+DROP TABLE offers;
@@ -0,0 +1,12 @@
+-- Offers to sell a name, from `market offer` events. The d tag (nsid) names the buyer, so the seller
+-- has at most one offer per buyer.
+CREATE TABLE offers (event_id, name, nsid, seller, buyer, price, psbt, created_at, indexed_at, raw_event);
+CREATE UNIQUE INDEX offers_unique_idx ON offers(seller, nsid);
+
+-- An offer is open while the seller still owns the name, and the transfer has not been mined.
+CREATE VIEW open_offers_vw AS
+    SELECT o.* FROM offers o
+    JOIN name_owners no ON o.name = no.name AND o.seller = no.pubkey
+    WHERE NOT EXISTS (
+        SELECT 1 FROM blockchain b WHERE b.nsid = o.nsid AND b.kind = 'transfer'
+    );
@@ -0,0 +1,26 @@
+DROP VIEW detail_vw;
+CREATE VIEW detail_vw AS
+    SELECT
+        b.nsid,
+        b.blockhash,
+        b.blocktime,
+        b.txid,
+        b.vout,
+        b.blockheight,
+        b.txheight,
+        b.fee,
+        b.size,
+        b.vsize,
+        r.name,
+        COALESCE(r.records, '{}') as records,
+        r.pubkey,
+        r.created_at as records_created_at
+    FROM records_vw r
+    JOIN ordered_blockchain_vw b ON r.fingerprint = b.fingerprint AND r.nsid = b.nsid;
+
+DROP INDEX blockchain_anchor_idx;
+ALTER TABLE blockchain DROP COLUMN spent_height;
+ALTER TABLE blockchain DROP COLUMN spent_txid;
+ALTER TABLE blockchain DROP COLUMN anchor_script;
+ALTER TABLE blockchain DROP COLUMN anchor_value;
+ALTER TABLE blockchain DROP COLUMN anchor_vout;
@@ -0,0 +1,34 @@
+-- The first spendable output of each Nomen transaction is its anchor. It is tracked until it is spent,
+-- so later protocol rules can tie renewals and transfers to spending it. anchor_vout is NULL if the
+-- transaction has no spendable output.
+ALTER TABLE blockchain ADD COLUMN anchor_vout;
+ALTER TABLE blockchain ADD COLUMN anchor_value;
+ALTER TABLE blockchain ADD COLUMN anchor_script;
+ALTER TABLE blockchain ADD COLUMN spent_txid;
+ALTER TABLE blockchain ADD COLUMN spent_height;
+CREATE INDEX blockchain_anchor_idx ON blockchain(txid, anchor_vout);
+
+DROP VIEW detail_vw;
+CREATE VIEW detail_vw AS
+    SELECT
+        b.nsid,
+        b.blockhash,
+        b.blocktime,
+        b.txid,
+        b.vout,
+        b.blockheight,
+        b.txheight,
+        b.fee,
+        b.size,
+        b.vsize,
+        b.anchor_vout,
+        b.anchor_value,
+        b.anchor_script,
+        b.spent_txid,
+        b.spent_height,
+        r.name,
+        COALESCE(r.records, '{}') as records,
+        r.pubkey,
+        r.created_at as records_created_at
+    FROM records_vw r
+    JOIN ordered_blockchain_vw b ON r.fingerprint = b.fingerprint AND r.nsid = b.nsid;
@@ -0,0 +1,5 @@
+-- API keys from `server apikey add`. Only the SHA-256 hash of each key is stored.
+CREATE TABLE api_keys (id INTEGER PRIMARY KEY, name UNIQUE, key_hash UNIQUE, created_at, revoked_at);
+
+-- Requests made with each key, per day and endpoint.
+CREATE TABLE api_usage (key_id, day, endpoint, requests, PRIMARY KEY (key_id, day, endpoint));
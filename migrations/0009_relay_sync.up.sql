@@ -0,0 +1,2 @@
+-- Per relay sync cursors: the newest event seen from each relay, for each event kind.
+CREATE TABLE relay_sync (relay, kind, last_seen, last_event_id, synced_at, PRIMARY KEY (relay, kind));
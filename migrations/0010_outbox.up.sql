@@ -0,0 +1,2 @@
+-- Signed events waiting to be acknowledged by a relay, retried with exponential backoff.
+CREATE TABLE outbox (event_id PRIMARY KEY, event, queued_at, attempts DEFAULT 0, next_attempt_at, last_error);
@@ -0,0 +1,45 @@
+DROP VIEW ordered_blockchain_vw;
+CREATE VIEW ordered_blockchain_vw AS
+    SELECT b.* FROM blockchain b
+    ORDER BY b.blockheight, b.txheight, b.vout;
+
+DROP VIEW ownership_chain_vw;
+CREATE VIEW ownership_chain_vw AS
+    WITH RECURSIVE owners(name, pk) as (
+        SELECT name, pubkey FROM name_vw
+        UNION ALL
+        SELECT te.name, te.content
+            FROM transfer_events te
+            JOIN owners ON te.pubkey = owners.pk AND te.name = owners.name
+            JOIN blockchain b on te.nsid = b.nsid AND te.fingerprint = b.fingerprint
+            WHERE b.kind = 'transfer'
+    )
+    SELECT name, pk FROM owners;
+
+DROP VIEW detail_vw;
+CREATE VIEW detail_vw AS
+    SELECT
+        b.nsid,
+        b.blockhash,
+        b.blocktime,
+        b.txid,
+        b.vout,
+        b.blockheight,
+        b.txheight,
+        b.fee,
+        b.size,
+        b.vsize,
+        b.anchor_vout,
+        b.anchor_value,
+        b.anchor_script,
+        b.spent_txid,
+        b.spent_height,
+        r.name,
+        COALESCE(r.records, '{}') as records,
+        r.pubkey,
+        r.created_at as records_created_at
+    FROM records_vw r
+    JOIN ordered_blockchain_vw b ON r.fingerprint = b.fingerprint AND r.nsid = b.nsid;
+
+ALTER TABLE blockchain DROP COLUMN status_reason;
+ALTER TABLE blockchain DROP COLUMN status;
@@ -0,0 +1,10 @@
+DROP VIEW records_vw;
+CREATE VIEW records_vw AS
+    SELECT ne.* FROM name_owners no
+    JOIN name_events ne on no.name = ne.name AND no.pubkey = ne.pubkey
+    ORDER BY ne.created_at DESC;
+
+DROP VIEW current_anchor_vw;
+
+ALTER TABLE name_events DROP COLUMN anchor_height;
+ALTER TABLE name_events DROP COLUMN anchor_txid;
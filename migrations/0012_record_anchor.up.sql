@@ -0,0 +1,29 @@
+-- The anchor (txid and height) a records event is bound to by its optional `anchor` tag. Records of
+-- an event bound to an earlier anchor than the name's current one are not served.
+ALTER TABLE name_events ADD COLUMN anchor_txid;
+ALTER TABLE name_events ADD COLUMN anchor_height;
+
+-- The latest transaction of each fingerprint and nsid: the anchor records events are bound to.
+CREATE VIEW current_anchor_vw AS
+    SELECT fingerprint, nsid, txid, MAX(blockheight) AS blockheight
+    FROM ordered_blockchain_vw
+    GROUP BY fingerprint, nsid;
+
+DROP VIEW records_vw;
+CREATE VIEW records_vw AS
+    SELECT
+        ne.name,
+        ne.fingerprint,
+        ne.nsid,
+        ne.pubkey,
+        ne.created_at,
+        ne.event_id,
+        CASE WHEN ne.anchor_txid IS NULL OR ne.anchor_txid = a.txid THEN ne.records END AS records,
+        ne.indexed_at,
+        ne.raw_event,
+        ne.anchor_txid,
+        ne.anchor_height
+    FROM name_owners no
+    JOIN name_events ne on no.name = ne.name AND no.pubkey = ne.pubkey
+    LEFT JOIN current_anchor_vw a ON a.fingerprint = ne.fingerprint AND a.nsid = ne.nsid
+    ORDER BY ne.created_at DESC;
@@ -0,0 +1,2 @@
+DROP TABLE checkpoint_names;
+DROP TABLE checkpoint;
@@ -0,0 +1,12 @@
+-- The operator's latest signed names checkpoint, and the sorted names it commits to, for proving a
+-- name was not registered at its height.
+CREATE TABLE checkpoint (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    index_height INTEGER NOT NULL,
+    event TEXT NOT NULL
+);
+
+CREATE TABLE checkpoint_names (
+    position INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+DROP VIEW approved_subnames_vw;
+DROP TABLE subname_approvals;
@@ -0,0 +1,11 @@
+-- Approvals of subnames by the owner of their parent, from `name approve` events. The d tag (the
+-- subname) makes them replaceable, so an approver has one approval per subname.
+CREATE TABLE subname_approvals (event_id, name, parent, pubkey, approver, created_at, indexed_at, raw_event);
+CREATE UNIQUE INDEX subname_approvals_unique_idx ON subname_approvals(approver, name);
+
+-- Subnames approved for their current owner by the current owner of their parent. Approval of
+-- the parent itself is not checked here.
+CREATE VIEW approved_subnames_vw AS
+    SELECT a.name FROM subname_approvals a
+    JOIN name_owners child ON a.name = child.name AND a.pubkey = child.pubkey
+    JOIN name_owners parent ON a.parent = parent.name AND a.approver = parent.pubkey;
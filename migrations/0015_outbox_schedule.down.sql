@@ -0,0 +1 @@
+ALTER TABLE outbox DROP COLUMN publish_at;
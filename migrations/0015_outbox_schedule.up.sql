@@ -0,0 +1,2 @@
+-- Events scheduled with `nomen name record --at` are not sent before this time.
+ALTER TABLE outbox ADD COLUMN publish_at;
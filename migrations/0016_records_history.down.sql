@@ -0,0 +1,2 @@
+DROP VIEW ownership_history_vw;
+DROP TABLE records_history;
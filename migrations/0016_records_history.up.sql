@@ -0,0 +1,24 @@
+-- Every records event the indexer accepted, not just the latest one per owner as in name_events,
+-- to resolve what a name's records were at a past height or time. Events indexed before this
+-- migration only have their latest one.
+CREATE TABLE records_history (event_id PRIMARY KEY, name, fingerprint, nsid, pubkey, created_at, records, indexed_at, raw_event, anchor_txid, anchor_height);
+CREATE INDEX records_history_name_idx ON records_history(name, pubkey, created_at);
+INSERT INTO records_history
+    SELECT event_id, name, fingerprint, nsid, pubkey, created_at, records, indexed_at, raw_event, anchor_txid, anchor_height
+    FROM name_events WHERE event_id IS NOT NULL;
+
+-- Like ownership_chain_vw, with the block each owner took over the name in. Each transfer must come
+-- after the previous one, so a name transferred back to an earlier owner doesn't loop.
+CREATE VIEW ownership_history_vw AS
+    WITH RECURSIVE owners(name, pubkey, blockheight, blocktime) as (
+        SELECT n.name, n.pubkey, b.blockheight, b.blocktime FROM name_vw n
+        JOIN ordered_blockchain_vw b ON b.fingerprint = n.fingerprint AND b.nsid = n.nsid
+        WHERE b.kind = 'create'
+        UNION ALL
+        SELECT te.name, te.content, b.blockheight, b.blocktime
+            FROM transfer_events te
+            JOIN owners ON te.pubkey = owners.pubkey AND te.name = owners.name
+            JOIN blockchain b on te.nsid = b.nsid AND te.fingerprint = b.fingerprint
+            WHERE b.kind = 'transfer' AND b.status <> 'revoked' AND b.blockheight > owners.blockheight
+    )
+    SELECT name, pubkey, blockheight, blocktime FROM owners;
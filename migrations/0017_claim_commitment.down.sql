@@ -0,0 +1 @@
+ALTER TABLE blockchain DROP COLUMN commitment;
@@ -0,0 +1,3 @@
+-- The hex encoded commitment a claim's OP_RETURN may carry to the owner's first records event. Until
+-- the indexer has seen a records event whose content hashes to it, the name has no records.
+ALTER TABLE blockchain ADD COLUMN commitment;
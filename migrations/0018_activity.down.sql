@@ -0,0 +1,3 @@
+DROP VIEW activity_vw;
+DROP INDEX records_history_created_at_idx;
+DROP INDEX blockchain_blocktime_idx;
@@ -0,0 +1,18 @@
+-- Recent activity for the explorer: registrations and transfers when they were mined, and records
+-- updates when they were signed, newest first.
+CREATE INDEX blockchain_blocktime_idx ON blockchain(blocktime);
+CREATE INDEX records_history_created_at_idx ON records_history(created_at);
+
+CREATE VIEW activity_vw AS
+    SELECT 'registration' AS kind, n.name, n.pubkey, b.blocktime AS time
+        FROM blockchain b
+        JOIN name_vw n ON n.fingerprint = b.fingerprint AND n.nsid = b.nsid
+        WHERE b.kind = 'create' AND b.status <> 'revoked'
+    UNION ALL
+    SELECT 'transfer' AS kind, te.name, te.content AS pubkey, b.blocktime AS time
+        FROM blockchain b
+        JOIN transfer_events te ON te.nsid = b.nsid AND te.fingerprint = b.fingerprint
+        WHERE b.kind = 'transfer' AND b.status <> 'revoked'
+    UNION ALL
+    SELECT 'records' AS kind, name, pubkey, created_at AS time
+        FROM records_history;
@@ -0,0 +1,4 @@
+-- Pairs of indexed names that look alike, in both directions, rebuilt by each indexer run. The score
+-- is from 0 to 1, the share of characters that are the same.
+CREATE TABLE similar_names (name, similar, score);
+CREATE INDEX similar_names_name_idx ON similar_names(name);
@@ -0,0 +1,21 @@
+DROP VIEW records_vw;
+CREATE VIEW records_vw AS
+    SELECT
+        ne.name,
+        ne.fingerprint,
+        ne.nsid,
+        ne.pubkey,
+        ne.created_at,
+        ne.event_id,
+        CASE WHEN ne.anchor_txid IS NULL OR ne.anchor_txid = a.txid THEN ne.records END AS records,
+        ne.indexed_at,
+        ne.raw_event,
+        ne.anchor_txid,
+        ne.anchor_height
+    FROM name_owners no
+    JOIN name_events ne on no.name = ne.name AND no.pubkey = ne.pubkey
+    LEFT JOIN current_anchor_vw a ON a.fingerprint = ne.fingerprint AND a.nsid = ne.nsid
+    ORDER BY ne.created_at DESC;
+
+ALTER TABLE records_history DROP COLUMN expires_at;
+ALTER TABLE name_events DROP COLUMN expires_at;
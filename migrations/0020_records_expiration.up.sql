@@ -0,0 +1,37 @@
+-- When a records event expires, from its NIP-40 `expiration` tag. Expired records are kept in the
+-- history, but not served.
+ALTER TABLE name_events ADD COLUMN expires_at;
+ALTER TABLE records_history ADD COLUMN expires_at;
+UPDATE name_events SET expires_at = (
+    SELECT CAST(json_extract(t.value, '$[1]') AS INTEGER)
+    FROM json_each(json_extract(raw_event, '$.tags')) t
+    WHERE json_extract(t.value, '$[0]') = 'expiration'
+);
+UPDATE records_history SET expires_at = (
+    SELECT CAST(json_extract(t.value, '$[1]') AS INTEGER)
+    FROM json_each(json_extract(raw_event, '$.tags')) t
+    WHERE json_extract(t.value, '$[0]') = 'expiration'
+);
+
+DROP VIEW records_vw;
+CREATE VIEW records_vw AS
+    SELECT
+        ne.name,
+        ne.fingerprint,
+        ne.nsid,
+        ne.pubkey,
+        ne.created_at,
+        ne.event_id,
+        CASE
+            WHEN (ne.anchor_txid IS NULL OR ne.anchor_txid = a.txid)
+                AND (ne.expires_at IS NULL OR ne.expires_at > unixepoch())
+            THEN ne.records
+        END AS records,
+        ne.indexed_at,
+        ne.raw_event,
+        ne.anchor_txid,
+        ne.anchor_height
+    FROM name_owners no
+    JOIN name_events ne on no.name = ne.name AND no.pubkey = ne.pubkey
+    LEFT JOIN current_anchor_vw a ON a.fingerprint = ne.fingerprint AND a.nsid = ne.nsid
+    ORDER BY ne.created_at DESC;
@@ -0,0 +1,2 @@
+-- The next index to derive a change address at, for each `wallet.descriptor` used.
+CREATE TABLE wallet_derivation (descriptor PRIMARY KEY, next_index);
@@ -0,0 +1,3 @@
+-- The relays each indexed records or transfer event was fetched from.
+CREATE TABLE event_sources (event_id, relay, first_seen, PRIMARY KEY (event_id, relay));
+CREATE INDEX event_sources_relay_idx ON event_sources(relay);
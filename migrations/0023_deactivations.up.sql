@@ -0,0 +1,29 @@
+-- Owners deactivating their names. Records are not served while the owner's deactivation is newer
+-- than their records event, but stay in the history.
+CREATE TABLE deactivations (event_id, name, pubkey, reason, created_at, indexed_at, raw_event);
+CREATE UNIQUE INDEX deactivations_unique_idx ON deactivations(pubkey, name);
+
+DROP VIEW records_vw;
+CREATE VIEW records_vw AS
+    SELECT
+        ne.name,
+        ne.fingerprint,
+        ne.nsid,
+        ne.pubkey,
+        ne.created_at,
+        ne.event_id,
+        CASE
+            WHEN (ne.anchor_txid IS NULL OR ne.anchor_txid = a.txid)
+                AND (ne.expires_at IS NULL OR ne.expires_at > unixepoch())
+                AND (d.created_at IS NULL OR d.created_at < ne.created_at)
+            THEN ne.records
+        END AS records,
+        ne.indexed_at,
+        ne.raw_event,
+        ne.anchor_txid,
+        ne.anchor_height
+    FROM name_owners no
+    JOIN name_events ne on no.name = ne.name AND no.pubkey = ne.pubkey
+    LEFT JOIN current_anchor_vw a ON a.fingerprint = ne.fingerprint AND a.nsid = ne.nsid
+    LEFT JOIN deactivations d ON d.name = ne.name AND d.pubkey = ne.pubkey
+    ORDER BY ne.created_at DESC;
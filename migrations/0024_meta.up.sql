@@ -0,0 +1,2 @@
+-- Facts about the index as a whole, such as the network and genesis block it was built from.
+CREATE TABLE meta (key PRIMARY KEY, value);
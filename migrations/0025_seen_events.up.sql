@@ -0,0 +1,3 @@
+-- Events the indexer has processed, valid or not, so refetching them from relays skips them.
+CREATE TABLE seen_events (event_id PRIMARY KEY, kind, seen_at);
+CREATE INDEX seen_events_seen_at_idx ON seen_events(seen_at);
@@ -0,0 +1,2 @@
+-- Administrative and mutating actions, who took them and when. Rows are only ever added.
+CREATE TABLE audit_log (id INTEGER PRIMARY KEY, created_at, actor, action, detail);
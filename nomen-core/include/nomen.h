@@ -0,0 +1,47 @@
+/*
+ * C ABI of nomen-core, built with `make core-ffi`.
+ *
+ * Strings are NUL terminated UTF-8. Strings returned by these functions are owned by the caller
+ * and must be released with nomen_string_free(). On failure a function returns NULL (or -1) and
+ * nomen_last_error() describes why.
+ */
+
+#ifndef NOMEN_H
+#define NOMEN_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Hex encoded nsid of `name` owned by the hex encoded x-only public key `pubkey`. */
+char *nomen_derive_nsid(const char *name, const char *pubkey);
+
+/*
+ * Whether the hex encoded OP_RETURN data `op_return` claims the name of the name or transfer
+ * event `event` (JSON): 1 if it does, 0 if it does not, -1 on error.
+ */
+int32_t nomen_verify_proof(const char *op_return, const char *event);
+
+/*
+ * Records (a JSON object) of `name` for its owner, whose hex encoded nsid `nsid` is known from
+ * the chain, from the newest valid name event in `events` (a JSON array of events, as fetched
+ * from relays). NULL with no error set if none of the events qualify.
+ */
+char *nomen_resolve_name(const char *name, const char *nsid, const char *events);
+
+/*
+ * Why the last failed call on this thread failed, or NULL. The string is owned by the library
+ * and valid until the next call on this thread.
+ */
+const char *nomen_last_error(void);
+
+/* Release a string returned by this library. */
+void nomen_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NOMEN_H */
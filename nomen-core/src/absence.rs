@@ -0,0 +1,209 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::Error;
+
+/// Leaf of `name` in a names tree: `SHA256(0x00 || name)`.
+pub fn name_leaf(name: &str) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([0])
+        .chain_update(name.as_bytes())
+        .finalize()
+        .into()
+}
+
+/// Inner node of a names tree: `SHA256(0x01 || left || right)`.
+fn branch(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([1])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// The level above `level`. A node without a sibling moves up unchanged.
+fn parents(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => branch(left, right),
+            [node] => *node,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Merkle root of `names`, which must be sorted and distinct. The root of no names is all zeros.
+pub fn names_root<S: AsRef<str>>(names: &[S]) -> [u8; 32] {
+    let mut level: Vec<_> = names.iter().map(|n| name_leaf(n.as_ref())).collect();
+    if level.is_empty() {
+        return [0; 32];
+    }
+    while level.len() > 1 {
+        level = parents(&level);
+    }
+    level[0]
+}
+
+/// Siblings of the leaf at `index` in the tree of `names`, bottom up. Levels where it has no
+/// sibling are skipped.
+pub fn names_path<S: AsRef<str>>(names: &[S], mut index: usize) -> Vec<[u8; 32]> {
+    let mut level: Vec<_> = names.iter().map(|n| name_leaf(n.as_ref())).collect();
+    let mut path = Vec::new();
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            path.push(*sibling);
+        }
+        level = parents(&level);
+        index /= 2;
+    }
+    path
+}
+
+/// Root of a tree of `count` names, from the leaf at `index` and its path.
+fn path_root(
+    leaf: [u8; 32],
+    mut index: u64,
+    mut count: u64,
+    path: &[[u8; 32]],
+) -> Option<[u8; 32]> {
+    let mut hash = leaf;
+    let mut path = path.iter();
+    while count > 1 {
+        if index ^ 1 < count {
+            let sibling = path.next()?;
+            hash = if index.is_multiple_of(2) {
+                branch(&hash, sibling)
+            } else {
+                branch(sibling, &hash)
+            };
+        }
+        index /= 2;
+        count = count.div_ceil(2);
+    }
+    path.next().is_none().then_some(hash)
+}
+
+/// A name next to where a missing name would be in a names tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Neighbour {
+    pub name: String,
+    /// Position of `name` in the sorted names.
+    pub index: u64,
+    /// Hex encoded siblings of its leaf, bottom up.
+    pub path: Vec<String>,
+}
+
+/// Proof that `name` is not in a names tree: the names just before and after where it would be,
+/// with their Merkle paths. `before` is missing if it would be first, `after` if it would be last.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbsenceProof {
+    pub name: String,
+    pub before: Option<Neighbour>,
+    pub after: Option<Neighbour>,
+}
+
+impl AbsenceProof {
+    /// The proof that `name` is not in `names`, which must be sorted and distinct. None if it is.
+    pub fn new<S: AsRef<str>>(names: &[S], name: &str) -> Option<AbsenceProof> {
+        let index = names.binary_search_by(|n| n.as_ref().cmp(name)).err()?;
+        let neighbour = |index: usize| Neighbour {
+            name: names[index].as_ref().to_string(),
+            index: index as u64,
+            path: names_path(names, index).iter().map(hex::encode).collect(),
+        };
+        Some(AbsenceProof {
+            name: name.to_string(),
+            before: index.checked_sub(1).map(neighbour),
+            after: (index < names.len()).then(|| neighbour(index)),
+        })
+    }
+}
+
+/// Whether `proof` shows its name is not among the `count` names with Merkle root `root`: its
+/// neighbours are adjacent leaves of that tree, and sort on either side of it.
+pub fn verify_absence(proof: &AbsenceProof, root: &[u8; 32], count: u64) -> Result<bool, Error> {
+    let adjacent = match (&proof.before, &proof.after) {
+        (None, None) => count == 0 && root == &[0; 32],
+        (Some(before), None) => before.index + 1 == count,
+        (None, Some(after)) => after.index == 0,
+        (Some(before), Some(after)) => before.index + 1 == after.index,
+    };
+    let sorted = proof
+        .before
+        .as_ref()
+        .is_none_or(|before| before.name < proof.name)
+        && proof
+            .after
+            .as_ref()
+            .is_none_or(|after| after.name > proof.name);
+    if !adjacent || !sorted {
+        return Ok(false);
+    }
+
+    for neighbour in proof.before.iter().chain(proof.after.iter()) {
+        let path = neighbour
+            .path
+            .iter()
+            .map(|hash| {
+                let mut out = [0; 32];
+                hex::decode_to_slice(hash, &mut out)
+                    .map_err(|_| Error::Proof("Invalid hex in Merkle path".into()))?;
+                Ok(out)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let leaf = name_leaf(&neighbour.name);
+        if path_root(leaf, neighbour.index, count, &path).as_ref() != Some(root) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_absence_proof() {
+        let names = ["alice", "bob", "carol", "dave", "erin"];
+        let root = names_root(&names);
+        let count = names.len() as u64;
+
+        for name in ["aaron", "bobby", "dan", "zed"] {
+            let proof = AbsenceProof::new(&names, name).unwrap();
+            assert!(verify_absence(&proof, &root, count).unwrap(), "{name}");
+        }
+        assert!(AbsenceProof::new(&names, "carol").is_none());
+
+        // Claiming carol is missing by skipping over her.
+        let mut proof = AbsenceProof::new(&names, "bobby").unwrap();
+        proof.name = "carl".into();
+        assert!(verify_absence(&proof, &root, count).unwrap());
+        proof.name = "caz".into();
+        assert!(!verify_absence(&proof, &root, count).unwrap());
+        proof.after = AbsenceProof::new(&names, "dan").unwrap().after;
+        assert!(!verify_absence(&proof, &root, count).unwrap());
+
+        // A proof against a tree with a name left out.
+        let fewer = ["alice", "bob", "dave", "erin"];
+        let proof = AbsenceProof::new(&fewer, "carol").unwrap();
+        assert!(!verify_absence(&proof, &root, count).unwrap());
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let names: Vec<&str> = vec![];
+        let proof = AbsenceProof::new(&names, "alice").unwrap();
+        assert!(verify_absence(&proof, &names_root(&names), 0).unwrap());
+        assert!(!verify_absence(&proof, &names_root(&["bob"]), 1).unwrap());
+    }
+}
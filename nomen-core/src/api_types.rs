@@ -0,0 +1,587 @@
+//! Requests and responses of an indexer's HTTP API, shared by the server and [`crate::client`].
+//!
+//! Query structs serialize to the query string of their endpoint, responses are the JSON bodies.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::AbsenceProof;
+
+/// `/api/name/similar`, `/api/name/absence`, `/api/v1/name` and `/api/name/proof`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameQuery {
+    pub name: String,
+}
+
+/// `/api/name`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameAtQuery {
+    pub name: String,
+    /// Resolve the records as they were at this block height.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_height: Option<i64>,
+    /// Resolve the records as they were at this time, as RFC 3339 or Unix seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameResponse {
+    /// Records are flattened into the top level object, as they were before these fields existed.
+    #[serde(flatten)]
+    pub records: HashMap<String, String>,
+    pub confirmations: u32,
+    pub confirmed: bool,
+    /// `valid`, or `pending` while the name transaction waits to be mined again after a reorg.
+    pub status: String,
+    /// Whether the name transaction's anchor output has been spent. Null if it has none, or was
+    /// indexed before anchors were tracked.
+    pub anchor_spent: Option<bool>,
+    /// Whether the records are the `server.inherit_records` of the name's parents, as it has none.
+    pub inherited: bool,
+    /// Whether the owner deactivated the name, so it has no records until they publish new ones.
+    pub deactivated: bool,
+    /// Whether the index is more than `server.max_index_lag` blocks behind the node, so the
+    /// answer may be out of date.
+    pub stale: bool,
+}
+
+/// Records of a name at a past height or time (`at_height`, `at`), flattened like those of
+/// `NameResponse`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameAtResponse {
+    #[serde(flatten)]
+    pub records: HashMap<String, String>,
+    /// Owner of the name at the time.
+    pub pubkey: String,
+    /// Height of the block the owner took over the name in.
+    pub owned_since: i64,
+    /// The owner's records event in effect at the time, if they had published one.
+    pub event_id: Option<String>,
+    pub records_created_at: Option<i64>,
+}
+
+/// `/api/name/diff`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameDiffQuery {
+    pub name: String,
+    /// Id of the older records event.
+    pub from: String,
+    /// Id of the newer records event.
+    pub to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameDiffResponse {
+    pub from_created_at: i64,
+    pub to_created_at: i64,
+    #[serde(flatten)]
+    pub diff: RecordsDiff,
+}
+
+/// A value that differs between two record sets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordChange {
+    pub from: String,
+    pub to: String,
+}
+
+/// How one record set differs from another, by key.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordsDiff {
+    pub added: BTreeMap<String, String>,
+    pub removed: BTreeMap<String, String>,
+    pub changed: BTreeMap<String, RecordChange>,
+}
+
+impl RecordsDiff {
+    pub fn between(from: &HashMap<String, String>, to: &HashMap<String, String>) -> RecordsDiff {
+        let mut diff = RecordsDiff::default();
+        for (key, old) in from {
+            match to.get(key) {
+                None => {
+                    diff.removed.insert(key.clone(), old.clone());
+                }
+                Some(new) if new != old => {
+                    diff.changed.insert(
+                        key.clone(),
+                        RecordChange {
+                            from: old.clone(),
+                            to: new.clone(),
+                        },
+                    );
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, new) in to {
+            if !from.contains_key(key) {
+                diff.added.insert(key.clone(), new.clone());
+            }
+        }
+        diff
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimilarName {
+    pub name: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NameSimilarResponse {
+    /// The name asked for, in lowercase.
+    pub name: String,
+    /// What the name looks like, shared by every name it can be mistaken for.
+    pub skeleton: String,
+    pub similar: Vec<SimilarName>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NameAbsenceResponse {
+    /// The operator's signed checkpoint event. Its content has the `root` and `count` of the
+    /// names tree; check the signer is an operator you trust.
+    pub checkpoint: serde_json::Value,
+    /// Check with `nomen_core::verify_absence` against the checkpoint's root and count.
+    pub proof: AbsenceProof,
+    /// Current index height. Names registered since the checkpoint are not covered by it.
+    pub index_height: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NameV1Response {
+    pub name: String,
+    pub nsid: String,
+    pub nsid_bech32: String,
+    pub pubkey: String,
+    /// Typed records (e.g. `DNS`) are parsed JSON, the rest are strings.
+    pub records: BTreeMap<String, serde_json::Value>,
+    pub confirmations: u32,
+    pub confirmed: bool,
+    pub status: String,
+    pub anchor_spent: Option<bool>,
+    pub inherited: bool,
+    pub deactivated: bool,
+    pub stale: bool,
+}
+
+/// Everything needed to check a name's records against the chain, with only block headers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NameProofResponse {
+    pub name: String,
+    pub nsid: String,
+    /// Block of the transaction anchoring `nsid`. Check it is in your header chain.
+    pub blockhash: String,
+    pub blockheight: i64,
+    pub txid: String,
+    /// Merkle block proving `txid` is in `blockhash`, hex encoded, as from `gettxoutproof`.
+    pub txoutproof: String,
+    /// The raw transaction, hex encoded. Its hash is `txid`.
+    pub tx: String,
+    /// Output of `tx` with the OP_RETURN.
+    pub vout: i64,
+    /// Data pushed by the OP_RETURN, hex encoded: its fingerprint and nsid must match `event`.
+    pub op_return: String,
+    /// The signed records event of the owner.
+    pub event: serde_json::Value,
+    /// Whether the index is more than `server.max_index_lag` blocks behind the node.
+    pub stale: bool,
+}
+
+/// `/api/names`, `/api/children` and `/api/search`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListQuery {
+    /// Name to search for (`/api/names`, `/api/search`), or parent name (`/api/children`).
+    #[serde(alias = "q", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// `next_cursor` from the previous page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameItem {
+    pub name: String,
+    pub nsid: String,
+}
+
+/// One page of a list endpoint. `next_cursor` is absent on the last page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListResponse<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total: i64,
+}
+
+/// A line of `/api/dump`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedName {
+    pub name: String,
+    pub nsid: String,
+    pub pubkey: String,
+    pub blockheight: i64,
+    pub records: HashMap<String, String>,
+}
+
+/// `/api/offers`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffersQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenOffer {
+    pub event_id: String,
+    pub name: String,
+    pub nsid: String,
+    pub seller: String,
+    pub buyer: String,
+    pub price: i64,
+    pub psbt: String,
+    pub created_at: i64,
+}
+
+/// `/api/mirror/blockchain` and `/api/mirror/events`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorQuery {
+    /// Lowest block height (`/api/mirror/blockchain`) or event `created_at` (`/api/mirror/events`).
+    #[serde(default)]
+    pub from: i64,
+
+    /// `next_cursor` from the previous page.
+    #[serde(default)]
+    pub cursor: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+
+    /// `name` or `transfer`, for `/api/mirror/events`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+/// A row of the `blockchain` table, as served to and inserted by index mirrors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockchainRow {
+    pub id: i64,
+    pub fingerprint: String,
+    pub nsid: String,
+    pub blockhash: String,
+    pub txid: String,
+    pub blocktime: i64,
+    pub blockheight: i64,
+    pub txheight: i64,
+    pub vout: i64,
+    pub kind: String,
+    pub fee: Option<i64>,
+    pub size: Option<i64>,
+    pub vsize: Option<i64>,
+    pub anchor_vout: Option<i64>,
+    pub anchor_value: Option<i64>,
+    pub anchor_script: Option<String>,
+    pub spent_txid: Option<String>,
+    pub spent_height: Option<i64>,
+    /// Missing from mirrors that predate commitments.
+    #[serde(default)]
+    pub commitment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorBlockchainResponse {
+    pub items: Vec<BlockchainRow>,
+    pub next_cursor: Option<i64>,
+    /// Last block indexed by the server, so a mirror can continue indexing from there.
+    pub index_height: Option<i64>,
+    pub index_blockhash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorEventsResponse {
+    /// Signed events, as JSON strings.
+    pub items: Vec<String>,
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventResponse {
+    pub event_id: String,
+}
+
+/// `/api/tx`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxRequest {
+    /// Hex encoded, signed raw transaction.
+    pub tx: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxResponse {
+    pub txid: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiUsage {
+    pub day: String,
+    pub endpoint: String,
+    pub requests: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageResponse {
+    pub name: String,
+    pub created_at: i64,
+    pub total: i64,
+    pub usage: Vec<ApiUsage>,
+}
+
+/// `/api/stats/fees`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeesQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<FeeInterval>,
+}
+
+/// Length of each period in fee statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeInterval {
+    Day,
+    Week,
+}
+
+impl FeeInterval {
+    /// Days for short windows, weeks for longer ones.
+    pub fn for_days(days: i64) -> FeeInterval {
+        if days <= 31 {
+            FeeInterval::Day
+        } else {
+            FeeInterval::Week
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            FeeInterval::Day => 86_400,
+            FeeInterval::Week => 7 * 86_400,
+        }
+    }
+}
+
+/// Fee rate percentiles of a set of name claims, in sat/vB.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeeStats {
+    pub claims: usize,
+    pub p10: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p90: f64,
+}
+
+impl FeeStats {
+    /// Nearest-rank percentiles of `rates`, rounded to 0.1 sat/vB. All zero if there are none.
+    pub fn new(mut rates: Vec<f64>) -> FeeStats {
+        rates.sort_by(f64::total_cmp);
+        let n = rates.len();
+        let percentile = |p: f64| {
+            if n == 0 {
+                return 0.0;
+            }
+            let rank = ((p * n as f64).ceil() as usize).clamp(1, n);
+            (rates[rank - 1] * 10.0).round() / 10.0
+        };
+        FeeStats {
+            claims: n,
+            p10: percentile(0.10),
+            p25: percentile(0.25),
+            p50: percentile(0.50),
+            p75: percentile(0.75),
+            p90: percentile(0.90),
+        }
+    }
+}
+
+/// Fee statistics of the claims confirmed in one period.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeePeriod {
+    pub start: i64,
+    #[serde(flatten)]
+    pub stats: FeeStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeesResponse {
+    pub days: i64,
+    pub interval: FeeInterval,
+    #[serde(flatten)]
+    pub overall: FeeStats,
+    pub periods: Vec<FeePeriod>,
+}
+
+/// An indexed event of a name, and the relays it was fetched from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventProvenance {
+    pub event_id: String,
+    /// `records` or `transfer`.
+    pub kind: String,
+    pub created_at: i64,
+    pub relays: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NsidResponse {
+    pub nsid: String,
+    pub name: String,
+    pub pubkey: String,
+    pub txid: String,
+    pub blockheight: i64,
+    pub status: String,
+    /// The name's indexed records and transfer events, newest first, with the relays each
+    /// was fetched from.
+    pub events: Vec<EventProvenance>,
+}
+
+/// How many indexed events a relay supplied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayContribution {
+    pub relay: String,
+    pub events: i64,
+    /// Events no other relay supplied.
+    pub sole: i64,
+    /// When the relay last supplied an event the index didn't have from it yet.
+    pub last_seen: i64,
+}
+
+/// `/.well-known/nomen.json`: the key API responses are signed with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WellKnown {
+    pub pubkey: String,
+    pub npub: String,
+    pub algorithm: String,
+    pub label: String,
+    pub components: Vec<String>,
+}
+
+/// `get_status` over `/rpc`: how far the index is behind the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusResponse {
+    /// Last block indexed, and its hash.
+    pub index_height: Option<i64>,
+    pub index_blockhash: Option<String>,
+    /// Height of the node's best block.
+    pub node_height: u64,
+    /// Whether the index is more than `server.max_index_lag` blocks behind the node.
+    pub stale: bool,
+}
+
+/// A JSON-RPC 2.0 call to `/rpc`, alone or in a batch. Without an `id` it is a notification,
+/// which gets no response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcRequest {
+    /// Always `2.0`.
+    pub jsonrpc: String,
+    /// `resolve`, `list_names`, `get_proof` or `get_status`.
+    pub method: String,
+    /// By name, like the query of the matching HTTP endpoint, or by position.
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub params: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+}
+
+/// The answer to an `RpcRequest` with the same `id`: either a `result` or an `error`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    /// Null if the request's id could not be read.
+    pub id: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcError {
+    /// A JSON-RPC 2.0 error code, or for errors of the method, the HTTP status the matching
+    /// endpoint would have answered with, e.g. 404 for a name that is not registered.
+    pub code: i64,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flattened_round_trip() {
+        let name = NameResponse {
+            records: HashMap::from([("IP4".into(), "127.0.0.1".into())]),
+            confirmations: 3,
+            confirmed: true,
+            status: "valid".into(),
+            anchor_spent: None,
+            inherited: false,
+            deactivated: false,
+            stale: false,
+        };
+        let json = serde_json::to_value(&name).unwrap();
+        assert_eq!(json["IP4"], "127.0.0.1");
+        assert_eq!(serde_json::from_value::<NameResponse>(json).unwrap(), name);
+
+        let fees = FeesResponse {
+            days: 7,
+            interval: FeeInterval::Day,
+            overall: FeeStats::new(vec![1.0, 2.0]),
+            periods: vec![FeePeriod {
+                start: 0,
+                stats: FeeStats::new(vec![1.0, 2.0]),
+            }],
+        };
+        let json = serde_json::to_value(&fees).unwrap();
+        assert_eq!(json["claims"], 2);
+        assert_eq!(json["periods"][0]["p90"], 2.0);
+        assert_eq!(serde_json::from_value::<FeesResponse>(json).unwrap(), fees);
+    }
+
+    #[test]
+    fn test_records_diff() {
+        let records = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let from = records(&[("WEB", "https://a.example"), ("LN", "a@ln"), ("MOTD", "hi")]);
+        let to = records(&[
+            ("WEB", "https://b.example"),
+            ("LN", "a@ln"),
+            ("NPUB", "npub1"),
+        ]);
+
+        let diff = RecordsDiff::between(&from, &to);
+        assert_eq!(
+            diff.added,
+            BTreeMap::from([("NPUB".into(), "npub1".into())])
+        );
+        assert_eq!(diff.removed, BTreeMap::from([("MOTD".into(), "hi".into())]));
+        assert_eq!(
+            diff.changed,
+            BTreeMap::from([(
+                "WEB".into(),
+                RecordChange {
+                    from: "https://a.example".into(),
+                    to: "https://b.example".into(),
+                }
+            )])
+        );
+        assert_eq!(RecordsDiff::between(&to, &to), RecordsDiff::default());
+    }
+}
@@ -0,0 +1,281 @@
+//! Async client of an indexer's HTTP API, with the models of [`crate::api_types`].
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), nomen_core::client::Error> {
+//! let client = nomen_core::client::Client::new("https://nomen.example");
+//! let smith = client.name("smith").await?;
+//! println!("{:?}", smith.records.get("IP4"));
+//! # Ok(())
+//! # }
+//! ```
+
+use core::fmt::{self, Display};
+
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::api_types::*;
+
+/// Header carrying an API key from `server apikey add`.
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Why a request failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The server could not be reached, answered with an error status, or sent something else
+    /// than the expected JSON.
+    Http(reqwest::Error),
+
+    /// A line of `/api/dump`, or a `/rpc` response, is not what was expected.
+    Json(serde_json::Error),
+}
+
+impl Error {
+    /// The error status the server answered with, e.g. 404 for a name that is not registered.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Http(e) => e.status(),
+            Error::Json(_) => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => e.fmt(f),
+            Error::Json(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+/// A Nomen indexer at `url`, e.g. `https://nomen.example`.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl Client {
+    pub fn new(url: &str) -> Client {
+        Client::with_http(reqwest::Client::new(), url)
+    }
+
+    /// A client sending its requests with `http`, e.g. one set up with a proxy.
+    pub fn with_http(http: reqwest::Client, url: &str) -> Client {
+        Client {
+            http,
+            url: url.trim_end_matches('/').into(),
+            api_key: None,
+        }
+    }
+
+    /// Send an API key with every request, lifting rate limits and the private mode.
+    pub fn api_key(mut self, key: &str) -> Client {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    fn request(&self, builder: RequestBuilder) -> RequestBuilder {
+        match &self.api_key {
+            Some(key) => builder.header(API_KEY_HEADER, key),
+            None => builder,
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &impl Serialize,
+    ) -> Result<T, Error> {
+        let builder = self.http.get(format!("{}{path}", self.url)).query(query);
+        Ok(self
+            .request(builder)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, Error> {
+        let builder = self.http.post(format!("{}{path}", self.url)).json(body);
+        Ok(self
+            .request(builder)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// The current records of `name` (`/api/name`).
+    pub async fn name(&self, name: &str) -> Result<NameResponse, Error> {
+        self.get("/api/name", &NameQuery { name: name.into() })
+            .await
+    }
+
+    /// The records of a name at a past height or time, one of which must be set (`/api/name`).
+    pub async fn name_at(&self, query: &NameAtQuery) -> Result<NameAtResponse, Error> {
+        self.get("/api/name", query).await
+    }
+
+    /// A name with its nsid, owner and typed records (`/api/v1/name`).
+    pub async fn name_v1(&self, name: &str) -> Result<NameV1Response, Error> {
+        self.get("/api/v1/name", &NameQuery { name: name.into() })
+            .await
+    }
+
+    /// Merkle proof, OP_RETURN and records event of a name, for [`crate::verify_proof`]
+    /// (`/api/name/proof`).
+    pub async fn name_proof(&self, name: &str) -> Result<NameProofResponse, Error> {
+        self.get("/api/name/proof", &NameQuery { name: name.into() })
+            .await
+    }
+
+    /// Proof that `name` is not registered, for [`crate::verify_absence`] (`/api/name/absence`).
+    pub async fn name_absence(&self, name: &str) -> Result<NameAbsenceResponse, Error> {
+        self.get("/api/name/absence", &NameQuery { name: name.into() })
+            .await
+    }
+
+    /// Records changed between two records events of a name (`/api/name/diff`).
+    pub async fn name_diff(&self, query: &NameDiffQuery) -> Result<NameDiffResponse, Error> {
+        self.get("/api/name/diff", query).await
+    }
+
+    /// Indexed names that look like `name` (`/api/name/similar`).
+    pub async fn name_similar(&self, name: &str) -> Result<NameSimilarResponse, Error> {
+        self.get("/api/name/similar", &NameQuery { name: name.into() })
+            .await
+    }
+
+    /// A page of every indexed name, optionally filtered by `query.name` (`/api/names`).
+    pub async fn names(&self, query: &ListQuery) -> Result<ListResponse<NameItem>, Error> {
+        self.get("/api/names", query).await
+    }
+
+    /// A page of the names directly below `query.name` (`/api/children`).
+    pub async fn children(&self, query: &ListQuery) -> Result<ListResponse<NameItem>, Error> {
+        self.get("/api/children", query).await
+    }
+
+    /// A page of the names containing `query.name` (`/api/search`).
+    pub async fn search(&self, query: &ListQuery) -> Result<ListResponse<NameItem>, Error> {
+        self.get("/api/search", query).await
+    }
+
+    /// Every indexed name with its records (`/api/dump`). The whole dump is read before it
+    /// is returned.
+    pub async fn dump(&self) -> Result<Vec<ExportedName>, Error> {
+        let builder = self.http.get(format!("{}/api/dump", self.url));
+        let body = self
+            .request(builder)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        body.lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    /// Open offers to sell a name, optionally only those for `name` (`/api/offers`).
+    pub async fn offers(&self, name: Option<&str>) -> Result<Vec<OpenOffer>, Error> {
+        let query = OffersQuery {
+            name: name.map(Into::into),
+        };
+        self.get("/api/offers", &query).await
+    }
+
+    /// A claim by nsid, hex or bech32, with where its events came from (`/api/nsid/:nsid`).
+    pub async fn nsid(&self, nsid: &str) -> Result<NsidResponse, Error> {
+        self.get(&format!("/api/nsid/{nsid}"), &()).await
+    }
+
+    /// The signed records event of a claim by nsid (`/api/nsid/:nsid/raw`).
+    pub async fn nsid_raw(&self, nsid: &str) -> Result<serde_json::Value, Error> {
+        self.get(&format!("/api/nsid/{nsid}/raw"), &()).await
+    }
+
+    /// Fee rate percentiles of recent name claims (`/api/stats/fees`).
+    pub async fn fees(&self, query: &FeesQuery) -> Result<FeesResponse, Error> {
+        self.get("/api/stats/fees", query).await
+    }
+
+    /// Indexed events supplied by each relay (`/api/stats/relays`).
+    pub async fn relay_stats(&self) -> Result<Vec<RelayContribution>, Error> {
+        self.get("/api/stats/relays", &()).await
+    }
+
+    /// A page of raw `blockchain` rows (`/api/mirror/blockchain`).
+    pub async fn mirror_blockchain(
+        &self,
+        query: &MirrorQuery,
+    ) -> Result<MirrorBlockchainResponse, Error> {
+        self.get("/api/mirror/blockchain", query).await
+    }
+
+    /// A page of raw name or transfer events, by `query.kind` (`/api/mirror/events`).
+    pub async fn mirror_events(&self, query: &MirrorQuery) -> Result<MirrorEventsResponse, Error> {
+        self.get("/api/mirror/events", query).await
+    }
+
+    /// Relay a signed name or transfer event, as Nostr JSON (`/api/event`).
+    pub async fn send_event(&self, event: &serde_json::Value) -> Result<EventResponse, Error> {
+        self.post("/api/event", event).await
+    }
+
+    /// Broadcast a signed transaction with a Nomen output, hex encoded (`/api/tx`).
+    pub async fn send_tx(&self, tx: &str) -> Result<TxResponse, Error> {
+        self.post("/api/tx", &TxRequest { tx: tx.into() }).await
+    }
+
+    /// Make JSON-RPC 2.0 calls in one batch (`/rpc`). Calls without an id are notifications,
+    /// which get no response.
+    pub async fn rpc(&self, calls: &[RpcRequest]) -> Result<Vec<RpcResponse>, Error> {
+        let builder = self.http.post(format!("{}/rpc", self.url)).json(calls);
+        let body = self
+            .request(builder)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        if body.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Requests made with the client's API key (`/api/usage`).
+    pub async fn usage(&self) -> Result<UsageResponse, Error> {
+        self.get("/api/usage", &()).await
+    }
+
+    /// The key the server signs responses with (`/.well-known/nomen.json`).
+    pub async fn well_known(&self) -> Result<WellKnown, Error> {
+        self.get("/.well-known/nomen.json", &()).await
+    }
+}
@@ -0,0 +1,31 @@
+use alloc::string::String;
+use core::fmt::{self, Display};
+
+/// Why a name, OP_RETURN or event failed verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The name breaks the protocol grammar.
+    Name(String),
+
+    /// The OP_RETURN payload is not a Nomen output.
+    OpReturn(String),
+
+    /// The event is malformed, badly signed, or its nsid does not match.
+    Event(String),
+
+    /// The absence proof is malformed.
+    Proof(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Name(msg) | Error::OpReturn(msg) | Error::Event(msg) | Error::Proof(msg) => {
+                f.write_str(msg)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
@@ -0,0 +1,280 @@
+use alloc::{string::String, vec::Vec};
+
+use secp256k1::{schnorr::Signature, Message, Secp256k1, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{derive_nsid, fingerprint, Error, Grammar, NomenKind, OpReturn};
+
+/// Kind of a name event, which sets a name's records.
+pub const NAME_KIND: u64 = 38300;
+
+/// Kind of a transfer event, which names the new owner of a name.
+pub const TRANSFER_KIND: u64 = 38301;
+
+/// A signed Nostr event, as in NIP-01.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    pub id: String,
+    pub pubkey: String,
+    pub created_at: u64,
+    pub kind: u64,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+    pub sig: String,
+}
+
+impl Event {
+    pub fn from_json(json: &str) -> Result<Event, Error> {
+        serde_json::from_str(json).map_err(|e| Error::Event(alloc::format!("Invalid event: {e}")))
+    }
+
+    /// SHA-256 of the NIP-01 serialization of the event.
+    pub fn compute_id(&self) -> Result<[u8; 32], Error> {
+        let serialized = serde_json::to_string(&(
+            0,
+            &self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            &self.content,
+        ))
+        .map_err(|_| event_error("Event could not be serialized"))?;
+        Ok(Sha256::digest(serialized.as_bytes()).into())
+    }
+
+    /// Check the id is the hash of the event, and the signature is the pubkey's signature of the id.
+    pub fn verify(&self) -> Result<(), Error> {
+        let id = self.compute_id()?;
+        if hex::encode(id) != self.id {
+            return Err(event_error("Event id does not match its contents"));
+        }
+
+        let pubkey = x_only_key(&self.pubkey)?;
+        let sig = hex::decode(&self.sig)
+            .ok()
+            .and_then(|sig| Signature::from_slice(&sig).ok())
+            .ok_or_else(|| event_error("Invalid event signature"))?;
+        let message = Message::from_slice(&id).map_err(|_| event_error("Invalid event id"))?;
+        Secp256k1::verification_only()
+            .verify_schnorr(&sig, &message, &pubkey)
+            .map_err(|_| event_error("Invalid event signature"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    Name,
+    Transfer,
+}
+
+impl TryFrom<u64> for NameKind {
+    type Error = Error;
+
+    fn try_from(kind: u64) -> Result<Self, Self::Error> {
+        match kind {
+            NAME_KIND => Ok(NameKind::Name),
+            TRANSFER_KIND => Ok(NameKind::Transfer),
+            _ => Err(event_error("Invalid Event kind")),
+        }
+    }
+}
+
+/// The Nomen fields of a name or transfer event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameEvent {
+    pub kind: NameKind,
+    pub name: String,
+    pub fingerprint: [u8; 5],
+    /// The nsid in the event's `d` tag.
+    pub nsid: [u8; 20],
+    /// The nsid derived from the name and its owner: the signer of a name event, or the new
+    /// owner in the content of a transfer event.
+    pub calculated_nsid: [u8; 20],
+    pub pubkey: [u8; 32],
+}
+
+impl NameEvent {
+    /// Read the `d` and `nom` tags and derive the nsid. The name may be a subname, see
+    /// [`Grammar::SUBNAME`].
+    /// This neither checks the signature nor compares the nsids, see [`verify_name_event`].
+    pub fn from_parts(
+        kind: u64,
+        pubkey: &[u8; 32],
+        tags: &[Vec<String>],
+        content: &str,
+    ) -> Result<NameEvent, Error> {
+        let kind = NameKind::try_from(kind)?;
+        let nsid = tag_value(tags, "d").ok_or_else(|| event_error("Missing 'd' tag"))?;
+        let nsid = decode_hex::<20>(nsid).ok_or_else(|| event_error("Invalid 'd' tag"))?;
+        let name =
+            tag_value(tags, "nom").ok_or_else(|| event_error("Missing or invalid 'nom' tag"))?;
+        Grammar::SUBNAME.validate(name)?;
+
+        let owner = match kind {
+            NameKind::Name => *pubkey,
+            NameKind::Transfer => x_only_key(content)?.serialize(),
+        };
+
+        Ok(NameEvent {
+            kind,
+            name: name.into(),
+            fingerprint: fingerprint(name),
+            nsid,
+            calculated_nsid: derive_nsid(name, &owner),
+            pubkey: *pubkey,
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.nsid != self.calculated_nsid {
+            return Err(event_error("Invalid nsid"));
+        }
+        Ok(())
+    }
+}
+
+/// Parse a name or transfer event, and check its id, signature and nsid.
+pub fn verify_name_event(json: &str) -> Result<NameEvent, Error> {
+    let event = Event::from_json(json)?;
+    event.verify()?;
+    let pubkey = decode_hex::<32>(&event.pubkey).ok_or_else(|| event_error("Invalid pubkey"))?;
+    let name_event = NameEvent::from_parts(event.kind, &pubkey, &event.tags, &event.content)?;
+    name_event.validate()?;
+    Ok(name_event)
+}
+
+/// Whether the OP_RETURN `op_return` claims the name of `event`, which must be a valid name or
+/// transfer event: same kind, fingerprint, and nsid.
+pub fn verify_proof(op_return: &[u8], event: &str) -> Result<bool, Error> {
+    let op_return = OpReturn::parse(op_return)?;
+    let event = verify_name_event(event)?;
+    let kind_matches = matches!(
+        (op_return.kind, event.kind),
+        (NomenKind::Create, NameKind::Name) | (NomenKind::Transfer, NameKind::Transfer)
+    );
+    Ok(kind_matches && op_return.fingerprint == event.fingerprint && op_return.nsid == event.nsid)
+}
+
+/// Commitment to records event `content`, for an OP_RETURN: its SHA-256 hash.
+pub fn records_commitment(content: &str) -> [u8; 32] {
+    Sha256::digest(content.as_bytes()).into()
+}
+
+/// The records of `name` for its owner, whose nsid is known from the chain: the content of the
+/// newest valid name event in `events` for that name and nsid. Other events are skipped.
+pub fn resolve_records(name: &str, nsid: &[u8; 20], events: &[Event]) -> Option<String> {
+    events
+        .iter()
+        .filter(|event| {
+            event.verify().is_ok()
+                && decode_hex::<32>(&event.pubkey)
+                    .and_then(|pk| {
+                        NameEvent::from_parts(event.kind, &pk, &event.tags, &event.content).ok()
+                    })
+                    .filter(|ne| ne.kind == NameKind::Name && ne.validate().is_ok())
+                    .is_some_and(|ne| ne.name == name && &ne.nsid == nsid)
+        })
+        .max_by_key(|event| event.created_at)
+        .map(|event| event.content.clone())
+}
+
+/// Hex encoded nsid of `name` owned by the hex encoded x-only public key `pubkey`.
+pub fn derive_nsid_hex(name: &str, pubkey: &str) -> Result<String, Error> {
+    Grammar::SUBNAME.validate(name)?;
+    let pubkey = x_only_key(pubkey)?.serialize();
+    Ok(hex::encode(derive_nsid(name, &pubkey)))
+}
+
+/// First value of the first tag named `name`.
+fn tag_value<'a>(tags: &'a [Vec<String>], name: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|t| t.first().map(String::as_str) == Some(name))
+        .and_then(|t| t.get(1))
+        .map(String::as_str)
+}
+
+fn decode_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    let mut out = [0; N];
+    hex::decode_to_slice(s, &mut out).ok()?;
+    Some(out)
+}
+
+fn x_only_key(s: &str) -> Result<XOnlyPublicKey, Error> {
+    decode_hex::<32>(s)
+        .and_then(|pk| XOnlyPublicKey::from_slice(&pk).ok())
+        .ok_or_else(|| event_error("Invalid public key"))
+}
+
+fn event_error(msg: &str) -> Error {
+    Error::Event(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENT: &str = r#"{"id":"4fb5485ad12706f3ddbde1cdeab3199fcbef01b4c2456a7420ef5acb400d29e5","pubkey":"d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f","created_at":1682476154,"kind":38300,"tags":[["d","28d63a9a61c6c5ce6be37a830105c92cf7a8f365"],["nom","smith"]],"content":"{\"IP4\":\"127.0.0.1\",\"NPUB\":\"npub1234\"}","sig":"53a629c8169c29abc971653b71ebf8ceb185735170b702dd48377a3336819680577ef28a257b8e4db5e8101531232e1c886a35721b5af1399c32cb526fd61bb6"}"#;
+
+    #[test]
+    fn test_verify_name_event() {
+        let event = verify_name_event(EVENT).unwrap();
+        assert_eq!(event.kind, NameKind::Name);
+        assert_eq!(event.name, "smith");
+        assert_eq!(
+            hex::encode(event.nsid),
+            "28d63a9a61c6c5ce6be37a830105c92cf7a8f365"
+        );
+
+        let tampered = EVENT.replace("127.0.0.1", "127.0.0.2");
+        assert!(verify_name_event(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_resolve_records() {
+        let event = Event::from_json(EVENT).unwrap();
+        let nsid = decode_hex::<20>("28d63a9a61c6c5ce6be37a830105c92cf7a8f365").unwrap();
+        let mut forged = event.clone();
+        forged.created_at += 1;
+        forged.content = "{}".into();
+
+        let events = [forged, event.clone()];
+        assert_eq!(
+            resolve_records("smith", &nsid, &events),
+            Some(event.content.clone())
+        );
+        assert_eq!(resolve_records("smith", &[0; 20], &events), None);
+        assert_eq!(resolve_records("other", &nsid, &events), None);
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let op_return = OpReturn {
+            kind: NomenKind::Create,
+            fingerprint: fingerprint("smith"),
+            nsid: decode_hex("28d63a9a61c6c5ce6be37a830105c92cf7a8f365").unwrap(),
+            commitment: None,
+        };
+        assert!(verify_proof(&op_return.to_bytes(), EVENT).unwrap());
+
+        let transfer = OpReturn {
+            kind: NomenKind::Transfer,
+            ..op_return
+        };
+        assert!(!verify_proof(&transfer.to_bytes(), EVENT).unwrap());
+    }
+
+    /// Every event in the published test vectors passes the same checks as in the indexer.
+    #[test]
+    fn test_vector_events() {
+        let vectors: serde_json::Value =
+            serde_json::from_str(include_str!("../../docs/test-vectors.json")).unwrap();
+        for vector in vectors["vectors"].as_array().unwrap() {
+            for field in ["records_event", "transfer_event"] {
+                let json = vector[field].to_string();
+                let event = verify_name_event(&json).unwrap();
+                assert_eq!(event.name, vector["name"].as_str().unwrap());
+            }
+        }
+    }
+}
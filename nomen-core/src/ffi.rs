@@ -0,0 +1,163 @@
+//! C ABI, declared in `include/nomen.h`. Build the shared library with `make core-ffi`, which runs
+//! `cargo rustc -p nomen-core --release --features ffi --crate-type cdylib`.
+//!
+//! Strings are NUL terminated UTF-8. Strings returned by these functions are owned by the caller
+//! and must be released with [`nomen_string_free`]. On failure a function returns `NULL` (or `-1`)
+//! and [`nomen_last_error`] describes why.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+use crate::{Error, Event};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Hex encoded nsid of `name` owned by the hex encoded x-only public key `pubkey`.
+///
+/// # Safety
+///
+/// `name` and `pubkey` must be valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn nomen_derive_nsid(
+    name: *const c_char,
+    pubkey: *const c_char,
+) -> *mut c_char {
+    returning_string(|| crate::derive_nsid_hex(str_arg(name)?, str_arg(pubkey)?).map(Some))
+}
+
+/// Whether the hex encoded OP_RETURN data `op_return` claims the name of the name or transfer
+/// event `event` (JSON): `1` if it does, `0` if it does not, `-1` on error.
+///
+/// # Safety
+///
+/// `op_return` and `event` must be valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn nomen_verify_proof(op_return: *const c_char, event: *const c_char) -> i32 {
+    clear_last_error();
+    let verified = (|| {
+        let op_return =
+            hex::decode(str_arg(op_return)?).map_err(|_| Error::OpReturn("Invalid hex".into()))?;
+        crate::verify_proof(&op_return, str_arg(event)?)
+    })();
+    match verified {
+        Ok(verified) => verified as i32,
+        Err(err) => {
+            set_last_error(err);
+            -1
+        }
+    }
+}
+
+/// Records (a JSON object) of `name` for its owner, whose hex encoded nsid `nsid` is known from
+/// the chain, from the newest valid name event in `events` (a JSON array of events, as fetched
+/// from relays). `NULL` with no error set if none of the events qualify.
+///
+/// # Safety
+///
+/// `name`, `nsid` and `events` must be valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn nomen_resolve_name(
+    name: *const c_char,
+    nsid: *const c_char,
+    events: *const c_char,
+) -> *mut c_char {
+    returning_string(|| {
+        let nsid: [u8; 20] = hex::decode(str_arg(nsid)?)
+            .ok()
+            .and_then(|nsid| nsid.try_into().ok())
+            .ok_or_else(|| Error::Event("Invalid nsid".into()))?;
+        let events: Vec<Event> =
+            serde_json::from_str(str_arg(events)?).map_err(|e| Error::Event(e.to_string()))?;
+        Ok(crate::resolve_records(str_arg(name)?, &nsid, &events))
+    })
+}
+
+/// Why the last failed call on this thread failed, or `NULL`. The string is owned by the library
+/// and valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn nomen_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |err| err.as_ptr())
+    })
+}
+
+/// Release a string returned by this library.
+///
+/// # Safety
+///
+/// `s` must be `NULL` or a string returned by this library, not already released.
+#[no_mangle]
+pub unsafe extern "C" fn nomen_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Result<&'a str, Error> {
+    if s.is_null() {
+        return Err(Error::Name("Unexpected NULL argument".into()));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| Error::Name("Argument is not UTF-8".into()))
+}
+
+fn returning_string(f: impl FnOnce() -> Result<Option<String>, Error>) -> *mut c_char {
+    clear_last_error();
+    match f() {
+        Ok(Some(s)) => CString::new(s).map_or(ptr::null_mut(), CString::into_raw),
+        Ok(None) => ptr::null_mut(),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|last| last.replace(None));
+}
+
+fn set_last_error(err: Error) {
+    let message = CString::new(err.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|last| last.replace(Some(message)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take(s: *mut c_char) -> Option<String> {
+        if s.is_null() {
+            return None;
+        }
+        let owned = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+        unsafe { nomen_string_free(s) };
+        Some(owned)
+    }
+
+    #[test]
+    fn test_derive_nsid() {
+        let name = CString::new("smith").unwrap();
+        let pubkey =
+            CString::new("d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f")
+                .unwrap();
+        let nsid = take(unsafe { nomen_derive_nsid(name.as_ptr(), pubkey.as_ptr()) });
+        assert_eq!(
+            nsid.as_deref(),
+            Some("28d63a9a61c6c5ce6be37a830105c92cf7a8f365")
+        );
+        assert!(nomen_last_error().is_null());
+
+        let invalid = CString::new("Smith").unwrap();
+        assert!(take(unsafe { nomen_derive_nsid(invalid.as_ptr(), pubkey.as_ptr()) }).is_none());
+        assert!(!nomen_last_error().is_null());
+    }
+}
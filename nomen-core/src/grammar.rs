@@ -0,0 +1,197 @@
+use alloc::format;
+
+use crate::Error;
+
+/// Rules that decide whether a string is a valid Nomen name.
+///
+/// Names are made of one or more labels joined by the label separator. Each label
+/// may only contain lowercase ASCII letters, digits, and hyphens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grammar {
+    /// Minimum length of a name, in bytes.
+    pub min_length: usize,
+
+    /// Maximum length of a name, in bytes.
+    pub max_length: usize,
+
+    /// Character used to join labels (e.g. `sub.name`).
+    pub label_separator: char,
+
+    /// Maximum number of labels in a name. Top level names only have one.
+    pub max_labels: usize,
+}
+
+/// Minimum length of a name, in bytes.
+pub const MIN_NAME_LENGTH: usize = 3;
+
+/// Maximum length of a name, in bytes.
+pub const MAX_NAME_LENGTH: usize = 256;
+
+/// Separator between the labels of a name.
+pub const LABEL_SEPARATOR: char = '.';
+
+impl Grammar {
+    /// The grammar used by the protocol for top level names.
+    pub const STANDARD: Grammar = Grammar {
+        min_length: MIN_NAME_LENGTH,
+        max_length: MAX_NAME_LENGTH,
+        label_separator: LABEL_SEPARATOR,
+        max_labels: 1,
+    };
+
+    /// The grammar of every name the protocol claims: top level names, and subnames
+    /// (`child.parent`) of any number of standard labels.
+    pub const SUBNAME: Grammar = Grammar {
+        max_labels: usize::MAX,
+        ..Grammar::STANDARD
+    };
+
+    /// Returns true if `c` may appear inside a label.
+    pub fn is_label_char(c: char) -> bool {
+        matches!(c, 'a'..='z' | '0'..='9' | '-')
+    }
+
+    pub fn labels<'a>(&self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        name.split(self.label_separator)
+    }
+
+    pub fn validate(&self, name: &str) -> Result<(), Error> {
+        if name.len() < self.min_length {
+            return Err(Error::Name(format!(
+                "Name must be at least {} characters",
+                self.min_length
+            )));
+        }
+        if name.len() > self.max_length {
+            return Err(Error::Name(format!(
+                "Name must be at most {} characters",
+                self.max_length
+            )));
+        }
+
+        let labels = self.labels(name).count();
+        if labels > self.max_labels {
+            return Err(Error::Name(format!(
+                "Name may have at most {} label(s)",
+                self.max_labels
+            )));
+        }
+
+        for label in self.labels(name) {
+            if label.is_empty() {
+                return Err(Error::Name("Name contains an empty label".into()));
+            }
+            if let Some(c) = label.chars().find(|c| !Grammar::is_label_char(*c)) {
+                return Err(Error::Name(format!("Invalid character {c:?} in name")));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Grammar {
+    fn default() -> Self {
+        Grammar::STANDARD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// A grammar with arbitrary limits, always allowing at least one label.
+    fn grammar() -> impl Strategy<Value = Grammar> {
+        (0usize..16, 0usize..64, 1usize..5).prop_map(|(min_length, max_length, max_labels)| {
+            Grammar {
+                min_length,
+                max_length,
+                max_labels,
+                ..Grammar::STANDARD
+            }
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_valid_names(
+            grammar in grammar(),
+            labels in prop::collection::vec("[a-z0-9-]{1,12}", 1..6),
+        ) {
+            let name = labels.join(".");
+            let expected = (grammar.min_length..=grammar.max_length).contains(&name.len())
+                && labels.len() <= grammar.max_labels;
+            prop_assert_eq!(grammar.validate(&name).is_ok(), expected);
+        }
+
+        #[test]
+        fn prop_invalid_chars(grammar in grammar(), name in "[a-z]{0,8}[^a-z0-9.-][a-z]{0,8}") {
+            prop_assert!(grammar.validate(&name).is_err());
+        }
+
+        #[test]
+        fn prop_empty_labels(
+            grammar in grammar(),
+            labels in prop::collection::vec("[a-z]{0,4}", 2..5),
+        ) {
+            let name = labels.join(".");
+            prop_assume!(labels.iter().any(String::is_empty));
+            prop_assert!(grammar.validate(&name).is_err());
+        }
+
+        #[test]
+        fn prop_labels(name in "[a-z0-9.-]{0,40}") {
+            let labels: Vec<&str> = Grammar::SUBNAME.labels(&name).collect();
+            prop_assert_eq!(labels.join("."), name.as_str());
+            // Anything STANDARD accepts, SUBNAME accepts too.
+            if Grammar::STANDARD.validate(&name).is_ok() {
+                prop_assert!(Grammar::SUBNAME.validate(&name).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_label_chars() {
+        for b in 0u8..=127 {
+            let c = b as char;
+            let name = format!("ab{c}");
+            let expected = c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-';
+            assert_eq!(
+                Grammar::STANDARD.validate(&name).is_ok(),
+                expected,
+                "char {c:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_length_limits() {
+        for len in 0..=MAX_NAME_LENGTH + 2 {
+            let name: String = "a".repeat(len);
+            let expected = (MIN_NAME_LENGTH..=MAX_NAME_LENGTH).contains(&len);
+            assert_eq!(
+                Grammar::STANDARD.validate(&name).is_ok(),
+                expected,
+                "len {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_labels() {
+        let grammar = Grammar {
+            max_labels: 2,
+            ..Grammar::STANDARD
+        };
+        assert!(Grammar::STANDARD.validate("sub.smith").is_err());
+        assert!(Grammar::SUBNAME.validate("a.b.smith").is_ok());
+        assert!(grammar.validate("sub.smith").is_ok());
+        assert!(grammar.validate("a.b.smith").is_err());
+        assert!(grammar.validate(".smith").is_err());
+        assert!(grammar.validate("smith.").is_err());
+    }
+}
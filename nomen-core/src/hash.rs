@@ -1,12 +1,12 @@
 use ripemd::{Digest, Ripemd160};
 use sha2::Sha256;
 
+/// `RIPEMD160(SHA256(data))`, with the data added in parts.
 #[derive(Default)]
 pub struct Hash160 {
     hasher: Sha256,
 }
 
-#[allow(unused)]
 impl Hash160 {
     pub fn update(&mut self, data: &[u8]) {
         self.hasher.update(data);
@@ -17,7 +17,6 @@ impl Hash160 {
         self
     }
 
-    #[allow(dead_code)]
     pub fn chain_optional(mut self, data: &Option<&[u8]>) -> Hash160 {
         if let Some(data) = data {
             self.update(data);
@@ -26,15 +25,12 @@ impl Hash160 {
     }
 
     pub fn finalize(self) -> [u8; 20] {
-        let f = self.hasher.finalize();
-        Ripemd160::digest(f)
-            .try_into()
-            .expect("Hash160 struct should return 20 bytes")
+        Ripemd160::digest(self.hasher.finalize()).into()
     }
 
     pub fn fingerprint(self) -> [u8; 5] {
         let h = self.finalize();
-        h[..5].try_into().unwrap()
+        [h[0], h[1], h[2], h[3], h[4]]
     }
 
     pub fn digest(data: &[u8]) -> [u8; 20] {
@@ -48,9 +44,20 @@ impl Hash160 {
     }
 }
 
+/// The first 5 bytes of the hash160 of a name, which claims it on chain.
+pub fn fingerprint(name: &str) -> [u8; 5] {
+    Hash160::default()
+        .chain_update(name.as_bytes())
+        .fingerprint()
+}
+
+/// The nsid of `name` owned by the x-only public key `pubkey`: the hash160 of the name and the key.
+pub fn derive_nsid(name: &str, pubkey: &[u8; 32]) -> [u8; 20] {
+    Hash160::digest_slices(&[name.as_bytes(), pubkey])
+}
+
 #[cfg(test)]
 mod tests {
-
     use super::*;
 
     #[test]
@@ -63,10 +70,7 @@ mod tests {
 
     #[test]
     fn test_fingerprint() {
-        let mut h = Hash160::default();
-        h.update(b"hello");
-        let d = hex::encode(h.fingerprint());
-        assert_eq!(d, "b6a9c8c230");
+        assert_eq!(hex::encode(fingerprint("hello")), "b6a9c8c230");
     }
 
     #[test]
@@ -82,4 +86,18 @@ mod tests {
         let hashed = hex::encode(Hash160::digest_slices(&[b"hello", b"world"]));
         assert_eq!(hashed, "b36c87f1c6d9182eb826d7d987f9081adf15b772");
     }
+
+    #[test]
+    fn test_derive_nsid() {
+        let mut pk = [0; 32];
+        hex::decode_to_slice(
+            "d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f",
+            &mut pk,
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(derive_nsid("smith", &pk)),
+            "28d63a9a61c6c5ce6be37a830105c92cf7a8f365"
+        );
+    }
 }
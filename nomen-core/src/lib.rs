@@ -0,0 +1,35 @@
+//! The parts of the Nomen protocol needed to verify names, shared by the indexer and by clients
+//! that check name proofs themselves, such as browser wallets.
+//!
+//! The crate is `no_std` (with `alloc`) when built without the default `std` feature. The `wasm`
+//! feature adds JavaScript bindings, see [`wasm`], and the `ffi` feature a C ABI for native
+//! callers such as mobile wallets, see [`ffi`].
+//!
+//! With `std`, [`api_types`] has the requests and responses of an indexer's HTTP API, and the
+//! `client` feature adds an async client of it, see [`client`].
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod absence;
+#[cfg(feature = "std")]
+pub mod api_types;
+#[cfg(feature = "client")]
+pub mod client;
+mod error;
+mod event;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod grammar;
+mod hash;
+mod op_return;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use absence::*;
+pub use error::*;
+pub use event::*;
+pub use grammar::*;
+pub use hash::*;
+pub use op_return::*;
@@ -0,0 +1,143 @@
+use alloc::{string::String, vec::Vec};
+use core::{fmt::Display, str::FromStr};
+
+use crate::Error;
+
+/// Prefix of every Nomen OP_RETURN payload, followed by the protocol version.
+pub const OP_RETURN_PREFIX: &[u8; 4] = b"NOM\x00";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NomenKind {
+    Create,
+    Transfer,
+}
+
+impl Display for NomenKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let s = match self {
+            NomenKind::Create => "create",
+            NomenKind::Transfer => "transfer",
+        };
+        f.write_str(s)
+    }
+}
+
+impl From<NomenKind> for u8 {
+    fn from(value: NomenKind) -> Self {
+        match value {
+            NomenKind::Create => 0x00,
+            NomenKind::Transfer => 0x01,
+        }
+    }
+}
+
+impl FromStr for NomenKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(NomenKind::Create),
+            "transfer" => Ok(NomenKind::Transfer),
+            _ => Err(Error::OpReturn(
+                "Unrecognized Nomen transaction type".into(),
+            )),
+        }
+    }
+}
+
+/// The contents of a Nomen OP_RETURN: `NOM`, version 0, the kind, the name's fingerprint and the
+/// nsid, optionally followed by a commitment to the owner's first records event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OpReturn {
+    pub kind: NomenKind,
+    pub fingerprint: [u8; 5],
+    pub nsid: [u8; 20],
+    /// [`records_commitment`](crate::records_commitment) of the content of the first records event
+    /// the owner publishes for this claim. The indexer ignores the owner's records until it has that
+    /// event.
+    pub commitment: Option<[u8; 32]>,
+}
+
+impl OpReturn {
+    /// Parse the data pushed by an OP_RETURN output, without the `OP_RETURN <push>` opcodes.
+    pub fn parse(data: &[u8]) -> Result<OpReturn, Error> {
+        let err = |msg: &str| Error::OpReturn(String::from(msg));
+        let data = data
+            .strip_prefix(b"NOM")
+            .ok_or_else(|| err("Not an Nomen transaction"))?;
+        let data = data
+            .strip_prefix(&[0x00])
+            .ok_or_else(|| err("Unsupported Nomen version"))?;
+        let (kind, data) = match data.split_first() {
+            Some((0x00, rest)) => (NomenKind::Create, rest),
+            Some((0x01, rest)) => (NomenKind::Transfer, rest),
+            _ => return Err(err("Unexpected blockchain tx type")),
+        };
+        if data.len() != 25 && data.len() != 57 {
+            return Err(err(
+                "Nomen output must have a 5 byte fingerprint, a 20 byte nsid and an optional 32 byte commitment",
+            ));
+        }
+
+        let mut fingerprint = [0; 5];
+        fingerprint.copy_from_slice(&data[..5]);
+        let mut nsid = [0; 20];
+        nsid.copy_from_slice(&data[5..25]);
+        let commitment = (data.len() == 57).then(|| {
+            let mut commitment = [0; 32];
+            commitment.copy_from_slice(&data[25..]);
+            commitment
+        });
+        Ok(OpReturn {
+            kind,
+            fingerprint,
+            nsid,
+            commitment,
+        })
+    }
+
+    /// The payload to push after `OP_RETURN`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = Vec::with_capacity(62);
+        v.extend(OP_RETURN_PREFIX);
+        v.push(self.kind.into());
+        v.extend(self.fingerprint);
+        v.extend(self.nsid);
+        if let Some(commitment) = self.commitment {
+            v.extend(commitment);
+        }
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let op_return = OpReturn {
+            kind: NomenKind::Create,
+            fingerprint: [1, 2, 3, 4, 5],
+            nsid: [7; 20],
+            commitment: None,
+        };
+        let bytes = op_return.to_bytes();
+        assert_eq!(&bytes[..5], b"NOM\x00\x00");
+        assert_eq!(OpReturn::parse(&bytes).unwrap(), op_return);
+
+        let committed = OpReturn {
+            commitment: Some([9; 32]),
+            ..op_return
+        };
+        let committed_bytes = committed.to_bytes();
+        assert_eq!(committed_bytes.len(), 62);
+        assert_eq!(OpReturn::parse(&committed_bytes).unwrap(), committed);
+        assert!(OpReturn::parse(&committed_bytes[..committed_bytes.len() - 1]).is_err());
+
+        assert!(OpReturn::parse(b"NOM\x01\x00").is_err());
+        assert!(OpReturn::parse(b"NOZ\x00\x00").is_err());
+        assert!(OpReturn::parse(b"NOM\x00\x10").is_err());
+        assert!(OpReturn::parse(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! JavaScript bindings, built with `make core-wasm`.
+
+use alloc::string::{String, ToString};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Event, NameKind, OpReturn};
+
+/// Hex encoded nsid of `name` owned by the hex encoded x-only public key `pubkey`.
+#[wasm_bindgen(js_name = deriveNsid)]
+pub fn derive_nsid(name: &str, pubkey: &str) -> Result<String, JsError> {
+    Ok(crate::derive_nsid_hex(name, pubkey)?)
+}
+
+/// Whether `name` is a valid name or subname. Throws the reason if it is not.
+#[wasm_bindgen(js_name = validateName)]
+pub fn validate_name(name: &str) -> Result<(), JsError> {
+    Ok(crate::Grammar::SUBNAME.validate(name)?)
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct ParsedOpReturn {
+    /// `create` or `transfer`.
+    pub kind: String,
+    pub fingerprint: String,
+    pub nsid: String,
+    /// Commitment to the owner's first records event, if the claim has one.
+    pub commitment: Option<String>,
+}
+
+/// Parse the hex encoded data pushed by a Nomen OP_RETURN output.
+#[wasm_bindgen(js_name = parseOpReturn)]
+pub fn parse_op_return(data: &str) -> Result<ParsedOpReturn, JsError> {
+    let op_return = OpReturn::parse(&decode(data)?)?;
+    Ok(ParsedOpReturn {
+        kind: op_return.kind.to_string(),
+        fingerprint: hex::encode(op_return.fingerprint),
+        nsid: hex::encode(op_return.nsid),
+        commitment: op_return.commitment.map(hex::encode),
+    })
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct VerifiedNameEvent {
+    /// `name` or `transfer`.
+    pub kind: String,
+    pub name: String,
+    pub fingerprint: String,
+    pub nsid: String,
+    pub pubkey: String,
+}
+
+/// Check a name or transfer event's id, signature and nsid, as the indexer does.
+#[wasm_bindgen(js_name = verifyNameEvent)]
+pub fn verify_name_event(json: &str) -> Result<VerifiedNameEvent, JsError> {
+    let event = crate::verify_name_event(json)?;
+    Ok(VerifiedNameEvent {
+        kind: match event.kind {
+            NameKind::Name => "name",
+            NameKind::Transfer => "transfer",
+        }
+        .into(),
+        name: event.name,
+        fingerprint: hex::encode(event.fingerprint),
+        nsid: hex::encode(event.nsid),
+        pubkey: hex::encode(event.pubkey),
+    })
+}
+
+/// Whether the OP_RETURN data `op_return` (hex) claims the name of the event `event` (JSON).
+#[wasm_bindgen(js_name = verifyProof)]
+pub fn verify_proof(op_return: &str, event: &str) -> Result<bool, JsError> {
+    Ok(crate::verify_proof(&decode(op_return)?, event)?)
+}
+
+/// Whether the absence proof `proof` (JSON, as from `/api/name/absence`) shows its name is not
+/// among the `count` names of a checkpoint with root `root` (hex).
+#[wasm_bindgen(js_name = verifyAbsence)]
+pub fn verify_absence(proof: &str, root: &str, count: u64) -> Result<bool, JsError> {
+    let proof: crate::AbsenceProof =
+        serde_json::from_str(proof).map_err(|e| JsError::new(&e.to_string()))?;
+    let root: [u8; 32] = decode(root)?
+        .try_into()
+        .map_err(|_| JsError::new("Invalid root"))?;
+    Ok(crate::verify_absence(&proof, &root, count)?)
+}
+
+/// Records (JSON) of `name` for the owner of `nsid` (hex), from the newest valid name event in
+/// `events` (a JSON array). Undefined if there is none.
+#[wasm_bindgen(js_name = resolveName)]
+pub fn resolve_name(name: &str, nsid: &str, events: &str) -> Result<Option<String>, JsError> {
+    let nsid: [u8; 20] = decode(nsid)?
+        .try_into()
+        .map_err(|_| JsError::new("Invalid nsid"))?;
+    let events: alloc::vec::Vec<Event> =
+        serde_json::from_str(events).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(crate::resolve_records(name, &nsid, &events))
+}
+
+fn decode(data: &str) -> Result<alloc::vec::Vec<u8>, JsError> {
+    hex::decode(data).map_err(|_| JsError::new("Invalid hex"))
+}
@@ -1,73 +1,169 @@
-use std::path::PathBuf;
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 use anyhow::anyhow;
 use bitcoin::{FeeRate, Network};
 use nostr_sdk::{
     prelude::{FromSkStr, ToBech32},
-    Options,
+    Kind, Options,
+};
+use sqlx::{
+    sqlite::{self, SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
+
+use crate::{
+    error::NomenError,
+    protocol::Grammar,
+    rpc::{Rpc, SocksTransport},
+    util::{Handler, RecordLimits},
 };
-use sqlx::{sqlite, SqlitePool};
 
 use super::{
-    Cli, ConfigFile, NameNewSubcommand, NameTransferSubcommand, ServerSubcommand, Subcommand,
+    route_kind, BindAddr, Binds, BrandingConfig, Cli, ConfigFile, DmConfig, LinksConfig,
+    LoggingConfig, NameNewSubcommand, PrivacyMode, QueueConfig, ServerSubcommand, Subcommand,
+    ThemeMode, TrustedProxy,
 };
 
+/// `data` value that keeps the index in memory.
+pub const MEMORY_DATA: &str = ":memory:";
+
+/// Source of the per-[`Config`] in-memory database names.
+static MEMORY_DATABASES: AtomicU64 = AtomicU64::new(0);
+
+/// Whether relays are asked for events or sent them, for [`Config::relays_for`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayUse {
+    Read,
+    Write,
+}
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub cli: Cli,
     pub file: ConfigFile,
+    /// Names this config's in-memory database; clones share it, other configs don't.
+    memory: u64,
 }
 
 impl Config {
     pub fn new(cli: Cli, file: ConfigFile) -> Self {
-        Self { cli, file }
+        Self {
+            cli,
+            file,
+            memory: MEMORY_DATABASES.fetch_add(1, Ordering::Relaxed),
+        }
     }
 
-    pub fn rpc_auth(&self) -> bitcoincore_rpc::Auth {
-        if let Some(cookie) = &self.rpc_cookie() {
+    /// This config with `file` swapped in, still using the same in-memory database.
+    pub fn with_file(&self, file: ConfigFile) -> Self {
+        Self {
+            file,
+            ..self.clone()
+        }
+    }
+
+    /// Location of the config file, whether or not it exists.
+    pub fn config_file(cli: &Cli) -> PathBuf {
+        cli.config
+            .clone()
+            .unwrap_or_else(super::paths::default_config_file)
+    }
+
+    pub fn rpc_auth(&self) -> anyhow::Result<bitcoincore_rpc::Auth> {
+        let auth = if let Some(cookie) = &self.rpc_cookie() {
             bitcoincore_rpc::Auth::CookieFile(cookie.clone())
         } else if self.rpc_user().is_some() || self.rpc_password().is_some() {
             bitcoincore_rpc::Auth::UserPass(
-                self.rpc_user().expect("RPC user not configured"),
-                self.rpc_password().expect("RPC password not configured"),
+                self.rpc_user()
+                    .ok_or_else(|| NomenError::Config("RPC user not configured".into()))?,
+                self.rpc_password()
+                    .ok_or_else(|| NomenError::Config("RPC password not configured".into()))?,
             )
         } else {
             bitcoincore_rpc::Auth::None
-        }
+        };
+        Ok(auth)
+    }
+
+    /// The node's RPC client, for async code.
+    pub fn rpc(&self) -> anyhow::Result<Rpc> {
+        Ok(Rpc::new(self.rpc_client()?))
     }
 
-    pub fn rpc_client(&self) -> anyhow::Result<bitcoincore_rpc::Client> {
+    fn rpc_client(&self) -> anyhow::Result<bitcoincore_rpc::Client> {
         let host = self.rpc_host();
-        let port = self.rpc_port();
-        let url = format!("{host}:{port}");
-        let auth = self.rpc_auth();
-        Ok(bitcoincore_rpc::Client::new(&url, auth)?)
+        let port = self.rpc_port()?;
+        let auth = self.rpc_auth()?;
+        match self.network_proxy().filter(|_| !is_loopback(&host)) {
+            Some(proxy) => {
+                let host = host.trim_start_matches("http://");
+                let transport = SocksTransport::new(proxy, host, port, auth)?;
+                Ok(bitcoincore_rpc::Client::from_jsonrpc(
+                    bitcoincore_rpc::jsonrpc::Client::with_transport(transport),
+                ))
+            }
+            None => {
+                let url = format!("{host}:{port}");
+                Ok(bitcoincore_rpc::Client::new(&url, auth)?)
+            }
+        }
     }
 
-    pub async fn sqlite(&self) -> anyhow::Result<sqlite::SqlitePool> {
-        let db = self.data();
+    /// An HTTP client for webhooks and other indexers, through `network.proxy` if it is set.
+    pub fn http_client(&self) -> anyhow::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = self.network_proxy() {
+            // `socks5h` lets the proxy resolve host names, which `.onion` addresses need.
+            builder = builder.proxy(reqwest::Proxy::all(format!("socks5h://{proxy}"))?);
+        }
+        Ok(builder.build()?)
+    }
 
-        // SQLx doesn't seem to like it if a db file does not already exist, so let's create an empty one
-        if !tokio::fs::try_exists(&db).await? {
-            tokio::fs::OpenOptions::new()
-                .write(true)
-                .create(true)
-                .open(&db)
-                .await?;
+    pub async fn sqlite(&self) -> anyhow::Result<sqlite::SqlitePool> {
+        if self.data_in_memory() {
+            // Every pool from this config opens the same named, shared cache database. SQLite drops
+            // it when the last connection closes, so keep one open for as long as the pool lives.
+            let options = SqliteConnectOptions::from_str(&format!(
+                "sqlite:file:nomen-memory-{}-{}?mode=memory&cache=shared",
+                std::process::id(),
+                self.memory
+            ))?;
+            return Ok(SqlitePoolOptions::new()
+                .min_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+                .connect_with(options)
+                .await?);
         }
 
-        Ok(SqlitePool::connect(&format!("sqlite:{}", db.to_string_lossy())).await?)
+        let db = self.data();
+        if let Some(parent) = db.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let options = SqliteConnectOptions::new()
+            .filename(&db)
+            .create_if_missing(true);
+        Ok(SqlitePool::connect_with(options).await?)
     }
 
+    /// A client connected to the relays routed for `kinds` and `usage`, see [`Config::relays_for`].
     pub async fn nostr_client(
         &self,
         sk: &str,
+        kinds: &[Kind],
+        usage: RelayUse,
     ) -> anyhow::Result<(nostr_sdk::Keys, nostr_sdk::Client)> {
         let keys = nostr_sdk::Keys::from_sk_str(sk)?;
         let client = nostr_sdk::Client::with_opts(&keys, Options::new().wait_for_send(true));
-        let relays = self.relays();
+        let relays = self.relays_for(kinds, usage);
         for relay in relays {
-            client.add_relay(relay, None).await?;
+            client.add_relay(relay, self.network_proxy()).await?;
         }
         client.connect().await;
         Ok((keys, client))
@@ -75,10 +171,12 @@ impl Config {
 
     pub async fn nostr_random_client(
         &self,
+        kinds: &[Kind],
+        usage: RelayUse,
     ) -> anyhow::Result<(nostr_sdk::Keys, nostr_sdk::Client)> {
         let keys = nostr_sdk::Keys::generate();
         let sk = keys.secret_key()?.to_bech32()?;
-        self.nostr_client(&sk).await
+        self.nostr_client(&sk, kinds, usage).await
     }
 
     pub fn starting_block_height(&self) -> usize {
@@ -113,11 +211,12 @@ impl Config {
             .cloned()
     }
 
-    fn rpc_port(&self) -> u16 {
-        self.cli
+    fn rpc_port(&self) -> anyhow::Result<u16> {
+        Ok(self
+            .cli
             .rpcport
             .or(self.file.rpc.port)
-            .expect("RPC port required")
+            .ok_or_else(|| NomenError::Config("RPC port required".into()))?)
     }
 
     fn rpc_host(&self) -> String {
@@ -129,13 +228,18 @@ impl Config {
             .unwrap_or_else(|| "127.0.0.1".to_string())
     }
 
-    fn data(&self) -> PathBuf {
+    pub fn data(&self) -> PathBuf {
         self.cli
             .data
             .as_ref()
             .or(self.file.data.as_ref())
             .cloned()
-            .unwrap_or_else(|| "nomen.db".into())
+            .unwrap_or_else(super::paths::default_data_file)
+    }
+
+    /// Whether the index is kept in memory (`--data :memory:`), instead of a file.
+    pub fn data_in_memory(&self) -> bool {
+        self.data() == Path::new(MEMORY_DATA)
     }
 
     pub fn relays(&self) -> Vec<String> {
@@ -154,6 +258,61 @@ impl Config {
             })
     }
 
+    /// Relays to fetch (`Read`) or publish (`Write`) events of `kinds` with, following
+    /// `[nostr.routes]`: each kind's own list, else the `default` route's, else [`Config::relays`],
+    /// without the relays either route denies. With several kinds, the relays of all of them.
+    pub fn relays_for(&self, kinds: &[Kind], usage: RelayUse) -> Vec<String> {
+        let routes = self.file.nostr.routes.clone().unwrap_or_default();
+        let default = routes.get("default");
+        let kinds: Vec<Option<u64>> = match kinds {
+            [] => vec![None],
+            kinds => kinds.iter().map(|k| Some(k.as_u64())).collect(),
+        };
+
+        let mut relays: Vec<String> = vec![];
+        for kind in kinds {
+            let route = routes
+                .iter()
+                .find(|(key, _)| kind.is_some() && route_kind(key) == kind)
+                .map(|(_, route)| route);
+            let listed = [route, default]
+                .into_iter()
+                .flatten()
+                .find_map(|route| match usage {
+                    RelayUse::Read => route.read.clone(),
+                    RelayUse::Write => route.write.clone(),
+                })
+                .unwrap_or_else(|| self.relays());
+            let denied: Vec<&str> = [route, default]
+                .into_iter()
+                .flatten()
+                .flat_map(|route| route.deny.iter().flatten())
+                .map(|relay| relay.trim_end_matches('/'))
+                .collect();
+            for relay in listed {
+                let url = relay.trim_end_matches('/');
+                if !denied.contains(&url) && !relays.iter().any(|r| r.trim_end_matches('/') == url)
+                {
+                    relays.push(relay);
+                }
+            }
+        }
+        relays
+    }
+
+    /// Every relay in `nostr.relays` and `[nostr.routes]`, for health checks.
+    pub fn all_relays(&self) -> Vec<String> {
+        let mut relays = self.relays();
+        for route in self.file.nostr.routes.iter().flat_map(|r| r.values()) {
+            for relay in route.read.iter().chain(route.write.iter()).flatten() {
+                if !relays.contains(relay) {
+                    relays.push(relay.clone());
+                }
+            }
+        }
+        relays
+    }
+
     pub fn network(&self) -> Network {
         self.cli
             .network
@@ -161,12 +320,31 @@ impl Config {
             .unwrap_or(Network::Bitcoin)
     }
 
-    pub fn server_bind(&self) -> Option<String> {
-        match &self.cli.subcommand {
-            Subcommand::Server(ServerSubcommand { bind, .. }) => bind.clone(),
-            _ => None,
-        }
-        .or_else(|| self.file.server.bind.clone())
+    /// Addresses the server listens on, from `--bind` or else `server.bind`.
+    pub fn server_bind(&self) -> anyhow::Result<Vec<BindAddr>> {
+        let binds = match &self.cli.subcommand {
+            Subcommand::Server(ServerSubcommand { bind, .. }) if !bind.is_empty() => bind.clone(),
+            _ => self
+                .file
+                .server
+                .bind
+                .as_ref()
+                .map(Binds::to_vec)
+                .unwrap_or_default(),
+        };
+        binds.iter().map(|bind| bind.parse()).collect()
+    }
+
+    /// Serve the index as it is, from `--without-indexer` or `server.without_indexer`, without
+    /// asking a node for anything but proofs and broadcasts.
+    pub fn server_without_indexer(&self) -> bool {
+        matches!(
+            &self.cli.subcommand,
+            Subcommand::Server(ServerSubcommand {
+                without_indexer: true,
+                ..
+            })
+        ) || self.file.server.without_indexer.unwrap_or(false)
     }
 
     pub fn server_indexer_delay(&self) -> u64 {
@@ -181,4 +359,283 @@ impl Config {
     pub fn confirmations(&self) -> anyhow::Result<usize> {
         Ok(self.file.server.confirmations.unwrap_or(3))
     }
+
+    /// Requests per minute, per IP address, for API endpoints that broadcast data.
+    pub fn server_rate_limit(&self) -> u32 {
+        self.file.server.rate_limit.unwrap_or(10)
+    }
+
+    /// Seconds a record event's timestamp may be in the future, or before its name's anchor block.
+    pub fn indexer_max_time_skew(&self) -> i64 {
+        self.file.indexer.max_time_skew.unwrap_or(86400) as i64
+    }
+
+    /// Rules for names, from `[protocol]`. Names must pass [`Grammar::SUBNAME`] as well, so these
+    /// can only make it stricter.
+    pub fn grammar(&self) -> Grammar {
+        let default = Grammar::SUBNAME;
+        let protocol = &self.file.protocol;
+        Grammar {
+            min_length: protocol.min_name_length.unwrap_or(default.min_length),
+            max_length: protocol.max_name_length.unwrap_or(default.max_length),
+            max_labels: protocol.max_labels.unwrap_or(default.max_labels),
+            ..default
+        }
+    }
+
+    /// Limits on record events, from `[protocol]`.
+    pub fn record_limits(&self) -> RecordLimits {
+        let default = RecordLimits::default();
+        let protocol = &self.file.protocol;
+        RecordLimits {
+            max_size: protocol.max_records_size.unwrap_or(default.max_size),
+            max_records: protocol.max_records.unwrap_or(default.max_records),
+            max_value: protocol.max_record_value.unwrap_or(default.max_value),
+        }
+    }
+
+    pub fn nostr_read_quorum(&self) -> usize {
+        self.file.nostr.read_quorum.unwrap_or(1).max(1)
+    }
+
+    pub fn protocol_subname_approval(&self) -> bool {
+        self.file.protocol.subname_approval.unwrap_or(false)
+    }
+
+    pub fn indexer_sync_overlap(&self) -> i64 {
+        self.file.indexer.sync_overlap.unwrap_or(600) as i64
+    }
+
+    pub fn indexer_seen_events_retention(&self) -> i64 {
+        self.file.indexer.seen_events_retention.unwrap_or(604800) as i64
+    }
+
+    pub fn indexer_block_cache(&self) -> Option<PathBuf> {
+        self.file.indexer.block_cache.clone()
+    }
+
+    pub fn indexer_block_cache_max_size(&self) -> u64 {
+        self.file
+            .indexer
+            .block_cache_max_size
+            .unwrap_or(1024 * 1024 * 1024)
+    }
+
+    /// Show a page linking to a name's `WEB` record from `/n/<name>`, instead of redirecting straight to it.
+    /// Proxies whose `X-Forwarded-For` header is taken as the client's address.
+    pub fn server_trusted_proxies(&self) -> anyhow::Result<Vec<TrustedProxy>> {
+        self.file
+            .server
+            .trusted_proxies
+            .iter()
+            .flatten()
+            .map(|proxy| {
+                proxy.parse().map_err(|e| {
+                    anyhow!(NomenError::Config(format!("server.trusted_proxies: {e}")))
+                })
+            })
+            .collect()
+    }
+
+    pub fn server_redirect_interstitial(&self) -> bool {
+        self.file.server.redirect_interstitial.unwrap_or(false)
+    }
+
+    pub fn server_inherit_records(&self) -> Vec<String> {
+        self.file.server.inherit_records.clone().unwrap_or_default()
+    }
+
+    pub fn server_max_index_lag(&self) -> u64 {
+        self.file.server.max_index_lag.unwrap_or(6)
+    }
+
+    pub fn server_reject_stale(&self) -> bool {
+        self.file.server.reject_stale.unwrap_or(false)
+    }
+
+    pub fn server_compression(&self) -> bool {
+        self.file.server.compression.unwrap_or(true)
+    }
+
+    pub fn server_max_response_size(&self) -> usize {
+        self.file.server.max_response_size.unwrap_or(1024 * 1024)
+    }
+
+    pub fn wallet_descriptor(&self) -> Option<String> {
+        self.file.wallet.descriptor.clone()
+    }
+
+    /// Keys API responses are signed with, if `server.signing_key` is set.
+    pub fn server_signing_keys(&self) -> anyhow::Result<Option<nostr_sdk::Keys>> {
+        self.file
+            .server
+            .signing_key
+            .as_deref()
+            .map(|sk| {
+                nostr_sdk::Keys::from_sk_str(sk).map_err(|_| {
+                    anyhow!(NomenError::Config(
+                        "server.signing_key is not a valid private key".into()
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    /// Block explorer link templates for the current network, if there are any.
+    pub fn server_links(&self) -> Option<LinksConfig> {
+        let links = self
+            .file
+            .server
+            .links
+            .clone()
+            .unwrap_or_else(LinksConfig::init);
+        links.get(&self.network().to_string()).cloned()
+    }
+
+    pub fn server_tx_link(&self, txid: &str) -> Option<String> {
+        self.server_links()?.tx.map(|t| t.replace("{txid}", txid))
+    }
+
+    pub fn server_block_link(&self, blockhash: &str) -> Option<String> {
+        self.server_links()?
+            .block
+            .map(|t| t.replace("{blockhash}", blockhash))
+    }
+
+    pub fn server_records_script(&self) -> Option<PathBuf> {
+        self.file
+            .server
+            .script
+            .as_ref()
+            .and_then(|s| s.records.clone())
+    }
+
+    pub fn server_branding(&self) -> BrandingConfig {
+        self.file.server.branding.clone().unwrap_or_default()
+    }
+
+    /// The NIP-89 handler announcing this server, if `server.announce.url` is set.
+    pub fn server_handler(&self) -> Option<Handler> {
+        let announce = self.file.server.announce.as_ref()?;
+        Some(Handler {
+            name: announce
+                .name
+                .clone()
+                .or_else(|| self.server_branding().title)
+                .unwrap_or_else(|| "Nomen indexer".into()),
+            about: announce.about.clone(),
+            url: announce.url.clone()?,
+        })
+    }
+
+    pub fn server_announce_on_start(&self) -> bool {
+        self.file
+            .server
+            .announce
+            .as_ref()
+            .and_then(|a| a.on_start)
+            .unwrap_or(false)
+    }
+
+    pub fn server_script_timeout(&self) -> Duration {
+        let ms = self
+            .file
+            .server
+            .script
+            .as_ref()
+            .and_then(|s| s.timeout_ms)
+            .unwrap_or(50);
+        Duration::from_millis(ms)
+    }
+
+    fn server_logging(&self) -> Option<&LoggingConfig> {
+        self.file.server.logging.as_ref()
+    }
+
+    pub fn server_trace_requests(&self) -> bool {
+        self.server_logging().and_then(|l| l.trace).unwrap_or(false)
+    }
+
+    pub fn server_access_log(&self) -> Option<PathBuf> {
+        self.server_logging().and_then(|l| l.access_log.clone())
+    }
+
+    pub fn server_access_log_max_size(&self) -> u64 {
+        self.server_logging()
+            .and_then(|l| l.max_size)
+            .unwrap_or(10 * 1024 * 1024)
+    }
+
+    pub fn server_access_log_max_files(&self) -> usize {
+        self.server_logging().and_then(|l| l.max_files).unwrap_or(5)
+    }
+
+    pub fn indexer_profiles(&self) -> bool {
+        self.file.indexer.profiles.unwrap_or(false)
+    }
+
+    pub fn notifications_queue(&self) -> Option<QueueConfig> {
+        self.file.notifications.queue.clone()
+    }
+
+    pub fn notifications_stdout(&self) -> bool {
+        self.file.notifications.stdout.unwrap_or(true)
+    }
+
+    pub fn notifications_webhook(&self) -> Option<String> {
+        self.file.notifications.webhook.clone()
+    }
+
+    pub fn notifications_dm(&self) -> Option<DmConfig> {
+        self.file.notifications.dm.clone()
+    }
+
+    pub fn notifications_error_streak(&self) -> u32 {
+        self.file.notifications.error_streak.unwrap_or(3)
+    }
+
+    /// SOCKS5 proxy for relay, RPC and HTTP connections.
+    pub fn network_proxy(&self) -> Option<SocketAddr> {
+        self.file.network.proxy.as_ref().map(|p| p.address)
+    }
+
+    pub fn server_theme_mode(&self) -> ThemeMode {
+        self.file
+            .server
+            .theme
+            .as_ref()
+            .and_then(|t| t.mode)
+            .unwrap_or_default()
+    }
+
+    pub fn server_theme_accent(&self) -> String {
+        self.file
+            .server
+            .theme
+            .as_ref()
+            .and_then(|t| t.accent.clone())
+            .unwrap_or_else(|| "violet".into())
+    }
+
+    /// Whether `[server.privacy]` hides owners and transaction details from the public.
+    pub fn server_private(&self) -> bool {
+        self.file
+            .server
+            .privacy
+            .as_ref()
+            .and_then(|p| p.mode)
+            .unwrap_or_default()
+            == PrivacyMode::Private
+    }
+}
+
+/// Whether the RPC `host` is this machine, which a proxy like Tor would refuse to connect to.
+fn is_loopback(host: &str) -> bool {
+    let host = host.trim_start_matches("http://");
+    host == "localhost"
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
 }
@@ -4,7 +4,7 @@ use bitcoin::{
     address::{NetworkChecked, NetworkUnchecked},
     psbt::Psbt,
     secp256k1::SecretKey,
-    Network,
+    Address, Network,
 };
 use clap::Parser;
 use nostr_sdk::{
@@ -15,17 +15,25 @@ use secp256k1::XOnlyPublicKey;
 use serde::{Deserialize, Serialize};
 use sqlx::{sqlite, SqlitePool};
 
-use crate::util::{KeyVal, Name, NomenKind, NostrSk};
+use crate::{
+    subcommands::BunkerUri,
+    util::{DateTime, DocumentKind, KeyVal, Lifetime, Moment, Name, NomenKind, NostrPk, NostrSk},
+};
 
 use super::ConfigFile;
 
 #[derive(Parser, Debug, Clone)]
 pub struct Cli {
-    /// Location of config file: Default: nomen.toml
+    /// Location of config file. Default: ./nomen.toml if it exists, otherwise $XDG_CONFIG_HOME/nomen/nomen.toml
     #[arg(short, long)]
     pub config: Option<PathBuf>,
 
-    /// Path for index data. Default: nomen.db
+    /// Named profile from the config file (`[profiles.<name>]`) to use.
+    #[arg(short = 'P', long)]
+    pub profile: Option<String>,
+
+    /// Path for index data. Default: ./nomen.db if it exists, otherwise $XDG_DATA_HOME/nomen/nomen.db
+    /// Use `:memory:` to keep the index in memory, for tests and short-lived runs.
     #[arg(short, long)]
     pub data: Option<PathBuf>,
 
@@ -76,10 +84,39 @@ pub enum Subcommand {
     Name(Box<NameSubcommand>),
 
     /// Scan and index the blockchain.
-    Index,
+    Index(IndexSubcommand),
 
     /// Start the HTTP server
     Server(ServerSubcommand),
+
+    /// Inspect and export the index database.
+    #[command(subcommand)]
+    Db(DbSubcommand),
+
+    /// Run a local development environment against a regtest node,
+    /// with a temporary database and sample names.
+    Dev(DevSubcommand),
+
+    /// Manage the watch list. The indexer sends an alert when a watched name
+    /// changes owner or records.
+    #[command(subcommand)]
+    Watch(WatchSubcommand),
+
+    /// Events queued for the relays. Events that no relay acknowledged are retried with
+    /// exponential backoff, by the server or with `outbox flush`.
+    #[command(subcommand)]
+    Outbox(OutboxSubcommand),
+
+    /// Buy and sell names for bitcoin.
+    ///
+    /// The seller's signed transaction pays them and transfers the name in one step,
+    /// and the buyer completes it with their own inputs.
+    #[command(subcommand)]
+    Market(MarketSubcommand),
+
+    /// Run the operations of a JSON batch document in order: new names, records and transfer
+    /// handovers, all signed with the same key. Prints a summary of each operation's result.
+    Batch(BatchSubcommand),
 }
 
 impl Default for Subcommand {
@@ -95,7 +132,7 @@ pub enum UtilSubcommand {
 
     /// Initialize a new config file.
     Init {
-        /// Optional filename to write
+        /// Optional filename to write. Default: $XDG_CONFIG_HOME/nomen/nomen.toml
         file: Option<PathBuf>,
     },
 
@@ -112,21 +149,476 @@ pub enum UtilSubcommand {
     /// Useful when constructing transaction separately.
     OpReturn {
         /// The name to register
-        name: String,
+        name: Name,
 
         /// The public key of the owner
         pubkey: XOnlyPublicKey,
 
         /// Transaction kind. Possible values: create, transfer
         kind: NomenKind,
+
+        /// Hex encoded SHA-256 of the content of the owner's first records event, to commit to
+        /// in the claim. Only for `create`.
+        #[arg(long)]
+        commitment: Option<String>,
+    },
+
+    /// Check each configured relay: latency, read/write support, NIP-11 information,
+    /// and how many of the indexed name events it has stored.
+    Relays,
+
+    /// Check that the indexed records event of a name is the latest one, as agreed by
+    /// `nostr.read_quorum` of the configured relays, each asked in parallel.
+    Verify {
+        /// Name to verify
+        name: String,
+    },
+
+    /// Check the config: parse and validate every setting, connect to the Bitcoin node and
+    /// each relay, and check the database is writable. Exits with an error if any check fails.
+    CheckConfig,
+
+    /// List the Bitcoin Core wallet's UTXOs that can fund a name claim on their own: confirmed,
+    /// safe, and worth more than the claim's fee plus a dust change output. Use one as an input of
+    /// the PSBT given to `name new`.
+    ListUtxos {
+        /// Fee rate for the claim, in sat/vB. Default: the node's estimate for 6 blocks
+        #[arg(long)]
+        fee_rate: Option<f64>,
+
+        /// Confirmations a UTXO needs.
+        #[arg(long, default_value = "1")]
+        min_conf: usize,
+
+        /// Print the UTXOs as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Decode the data of a Nomen OP_RETURN, as produced by `op-return`.
+    /// Accepts either the bare payload or the full output script (starting with `6a`).
+    DecodeOpReturn {
+        /// Hex encoded payload or output script
+        hex: String,
+    },
+
+    /// Generate deterministic test vectors from fixed seeds: keys, fingerprints, nsids,
+    /// OP_RETURN payloads and signed example events.
+    TestVectors {
+        /// Check a test vectors file against the generated vectors instead.
+        #[arg(long)]
+        verify: Option<PathBuf>,
+
+        /// Write the vectors to a file, instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Add a sponsor's inputs to a claim made with `name new --sponsor-psbt`, so the sponsor pays
+    /// the fee, and print the fee paid.
+    MergePsbt(MergePsbtSubcommand),
+
+    /// Print an example of a protocol document as JSON, or its JSON Schema with `--schema`.
+    Example {
+        /// Document type.
+        #[arg(value_enum)]
+        kind: DocumentKind,
+
+        /// Print the JSON Schema of the document type instead.
+        #[arg(long)]
+        schema: bool,
+    },
+
+    /// Print a shell completion script for every nomen command and option.
+    /// For example: `nomen util completions bash > /etc/bash_completion.d/nomen`
+    Completions {
+        /// Shell to complete in.
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Write a man page for nomen and each of its subcommands (`nomen-name-new.1`, ...) to a directory.
+    Manpages {
+        /// Directory to write the pages to. Created if it doesn't exist.
+        dir: PathBuf,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MergePsbtSubcommand {
+    /// The claim transaction from `name new --sponsor-psbt`, signed or not.
+    /// May be a path to a PSBT file or a Base64 encoded PSBT string.
+    pub claim: String,
+
+    /// The sponsor's transaction, with the inputs paying the fee. Once the claim is signed, it
+    /// must not have outputs, as the claimant's signature covers all outputs, so all of its
+    /// inputs go to the fee. May be a path to a PSBT file or a Base64 encoded PSBT string.
+    pub sponsor: String,
+
+    /// JSON command output
+    #[arg(short, long)]
+    pub json: bool,
+
+    /// File path to write a serialized PSBT file
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub hwi: HwiArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct BatchSubcommand {
+    /// Path to the batch document. See `docs/HOWTO.md` for its format.
+    pub file: PathBuf,
+
+    /// Specify your private key on the command line. May be useful for scripts. Beware of shell history!
+    /// Will prompt once if not provided.
+    #[arg(short, long)]
+    pub privkey: Option<NostrSk>,
+
+    /// Sign with a NIP-46 remote signer instead of a private key.
+    #[arg(long, conflicts_with = "privkey")]
+    pub bunker: Option<BunkerUri>,
+
+    /// Stop at the first operation that fails, instead of running the rest.
+    #[arg(long)]
+    pub stop_on_error: bool,
+
+    /// Print the summary as JSON, with each operation's full output.
+    #[arg(short, long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DevSubcommand {
+    /// Address and port to bind the server.
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    pub bind: String,
+
+    /// Seconds between mined blocks.
+    #[arg(long, default_value = "10")]
+    pub block_interval: u64,
+
+    /// Path for the development index. Default: an in-memory index, dropped on exit
+    #[arg(long)]
+    pub data: Option<PathBuf>,
+
+    /// Do not register the sample names.
+    #[arg(long)]
+    pub no_samples: bool,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum DbSubcommand {
+    /// Export every indexed name, with owner, block height and records.
+    ExportNames {
+        /// Output format.
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+
+        /// File to write the export. Default: stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Check the index for rows that no longer hold up: mismatched nsids,
+    /// bad event signatures, and on-chain claims that are missing or stale.
+    Verify {
+        /// Move bad rows to the quarantine table, and rebuild name owners.
+        #[arg(long)]
+        fix: bool,
+
+        /// Skip checking on-chain claims against the Bitcoin node.
+        #[arg(long)]
+        skip_rpc: bool,
     },
+
+    /// Apply pending schema migrations, or roll back to an earlier version.
+    /// The database file is backed up before any migration runs.
+    Migrate {
+        /// Target schema version. Default: latest
+        #[arg(long)]
+        to: Option<i64>,
+    },
+
+    /// Export or import a copy of the index, with a manifest signed by the indexer operator.
+    #[command(subcommand)]
+    Snapshot(SnapshotSubcommand),
+
+    /// Copy the whole database to a new file with SQLite's online backup API. The copy is the
+    /// database as of when the backup started, and the indexer doesn't need to be stopped.
+    Backup {
+        /// Backup file to create.
+        path: PathBuf,
+
+        /// Open the copy and run SQLite's integrity check on it.
+        #[arg(long)]
+        verify: bool,
+    },
+
+    /// Show the latest administrative and mutating actions, from the command line (`local`), an
+    /// API key (`key:<name>`) or an API client without one (`ip:<address>`), oldest first.
+    Audit {
+        /// Only show the actions of this actor.
+        #[arg(long)]
+        actor: Option<String>,
+
+        /// Number of actions to show.
+        #[arg(short = 'n', long, default_value = "50")]
+        limit: i64,
+    },
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum SnapshotSubcommand {
+    /// Write a snapshot of the index, and a manifest with its tip and hash signed with your Nostr key
+    /// to `<OUTPUT>.manifest.json`. API keys, usage and the watch list are left out.
+    Export {
+        /// Snapshot file to create.
+        output: PathBuf,
+
+        /// Specify your private key on the command line. May be useful for scripts. Beware of shell history!
+        /// Will prompt if not provided.
+        #[arg(short, long)]
+        privkey: Option<NostrSk>,
+
+        /// Sign the manifest with a NIP-46 remote signer instead of a private key.
+        #[arg(long, conflicts_with = "privkey")]
+        bunker: Option<BunkerUri>,
+    },
+
+    /// Verify a snapshot against its signed manifest and the local node, then use it as the index.
+    Import {
+        /// Snapshot file to import.
+        snapshot: PathBuf,
+
+        /// Signed manifest. Default: `<SNAPSHOT>.manifest.json`
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+
+        /// Operator pubkeys whose snapshots you trust. May be repeated.
+        /// Without it, any valid signature is accepted with a warning.
+        #[arg(long)]
+        trust: Vec<NostrPk>,
+
+        /// Number of blockchain entries to check against the local node.
+        #[arg(long, default_value = "20")]
+        sample: usize,
+
+        /// Skip checking the snapshot against the Bitcoin node.
+        #[arg(long)]
+        skip_rpc: bool,
+
+        /// Replace an existing index.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct IndexSubcommand {
+    #[command(subcommand)]
+    pub command: Option<IndexCommand>,
+
+    /// Bootstrap or continue the index from another Nomen indexer's API (e.g. https://nomen.example.com),
+    /// before indexing the rest from the node and relays.
+    #[arg(long)]
+    pub mirror: Option<String>,
+
+    /// Number of mirrored blockchain entries to check against the local node.
+    #[arg(long, default_value = "20", requires = "mirror")]
+    pub mirror_sample: usize,
+
+    /// Fetch and parse blocks with this many parallel workers, for a faster initial sync against
+    /// a node that can take it. Blocks are still committed in chain order.
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u16).range(1..))]
+    pub workers: u16,
+
+    /// Discard the index if it was built from another network than the node's, and index the
+    /// node's from scratch. Without it, indexing refuses to run against a node on another network.
+    #[arg(long)]
+    pub force_reset: bool,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum IndexCommand {
+    /// Replay already indexed blocks and stored events through the indexer into a scratch
+    /// database, and print the time spent in RPC, parsing, validation and the database.
+    Bench(IndexBenchSubcommand),
+
+    /// Sign a checkpoint committing to every indexed name, so the server can prove a name is not
+    /// registered (`/api/name/absence`). Replaces the previous checkpoint; run it periodically.
+    Checkpoint(IndexCheckpointSubcommand),
+
+    /// Re-validate a single name from its stored raw events and on-chain claims, e.g. after fixing
+    /// a validation bug, without a full reindex.
+    RebuildName {
+        /// Name to rebuild
+        name: Name,
+
+        /// Skip checking the name's on-chain claims against the Bitcoin node.
+        #[arg(long)]
+        skip_rpc: bool,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct IndexBenchSubcommand {
+    /// Number of indexed blocks to replay, fetched again from the node.
+    #[arg(long, default_value = "100")]
+    pub blocks: usize,
+
+    /// Number of stored records events to replay.
+    #[arg(long, default_value = "1000")]
+    pub events: usize,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct IndexCheckpointSubcommand {
+    /// Specify your private key on the command line. May be useful for scripts. Beware of shell history!
+    /// Will prompt if not provided.
+    #[arg(short, long)]
+    pub privkey: Option<NostrSk>,
+
+    /// Sign the checkpoint with a NIP-46 remote signer instead of a private key.
+    #[arg(long, conflicts_with = "privkey")]
+    pub bunker: Option<BunkerUri>,
+
+    /// Also publish the checkpoint event to the relays.
+    #[arg(long)]
+    pub publish: bool,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum WatchSubcommand {
+    /// Add a name to the watch list.
+    Add {
+        /// Name to watch
+        name: Name,
+    },
+
+    /// Remove a name from the watch list.
+    Remove {
+        /// Name to stop watching
+        name: Name,
+    },
+
+    /// List watched names.
+    List,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum OutboxSubcommand {
+    /// Send every queued event now, without waiting for its next retry.
+    Flush,
+
+    /// List queued events, with their attempts and last error.
+    List,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum MarketSubcommand {
+    /// Seller: sign a transaction that pays you and transfers the name, and publish it as an offer.
+    Offer(Box<MarketOfferSubcommand>),
+
+    /// Buyer: add your inputs to an offer's transaction, sign and broadcast it.
+    Buy(Box<MarketBuySubcommand>),
+
+    /// List open offers from the index.
+    List {
+        /// Only list offers for this name
+        name: Option<Name>,
+
+        /// JSON command output
+        #[arg(short, long)]
+        json: bool,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MarketOfferSubcommand {
+    /// The name to sell
+    pub name: Name,
+
+    /// Public key of the buyer, who will become the new owner
+    pub buyer: XOnlyPublicKey,
+
+    /// The seller's transaction: your inputs, and an output paying you their value plus the price.
+    /// May be a path to a PSBT file or a Base64 encoded PSBT string.
+    pub psbt: String,
+
+    /// Specify your private key on the command line. May be useful for scripts. Beware of shell history!
+    /// Will prompt if not provided.
+    /// This is the private key of the current owner of the name.
+    #[arg(short, long)]
+    pub privkey: Option<NostrSk>,
+
+    /// Sign the Nostr event with a NIP-46 remote signer instead of a private key.
+    #[arg(long, conflicts_with = "privkey")]
+    pub bunker: Option<BunkerUri>,
+
+    /// JSON command output
+    #[arg(short, long)]
+    pub json: bool,
+
+    /// Publish the offer. Every input must be signed with SIGHASH_ALL|SIGHASH_ANYONECANPAY.
+    #[arg(short, long)]
+    pub broadcast: bool,
+
+    /// Verify against the index that you own the name.
+    /// Be sure to run the indexer first, or this is not very useful.
+    #[arg(short, long)]
+    pub validate: bool,
+
+    /// File path to write a serialized PSBT file
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub hwi: HwiArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MarketBuySubcommand {
+    /// The offer event id, or a path to a file or the offer event JSON.
+    pub offer: String,
+
+    /// The buyer's transaction, with inputs paying the price plus the fee. It must not have outputs,
+    /// as the seller's signature covers all outputs, so anything over the price goes to the fee.
+    /// May be a path to a PSBT file or a Base64 encoded PSBT string.
+    pub psbt: String,
+
+    /// JSON command output
+    #[arg(short, long)]
+    pub json: bool,
+
+    /// File path to write a serialized PSBT file
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub hwi: HwiArgs,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma separated values, one column per record key.
+    Csv,
+
+    /// One JSON object per line.
+    Jsonl,
 }
 
 #[derive(clap::Args, Debug, Clone, Serialize, Deserialize)]
 pub struct ServerSubcommand {
-    /// Address and port to bind.
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<ServerCommand>,
+
+    /// Address and port, or `unix:<path>`, to bind. Repeat to listen on several.
     #[arg(short, long)]
-    pub bind: Option<String>,
+    pub bind: Vec<String>,
 
     /// Start server without explorer.
     #[arg(long)]
@@ -145,6 +637,47 @@ pub struct ServerSubcommand {
     pub indexer_delay: Option<u64>,
 }
 
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum ServerCommand {
+    /// Manage API keys. Requests with a key in the `X-Api-Key` header are not rate limited,
+    /// and are counted per key at `/api/usage`.
+    #[command(subcommand)]
+    Apikey(ApiKeySubcommand),
+
+    /// Publish a NIP-89 handler event announcing this server as a resolver of Nomen names, at
+    /// `server.announce.url`. Publishing again replaces the previous announcement.
+    Announce(ServerAnnounceSubcommand),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ServerAnnounceSubcommand {
+    /// Sign with this private key instead of `server.signing_key`. Will prompt if neither is set.
+    #[arg(short, long)]
+    pub privkey: Option<NostrSk>,
+
+    /// Sign with a NIP-46 remote signer instead of a private key.
+    #[arg(long, conflicts_with = "privkey")]
+    pub bunker: Option<BunkerUri>,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum ApiKeySubcommand {
+    /// Create a key, and print it. Only its hash is stored, so it cannot be shown again.
+    Add {
+        /// Name of the key, e.g. the user it is for.
+        name: String,
+    },
+
+    /// Revoke a key.
+    Revoke {
+        /// Name of the key.
+        name: String,
+    },
+
+    /// List keys, with their total number of requests.
+    List,
+}
+
 #[derive(clap::Subcommand, Debug, Clone)]
 pub enum NameSubcommand {
     /// Create a new name.
@@ -158,7 +691,19 @@ pub enum NameSubcommand {
     Record(NameRecordSubcomand),
 
     /// Transfer a domain to a new keypair.
+    #[command(subcommand)]
     Transfer(NameTransferSubcommand),
+
+    /// Approve the owner of a subname of your name (`child.yourname`), for indexers that require
+    /// parent approval (`protocol.subname_approval`).
+    Approve(NameApproveSubcommand),
+
+    /// Deactivate a name you own: indexers stop serving its records, but keep them in its history.
+    /// Publishing records again reactivates it.
+    Deactivate(NameDeactivateSubcommand),
+
+    /// Show the owner and records of a name from the index, now or at a past height or time.
+    Show(NameShowSubcommand),
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -173,6 +718,11 @@ pub struct NameNewSubcommand {
     #[arg(short, long)]
     pub privkey: Option<NostrSk>,
 
+    /// Sign the Nostr event with a NIP-46 remote signer instead of a private key.
+    /// Format: bunker://<remote pubkey>?relay=<relay url>&secret=<optional secret>
+    #[arg(long, conflicts_with = "privkey")]
+    pub bunker: Option<BunkerUri>,
+
     /// Command output as JSON
     #[arg(short, long)]
     pub json: bool,
@@ -181,14 +731,42 @@ pub struct NameNewSubcommand {
     #[arg(short, long)]
     pub broadcast: bool,
 
-    /// Verify against index that name is available.
-    /// Be sure to run the indexer first, or this is not very useful.
-    #[arg(short, long)]
+    /// No longer needed: competing claims in the index, the mempool and on the relays are always
+    /// checked for first.
+    #[arg(short, long, hide = true)]
     pub validate: bool,
 
+    /// Claim the name even if competing claims are found.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Commit to the records event broadcast with the claim in the OP_RETURN. Indexers then only
+    /// serve records for the name once they have seen that event.
+    #[arg(long)]
+    pub commit_records: bool,
+
+    /// Pay what the PSBT's inputs leave over, less the fee, to this address. Default: the next
+    /// address of `wallet.descriptor`, if it is set. A PSBT that already has change is kept as is.
+    #[arg(long)]
+    pub change_address: Option<Address<NetworkUnchecked>>,
+
+    /// Fee rate to leave for when adding change, in sat/vB. Default: the node's estimate for 6
+    /// blocks
+    #[arg(long)]
+    pub fee_rate: Option<f64>,
+
+    /// Leave the fee to a sponsor. No change is added, and the inputs are to be signed with
+    /// `SIGHASH_ALL|SIGHASH_ANYONECANPAY`, so that once signed, the sponsor can add an input paying
+    /// the fee with `util merge-psbt`.
+    #[arg(long, conflicts_with_all = ["change_address", "fee_rate", "broadcast_tx"])]
+    pub sponsor_psbt: bool,
+
     /// File path to write a serialized PSBT file
     #[arg(short, long)]
     pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub hwi: HwiArgs,
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -199,34 +777,118 @@ pub struct NameRecordSubcomand {
     /// Records to broadcast (format "key=value")
     pub records: Vec<KeyVal>,
 
+    /// Read the records from a JSON object of keys to values in this file, or `-` for stdin,
+    /// instead of "key=value" arguments.
+    #[arg(long, conflicts_with = "records")]
+    pub file: Option<PathBuf>,
+
+    /// Bind the records to the name's current transaction, from the local index, so they are not
+    /// served again if the name is later transferred away and back.
+    #[arg(long)]
+    pub bind_anchor: bool,
+
+    /// Publish the records at this time instead of now, as RFC 3339 (2024-05-01T12:00:00Z) or Unix
+    /// seconds. The event is signed now and kept in the outbox until then, when the server's outbox
+    /// flusher sends it, so the server must be running at that time.
+    #[arg(long)]
+    pub at: Option<DateTime>,
+
+    /// Stop serving the records this long after they are published (90s, 30m, 12h, 7d, 2w), with a
+    /// NIP-40 expiration tag. Indexers then resolve the name with no records, but keep them in its
+    /// history.
+    #[arg(long)]
+    pub expires: Option<Lifetime>,
+
     /// Specify your private key on the command line. May be useful for scripts. Beware of shell history!
     /// Will prompt if not provided.
     #[arg(short, long)]
     pub privkey: Option<NostrSk>,
+
+    /// Sign the Nostr event with a NIP-46 remote signer instead of a private key.
+    #[arg(long, conflicts_with = "privkey")]
+    pub bunker: Option<BunkerUri>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct NameShowSubcommand {
+    /// Name to show.
+    pub name: Name,
+
+    /// Show the records as they were at this block height, or at this time as RFC 3339 or Unix
+    /// seconds. As with a transaction's lock time, numbers below 500000000 are heights.
+    #[arg(long)]
+    pub at: Option<Moment>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct NameApproveSubcommand {
+    /// The subname to approve, directly below a name you own.
+    pub name: String,
+
+    /// Public key of the subname's owner.
+    pub pubkey: NostrPk,
+
+    /// Private key of the parent name's owner. Will prompt if not provided.
+    #[arg(short, long)]
+    pub privkey: Option<NostrSk>,
+
+    /// Sign the approval with a NIP-46 remote signer instead of a private key.
+    #[arg(long, conflicts_with = "privkey")]
+    pub bunker: Option<BunkerUri>,
 }
 
 #[derive(clap::Args, Debug, Clone)]
-pub struct NameTransferSubcommand {
-    /// The name to broadcast records
+pub struct NameDeactivateSubcommand {
+    /// The name to deactivate.
+    pub name: Name,
+
+    /// Why the name is deactivated, published with the event.
+    #[arg(long, default_value = "")]
+    pub reason: String,
+
+    /// Private key of the name's owner. Will prompt if not provided.
+    #[arg(short, long)]
+    pub privkey: Option<NostrSk>,
+
+    /// Sign the deactivation with a NIP-46 remote signer instead of a private key.
+    #[arg(long, conflicts_with = "privkey")]
+    pub bunker: Option<BunkerUri>,
+}
+
+/// A transfer takes two steps: the current owner signs a handover event naming the new owner,
+/// then the new owner checks it and adds the transfer output to a transaction.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum NameTransferSubcommand {
+    /// Current owner: sign the handover event naming the new owner.
+    Sign(NameTransferSignSubcommand),
+
+    /// New owner: check a signed handover event, and add the transfer OP_RETURN to a transaction.
+    Finalize(NameTransferFinalizeSubcommand),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct NameTransferSignSubcommand {
+    /// The name to transfer
     pub name: Name,
 
     /// Public key of the new owner
     pub pubkey: XOnlyPublicKey,
 
-    /// The transaction to sign. May be a path to a PSBT file or a Base64 encoded PSBT string.
-    pub psbt: String,
-
     /// Specify your private key on the command line. May be useful for scripts. Beware of shell history!
     /// Will prompt if not provided.
     /// This is the private key of the current owner of the name.
     #[arg(short, long)]
     pub privkey: Option<NostrSk>,
 
+    /// Sign the Nostr event with a NIP-46 remote signer instead of a private key.
+    #[arg(long, conflicts_with = "privkey")]
+    pub bunker: Option<BunkerUri>,
+
     /// JSON command output
     #[arg(short, long)]
     pub json: bool,
 
-    /// Broadcast the associated Nostr event
+    /// Broadcast the handover event now. Usually the new owner broadcasts it with `finalize`.
     #[arg(short, long)]
     pub broadcast: bool,
 
@@ -235,9 +897,66 @@ pub struct NameTransferSubcommand {
     #[arg(short, long)]
     pub validate: bool,
 
+    /// File path to write the signed handover event, to give to the new owner
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct NameTransferFinalizeSubcommand {
+    /// The signed handover event from `transfer sign`. May be a path to a file or the event JSON.
+    pub event: String,
+
+    /// The transaction to sign. May be a path to a PSBT file or a Base64 encoded PSBT string.
+    pub psbt: String,
+
+    /// JSON command output
+    #[arg(short, long)]
+    pub json: bool,
+
+    /// Broadcast the handover event
+    #[arg(short, long)]
+    pub broadcast: bool,
+
+    /// Verify against the index that the handover event is signed by the current owner.
+    /// Be sure to run the indexer first, or this is not very useful.
+    #[arg(short, long)]
+    pub validate: bool,
+
     /// File path to write a serialized PSBT file
     #[arg(short, long)]
     pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub hwi: HwiArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct HwiArgs {
+    /// Sign the transaction with a hardware wallet, using HWI (https://github.com/bitcoin-core/HWI).
+    /// The signed transaction is finalized by your node.
+    #[arg(long)]
+    pub hwi: bool,
+
+    /// Path to the HWI executable.
+    #[arg(long, default_value = "hwi")]
+    pub hwi_path: PathBuf,
+
+    /// Device type passed to HWI, if no fingerprint is given.
+    #[arg(long, default_value = "coldcard")]
+    pub hwi_device: String,
+
+    /// Fingerprint of the hardware wallet to use.
+    #[arg(long)]
+    pub hwi_fingerprint: Option<String>,
+
+    /// Chain passed to HWI (main, test, signet, regtest). Default: the configured network
+    #[arg(long)]
+    pub hwi_chain: Option<String>,
+
+    /// Broadcast the transaction after signing with HWI.
+    #[arg(long, requires = "hwi")]
+    pub broadcast_tx: bool,
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -1,31 +1,318 @@
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
+use anyhow::anyhow;
 use bitcoin::Network;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    error::NomenError,
+    util::{
+        NameKind, APPROVAL_KIND, CHECKPOINT_KIND, DEACTIVATION_KIND, HANDLER_KIND, OFFER_KIND,
+        SNAPSHOT_KIND,
+    },
+};
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ServerConfig {
-    pub bind: Option<String>,
+    /// Addresses to listen on, as one or a list of `address:port` (e.g. `0.0.0.0:8080`,
+    /// `[::]:8080`) or `unix:<path>` for a Unix domain socket. Default: `0.0.0.0:8080`
+    pub bind: Option<Binds>,
     pub without_explorer: Option<bool>,
     pub without_api: Option<bool>,
     pub without_indexer: Option<bool>,
     pub indexer_delay: Option<u64>,
     pub confirmations: Option<usize>,
+    pub theme: Option<ThemeConfig>,
+    pub privacy: Option<PrivacyConfig>,
+    pub rate_limit: Option<u32>,
+
+    /// Reverse proxies trusted to give the client's address in `X-Forwarded-For`, for rate limits
+    /// and the access log: IP addresses, and `unix` for clients of the Unix sockets. Default: none
+    pub trusted_proxies: Option<Vec<String>>,
+    pub script: Option<ScriptConfig>,
+    pub redirect_interstitial: Option<bool>,
+
+    /// Record keys (e.g. `LN`, `WEB`) a subname with no records of its own takes from its
+    /// nearest parent that has them. Default: none
+    pub inherit_records: Option<Vec<String>>,
+
+    /// How many blocks the index may be behind the node before name lookups are flagged `stale`.
+    /// Default: 6
+    pub max_index_lag: Option<u64>,
+
+    /// Answer 503 with `Retry-After` to name lookups while the index is stale, instead of flagging
+    /// them. Default: false
+    pub reject_stale: Option<bool>,
+
+    /// Private key API responses are signed with (HTTP Message Signatures), as nsec or hex.
+    /// Default: responses are not signed
+    pub signing_key: Option<String>,
+
+    /// Compress JSON and HTML responses with gzip or brotli, as the client accepts.
+    /// Default: true
+    pub compression: Option<bool>,
+
+    /// Largest body, in bytes, of a page of a list endpoint (`/api/names`, `/api/mirror/events`, ...).
+    /// Longer pages are cut short, with a `next_cursor` to the rest. Default: 1048576
+    pub max_response_size: Option<usize>,
+    pub logging: Option<LoggingConfig>,
+    pub branding: Option<BrandingConfig>,
+    pub announce: Option<AnnounceConfig>,
+
+    /// Block explorer URL templates, by network name (`bitcoin`, `testnet`, `signet`, `regtest`).
+    pub links: Option<BTreeMap<String, LinksConfig>>,
 }
 impl ServerConfig {
     fn init() -> ServerConfig {
         ServerConfig {
-            bind: Some("0.0.0.0:8080".into()),
+            bind: Some(Binds::One("0.0.0.0:8080".into())),
             without_explorer: Some(false),
             without_api: Some(false),
             without_indexer: Some(false),
             indexer_delay: Some(30),
             confirmations: Some(3),
+            theme: Some(ThemeConfig::init()),
+            privacy: Some(PrivacyConfig::init()),
+            rate_limit: Some(10),
+            trusted_proxies: None,
+            script: None,
+            redirect_interstitial: Some(false),
+            inherit_records: None,
+            max_index_lag: Some(6),
+            reject_stale: Some(false),
+            signing_key: None,
+            compression: Some(true),
+            max_response_size: Some(1024 * 1024),
+            logging: Some(LoggingConfig::init()),
+            branding: None,
+            announce: None,
+            links: Some(LinksConfig::init()),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// `server.bind`, a single address or a list of them.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Binds {
+    One(String),
+    Many(Vec<String>),
+}
+impl Binds {
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            Binds::One(bind) => vec![bind.clone()],
+            Binds::Many(binds) => binds.clone(),
+        }
+    }
+}
+
+/// An address the server listens on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindAddr {
+    Tcp(SocketAddr),
+    /// A Unix domain socket, given as `unix:<path>`.
+    Unix(PathBuf),
+}
+
+impl FromStr for BindAddr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err(NomenError::Validation(format!("{s} has no socket path")).into());
+            }
+            return Ok(BindAddr::Unix(path.into()));
+        }
+        s.parse().map(BindAddr::Tcp).map_err(|_| {
+            NomenError::Validation(format!("{s} is not an address:port or unix:<path>")).into()
+        })
+    }
+}
+
+/// A reverse proxy trusted to set `X-Forwarded-For`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrustedProxy {
+    Ip(IpAddr),
+    /// Any client of a Unix domain socket, given as `unix`.
+    Unix,
+}
+
+impl FromStr for TrustedProxy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "unix" {
+            return Ok(TrustedProxy::Unix);
+        }
+        s.parse()
+            .map(TrustedProxy::Ip)
+            .map_err(|_| NomenError::Validation(format!("{s} is not an IP address or unix")).into())
+    }
+}
+
+impl std::fmt::Display for BindAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindAddr::Tcp(addr) => write!(f, "{addr}"),
+            BindAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    /// Follow the browser's color scheme preference.
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ThemeConfig {
+    pub mode: Option<ThemeMode>,
+    pub accent: Option<String>,
+}
+impl ThemeConfig {
+    fn init() -> ThemeConfig {
+        ThemeConfig {
+            mode: Some(ThemeMode::Auto),
+            accent: Some("violet".into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PrivacyMode {
+    /// Explorer pages show owners and name transactions.
+    #[default]
+    Public,
+    /// Explorer pages leave out owner pubkeys and transaction details, and the API only serves
+    /// them to requests with an API key.
+    Private,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct PrivacyConfig {
+    pub mode: Option<PrivacyMode>,
+}
+impl PrivacyConfig {
+    fn init() -> PrivacyConfig {
+        PrivacyConfig {
+            mode: Some(PrivacyMode::Public),
+        }
+    }
+}
+
+/// How the explorer pages present the operator's indexer.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BrandingConfig {
+    /// Site title, in place of the translated default.
+    pub title: Option<String>,
+
+    /// Image file shown in the header, served at `/logo`.
+    pub logo: Option<PathBuf>,
+
+    /// HTML shown at the bottom of every page. It is not escaped.
+    pub footer: Option<String>,
+
+    /// Message shown above every page, e.g. planned maintenance.
+    pub announcement: Option<String>,
+}
+
+/// The NIP-89 handler event announcing this server as a resolver of Nomen names.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AnnounceConfig {
+    /// Public URL of the server, e.g. `https://nomen.example`. Needed to announce it.
+    pub url: Option<String>,
+
+    /// Name shown to users choosing a resolver. Default: `server.branding.title`, or
+    /// "Nomen indexer"
+    pub name: Option<String>,
+    pub about: Option<String>,
+
+    /// Publish the announcement when the server starts, signed with `server.signing_key`.
+    /// Default: false
+    pub on_start: Option<bool>,
+}
+
+/// Links to an external block explorer. `{txid}` and `{blockhash}` are replaced in the templates.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct LinksConfig {
+    pub tx: Option<String>,
+    pub block: Option<String>,
+}
+impl LinksConfig {
+    fn mempool(base: &str) -> LinksConfig {
+        LinksConfig {
+            tx: Some(format!("{base}/tx/{{txid}}")),
+            block: Some(format!("{base}/block/{{blockhash}}")),
+        }
+    }
+
+    /// mempool.space for every public network. Regtest has no public explorer.
+    pub fn init() -> BTreeMap<String, LinksConfig> {
+        BTreeMap::from([
+            (
+                "bitcoin".into(),
+                LinksConfig::mempool("https://mempool.space"),
+            ),
+            (
+                "testnet".into(),
+                LinksConfig::mempool("https://mempool.space/testnet"),
+            ),
+            (
+                "signet".into(),
+                LinksConfig::mempool("https://mempool.space/signet"),
+            ),
+        ])
+    }
+}
+
+/// Rhai script applied to records before they are served. Needs the `scripting` feature.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ScriptConfig {
+    /// Path to a script defining `fn transform(name, records)`.
+    pub records: Option<PathBuf>,
+
+    /// Milliseconds a single run of the script may take. Default: 50
+    pub timeout_ms: Option<u64>,
+}
+
+/// HTTP request logging.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct LoggingConfig {
+    /// Log every request with its status and latency, at the `info` level. Default: false
+    pub trace: Option<bool>,
+
+    /// Write an access log in the combined log format to this file.
+    pub access_log: Option<PathBuf>,
+
+    /// Rotate the access log once it reaches this many bytes. Default: 10485760 (10 MiB)
+    pub max_size: Option<u64>,
+
+    /// Number of rotated access logs (`<access_log>.1` is the newest) to keep. Default: 5
+    pub max_files: Option<usize>,
+}
+impl LoggingConfig {
+    fn init() -> LoggingConfig {
+        LoggingConfig {
+            trace: Some(false),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct RpcConfig {
     pub cookie: Option<PathBuf>,
     pub user: Option<String>,
@@ -52,11 +339,224 @@ impl RpcConfig {
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct NostrConfig {
     pub relays: Option<Vec<String>>,
+
+    /// How many relays must agree on the latest event for proof lookups (`/api/name/proof`,
+    /// `util verify`). Default: 1, which serves the indexed event without asking the relays.
+    pub read_quorum: Option<usize>,
+
+    /// Relays for events of one kind, by kind name (see [`route_kind`]) or number. A `default`
+    /// route applies to every kind without its own `read` or `write` list, and its `deny` list to
+    /// every kind. Default: every kind uses `nostr.relays`
+    pub routes: Option<BTreeMap<String, RelayRoute>>,
 }
 impl NostrConfig {
     fn init() -> NostrConfig {
         NostrConfig {
             relays: Some(vec!["wss://relay.damus.io".into()]),
+            read_quorum: Some(1),
+            routes: None,
+        }
+    }
+}
+
+/// Where events of one kind are fetched from and published to.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RelayRoute {
+    /// Relays the events are fetched from, instead of `nostr.relays`.
+    pub read: Option<Vec<String>>,
+
+    /// Relays the events are published to, instead of `nostr.relays`.
+    pub write: Option<Vec<String>>,
+
+    /// Relays never used for the events, even if they are in `nostr.relays`.
+    pub deny: Option<Vec<String>>,
+}
+
+/// The event kind a `[nostr.routes]` key stands for: one of `name`, `transfer`, `offer`,
+/// `snapshot`, `checkpoint`, `approval`, `deactivation`, `handler`, `metadata` and `dm`, or a
+/// kind number.
+pub fn route_kind(key: &str) -> Option<u64> {
+    let kind = match key {
+        "name" => NameKind::Name as u64,
+        "transfer" => NameKind::Transfer as u64,
+        "offer" => OFFER_KIND as u64,
+        "snapshot" => SNAPSHOT_KIND as u64,
+        "checkpoint" => CHECKPOINT_KIND as u64,
+        "approval" => APPROVAL_KIND as u64,
+        "deactivation" => DEACTIVATION_KIND as u64,
+        "handler" => HANDLER_KIND as u64,
+        "metadata" => 0,
+        "dm" => 4,
+        _ => return key.parse().ok(),
+    };
+    Some(kind)
+}
+
+/// How nomen reaches relays, the Bitcoin node and HTTP services.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct NetworkConfig {
+    pub proxy: Option<ProxyConfig>,
+}
+
+/// A SOCKS5 proxy, such as Tor, for relay, RPC and HTTP connections. Host names are resolved by
+/// the proxy, so `.onion` relay URLs and RPC hosts work over Tor. RPC to a loopback host
+/// (`localhost`, `127.0.0.1`, `::1`) connects directly.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProxyConfig {
+    /// Address of the proxy, e.g. `127.0.0.1:9050` for Tor.
+    pub address: SocketAddr,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueBackend {
+    Nats,
+    Kafka,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct QueueConfig {
+    pub backend: QueueBackend,
+
+    /// NATS server URL, or comma separated list of Kafka brokers.
+    pub url: String,
+
+    /// NATS subject or Kafka topic. Default: nomen.index
+    pub topic: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct IndexerConfig {
+    /// Seconds a record event's `created_at` may be ahead of the current time, or behind the
+    /// name's on-chain anchor. Events outside this window are not indexed.
+    pub max_time_skew: Option<u64>,
+
+    /// Fetch the Nostr profile (kind 0) of each name owner, to show on the explorer.
+    pub profiles: Option<bool>,
+
+    /// Seconds before each relay's newest seen event to fetch again, to catch events the relay
+    /// received late.
+    pub sync_overlap: Option<u64>,
+
+    /// Seconds to remember the ids of processed events, valid or not, so the same event fetched
+    /// again from any relay is skipped. Default: 604800 (a week)
+    pub seen_events_retention: Option<u64>,
+
+    /// Directory to keep the raw blocks and transactions fetched from the node in, so scanning the
+    /// chain again reads them from disk. Not kept if unset.
+    pub block_cache: Option<PathBuf>,
+
+    /// Bytes the block cache may take before the least recently used blocks are removed.
+    /// Default: 1 GiB
+    pub block_cache_max_size: Option<u64>,
+}
+impl IndexerConfig {
+    fn init() -> IndexerConfig {
+        IndexerConfig {
+            max_time_skew: Some(86400),
+            profiles: Some(false),
+            sync_overlap: Some(600),
+            seen_events_retention: Some(604800),
+            block_cache: None,
+            block_cache_max_size: Some(1024 * 1024 * 1024),
+        }
+    }
+}
+
+/// Protocol limits on names and record events. Names or events over a limit can't be published,
+/// and are not indexed.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ProtocolConfig {
+    /// Shortest name, in bytes. Names shorter than 3 bytes are never valid. Default: 3
+    pub min_name_length: Option<usize>,
+
+    /// Longest name, in bytes. Names longer than 256 bytes are never valid. Default: 256
+    pub max_name_length: Option<usize>,
+
+    /// Most labels in a name, e.g. 1 to only accept top level names. Default: no limit
+    pub max_labels: Option<usize>,
+
+    /// Largest records JSON (the event content), in bytes. Default: 8192
+    pub max_records_size: Option<usize>,
+
+    /// Most records in one event. Default: 64
+    pub max_records: Option<usize>,
+
+    /// Largest record value, in bytes. Default: 2048
+    pub max_record_value: Option<usize>,
+
+    /// Only treat a subname as valid once the owner of its parent has approved its owner with
+    /// `name approve`, all the way up to the top level name. Default: false
+    pub subname_approval: Option<bool>,
+}
+impl ProtocolConfig {
+    fn init() -> ProtocolConfig {
+        ProtocolConfig {
+            min_name_length: Some(3),
+            max_name_length: Some(256),
+            max_labels: None,
+            max_records_size: Some(8192),
+            max_records: Some(64),
+            max_record_value: Some(2048),
+            subname_approval: Some(false),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct NotificationsConfig {
+    pub queue: Option<QueueConfig>,
+
+    /// Print watch list alerts to stdout. Default: true
+    pub stdout: Option<bool>,
+
+    /// URL to POST alerts to, as JSON.
+    pub webhook: Option<String>,
+
+    /// Send alerts as encrypted Nostr direct messages.
+    pub dm: Option<DmConfig>,
+
+    /// Consecutive failed index runs before the server sends an alert. Default: 3
+    pub error_streak: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DmConfig {
+    /// Recipient public key, as npub or hex.
+    pub to: String,
+
+    /// Sender private key, as nsec or hex. Default: a new random key for each message
+    pub privkey: Option<String>,
+}
+
+/// The wallet `name new` pays change to.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct WalletConfig {
+    /// Ranged output descriptor (e.g. `wpkh([d34db33f/84h/0h/0h]xpub.../1/*)`) to derive change
+    /// addresses from, one index after the other, with the node's `deriveaddresses`.
+    pub descriptor: Option<String>,
+}
+
+/// A named environment, selected with `--profile`. Any value set here replaces the top level value.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProfileConfig {
+    pub data: Option<PathBuf>,
+    pub network: Option<Network>,
+    pub relays: Option<Vec<String>>,
+    pub rpc: Option<RpcConfig>,
+    pub wallet: Option<WalletConfig>,
+}
+impl ProfileConfig {
+    fn init() -> ProfileConfig {
+        ProfileConfig {
+            data: Some("nomen-signet.db".into()),
+            network: Some(Network::Signet),
+            relays: None,
+            rpc: Some(RpcConfig {
+                port: Some(38332),
+                ..Default::default()
+            }),
+            wallet: None,
         }
     }
 }
@@ -68,15 +568,235 @@ pub struct ConfigFile {
     pub nostr: NostrConfig,
     pub server: ServerConfig,
     pub rpc: RpcConfig,
+    pub indexer: IndexerConfig,
+    pub protocol: ProtocolConfig,
+    pub notifications: NotificationsConfig,
+    pub network: NetworkConfig,
+    pub wallet: WalletConfig,
+    pub profiles: BTreeMap<String, ProfileConfig>,
 }
 
 impl ConfigFile {
     pub fn init() -> ConfigFile {
         ConfigFile {
-            data: Some("nomen.db".into()),
+            data: Some(super::paths::xdg_data_file().unwrap_or_else(|| "nomen.db".into())),
             nostr: NostrConfig::init(),
             server: ServerConfig::init(),
             rpc: RpcConfig::init(),
+            indexer: IndexerConfig::init(),
+            protocol: ProtocolConfig::init(),
+            notifications: NotificationsConfig::default(),
+            network: NetworkConfig::default(),
+            wallet: WalletConfig::default(),
+            profiles: BTreeMap::from([("signet".into(), ProfileConfig::init())]),
+        }
+    }
+
+    /// Read the config file at `path`, with `profile` applied. A missing file is an empty config.
+    pub fn load(path: &Path, profile: Option<&str>) -> anyhow::Result<ConfigFile> {
+        let file = if path.is_file() {
+            let config_str = std::fs::read_to_string(path)?;
+
+            toml::from_str(&config_str)
+                .map_err(|e| NomenError::Config(format!("Invalid config file: {e}")))?
+        } else {
+            log::info!("Config file not found. Skipping.");
+            ConfigFile::default()
+        };
+
+        match profile {
+            Some(profile) => Ok(file
+                .with_profile(profile)
+                .map_err(|e| NomenError::Config(e.to_string()))?),
+            None => Ok(file),
+        }
+    }
+
+    /// Apply the settings of profile `name` over the top level settings.
+    pub fn with_profile(mut self, name: &str) -> anyhow::Result<ConfigFile> {
+        let profile = self.profiles.get(name).cloned().ok_or_else(|| {
+            NomenError::Config(format!("Profile {name} not found in config file"))
+        })?;
+
+        if profile.data.is_some() {
+            self.data = profile.data;
+        }
+        if profile.network.is_some() {
+            self.rpc.network = profile.network;
+        }
+        if profile.relays.is_some() {
+            self.nostr.relays = profile.relays;
         }
+        if let Some(rpc) = profile.rpc {
+            self.rpc = RpcConfig {
+                cookie: rpc.cookie.or(self.rpc.cookie),
+                user: rpc.user.or(self.rpc.user),
+                password: rpc.password.or(self.rpc.password),
+                host: rpc.host.or(self.rpc.host),
+                port: rpc.port.or(self.rpc.port),
+                network: rpc.network.or(self.rpc.network),
+            };
+        }
+        if let Some(wallet) = profile.wallet {
+            self.wallet = wallet;
+        }
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_profile() {
+        let file: ConfigFile = toml::from_str(
+            r#"
+            data = "nomen.db"
+
+            [rpc]
+            host = "localhost"
+            port = 8332
+
+            [profiles.signet]
+            data = "signet.db"
+            network = "signet"
+
+            [profiles.signet.rpc]
+            port = 38332
+            "#,
+        )
+        .unwrap();
+
+        let signet = file.clone().with_profile("signet").unwrap();
+        assert_eq!(signet.data, Some("signet.db".into()));
+        assert_eq!(signet.rpc.network, Some(Network::Signet));
+        assert_eq!(signet.rpc.port, Some(38332));
+        assert_eq!(signet.rpc.host.as_deref(), Some("localhost"));
+
+        assert!(file.with_profile("mainnet").is_err());
+    }
+
+    #[test]
+    fn test_routes() {
+        use clap::Parser;
+        use nostr_sdk::Kind;
+
+        use crate::config::{Cli, Config, RelayUse};
+
+        let file: ConfigFile = toml::from_str(
+            r#"
+            [nostr]
+            relays = ["wss://a.example", "wss://b.example", "wss://spam.example"]
+
+            [nostr.routes.default]
+            deny = ["wss://spam.example/"]
+
+            [nostr.routes.name]
+            read = ["wss://archive.example", "wss://a.example"]
+
+            [nostr.routes.38301]
+            write = ["wss://b.example"]
+            deny = ["wss://a.example"]
+            "#,
+        )
+        .unwrap();
+        let config = Config::new(Cli::parse_from(["nomen", "watch", "list"]), file);
+        let name: Kind = NameKind::Name.into();
+        let transfer: Kind = NameKind::Transfer.into();
+
+        assert_eq!(
+            config.relays_for(&[name], RelayUse::Read),
+            ["wss://archive.example", "wss://a.example"]
+        );
+        assert_eq!(
+            config.relays_for(&[name], RelayUse::Write),
+            ["wss://a.example", "wss://b.example"]
+        );
+        assert_eq!(
+            config.relays_for(&[transfer], RelayUse::Read),
+            ["wss://b.example"]
+        );
+        assert_eq!(
+            config.relays_for(&[name, transfer], RelayUse::Read),
+            [
+                "wss://archive.example",
+                "wss://a.example",
+                "wss://b.example"
+            ]
+        );
+        assert_eq!(
+            config.relays_for(&[], RelayUse::Write),
+            ["wss://a.example", "wss://b.example"]
+        );
+        assert_eq!(route_kind("offer"), Some(OFFER_KIND as u64));
+        assert_eq!(route_kind("1"), Some(1));
+        assert_eq!(route_kind("names"), None);
+    }
+
+    #[test]
+    fn test_bind() {
+        use clap::Parser;
+
+        use crate::config::{Cli, Config};
+
+        let file: ConfigFile = toml::from_str(
+            r#"
+            [server]
+            bind = ["[::]:8080", "unix:/run/nomen.sock"]
+            "#,
+        )
+        .unwrap();
+        let config = Config::new(Cli::parse_from(["nomen", "server"]), file.clone());
+        assert_eq!(
+            config.server_bind().unwrap(),
+            [
+                BindAddr::Tcp("[::]:8080".parse().unwrap()),
+                BindAddr::Unix("/run/nomen.sock".into())
+            ]
+        );
+
+        // --bind replaces the config, and a single address is still accepted.
+        let config = Config::new(
+            Cli::parse_from(["nomen", "server", "--bind", "127.0.0.1:9000"]),
+            file,
+        );
+        assert_eq!(
+            config.server_bind().unwrap()[0].to_string(),
+            "127.0.0.1:9000"
+        );
+        let file: ConfigFile = toml::from_str("[server]\nbind = \"localhost\"").unwrap();
+        let config = Config::new(Cli::parse_from(["nomen", "server"]), file);
+        assert!(config.server_bind().is_err());
+        assert!("unix:".parse::<BindAddr>().is_err());
+    }
+
+    #[test]
+    fn test_grammar() {
+        use clap::Parser;
+
+        use crate::{
+            config::{Cli, Config},
+            protocol::Grammar,
+        };
+
+        let cli = Cli::parse_from(["nomen", "watch", "list"]);
+        let config = Config::new(cli.clone(), ConfigFile::default());
+        assert_eq!(config.grammar(), Grammar::SUBNAME);
+
+        let file: ConfigFile = toml::from_str(
+            r#"
+            [protocol]
+            max_name_length = 8
+            max_labels = 1
+            "#,
+        )
+        .unwrap();
+        let grammar = Config::new(cli, file).grammar();
+        assert!(grammar.validate("smith").is_ok());
+        assert!(grammar.validate("smithson1").is_err());
+        assert!(grammar.validate("a.smith").is_err());
+        assert_eq!(grammar.min_length, Grammar::SUBNAME.min_length);
     }
 }
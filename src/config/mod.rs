@@ -1,6 +1,7 @@
 mod cfg;
 mod cli;
 mod config_file;
+pub mod paths;
 
 pub use cfg::*;
 pub use cli::*;
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+const CONFIG_FILE: &str = "nomen.toml";
+const DATA_FILE: &str = "nomen.db";
+
+/// Config file to use when none is given: `./nomen.toml` if it exists, for existing setups,
+/// otherwise `$XDG_CONFIG_HOME/nomen/nomen.toml`.
+pub fn default_config_file() -> PathBuf {
+    local_or(CONFIG_FILE, xdg_config_file())
+}
+
+/// Index database to use when none is configured: `./nomen.db` if it exists, for existing setups,
+/// otherwise `$XDG_DATA_HOME/nomen/nomen.db`.
+pub fn default_data_file() -> PathBuf {
+    local_or(DATA_FILE, xdg_data_file())
+}
+
+/// `$XDG_CONFIG_HOME/nomen/nomen.toml`, falling back to `~/.config`.
+pub fn xdg_config_file() -> Option<PathBuf> {
+    Some(xdg_dir("XDG_CONFIG_HOME", ".config")?.join(CONFIG_FILE))
+}
+
+/// `$XDG_DATA_HOME/nomen/nomen.db`, falling back to `~/.local/share`.
+pub fn xdg_data_file() -> Option<PathBuf> {
+    Some(xdg_dir("XDG_DATA_HOME", ".local/share")?.join(DATA_FILE))
+}
+
+fn local_or(local: &str, xdg: Option<PathBuf>) -> PathBuf {
+    let local = PathBuf::from(local);
+    match xdg {
+        Some(xdg) if !local.exists() => xdg,
+        _ => local,
+    }
+}
+
+fn xdg_dir(var: &str, home_fallback: &str) -> Option<PathBuf> {
+    // The spec says relative paths are invalid and should be ignored.
+    let base = std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(home_fallback)))?;
+    Some(base.join("nomen"))
+}
@@ -1,120 +1,333 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::CStr,
+    path::Path,
+    ptr::NonNull,
+    time::Duration,
+};
 
+use anyhow::bail;
 use bitcoin::BlockHash;
+use futures::stream::BoxStream;
+use libsqlite3_sys::{self as ffi, sqlite3};
+pub use nomen_core::api_types::{
+    ApiUsage, BlockchainRow, EventProvenance, ExportedName, OpenOffer, RelayContribution,
+};
 use nostr_sdk::EventId;
 use secp256k1::XOnlyPublicKey;
-use sqlx::{FromRow, SqlitePool};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteRow},
+    ConnectOptions, Connection, FromRow, Row, SqliteConnection, SqlitePool,
+};
 
 use crate::{
     config::{Cli, Config},
-    util::{Hash160, Name, NomenKind, Nsid},
+    error::NomenError,
+    protocol::LABEL_SEPARATOR,
+    util::{Approval, Deactivation, Hash160, Name, NameKind, NomenKind, Nsid, Offer, RecordAnchor},
 };
 
-static MIGRATIONS: [&str; 16] = [
-    "CREATE TABLE index_height (blockheight INTEGER PRIMARY KEY, blockhash);",
-    "CREATE TABLE blockchain (id INTEGER PRIMARY KEY, fingerprint, nsid, blockhash, txid, blocktime, blockheight, txheight, vout, kind, indexed_at);",
-    "CREATE TABLE name_events (name, fingerprint, nsid, pubkey, created_at, event_id, records, indexed_at, raw_event);",
-    "CREATE UNIQUE INDEX name_events_unique_idx ON name_events(name, pubkey);",
-    "CREATE INDEX name_events_created_at_idx ON name_events(created_at);",
-    "CREATE TABLE transfer_events (nsid, name, fingerprint, pubkey, created_at, event_id, content, indexed_at, raw_event);",
-    "CREATE UNIQUE INDEX transfer_events_unique_idx ON transfer_events(nsid)",
-
-    // We order by blockheight -> txheight (height of tx inside block) and then vout (output inside tx)
-    // to make sure we are always looking in exact blockchain order
-    "CREATE VIEW ordered_blockchain_vw AS
-        SELECT b.* FROM blockchain b
-        ORDER BY b.blockheight, b.txheight, b.vout",
-
-    // Someone could theoretically try to claim a name a second time, we want to rank each blockchain event
-    // in order, partitioned by name. So if Person A claims 'domain-name' first, then Person B also claims 'domain-name'
-    // second, then Person A will be ranked 1, and Person B will be ranked 2.
-    "CREATE VIEW ranked_name_vw AS
-        SELECT ne.*, ROW_NUMBER() OVER (PARTITION BY ne.name) as row
-        FROM ordered_blockchain_vw b
-        JOIN name_events ne on b.fingerprint = ne.fingerprint AND b.nsid = ne.nsid
-        WHERE b.kind = 'create';",
-
-    // We select everyone that has rank 1. This is always going to be first claimed on blockchain.
-    "CREATE VIEW name_vw AS 
-        SELECT * FROM ranked_name_vw WHERE row = 1;",
-
-    // Starting with a valid name event, follow the graph recursively to each successive transfer_event (if such exists),
-    // connecting pubkey -> content (next pubkey) -> pubkey -> content (next pubkey), etc. The resulting query returns
-    // the successive owners of each name
-    "CREATE VIEW ownership_chain_vw AS
-        WITH RECURSIVE owners(name, pk) as (
-            SELECT name, pubkey FROM name_vw
-            UNION ALL
-            SELECT te.name, te.content
-                FROM transfer_events te
-                JOIN owners ON te.pubkey = owners.pk AND te.name = owners.name
-                JOIN blockchain b on te.nsid = b.nsid AND te.fingerprint = b.fingerprint
-                WHERE b.kind = 'transfer'
-        )
-        SELECT name, pk FROM owners;",
-
-    // Partition over the names, and only return the final value (the latest owner)
-    "CREATE VIEW owners_vw AS
-        SELECT DISTINCT name, last_value(pk) OVER (PARTITION BY name) AS pubkey 
-        FROM ownership_chain_vw;",
-
-    // This table is used to cache the owners_vw results, to avoid a full graph traversal every time.
-    "CREATE TABLE name_owners (name, pubkey);",
-
-    "CREATE VIEW records_vw AS
-        SELECT ne.* FROM name_owners no
-        JOIN name_events ne on no.name = ne.name AND no.pubkey = ne.pubkey
-        ORDER BY ne.created_at DESC;",
-
-    "CREATE VIEW detail_vw AS
-        SELECT 
-            b.nsid,
-            b.blockhash,
-            b.blocktime,
-            b.txid,
-            b.vout,
-            b.blockheight,
-            r.name, 
-            COALESCE(r.records, '{}') as records,
-            r.pubkey,
-            r.created_at as records_created_at
-        FROM records_vw r
-        JOIN ordered_blockchain_vw b ON r.fingerprint = b.fingerprint AND r.nsid = b.nsid;",
-
-    "CREATE TABLE event_log (created_at, type, data);",
-];
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Last version of the old `schema` table, before moving to versioned migrations.
+/// Versions 0-15 are `0001_initial`, version 16 is `0002_quarantine`.
+const LEGACY_SCHEMA_VERSION: i64 = 15;
 
 pub async fn initialize(config: &Config) -> anyhow::Result<SqlitePool> {
     let conn = config.sqlite().await?;
+    migrate(config, &conn, None).await?;
+    Ok(conn)
+}
+
+/// Bring the schema to `target` (default: latest), applying or reverting migrations as needed.
+/// The database file, unless it is in memory, is backed up before anything is changed.
+pub async fn migrate(
+    config: &Config,
+    conn: &SqlitePool,
+    target: Option<i64>,
+) -> anyhow::Result<()> {
+    adopt_legacy_schema(conn).await?;
+
+    let latest = latest_schema_version();
+    let target = target.unwrap_or(latest);
+    if target < 0 || target > latest {
+        bail!(NomenError::Validation(format!(
+            "Unknown schema version {target}, latest is {latest}"
+        )));
+    }
+
+    let applied = applied_versions(conn).await?;
+    let current = applied.iter().copied().max().unwrap_or_default();
+    let pending = MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .any(|m| m.version <= target && !applied.contains(&m.version));
+    if !pending && current <= target {
+        return Ok(());
+    }
 
-    sqlx::query("CREATE TABLE IF NOT EXISTS schema (version);")
-        .execute(&conn)
+    if !applied.is_empty() && !config.data_in_memory() {
+        backup(config, conn).await?;
+    }
+
+    if current > target {
+        log::info!("Reverting schema from version {current} to {target}");
+        MIGRATOR.undo(conn, target).await?;
+    } else {
+        log::info!("Migrating schema from version {current} to {target}");
+        let migrator = Migrator {
+            migrations: MIGRATOR
+                .iter()
+                .filter(|m| m.version <= target)
+                .cloned()
+                .collect::<Vec<_>>()
+                .into(),
+            ..MIGRATOR
+        };
+        migrator.run(conn).await?;
+    }
+
+    // The audit log only exists from its own migration on.
+    if !applied.is_empty() && target >= AUDIT_LOG_VERSION {
+        let detail = format!("schema version {current} to {target}");
+        audit(conn, LOCAL_ACTOR, "db migrate", &detail).await?;
+    }
+    Ok(())
+}
+
+/// Databases created before versioned migrations track their schema in a `schema` table.
+/// Mark the equivalent migrations as applied, so they are not run a second time.
+async fn adopt_legacy_schema(conn: &SqlitePool) -> anyhow::Result<()> {
+    let (exists,) = sqlx::query_as::<_, (bool,)>(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'schema';",
+    )
+    .fetch_one(conn)
+    .await?;
+    if !exists {
+        return Ok(());
+    }
+
+    let (version,) = sqlx::query_as::<_, (i64,)>("SELECT COALESCE(MAX(version), -1) FROM schema;")
+        .fetch_one(conn)
         .await?;
+    if version < LEGACY_SCHEMA_VERSION {
+        bail!(NomenError::Config(format!(
+            "Database schema version {version} is too old to migrate, delete the database and reindex"
+        )));
+    }
 
-    let (version,) =
-        sqlx::query_as::<_, (i64,)>("SELECT COALESCE(MAX(version) + 1, 0) FROM schema;")
-            .fetch_one(&conn)
-            .await?;
+    let mut tx = conn.begin().await?;
+    tx.ensure_migrations_table().await?;
+    let baseline = if version > LEGACY_SCHEMA_VERSION {
+        2
+    } else {
+        1
+    };
+    for migration in MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration() && m.version <= baseline)
+    {
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES (?, ?, TRUE, ?, 0);",
+        )
+        .bind(migration.version)
+        .bind(migration.description.as_ref())
+        .bind(migration.checksum.as_ref())
+        .execute(&mut tx)
+        .await?;
+    }
+    sqlx::query("DROP TABLE schema;").execute(&mut tx).await?;
+    tx.commit().await?;
 
-    for (idx, migration) in MIGRATIONS[version as usize..].iter().enumerate() {
-        let version = idx as i64 + version;
-        let mut tx = conn.begin().await?;
-        log::debug!("Migrations schema version {version}");
-        sqlx::query(migration).execute(&mut tx).await?;
-        sqlx::query("INSERT INTO schema (version) VALUES (?);")
-            .bind(version)
-            .execute(&mut tx)
+    log::info!("Adopted legacy schema version {version} as migration {baseline}");
+    Ok(())
+}
+
+async fn applied_versions(conn: &SqlitePool) -> anyhow::Result<Vec<i64>> {
+    let mut conn = conn.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    Ok(conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| m.version)
+        .collect())
+}
+
+/// Latest migration known to this version of Nomen.
+pub fn latest_schema_version() -> i64 {
+    MIGRATOR.iter().map(|m| m.version).max().unwrap_or_default()
+}
+
+/// Latest applied migration.
+pub async fn schema_version(conn: &SqlitePool) -> anyhow::Result<i64> {
+    Ok(applied_versions(conn)
+        .await?
+        .into_iter()
+        .max()
+        .unwrap_or_default())
+}
+
+/// Migration adding the `audit_log` table.
+const AUDIT_LOG_VERSION: i64 = 26;
+
+/// Tables that belong to one operator, and are left out of exported snapshots.
+const OPERATOR_TABLES: [&str; 7] = [
+    "api_keys",
+    "api_usage",
+    "audit_log",
+    "checkpoint",
+    "checkpoint_names",
+    "outbox",
+    "watch_list",
+];
+
+/// Copy the database to a new file at `path`, without the operator's own tables.
+pub async fn snapshot(conn: &SqlitePool, path: &Path) -> anyhow::Result<()> {
+    sqlx::query("VACUUM INTO ?;")
+        .bind(path.to_string_lossy())
+        .execute(conn)
+        .await?;
+
+    let options = SqliteConnectOptions::new().filename(path);
+    let snapshot = SqlitePool::connect_with(options).await?;
+    for table in OPERATOR_TABLES {
+        sqlx::query(&format!("DELETE FROM {table};"))
+            .execute(&snapshot)
             .await?;
-        tx.commit().await?;
     }
+    sqlx::query("VACUUM;").execute(&snapshot).await?;
+    snapshot.close().await;
+    Ok(())
+}
+
+/// How long to wait before retrying a backup that found the database locked.
+const BACKUP_RETRY: Duration = Duration::from_millis(100);
+
+/// Copy the database to a new file at `path` with SQLite's online backup API.
+///
+/// The copy is all of the database as it was when the backup started, including the operator's
+/// tables. The indexer and server can keep writing meanwhile.
+pub async fn backup_to(conn: &SqlitePool, path: &Path) -> anyhow::Result<()> {
+    let mut source = conn.acquire().await?;
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true);
+    let mut dest = SqliteConnection::connect_with(&options).await?;
+    {
+        let mut source = source.lock_handle().await?;
+        let mut dest = dest.lock_handle().await?;
+        online_backup(source.as_raw_handle(), dest.as_raw_handle())?;
+    }
+    dest.close().await?;
+    Ok(())
+}
+
+/// Copy every page of `source` to `dest` in one step, so the copy is consistent.
+fn online_backup(source: NonNull<sqlite3>, dest: NonNull<sqlite3>) -> anyhow::Result<()> {
+    // SAFETY: both handles are open connections, locked by the caller for the whole backup.
+    unsafe {
+        let backup = ffi::sqlite3_backup_init(
+            dest.as_ptr(),
+            c"main".as_ptr(),
+            source.as_ptr(),
+            c"main".as_ptr(),
+        );
+        if backup.is_null() {
+            bail!(NomenError::Db(sqlite_error(dest)));
+        }
+        while let ffi::SQLITE_OK | ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED =
+            ffi::sqlite3_backup_step(backup, -1)
+        {
+            std::thread::sleep(BACKUP_RETRY);
+        }
+        // Finishing returns the error that stopped the backup, if any.
+        if ffi::sqlite3_backup_finish(backup) != ffi::SQLITE_OK {
+            bail!(NomenError::Db(format!(
+                "Backup failed: {}",
+                sqlite_error(dest)
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The message of the last error on the connection `db`.
+unsafe fn sqlite_error(db: NonNull<sqlite3>) -> String {
+    CStr::from_ptr(ffi::sqlite3_errmsg(db.as_ptr()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Run SQLite's integrity check on the database file at `path`. Returns the problems found.
+pub async fn integrity_check(path: &Path) -> anyhow::Result<Vec<String>> {
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    let mut conn = SqliteConnection::connect_with(&options).await?;
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check;")
+        .fetch_all(&mut conn)
+        .await?;
+    conn.close().await?;
+    Ok(problems.into_iter().filter(|p| p != "ok").collect())
+}
 
+/// A private, empty in-memory database with the latest schema, e.g. to replay indexing into.
+pub async fn scratch() -> anyhow::Result<SqlitePool> {
+    let conn = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await?;
+    MIGRATOR.run(&conn).await?;
     Ok(conn)
 }
 
+/// Open a snapshot file without changing it.
+pub async fn open_snapshot(path: &Path) -> anyhow::Result<SqlitePool> {
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    Ok(SqlitePool::connect_with(options).await?)
+}
+
+async fn backup(config: &Config, conn: &SqlitePool) -> anyhow::Result<()> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let mut path = config.data().into_os_string();
+    path.push(format!(".backup-{now}"));
+    let path = path.to_string_lossy().to_string();
+
+    log::info!("Backing up database to {path}");
+    sqlx::query("VACUUM INTO ?;")
+        .bind(&path)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Size and fee of a transaction carrying a Nomen output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxStats {
+    /// Fee in sats, if all inputs could be looked up.
+    pub fee: Option<u64>,
+    pub size: usize,
+    pub vsize: usize,
+}
+
+/// The first spendable output of a transaction carrying a Nomen output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    pub vout: usize,
+    pub value: u64,
+    /// Hex encoded script pubkey.
+    pub script: String,
+}
+
 // TODO: combine these arguments into a simpler set for <8
 #[allow(clippy::too_many_arguments)]
 pub async fn insert_blockchain(
-    conn: &SqlitePool,
+    conn: &mut SqliteConnection,
     fingerprint: [u8; 5],
     nsid: Nsid,
     blockhash: String,
@@ -124,7 +337,16 @@ pub async fn insert_blockchain(
     txheight: usize,
     vout: usize,
     kind: NomenKind,
+    commitment: Option<[u8; 32]>,
+    tx: TxStats,
+    anchor: Option<Anchor>,
 ) -> anyhow::Result<()> {
+    // A transaction mined again after a reorg replaces its pending (or revoked) row.
+    sqlx::query("DELETE FROM blockchain WHERE txid = ? AND vout = ? AND status <> 'valid';")
+        .bind(&txid)
+        .bind(vout as i64)
+        .execute(&mut *conn)
+        .await?;
     sqlx::query(include_str!("./queries/insert_namespace.sql"))
         .bind(hex::encode(fingerprint))
         .bind(nsid.to_string())
@@ -135,12 +357,286 @@ pub async fn insert_blockchain(
         .bind(txheight as i64)
         .bind(vout as i64)
         .bind(kind.to_string())
+        .bind(tx.fee.map(|f| f as i64))
+        .bind(tx.size as i64)
+        .bind(tx.vsize as i64)
+        .bind(anchor.as_ref().map(|a| a.vout as i64))
+        .bind(anchor.as_ref().map(|a| a.value as i64))
+        .bind(anchor.map(|a| a.script))
+        .bind(commitment.map(hex::encode))
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Anchor outputs that are not spent yet, as (txid, vout).
+pub async fn unspent_anchors(conn: &SqlitePool) -> anyhow::Result<Vec<(String, i64)>> {
+    Ok(sqlx::query_as::<_, (String, i64)>(
+        "SELECT txid, anchor_vout FROM blockchain WHERE anchor_vout IS NOT NULL AND spent_txid IS NULL;",
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+#[derive(FromRow)]
+pub struct PendingTx {
+    pub id: i64,
+    pub txid: String,
+    /// The stale block the transaction was in.
+    pub blockhash: String,
+    pub kind: String,
+}
+
+/// Nomen transactions from blocks that were reorganized away, not mined again yet.
+pub async fn pending_txs(conn: &SqlitePool) -> anyhow::Result<Vec<PendingTx>> {
+    Ok(sqlx::query_as::<_, PendingTx>(
+        "SELECT id, txid, blockhash, kind FROM blockchain WHERE status = 'pending' ORDER BY id;",
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Mark the blockchain row `id` as conflicted out.
+pub async fn revoke_tx(conn: &SqlitePool, id: i64, reason: &str) -> anyhow::Result<()> {
+    sqlx::query("UPDATE blockchain SET status = 'revoked', status_reason = ? WHERE id = ?;")
+        .bind(reason)
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Why the claim of the name with `fingerprint` (hex encoded) was revoked, if it was, and no
+/// other claim of the name is left.
+pub async fn revoked_claim(conn: &SqlitePool, fingerprint: &str) -> anyhow::Result<Option<String>> {
+    Ok(sqlx::query_as::<_, (String,)>(
+        "SELECT COALESCE(status_reason, '') FROM blockchain b
+        WHERE fingerprint = ?1 AND kind = 'create' AND status = 'revoked'
+        AND NOT EXISTS (SELECT 1 FROM ordered_blockchain_vw v WHERE v.fingerprint = ?1 AND v.kind = 'create')
+        ORDER BY id DESC LIMIT 1;",
+    )
+    .bind(fingerprint)
+    .fetch_optional(conn)
+    .await?
+    .map(|(reason,)| reason))
+}
+
+/// Record that the anchor output `txid:vout` was spent by `spent_txid` at `spent_height`.
+pub async fn spend_anchor(
+    conn: &mut SqliteConnection,
+    txid: &str,
+    vout: i64,
+    spent_txid: &str,
+    spent_height: i64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "UPDATE blockchain SET spent_txid = ?, spent_height = ? WHERE txid = ? AND anchor_vout = ?;",
+    )
+    .bind(spent_txid)
+    .bind(spent_height)
+    .bind(txid)
+    .bind(vout)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// A `blockchain` row, selected with the columns of `BlockchainRow`.
+fn blockchain_row(row: SqliteRow) -> Result<BlockchainRow, sqlx::Error> {
+    Ok(BlockchainRow {
+        id: row.try_get("id")?,
+        fingerprint: row.try_get("fingerprint")?,
+        nsid: row.try_get("nsid")?,
+        blockhash: row.try_get("blockhash")?,
+        txid: row.try_get("txid")?,
+        blocktime: row.try_get("blocktime")?,
+        blockheight: row.try_get("blockheight")?,
+        txheight: row.try_get("txheight")?,
+        vout: row.try_get("vout")?,
+        kind: row.try_get("kind")?,
+        fee: row.try_get("fee")?,
+        size: row.try_get("size")?,
+        vsize: row.try_get("vsize")?,
+        anchor_vout: row.try_get("anchor_vout")?,
+        anchor_value: row.try_get("anchor_value")?,
+        anchor_script: row.try_get("anchor_script")?,
+        spent_txid: row.try_get("spent_txid")?,
+        spent_height: row.try_get("spent_height")?,
+        commitment: row.try_get("commitment")?,
+    })
+}
+
+/// Rows at or above `from_height` with an id after `cursor`, in insertion order.
+pub async fn blockchain_page(
+    conn: &SqlitePool,
+    from_height: i64,
+    cursor: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<BlockchainRow>> {
+    Ok(sqlx::query(
+        "SELECT id, fingerprint, nsid, blockhash, txid, blocktime, blockheight, txheight, vout, kind, fee, size, vsize,
+            anchor_vout, anchor_value, anchor_script, spent_txid, spent_height, commitment
+        FROM blockchain WHERE blockheight >= ? AND id > ? AND status = 'valid' ORDER BY id LIMIT ?;",
+    )
+    .bind(from_height)
+    .bind(cursor)
+    .bind(limit)
+    .try_map(blockchain_row)
+    .fetch_all(conn)
+    .await?)
+}
+
+pub async fn insert_blockchain_row(conn: &SqlitePool, row: &BlockchainRow) -> anyhow::Result<()> {
+    sqlx::query(include_str!("./queries/insert_namespace.sql"))
+        .bind(&row.fingerprint)
+        .bind(&row.nsid)
+        .bind(&row.blockhash)
+        .bind(&row.txid)
+        .bind(row.blocktime)
+        .bind(row.blockheight)
+        .bind(row.txheight)
+        .bind(row.vout)
+        .bind(&row.kind)
+        .bind(row.fee)
+        .bind(row.size)
+        .bind(row.vsize)
+        .bind(row.anchor_vout)
+        .bind(row.anchor_value)
+        .bind(&row.anchor_script)
+        .bind(&row.commitment)
         .execute(conn)
         .await?;
+    if let (Some(vout), Some(spent_txid), Some(spent_height)) =
+        (row.anchor_vout, &row.spent_txid, row.spent_height)
+    {
+        spend_anchor(
+            &mut *conn.acquire().await?,
+            &row.txid,
+            vout,
+            spent_txid,
+            spent_height,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Up to `n` blockchain rows picked at random.
+pub async fn sample_blockchain_rows(
+    conn: &SqlitePool,
+    n: usize,
+) -> anyhow::Result<Vec<BlockchainRow>> {
+    Ok(sqlx::query(
+        "SELECT id, fingerprint, nsid, blockhash, txid, blocktime, blockheight, txheight, vout, kind, fee, size, vsize,
+            anchor_vout, anchor_value, anchor_script, spent_txid, spent_height, commitment
+        FROM blockchain WHERE status = 'valid' ORDER BY RANDOM() LIMIT ?;",
+    )
+    .bind(n as i64)
+    .try_map(blockchain_row)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// A value from the `meta` table, such as the network the index was built from.
+pub async fn meta(conn: &SqlitePool, key: &str) -> anyhow::Result<Option<String>> {
+    let value = sqlx::query_as::<_, (String,)>("SELECT value FROM meta WHERE key = ?;")
+        .bind(key)
+        .fetch_optional(conn)
+        .await?;
+    Ok(value.map(|(value,)| value))
+}
+
+pub async fn set_meta(conn: &SqlitePool, key: &str, value: &str) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO meta (key, value) VALUES (?, ?) ON CONFLICT (key) DO UPDATE SET value = excluded.value;",
+    )
+    .bind(key)
+    .bind(value)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
 
+/// Tables built from the chain and the relays, emptied by `reset_index`.
+const INDEX_TABLES: [&str; 16] = [
+    "blockchain",
+    "index_height",
+    "name_events",
+    "transfer_events",
+    "name_owners",
+    "records_history",
+    "offers",
+    "deactivations",
+    "subname_approvals",
+    "event_sources",
+    "relay_sync",
+    "seen_events",
+    "similar_names",
+    "checkpoint",
+    "checkpoint_names",
+    "profiles",
+];
+
+/// Empty the index, so it is built again from the node and the relays. The operator's API keys,
+/// watch list, outbox and wallet are kept.
+pub async fn reset_index(conn: &SqlitePool) -> anyhow::Result<()> {
+    let mut tx = conn.begin().await?;
+    for table in INDEX_TABLES {
+        sqlx::query(&format!("DELETE FROM {table};"))
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await?;
     Ok(())
 }
 
+/// Height and hash of the last indexed block.
+pub async fn index_tip(conn: &SqlitePool) -> anyhow::Result<Option<(i64, String)>> {
+    Ok(sqlx::query_as::<_, (i64, String)>(
+        "SELECT blockheight, blockhash FROM index_height ORDER BY blockheight DESC LIMIT 1;",
+    )
+    .fetch_optional(conn)
+    .await?)
+}
+
+/// The last `n` indexed blocks, oldest first.
+pub async fn indexed_blocks(conn: &SqlitePool, n: usize) -> anyhow::Result<Vec<(i64, String)>> {
+    let mut blocks = sqlx::query_as::<_, (i64, String)>(
+        "SELECT blockheight, blockhash FROM index_height ORDER BY blockheight DESC LIMIT ?;",
+    )
+    .bind(n as i64)
+    .fetch_all(conn)
+    .await?;
+    blocks.reverse();
+    Ok(blocks)
+}
+
+/// Raw name or transfer events created at or after `since`, with a rowid after `cursor`.
+pub async fn raw_events_page(
+    conn: &SqlitePool,
+    kind: NameKind,
+    since: i64,
+    cursor: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<(i64, String)>> {
+    let table = match kind {
+        NameKind::Name => "name_events",
+        NameKind::Transfer => "transfer_events",
+    };
+    Ok(sqlx::query_as::<_, (i64, String)>(&format!(
+        "SELECT rowid, raw_event FROM {table}
+        WHERE raw_event IS NOT NULL AND created_at >= ? AND rowid > ? ORDER BY rowid LIMIT ?;"
+    ))
+    .bind(since)
+    .bind(cursor)
+    .bind(limit)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// The height after the last block marked indexed. Every scanned block is marked, with or without
+/// Nomen outputs, in the same transaction as what was found in it.
 pub async fn next_index_height(conn: &SqlitePool) -> anyhow::Result<usize> {
     let (h,) =
         sqlx::query_as::<_, (i64,)>("SELECT COALESCE(MAX(blockheight), 0) + 1 FROM index_height;")
@@ -150,7 +646,7 @@ pub async fn next_index_height(conn: &SqlitePool) -> anyhow::Result<usize> {
 }
 
 pub async fn insert_index_height(
-    conn: &SqlitePool,
+    conn: &mut SqliteConnection,
     height: i64,
     blockhash: &BlockHash,
 ) -> anyhow::Result<()> {
@@ -195,27 +691,247 @@ pub async fn insert_create_event(
 
 #[derive(FromRow)]
 pub struct NameDetails {
+    pub nsid: String,
     pub blockhash: String,
     pub txid: String,
     pub blocktime: i64,
     pub vout: i64,
     pub blockheight: i64,
+    pub txheight: i64,
+    pub fee: Option<i64>,
+    pub size: Option<i64>,
+    pub vsize: Option<i64>,
+    pub anchor_vout: Option<i64>,
+    pub anchor_value: Option<i64>,
+    pub anchor_script: Option<String>,
+    pub spent_txid: Option<String>,
+    pub spent_height: Option<i64>,
+    /// `valid`, or `pending` while the transaction waits to be mined again after a reorg.
+    pub status: String,
     pub name: String,
     pub records: String,
     pub pubkey: String,
     pub records_created_at: i64,
 }
 
-pub async fn name_details(conn: &SqlitePool, query: &str) -> anyhow::Result<NameDetails> {
+/// Details of the name whose nsid (hex or bech32) or name is `query`.
+pub async fn name_details(conn: &SqlitePool, query: &str) -> anyhow::Result<Option<NameDetails>> {
+    let nsid = query
+        .parse::<Nsid>()
+        .map(|nsid| nsid.to_string())
+        .unwrap_or_else(|_| query.to_string());
     let details =
         sqlx::query_as::<_, NameDetails>("SELECT * FROM detail_vw WHERE nsid = ? or name = ?")
+            .bind(nsid)
             .bind(query)
-            .bind(query)
-            .fetch_one(conn)
+            .fetch_optional(conn)
             .await?;
     Ok(details)
 }
 
+/// Records with one of `keys` from the parents of `name`, each from the nearest parent that has it.
+pub async fn inherited_records(
+    conn: &SqlitePool,
+    name: &str,
+    keys: &[String],
+) -> anyhow::Result<HashMap<String, String>> {
+    let records = sqlx::query_as::<_, (String, String)>(
+        "SELECT j.key, j.value FROM detail_vw d, json_each(d.records) j
+        WHERE substr(?1, -length(d.name) - 1) = '.' || d.name
+            AND j.key IN (SELECT value FROM json_each(?2))
+        ORDER BY length(d.name);",
+    )
+    .bind(name)
+    .bind(serde_json::to_string(keys)?)
+    .fetch_all(conn)
+    .await?;
+    // Nearer parents have longer names, and come last.
+    Ok(records.into_iter().collect())
+}
+
+/// The signed records event of `name` by `pubkey` (hex encoded), as JSON.
+pub async fn name_event_json(
+    conn: &SqlitePool,
+    name: &str,
+    pubkey: &str,
+) -> anyhow::Result<Option<String>> {
+    Ok(sqlx::query_as::<_, (String,)>(
+        "SELECT raw_event FROM name_events WHERE name = ? AND pubkey = ? AND raw_event IS NOT NULL;",
+    )
+    .bind(name)
+    .bind(pubkey)
+    .fetch_optional(conn)
+    .await?
+    .map(|(event,)| event))
+}
+
+#[derive(FromRow)]
+pub struct OwnedName {
+    pub name: String,
+    pub nsid: String,
+    pub blockheight: i64,
+    pub records_created_at: i64,
+}
+
+/// Names currently owned by `pubkey` (hex encoded), ordered by name.
+pub async fn names_for_pubkey(conn: &SqlitePool, pubkey: &str) -> anyhow::Result<Vec<OwnedName>> {
+    Ok(sqlx::query_as::<_, OwnedName>(
+        "SELECT name, nsid, blockheight, records_created_at FROM detail_vw WHERE pubkey = ? ORDER BY name;",
+    )
+    .bind(pubkey)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Pubkeys of every current name owner.
+pub async fn owner_pubkeys(conn: &SqlitePool) -> anyhow::Result<Vec<String>> {
+    let pubkeys = sqlx::query_as::<_, (String,)>("SELECT DISTINCT pubkey FROM name_owners;")
+        .fetch_all(conn)
+        .await?;
+    Ok(pubkeys.into_iter().map(|(p,)| p).collect())
+}
+
+/// Nostr profile (kind 0 metadata) of a name owner.
+#[derive(FromRow, Debug, Clone, Default)]
+pub struct Profile {
+    pub pubkey: String,
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub picture: Option<String>,
+    pub about: Option<String>,
+    pub created_at: i64,
+}
+
+pub async fn insert_profile(conn: &SqlitePool, profile: &Profile) -> anyhow::Result<()> {
+    sqlx::query(include_str!("./queries/insert_profile.sql"))
+        .bind(&profile.pubkey)
+        .bind(&profile.name)
+        .bind(&profile.display_name)
+        .bind(&profile.picture)
+        .bind(&profile.about)
+        .bind(profile.created_at)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn profile(conn: &SqlitePool, pubkey: &str) -> anyhow::Result<Option<Profile>> {
+    Ok(sqlx::query_as::<_, Profile>(
+        "SELECT pubkey, name, display_name, picture, about, created_at FROM profiles WHERE pubkey = ?;",
+    )
+    .bind(pubkey)
+    .fetch_optional(conn)
+    .await?)
+}
+
+/// One page of the current records for `name`, ordered by key, with the total number of records.
+pub async fn name_records_page(
+    conn: &SqlitePool,
+    name: &str,
+    limit: i64,
+    offset: i64,
+) -> anyhow::Result<(Vec<(String, String)>, i64)> {
+    let records = sqlx::query_as::<_, (String, String)>(
+        "SELECT r.key, r.value FROM detail_vw d, json_each(d.records) r
+        WHERE d.name = ? ORDER BY r.key LIMIT ? OFFSET ?;",
+    )
+    .bind(name)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(conn)
+    .await?;
+    let (total,) = sqlx::query_as::<_, (i64,)>(
+        "SELECT COUNT(*) FROM detail_vw d, json_each(d.records) WHERE d.name = ?;",
+    )
+    .bind(name)
+    .fetch_one(conn)
+    .await?;
+    Ok((records, total))
+}
+
+/// One page of the names directly below `name` (`<label>.<name>`), with the total number of children.
+pub async fn child_names(
+    conn: &SqlitePool,
+    name: &str,
+    limit: i64,
+    offset: i64,
+) -> anyhow::Result<(Vec<(String, String)>, i64)> {
+    let suffix = format!(".{name}");
+    let children = sqlx::query_as::<_, (String, String)>(
+        "SELECT nsid, name FROM detail_vw
+        WHERE substr(name, -length(?1)) = ?1 AND instr(substr(name, 1, length(name) - length(?1)), '.') = 0
+        ORDER BY name LIMIT ?2 OFFSET ?3;",
+    )
+    .bind(&suffix)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(conn)
+    .await?;
+    let (total,) = sqlx::query_as::<_, (i64,)>(
+        "SELECT COUNT(*) FROM detail_vw
+        WHERE substr(name, -length(?1)) = ?1 AND instr(substr(name, 1, length(name) - length(?1)), '.') = 0;",
+    )
+    .bind(&suffix)
+    .fetch_one(conn)
+    .await?;
+    Ok((children, total))
+}
+
+/// Which names a [`list_names`] query returns.
+pub enum NameFilter<'a> {
+    /// Names containing the text. An empty string matches every name.
+    Search(&'a str),
+
+    /// Names directly below this name.
+    Children(&'a str),
+}
+
+/// Up to `limit` names (nsid, name) after `cursor` in name order, with the total number of
+/// names matching `filter`. Pages by name rather than by OFFSET, so pages stay stable while
+/// the index grows.
+pub async fn list_names(
+    conn: &SqlitePool,
+    filter: NameFilter<'_>,
+    cursor: Option<&str>,
+    limit: i64,
+) -> anyhow::Result<(Vec<(String, String)>, i64)> {
+    let (condition, arg) = match filter {
+        NameFilter::Search(q) => ("instr(name, ?1)", q.to_lowercase()),
+        NameFilter::Children(name) => (
+            "substr(name, -length(?1)) = ?1 AND instr(substr(name, 1, length(name) - length(?1)), '.') = 0",
+            format!(".{name}"),
+        ),
+    };
+
+    let names = sqlx::query_as::<_, (String, String)>(&format!(
+        "SELECT nsid, name FROM detail_vw WHERE {condition} AND (?2 IS NULL OR name > ?2)
+        ORDER BY name LIMIT ?3;"
+    ))
+    .bind(&arg)
+    .bind(cursor)
+    .bind(limit)
+    .fetch_all(conn)
+    .await?;
+    let (total,) = sqlx::query_as::<_, (i64,)>(&format!(
+        "SELECT COUNT(*) FROM detail_vw WHERE {condition};"
+    ))
+    .bind(&arg)
+    .fetch_one(conn)
+    .await?;
+    Ok((names, total))
+}
+
+/// Block time of the earliest on-chain output for a name, if it has been indexed.
+pub async fn anchor_time(conn: &SqlitePool, fingerprint: [u8; 5]) -> anyhow::Result<Option<i64>> {
+    let (t,) = sqlx::query_as::<_, (Option<i64>,)>(
+        "SELECT MIN(blocktime) FROM blockchain WHERE fingerprint = ?;",
+    )
+    .bind(hex::encode(fingerprint))
+    .fetch_one(conn)
+    .await?;
+    Ok(t)
+}
+
 pub async fn last_records_time(conn: &SqlitePool) -> anyhow::Result<u64> {
     let (t,) = sqlx::query_as::<_, (i64,)>("SELECT COALESCE(MAX(created_at), 0) FROM name_events;")
         .fetch_one(conn)
@@ -234,6 +950,9 @@ pub async fn insert_name_event(
     event_id: EventId,
     records: String,
     raw_event: String,
+    expires_at: Option<i64>,
+    anchor: Option<&RecordAnchor>,
+    current_anchor: Option<&RecordAnchor>,
 ) -> anyhow::Result<()> {
     sqlx::query(include_str!("./queries/insert_name_event.sql"))
         .bind(name.to_string())
@@ -242,34 +961,192 @@ pub async fn insert_name_event(
         .bind(pubkey.to_string())
         .bind(created_at)
         .bind(event_id.to_string())
-        .bind(records)
-        .bind(raw_event)
+        .bind(&records)
+        .bind(&raw_event)
+        .bind(anchor.map(|a| a.txid.to_string()))
+        .bind(anchor.map(|a| a.height))
+        .bind(current_anchor.map(|a| a.txid.to_string()))
+        .bind(expires_at)
         .execute(conn)
         .await?;
+    sqlx::query(
+        "INSERT INTO records_history (event_id, name, fingerprint, nsid, pubkey, created_at, records, indexed_at, raw_event, anchor_txid, anchor_height, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, unixepoch(), ?, ?, ?, ?) ON CONFLICT DO NOTHING;",
+    )
+    .bind(event_id.to_string())
+    .bind(name.to_string())
+    .bind(hex::encode(fingerprint))
+    .bind(nsid.to_string())
+    .bind(pubkey.to_string())
+    .bind(created_at)
+    .bind(records)
+    .bind(raw_event)
+    .bind(anchor.map(|a| a.txid.to_string()))
+    .bind(anchor.map(|a| a.height))
+    .bind(expires_at)
+    .execute(conn)
+    .await?;
     Ok(())
 }
 
-pub async fn name_records(
-    conn: &SqlitePool,
-    name: String,
-) -> anyhow::Result<Option<HashMap<String, String>>> {
-    let content =
-        sqlx::query_as::<_, (String,)>("SELECT records from detail_vw where name = ? LIMIT 1;")
-            .bind(name)
-            .fetch_optional(conn)
-            .await?;
-
-    let records = content
-        .map(|s| s.0)
-        .map(|records| serde_json::from_str(&records))
-        .transpose()?;
-    Ok(records)
+#[derive(FromRow, Serialize)]
+pub struct HistoricalRecords {
+    /// Owner of the name at the time.
+    pub pubkey: String,
+    /// Height of the block the owner took over the name in.
+    pub owned_since: i64,
+    /// The owner's latest records event up to then, if any.
+    pub event_id: Option<String>,
+    pub records: Option<String>,
+    pub created_at: Option<i64>,
 }
 
-pub async fn top_level_names(
+/// What the records of `name` were as of block `height`, or as of `time` if there is no height:
+/// its owner then, and their latest records event created up to `time`. As for current records,
+/// an event bound to the anchor of the time wins, and events bound to other anchors don't count.
+/// If that event had expired by `time`, or the owner had deactivated the name after publishing it,
+/// it has no records. Only the owner's latest deactivation is kept, so an older one a later records
+/// event undid is not seen.
+pub async fn records_at(
     conn: &SqlitePool,
-    query: Option<String>,
-) -> anyhow::Result<Vec<(String, String)>> {
+    name: &str,
+    height: Option<i64>,
+    time: i64,
+) -> anyhow::Result<Option<HistoricalRecords>> {
+    Ok(sqlx::query_as::<_, HistoricalRecords>(
+        "WITH owner AS (
+            SELECT name, pubkey, blockheight FROM ownership_history_vw
+            WHERE name = ?1 AND blockheight <= ?2 AND blocktime <= ?3
+            ORDER BY blockheight DESC LIMIT 1
+        ), history AS (
+            SELECT r.*, r.anchor_txid = (
+                SELECT b.txid FROM ordered_blockchain_vw b
+                WHERE b.fingerprint = r.fingerprint AND b.nsid = r.nsid
+                    AND b.blockheight <= ?2 AND b.blocktime <= ?3
+                ORDER BY b.blockheight DESC LIMIT 1
+            ) AS bound
+            FROM records_history r JOIN owner o ON r.name = o.name AND r.pubkey = o.pubkey
+            WHERE r.created_at <= ?4
+        ), latest AS (
+            SELECT * FROM history WHERE anchor_txid IS NULL OR bound
+            ORDER BY COALESCE(bound, 0) DESC, created_at DESC LIMIT 1
+        )
+        SELECT o.pubkey, o.blockheight AS owned_since, l.event_id,
+            CASE WHEN (l.expires_at IS NULL OR l.expires_at > ?4) AND NOT EXISTS (
+                SELECT 1 FROM deactivations d
+                WHERE d.name = o.name AND d.pubkey = o.pubkey
+                    AND d.created_at >= l.created_at AND d.created_at <= ?4
+            ) THEN l.records END AS records,
+            l.created_at
+        FROM owner o LEFT JOIN latest l;",
+    )
+    .bind(name)
+    .bind(height.unwrap_or(i64::MAX))
+    .bind(if height.is_some() { i64::MAX } else { time })
+    .bind(time)
+    .fetch_optional(conn)
+    .await?)
+}
+
+/// The records JSON and creation time of the records event `event_id` of `name`, from the history.
+pub async fn history_records(
+    conn: &SqlitePool,
+    name: &str,
+    event_id: &str,
+) -> anyhow::Result<Option<(String, i64)>> {
+    Ok(sqlx::query_as::<_, (String, i64)>(
+        "SELECT records, created_at FROM records_history WHERE name = ? AND event_id = ?;",
+    )
+    .bind(name)
+    .bind(event_id)
+    .fetch_optional(conn)
+    .await?)
+}
+
+/// The latest transaction of `fingerprint` and `nsid`, which records events can be bound to.
+pub async fn current_anchor(
+    conn: &SqlitePool,
+    fingerprint: [u8; 5],
+    nsid: Nsid,
+) -> anyhow::Result<Option<RecordAnchor>> {
+    let anchor = sqlx::query_as::<_, (String, i64)>(
+        "SELECT txid, blockheight FROM current_anchor_vw WHERE fingerprint = ? AND nsid = ?;",
+    )
+    .bind(hex::encode(fingerprint))
+    .bind(nsid.to_string())
+    .fetch_optional(conn)
+    .await?;
+    anchor
+        .map(|(txid, height)| {
+            Ok(RecordAnchor {
+                txid: txid.parse()?,
+                height,
+            })
+        })
+        .transpose()
+}
+
+/// The commitment of the earliest claim of `nsid` that is not revoked, if it has one.
+pub async fn claim_commitment(
+    conn: &SqlitePool,
+    fingerprint: [u8; 5],
+    nsid: Nsid,
+) -> anyhow::Result<Option<[u8; 32]>> {
+    let commitment = sqlx::query_as::<_, (Option<String>,)>(
+        "SELECT commitment FROM ordered_blockchain_vw WHERE fingerprint = ? AND nsid = ? AND kind = 'create' LIMIT 1;",
+    )
+    .bind(hex::encode(fingerprint))
+    .bind(nsid.to_string())
+    .fetch_optional(conn)
+    .await?
+    .and_then(|(c,)| c);
+    commitment
+        .map(|c| {
+            <[u8; 32]>::try_from(hex::decode(c)?)
+                .map_err(|_| NomenError::Db("Stored commitment is not 32 bytes".into()).into())
+        })
+        .transpose()
+}
+
+/// Contents of the records events of `nsid` seen so far, created at or before `created_at`.
+pub async fn records_history_contents(
+    conn: &SqlitePool,
+    fingerprint: [u8; 5],
+    nsid: Nsid,
+    created_at: i64,
+) -> anyhow::Result<Vec<String>> {
+    let contents = sqlx::query_as::<_, (String,)>(
+        "SELECT records FROM records_history WHERE fingerprint = ? AND nsid = ? AND created_at <= ?;",
+    )
+    .bind(hex::encode(fingerprint))
+    .bind(nsid.to_string())
+    .bind(created_at)
+    .fetch_all(conn)
+    .await?;
+    Ok(contents.into_iter().map(|(c,)| c).collect())
+}
+
+pub async fn name_records(
+    conn: &SqlitePool,
+    name: String,
+) -> anyhow::Result<Option<HashMap<String, String>>> {
+    let content =
+        sqlx::query_as::<_, (String,)>("SELECT records from detail_vw where name = ? LIMIT 1;")
+            .bind(name)
+            .fetch_optional(conn)
+            .await?;
+
+    let records = content
+        .map(|s| s.0)
+        .map(|records| serde_json::from_str(&records))
+        .transpose()?;
+    Ok(records)
+}
+
+pub async fn top_level_names(
+    conn: &SqlitePool,
+    query: Option<String>,
+) -> anyhow::Result<Vec<(String, String)>> {
     let sql = match query {
         Some(q) => sqlx::query_as::<_, (String, String)>(
             "SELECT nsid, name FROM detail_vw WHERE instr(name, ?) ORDER BY name;",
@@ -283,6 +1160,346 @@ pub async fn top_level_names(
     Ok(sql.fetch_all(conn).await?)
 }
 
+/// A registration, transfer or records update, as listed on the explorer's activity page.
+#[derive(FromRow, Debug)]
+pub struct Activity {
+    /// `registration`, `transfer` or `records`.
+    pub kind: String,
+    pub name: String,
+    /// The owner, or the new owner of a transfer.
+    pub pubkey: String,
+    pub time: i64,
+}
+
+/// Recent activity, newest first, skipping the first `offset` items.
+pub async fn activity(conn: &SqlitePool, limit: i64, offset: i64) -> anyhow::Result<Vec<Activity>> {
+    Ok(sqlx::query_as::<_, Activity>(
+        "SELECT kind, name, pubkey, time FROM activity_vw ORDER BY time DESC, name LIMIT ? OFFSET ?;",
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(conn)
+    .await?)
+}
+
+pub async fn activity_count(conn: &SqlitePool) -> anyhow::Result<i64> {
+    let (count,) = sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM activity_vw;")
+        .fetch_one(conn)
+        .await?;
+    Ok(count)
+}
+
+/// Take the next unused derivation index of `descriptor`, starting at 0.
+pub async fn take_derivation_index(conn: &SqlitePool, descriptor: &str) -> anyhow::Result<i64> {
+    let (index,) = sqlx::query_as::<_, (i64,)>(
+        "INSERT INTO wallet_derivation (descriptor, next_index) VALUES (?, 1)
+        ON CONFLICT (descriptor) DO UPDATE SET next_index = next_index + 1
+        RETURNING next_index - 1;",
+    )
+    .bind(descriptor)
+    .fetch_one(conn)
+    .await?;
+    Ok(index)
+}
+
+/// Replace every stored pair of similar names with `pairs` of (name, similar name, score).
+pub async fn replace_similar_names(
+    conn: &SqlitePool,
+    pairs: &[(String, String, f64)],
+) -> anyhow::Result<()> {
+    let mut tx = conn.begin().await?;
+    sqlx::query("DELETE FROM similar_names;")
+        .execute(&mut tx)
+        .await?;
+    for (name, similar, score) in pairs {
+        sqlx::query("INSERT INTO similar_names (name, similar, score) VALUES (?, ?, ?);")
+            .bind(name)
+            .bind(similar)
+            .bind(score)
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Names that look like `name`, with their similarity, most similar first.
+pub async fn similar_names(conn: &SqlitePool, name: &str) -> anyhow::Result<Vec<(String, f64)>> {
+    Ok(sqlx::query_as::<_, (String, f64)>(
+        "SELECT similar, score FROM similar_names WHERE name = ? ORDER BY score DESC, similar;",
+    )
+    .bind(name)
+    .fetch_all(conn)
+    .await?)
+}
+
+#[derive(FromRow)]
+pub struct NameExport {
+    pub name: String,
+    pub nsid: String,
+    pub pubkey: String,
+    pub blockheight: i64,
+    pub records: String,
+}
+
+impl NameExport {
+    /// The name as one JSON object, with its records parsed.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(&ExportedName {
+            name: self.name.clone(),
+            nsid: self.nsid.clone(),
+            pubkey: self.pubkey.clone(),
+            blockheight: self.blockheight,
+            records: serde_json::from_str(&self.records)?,
+        })?)
+    }
+}
+
+pub fn export_names(conn: &SqlitePool) -> BoxStream<'_, Result<NameExport, sqlx::Error>> {
+    sqlx::query_as::<_, NameExport>(
+        "SELECT name, nsid, pubkey, blockheight, records FROM detail_vw ORDER BY name;",
+    )
+    .fetch(conn)
+}
+
+/// Every distinct record key in use by any name, sorted.
+pub async fn record_keys(conn: &SqlitePool) -> anyhow::Result<Vec<String>> {
+    let keys = sqlx::query_as::<_, (String,)>(
+        "SELECT DISTINCT j.key FROM detail_vw d, json_each(d.records) j ORDER BY j.key;",
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(keys.into_iter().map(|(k,)| k).collect())
+}
+
+/// The index tip and every indexed name, sorted bytewise, read at the same point in time.
+pub async fn names_at_tip(
+    conn: &SqlitePool,
+) -> anyhow::Result<(Option<(i64, String)>, Vec<String>)> {
+    let mut tx = conn.begin().await?;
+    let tip = sqlx::query_as::<_, (i64, String)>(
+        "SELECT blockheight, blockhash FROM index_height ORDER BY blockheight DESC LIMIT 1;",
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+    let names =
+        sqlx::query_as::<_, (String,)>("SELECT DISTINCT name FROM detail_vw ORDER BY name;")
+            .fetch_all(&mut tx)
+            .await?;
+    tx.commit().await?;
+    Ok((tip, names.into_iter().map(|(name,)| name).collect()))
+}
+
+/// Replace the checkpoint with the signed `event` at `index_height`, and the `names` it commits to.
+pub async fn save_checkpoint(
+    conn: &SqlitePool,
+    index_height: i64,
+    event: &str,
+    names: &[String],
+) -> anyhow::Result<()> {
+    let mut tx = conn.begin().await?;
+    sqlx::query("DELETE FROM checkpoint_names;")
+        .execute(&mut tx)
+        .await?;
+    for (position, name) in names.iter().enumerate() {
+        sqlx::query("INSERT INTO checkpoint_names (position, name) VALUES (?, ?);")
+            .bind(position as i64)
+            .bind(name)
+            .execute(&mut tx)
+            .await?;
+    }
+    sqlx::query("INSERT OR REPLACE INTO checkpoint (id, index_height, event) VALUES (1, ?, ?);")
+        .bind(index_height)
+        .bind(event)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// The height and event of the latest checkpoint, and the names it commits to in order.
+pub async fn checkpoint(conn: &SqlitePool) -> anyhow::Result<Option<(i64, String, Vec<String>)>> {
+    let mut tx = conn.begin().await?;
+    let checkpoint = sqlx::query_as::<_, (i64, String)>(
+        "SELECT index_height, event FROM checkpoint WHERE id = 1;",
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+    let Some((index_height, event)) = checkpoint else {
+        return Ok(None);
+    };
+    let names =
+        sqlx::query_as::<_, (String,)>("SELECT name FROM checkpoint_names ORDER BY position;")
+            .fetch_all(&mut tx)
+            .await?;
+    tx.commit().await?;
+    Ok(Some((
+        index_height,
+        event,
+        names.into_iter().map(|(name,)| name).collect(),
+    )))
+}
+
+/// A stored name or transfer event, as checked by `db verify`.
+#[derive(FromRow, Serialize)]
+pub struct StoredEvent {
+    pub rowid: i64,
+    pub name: String,
+    pub nsid: String,
+    pub pubkey: String,
+    pub event_id: String,
+    pub raw_event: String,
+}
+
+pub async fn stored_name_events(conn: &SqlitePool) -> anyhow::Result<Vec<StoredEvent>> {
+    Ok(sqlx::query_as::<_, StoredEvent>(
+        "SELECT rowid, name, nsid, pubkey, event_id, raw_event FROM name_events;",
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Stored name events of `nsid`.
+pub async fn claim_name_events(
+    conn: &SqlitePool,
+    fingerprint: [u8; 5],
+    nsid: Nsid,
+) -> anyhow::Result<Vec<StoredEvent>> {
+    Ok(sqlx::query_as::<_, StoredEvent>(
+        "SELECT rowid, name, nsid, pubkey, event_id, raw_event FROM name_events WHERE fingerprint = ? AND nsid = ?;",
+    )
+    .bind(hex::encode(fingerprint))
+    .bind(nsid.to_string())
+    .fetch_all(conn)
+    .await?)
+}
+
+pub async fn stored_transfer_events(conn: &SqlitePool) -> anyhow::Result<Vec<StoredEvent>> {
+    Ok(sqlx::query_as::<_, StoredEvent>(
+        "SELECT rowid, name, nsid, pubkey, event_id, raw_event FROM transfer_events;",
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+/// An on-chain claim stored in the `blockchain` table.
+#[derive(FromRow, Serialize, Clone)]
+pub struct StoredAnchor {
+    pub id: i64,
+    pub fingerprint: String,
+    pub nsid: String,
+    pub blockhash: String,
+    pub txid: String,
+    pub blockheight: i64,
+    pub vout: i64,
+    pub kind: String,
+}
+
+pub async fn stored_anchors(conn: &SqlitePool) -> anyhow::Result<Vec<StoredAnchor>> {
+    Ok(sqlx::query_as::<_, StoredAnchor>(
+        "SELECT id, fingerprint, nsid, blockhash, txid, blockheight, vout, kind FROM blockchain WHERE status = 'valid';",
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Valid on-chain claims of `fingerprint`.
+pub async fn name_anchors(
+    conn: &SqlitePool,
+    fingerprint: [u8; 5],
+) -> anyhow::Result<Vec<StoredAnchor>> {
+    Ok(sqlx::query_as::<_, StoredAnchor>(
+        "SELECT id, fingerprint, nsid, blockhash, txid, blockheight, vout, kind FROM blockchain WHERE status = 'valid' AND fingerprint = ?;",
+    )
+    .bind(hex::encode(fingerprint))
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Raw records and transfer events stored for `name`, oldest first.
+pub async fn name_raw_events(conn: &SqlitePool, name: &str) -> anyhow::Result<Vec<String>> {
+    let events = sqlx::query_as::<_, (String,)>(
+        "SELECT raw_event FROM (
+            SELECT created_at, raw_event FROM records_history WHERE name = ?1
+            UNION
+            SELECT created_at, raw_event FROM name_events WHERE name = ?1
+            UNION
+            SELECT created_at, raw_event FROM transfer_events WHERE name = ?1
+        ) WHERE raw_event IS NOT NULL ORDER BY created_at;",
+    )
+    .bind(name)
+    .fetch_all(conn)
+    .await?;
+    Ok(events.into_iter().map(|(e,)| e).collect())
+}
+
+/// Delete the records and transfer events of `name`, to index them again.
+pub async fn delete_name_events(conn: &SqlitePool, name: &str) -> anyhow::Result<()> {
+    let mut tx = conn.begin().await?;
+    for table in ["records_history", "name_events", "transfer_events"] {
+        sqlx::query(&format!("DELETE FROM {table} WHERE name = ?;"))
+            .bind(name)
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Ids of the most recently created name and transfer events in the index.
+pub async fn recent_event_ids(conn: &SqlitePool, limit: i64) -> anyhow::Result<Vec<String>> {
+    let ids = sqlx::query_as::<_, (String,)>(
+        "SELECT event_id FROM (
+            SELECT event_id, created_at FROM name_events
+            UNION ALL
+            SELECT event_id, created_at FROM transfer_events
+        ) ORDER BY created_at DESC LIMIT ?;",
+    )
+    .bind(limit)
+    .fetch_all(conn)
+    .await?;
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
+/// Move a row out of `table` into the `quarantine` table.
+pub async fn quarantine(
+    conn: &SqlitePool,
+    table: &str,
+    rowid: i64,
+    row_data: &str,
+    reason: &str,
+) -> anyhow::Result<()> {
+    let delete = match table {
+        "name_events" => "DELETE FROM name_events WHERE rowid = ?;",
+        "transfer_events" => "DELETE FROM transfer_events WHERE rowid = ?;",
+        "blockchain" => "DELETE FROM blockchain WHERE id = ?;",
+        _ => bail!(NomenError::Db(format!(
+            "Cannot quarantine rows from {table}"
+        ))),
+    };
+
+    let mut tx = conn.begin().await?;
+    sqlx::query(
+        "INSERT INTO quarantine (table_name, row_data, reason, quarantined_at) VALUES (?, ?, ?, unixepoch());",
+    )
+    .bind(table)
+    .bind(row_data)
+    .bind(reason)
+    .execute(&mut tx)
+    .await?;
+    if table == "name_events" {
+        sqlx::query(
+            "DELETE FROM records_history WHERE event_id = (SELECT event_id FROM name_events WHERE rowid = ?);",
+        )
+        .bind(rowid)
+        .execute(&mut tx)
+        .await?;
+    }
+    sqlx::query(delete).bind(rowid).execute(&mut tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
 pub async fn save_event(conn: &SqlitePool, evt_type: &str, evt_data: &str) -> anyhow::Result<()> {
     sqlx::query("INSERT INTO event_log (created_at, type, data) VALUES (unixepoch(), ?, ?);")
         .bind(evt_type)
@@ -292,12 +1509,27 @@ pub async fn save_event(conn: &SqlitePool, evt_type: &str, evt_data: &str) -> an
     Ok(())
 }
 
-pub async fn last_index_time(conn: &SqlitePool) -> anyhow::Result<i64> {
-    let (created_at,) = sqlx::query_as::<_, (i64,)>(
+/// Block time and fee rate (sat/vB) of every name claim confirmed at or after `since`, oldest first.
+/// Claims indexed before fees were tracked are left out.
+pub async fn claim_fee_rates(conn: &SqlitePool, since: i64) -> anyhow::Result<Vec<(i64, f64)>> {
+    Ok(sqlx::query_as::<_, (i64, f64)>(
+        "SELECT blocktime, CAST(fee AS REAL) / vsize FROM blockchain
+        WHERE kind = 'create' AND fee IS NOT NULL AND vsize > 0 AND blocktime >= ?
+        ORDER BY blocktime;",
+    )
+    .bind(since)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// When the indexer last ran, if it ever has.
+pub async fn last_index_time(conn: &SqlitePool) -> anyhow::Result<Option<i64>> {
+    let created_at = sqlx::query_as::<_, (i64,)>(
         "SELECT created_at FROM event_log WHERE type = 'index' ORDER BY created_at DESC LIMIT 1;",
     )
-    .fetch_one(conn)
-    .await?;
+    .fetch_optional(conn)
+    .await?
+    .map(|(created_at,)| created_at);
 
     Ok(created_at)
 }
@@ -360,3 +1592,1549 @@ pub async fn name_owner(conn: &SqlitePool, name: &str) -> anyhow::Result<Option<
 
     Ok(pubkey.and_then(|(pk,)| pk.parse::<XOnlyPublicKey>().ok()))
 }
+
+pub async fn insert_watch(conn: &SqlitePool, name: &Name) -> anyhow::Result<()> {
+    let fingerprint = Hash160::default()
+        .chain_update(name.as_ref().as_bytes())
+        .fingerprint();
+    sqlx::query(
+        "INSERT INTO watch_list (name, fingerprint, added_at) VALUES (?, ?, unixepoch()) ON CONFLICT DO NOTHING;",
+    )
+    .bind(name.to_string())
+    .bind(hex::encode(fingerprint))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Remove `name` from the watch list, returning whether it was there.
+pub async fn delete_watch(conn: &SqlitePool, name: &Name) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM watch_list WHERE name = ?;")
+        .bind(name.to_string())
+        .execute(conn)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(FromRow, Serialize)]
+pub struct WatchedName {
+    pub name: String,
+    pub added_at: i64,
+}
+
+pub async fn watch_list(conn: &SqlitePool) -> anyhow::Result<Vec<WatchedName>> {
+    Ok(
+        sqlx::query_as::<_, WatchedName>("SELECT name, added_at FROM watch_list ORDER BY name;")
+            .fetch_all(conn)
+            .await?,
+    )
+}
+
+/// The watched name with `fingerprint` (hex encoded), if any.
+pub async fn watched_name(conn: &SqlitePool, fingerprint: &str) -> anyhow::Result<Option<String>> {
+    let name = sqlx::query_as::<_, (String,)>("SELECT name FROM watch_list WHERE fingerprint = ?;")
+        .bind(fingerprint)
+        .fetch_optional(conn)
+        .await?;
+    Ok(name.map(|(n,)| n))
+}
+
+/// Actor of actions taken from the command line.
+pub const LOCAL_ACTOR: &str = "local";
+
+/// An entry of the audit log: an administrative or mutating action.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub created_at: i64,
+    /// `local` for the command line, `key:<name>` for an API key, or `ip:<address>` for an API
+    /// request without one.
+    pub actor: String,
+    pub action: String,
+    pub detail: String,
+}
+
+/// Append an action taken by `actor` to the audit log.
+pub async fn audit(
+    conn: &SqlitePool,
+    actor: &str,
+    action: &str,
+    detail: &str,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO audit_log (created_at, actor, action, detail) VALUES (unixepoch(), ?, ?, ?);",
+    )
+    .bind(actor)
+    .bind(action)
+    .bind(detail)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// The latest `limit` entries of the audit log, optionally only those of `actor`, oldest first.
+pub async fn audit_log(
+    conn: &SqlitePool,
+    actor: Option<&str>,
+    limit: i64,
+) -> anyhow::Result<Vec<AuditEntry>> {
+    let mut entries = sqlx::query_as::<_, AuditEntry>(
+        "SELECT id, created_at, actor, action, detail FROM audit_log
+        WHERE ?1 IS NULL OR actor = ?1 ORDER BY id DESC LIMIT ?2;",
+    )
+    .bind(actor)
+    .bind(limit)
+    .fetch_all(conn)
+    .await?;
+    entries.reverse();
+    Ok(entries)
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub created_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+/// Store a new API key, by the hex encoded SHA-256 hash of the key.
+pub async fn insert_api_key(conn: &SqlitePool, name: &str, key_hash: &str) -> anyhow::Result<()> {
+    let exists = sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM api_keys WHERE name = ?;")
+        .bind(name)
+        .fetch_one(conn)
+        .await?;
+    if exists.0 > 0 {
+        bail!(NomenError::Validation(format!(
+            "An API key named {name} already exists"
+        )));
+    }
+    sqlx::query("INSERT INTO api_keys (name, key_hash, created_at) VALUES (?, ?, unixepoch());")
+        .bind(name)
+        .bind(key_hash)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Revoke the API key `name`, returning whether there was an active key with that name.
+pub async fn revoke_api_key(conn: &SqlitePool, name: &str) -> anyhow::Result<bool> {
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = unixepoch() WHERE name = ? AND revoked_at IS NULL;",
+    )
+    .bind(name)
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn api_keys(conn: &SqlitePool) -> anyhow::Result<Vec<ApiKey>> {
+    Ok(sqlx::query_as::<_, ApiKey>(
+        "SELECT id, name, created_at, revoked_at FROM api_keys ORDER BY name;",
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+/// The active (not revoked) API key with `key_hash`, if any.
+pub async fn active_api_key(conn: &SqlitePool, key_hash: &str) -> anyhow::Result<Option<ApiKey>> {
+    Ok(sqlx::query_as::<_, ApiKey>(
+        "SELECT id, name, created_at, revoked_at FROM api_keys WHERE key_hash = ? AND revoked_at IS NULL;",
+    )
+    .bind(key_hash)
+    .fetch_optional(conn)
+    .await?)
+}
+
+/// Count a request to `endpoint` made with the API key `key_id`.
+pub async fn record_api_usage(
+    conn: &SqlitePool,
+    key_id: i64,
+    endpoint: &str,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO api_usage (key_id, day, endpoint, requests) VALUES (?, date('now'), ?, 1)
+        ON CONFLICT (key_id, day, endpoint) DO UPDATE SET requests = requests + 1;",
+    )
+    .bind(key_id)
+    .bind(endpoint)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Requests made with the API key `key_id`, newest day first.
+pub async fn api_usage(conn: &SqlitePool, key_id: i64) -> anyhow::Result<Vec<ApiUsage>> {
+    let rows = sqlx::query_as::<_, (String, String, i64)>(
+        "SELECT day, endpoint, requests FROM api_usage WHERE key_id = ? ORDER BY day DESC, endpoint;",
+    )
+    .bind(key_id)
+    .fetch_all(conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(day, endpoint, requests)| ApiUsage {
+            day,
+            endpoint,
+            requests,
+        })
+        .collect())
+}
+
+pub async fn insert_offer(conn: &SqlitePool, offer: &Offer) -> anyhow::Result<()> {
+    sqlx::query(include_str!("./queries/insert_offer.sql"))
+        .bind(offer.event_id.to_hex())
+        .bind(offer.name.to_string())
+        .bind(offer.nsid.to_string())
+        .bind(offer.seller.to_string())
+        .bind(offer.buyer.to_string())
+        .bind(offer.price as i64)
+        .bind(offer.psbt.to_string())
+        .bind(offer.created_at)
+        .bind(&offer.raw_event)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_approval(conn: &SqlitePool, approval: &Approval) -> anyhow::Result<()> {
+    sqlx::query(include_str!("./queries/insert_approval.sql"))
+        .bind(approval.event_id.to_hex())
+        .bind(&approval.name)
+        .bind(&approval.parent)
+        .bind(approval.pubkey.to_string())
+        .bind(approval.approver.to_string())
+        .bind(approval.created_at)
+        .bind(&approval.raw_event)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_deactivation(
+    conn: &SqlitePool,
+    deactivation: &Deactivation,
+) -> anyhow::Result<()> {
+    sqlx::query(include_str!("./queries/insert_deactivation.sql"))
+        .bind(deactivation.event_id.to_hex())
+        .bind(&deactivation.name)
+        .bind(deactivation.pubkey.to_string())
+        .bind(&deactivation.reason)
+        .bind(deactivation.created_at)
+        .bind(&deactivation.raw_event)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Whether the current owner of `name` has deactivated it since publishing their latest records.
+pub async fn name_deactivated(conn: &SqlitePool, name: &str) -> anyhow::Result<bool> {
+    let (deactivated,) = sqlx::query_as::<_, (bool,)>(
+        "SELECT EXISTS (
+            SELECT 1 FROM deactivations d
+            JOIN name_owners no ON d.name = no.name AND d.pubkey = no.pubkey
+            LEFT JOIN name_events ne ON ne.name = d.name AND ne.pubkey = d.pubkey
+            WHERE d.name = ? AND (ne.created_at IS NULL OR d.created_at >= ne.created_at)
+        );",
+    )
+    .bind(name)
+    .fetch_one(conn)
+    .await?;
+    Ok(deactivated)
+}
+
+/// Whether `name` and each of its parents below the top level name are approved for their current
+/// owner by the current owner of their parent. Top level names are always approved.
+pub async fn subname_approved(conn: &SqlitePool, name: &str) -> anyhow::Result<bool> {
+    let mut child = name;
+    while let Some((_, parent)) = child.split_once(LABEL_SEPARATOR) {
+        let (approved,) = sqlx::query_as::<_, (bool,)>(
+            "SELECT EXISTS (SELECT 1 FROM approved_subnames_vw WHERE name = ?);",
+        )
+        .bind(child)
+        .fetch_one(conn)
+        .await?;
+        if !approved {
+            return Ok(false);
+        }
+        child = parent;
+    }
+    Ok(true)
+}
+
+/// Open offers, newest first, optionally only those for `name`.
+pub async fn open_offers(conn: &SqlitePool, name: Option<&str>) -> anyhow::Result<Vec<OpenOffer>> {
+    let rows = sqlx::query_as::<_, (String, String, String, String, String, i64, String, i64)>(
+        "SELECT event_id, name, nsid, seller, buyer, price, psbt, created_at FROM open_offers_vw
+        WHERE ?1 IS NULL OR name = ?1 ORDER BY created_at DESC;",
+    )
+    .bind(name)
+    .fetch_all(conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(event_id, name, nsid, seller, buyer, price, psbt, created_at)| OpenOffer {
+                event_id,
+                name,
+                nsid,
+                seller,
+                buyer,
+                price,
+                psbt,
+                created_at,
+            },
+        )
+        .collect())
+}
+
+/// The newest event seen from `relay` for `kind`: its `created_at` and id.
+pub async fn relay_cursor(
+    conn: &SqlitePool,
+    relay: &str,
+    kind: u64,
+) -> anyhow::Result<Option<(i64, String)>> {
+    Ok(sqlx::query_as::<_, (i64, String)>(
+        "SELECT last_seen, last_event_id FROM relay_sync WHERE relay = ? AND kind = ?;",
+    )
+    .bind(relay)
+    .bind(kind as i64)
+    .fetch_optional(conn)
+    .await?)
+}
+
+/// Move the cursor of `relay` for `kind` forward to `last_seen`. It never moves back.
+pub async fn update_relay_cursor(
+    conn: &SqlitePool,
+    relay: &str,
+    kind: u64,
+    last_seen: i64,
+    last_event_id: &EventId,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO relay_sync (relay, kind, last_seen, last_event_id, synced_at) VALUES (?, ?, ?, ?, unixepoch())
+        ON CONFLICT (relay, kind) DO UPDATE SET
+        last_seen = excluded.last_seen,
+        last_event_id = excluded.last_event_id,
+        synced_at = excluded.synced_at
+        WHERE excluded.last_seen >= last_seen;",
+    )
+    .bind(relay)
+    .bind(kind as i64)
+    .bind(last_seen)
+    .bind(last_event_id.to_hex())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Record that `relay` supplied each of `event_ids`, for those that are indexed records or
+/// transfer events.
+pub async fn save_event_sources(
+    conn: &SqlitePool,
+    relay: &str,
+    event_ids: &[EventId],
+) -> anyhow::Result<()> {
+    let mut tx = conn.begin().await?;
+    for event_id in event_ids {
+        sqlx::query(
+            "INSERT OR IGNORE INTO event_sources (event_id, relay, first_seen) SELECT ?1, ?2, unixepoch()
+            WHERE EXISTS (SELECT 1 FROM records_history WHERE event_id = ?1)
+            OR EXISTS (SELECT 1 FROM name_events WHERE event_id = ?1)
+            OR EXISTS (SELECT 1 FROM transfer_events WHERE event_id = ?1);",
+        )
+        .bind(event_id.to_hex())
+        .bind(relay)
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// The indexed records and transfer events of `name`, newest first, with their relays.
+pub async fn name_event_sources(
+    conn: &SqlitePool,
+    name: &str,
+) -> anyhow::Result<Vec<EventProvenance>> {
+    let rows = sqlx::query_as::<_, (String, String, i64, Option<String>)>(
+        "SELECT e.event_id, e.kind, e.created_at, s.relay FROM (
+            SELECT event_id, 'records' AS kind, created_at FROM records_history WHERE name = ?1
+            UNION
+            SELECT event_id, 'records', created_at FROM name_events WHERE name = ?1
+            UNION
+            SELECT event_id, 'transfer', created_at FROM transfer_events WHERE name = ?1
+        ) e LEFT JOIN event_sources s ON s.event_id = e.event_id
+        ORDER BY e.created_at DESC, e.event_id, s.relay;",
+    )
+    .bind(name)
+    .fetch_all(conn)
+    .await?;
+
+    let mut events: Vec<EventProvenance> = vec![];
+    for (event_id, kind, created_at, relay) in rows {
+        if events.last().map(|e| &e.event_id) != Some(&event_id) {
+            events.push(EventProvenance {
+                event_id,
+                kind,
+                created_at,
+                relays: vec![],
+            });
+        }
+        if let (Some(event), Some(relay)) = (events.last_mut(), relay) {
+            event.relays.push(relay);
+        }
+    }
+    Ok(events)
+}
+
+/// Relays the current records event of `name` was fetched from.
+pub async fn records_event_sources(conn: &SqlitePool, name: &str) -> anyhow::Result<Vec<String>> {
+    let relays = sqlx::query_as::<_, (String,)>(
+        "SELECT relay FROM event_sources
+        WHERE event_id = (SELECT event_id FROM records_vw WHERE name = ?) ORDER BY relay;",
+    )
+    .bind(name)
+    .fetch_all(conn)
+    .await?;
+    Ok(relays.into_iter().map(|(relay,)| relay).collect())
+}
+
+/// Indexed events supplied by each relay, most first.
+pub async fn relay_contributions(conn: &SqlitePool) -> anyhow::Result<Vec<RelayContribution>> {
+    let rows = sqlx::query_as::<_, (String, i64, i64, i64)>(
+        "SELECT relay, COUNT(*) AS events,
+            SUM((SELECT COUNT(*) FROM event_sources o WHERE o.event_id = s.event_id) = 1) AS sole,
+            MAX(first_seen) AS last_seen
+        FROM event_sources s GROUP BY relay ORDER BY events DESC, relay;",
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(relay, events, sole, last_seen)| RelayContribution {
+            relay,
+            events,
+            sole,
+            last_seen,
+        })
+        .collect())
+}
+
+/// Which of `event_ids` of `kind` were already processed by the indexer, valid or not, or are in
+/// the index.
+pub async fn processed_events(
+    conn: &SqlitePool,
+    kind: nostr_sdk::Kind,
+    event_ids: &[EventId],
+) -> anyhow::Result<HashSet<EventId>> {
+    let table = if kind == Offer::kind() {
+        "offers"
+    } else {
+        match NameKind::try_from(kind)? {
+            NameKind::Name => "name_events",
+            NameKind::Transfer => "transfer_events",
+        }
+    };
+    let ids = serde_json::to_string(&event_ids.iter().map(EventId::to_hex).collect::<Vec<_>>())?;
+    let processed = sqlx::query_as::<_, (String,)>(&format!(
+        "SELECT value FROM json_each(?)
+        WHERE value IN (SELECT event_id FROM seen_events)
+            OR value IN (SELECT event_id FROM {table});"
+    ))
+    .bind(ids)
+    .fetch_all(conn)
+    .await?
+    .into_iter()
+    .map(|(id,)| EventId::from_hex(id))
+    .collect::<Result<_, _>>()?;
+    Ok(processed)
+}
+
+/// Record that each of `event_ids` of `kind` was processed, so it is skipped when fetched again.
+pub async fn save_seen_events(
+    conn: &SqlitePool,
+    kind: u64,
+    event_ids: &[EventId],
+) -> anyhow::Result<()> {
+    let mut tx = conn.begin().await?;
+    for event_id in event_ids {
+        sqlx::query(
+            "INSERT OR IGNORE INTO seen_events (event_id, kind, seen_at) VALUES (?, ?, unixepoch());",
+        )
+        .bind(event_id.to_hex())
+        .bind(kind as i64)
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Forget the processed events seen more than `retention` seconds ago, returning how many.
+pub async fn prune_seen_events(conn: &SqlitePool, retention: i64) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM seen_events WHERE seen_at < unixepoch() - ?;")
+        .bind(retention)
+        .execute(conn)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Queue a signed event for the relays. Queuing the same event again leaves it as it is.
+pub async fn queue_event(conn: &SqlitePool, event: &nostr_sdk::Event) -> anyhow::Result<()> {
+    schedule_event(conn, event, None).await
+}
+
+/// Queue a signed event for the relays, not to be sent before `publish_at` if given.
+pub async fn schedule_event(
+    conn: &SqlitePool,
+    event: &nostr_sdk::Event,
+    publish_at: Option<i64>,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO outbox (event_id, event, queued_at, next_attempt_at, publish_at)
+        VALUES (?, ?, unixepoch(), MAX(unixepoch(), COALESCE(?3, 0)), ?3)
+        ON CONFLICT DO NOTHING;",
+    )
+    .bind(event.id.to_hex())
+    .bind(serde_json::to_string(event)?)
+    .bind(publish_at)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[derive(FromRow, Serialize)]
+pub struct OutboxEvent {
+    pub event_id: String,
+    pub event: String,
+    pub queued_at: i64,
+    pub attempts: i64,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub publish_at: Option<i64>,
+}
+
+/// Queued events, oldest first. With `due`, only those whose next attempt is due.
+pub async fn outbox(conn: &SqlitePool, due: bool) -> anyhow::Result<Vec<OutboxEvent>> {
+    Ok(sqlx::query_as::<_, OutboxEvent>(
+        "SELECT event_id, event, queued_at, attempts, next_attempt_at, last_error, publish_at FROM outbox
+        WHERE NOT ? OR next_attempt_at <= unixepoch() ORDER BY queued_at, event_id;",
+    )
+    .bind(due)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Remove an event a relay acknowledged from the outbox.
+pub async fn outbox_sent(conn: &SqlitePool, event_id: &str) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM outbox WHERE event_id = ?;")
+        .bind(event_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Record a failed attempt, and schedule the next one `retry_in` seconds from now.
+pub async fn outbox_failed(
+    conn: &SqlitePool,
+    event_id: &str,
+    error: &str,
+    retry_in: i64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "UPDATE outbox SET attempts = attempts + 1, next_attempt_at = unixepoch() + ?, last_error = ?
+        WHERE event_id = ?;",
+    )
+    .bind(retry_in)
+    .bind(error)
+    .bind(event_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::config::ConfigFile;
+
+    #[tokio::test]
+    async fn test_in_memory() {
+        let config = Config::new(
+            Cli::parse_from(["nomen", "--data", ":memory:", "watch", "list"]),
+            ConfigFile::default(),
+        );
+        assert!(config.data_in_memory());
+
+        let pool = initialize(&config).await.unwrap();
+        insert_watch(&pool, &"smith".parse().unwrap())
+            .await
+            .unwrap();
+
+        // A second pool sees the same database, as long as the first is open.
+        let other = config.sqlite().await.unwrap();
+        let watched = watch_list(&other).await.unwrap();
+        assert_eq!(watched.len(), 1);
+        assert_eq!(watched[0].name, "smith");
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_isolated() {
+        let config = Config::new(
+            Cli::parse_from(["nomen", "--data", ":memory:", "watch", "list"]),
+            ConfigFile::default(),
+        );
+        let pool = initialize(&config).await.unwrap();
+        insert_watch(&pool, &"smith".parse().unwrap())
+            .await
+            .unwrap();
+
+        // Another config gets its own database, while clones share it.
+        let other = initialize(&Config::new(config.cli.clone(), ConfigFile::default()))
+            .await
+            .unwrap();
+        assert!(watch_list(&other).await.unwrap().is_empty());
+        let clone = config.clone().sqlite().await.unwrap();
+        assert_eq!(watch_list(&clone).await.unwrap().len(), 1);
+    }
+
+    /// A private, empty in-memory database, without any migrations.
+    async fn empty() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    fn in_memory_config() -> Config {
+        Config::new(
+            Cli::parse_from(["nomen", "--data", ":memory:", "db", "migrate"]),
+            ConfigFile::default(),
+        )
+    }
+
+    async fn table_exists(pool: &SqlitePool, table: &str) -> bool {
+        sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?;",
+        )
+        .bind(table)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_migrate() {
+        let config = in_memory_config();
+        let pool = empty().await;
+        let latest = latest_schema_version();
+
+        migrate(&config, &pool, None).await.unwrap();
+        assert_eq!(schema_version(&pool).await.unwrap(), latest);
+        assert!(table_exists(&pool, "audit_log").await);
+
+        migrate(&config, &pool, Some(AUDIT_LOG_VERSION - 1))
+            .await
+            .unwrap();
+        assert_eq!(schema_version(&pool).await.unwrap(), AUDIT_LOG_VERSION - 1);
+        assert!(!table_exists(&pool, "audit_log").await);
+        assert!(table_exists(&pool, "seen_events").await);
+
+        migrate(&config, &pool, None).await.unwrap();
+        assert_eq!(schema_version(&pool).await.unwrap(), latest);
+        let audited = audit_log(&pool, Some(LOCAL_ACTOR), 10).await.unwrap();
+        assert_eq!(audited.len(), 1);
+        assert_eq!(audited[0].action, "db migrate");
+
+        assert!(migrate(&config, &pool, Some(latest + 1)).await.is_err());
+    }
+
+    /// A database as created before versioned migrations: the initial schema, tracked at
+    /// `version` in the old `schema` table.
+    async fn legacy(version: i64) -> SqlitePool {
+        let pool = empty().await;
+        let initial = Migrator {
+            migrations: MIGRATOR
+                .iter()
+                .filter(|m| m.version == 1)
+                .cloned()
+                .collect::<Vec<_>>()
+                .into(),
+            ..MIGRATOR
+        };
+        initial.run(&pool).await.unwrap();
+        sqlx::query("DROP TABLE _sqlx_migrations;")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE schema (version);")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO schema (version) VALUES (?);")
+            .bind(version)
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_adopt_legacy_schema() {
+        let config = in_memory_config();
+
+        let pool = legacy(LEGACY_SCHEMA_VERSION).await;
+        migrate(&config, &pool, Some(1)).await.unwrap();
+        assert!(!table_exists(&pool, "schema").await);
+        assert_eq!(applied_versions(&pool).await.unwrap(), vec![1]);
+        migrate(&config, &pool, None).await.unwrap();
+        assert_eq!(
+            schema_version(&pool).await.unwrap(),
+            latest_schema_version()
+        );
+
+        let pool = legacy(LEGACY_SCHEMA_VERSION - 1).await;
+        assert!(migrate(&config, &pool, None).await.is_err());
+        assert!(table_exists(&pool, "schema").await);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_claim() {
+        let pool = scratch().await.unwrap();
+        let insert = |pool: &SqlitePool| {
+            let pool = pool.clone();
+            async move {
+                insert_blockchain(
+                    &mut pool.acquire().await.unwrap(),
+                    [1; 5],
+                    Nsid::from_slice(&[2; 20]).unwrap(),
+                    "00".repeat(32),
+                    "11".repeat(32),
+                    0,
+                    100,
+                    1,
+                    0,
+                    NomenKind::Create,
+                    None,
+                    TxStats {
+                        fee: None,
+                        size: 100,
+                        vsize: 100,
+                    },
+                    None,
+                )
+                .await
+            }
+        };
+        insert(&pool).await.unwrap();
+        sqlx::query("UPDATE blockchain SET status = 'pending';")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Mined again after the reorg.
+        insert(&pool).await.unwrap();
+        assert!(pending_txs(&pool).await.unwrap().is_empty());
+        assert_eq!(revoked_claim(&pool, "0101010101").await.unwrap(), None);
+
+        sqlx::query("UPDATE blockchain SET status = 'pending';")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let pending = pending_txs(&pool).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        revoke_tx(&pool, pending[0].id, "double spent")
+            .await
+            .unwrap();
+        assert_eq!(
+            revoked_claim(&pool, "0101010101").await.unwrap().as_deref(),
+            Some("double spent")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_anchor() {
+        let pool = scratch().await.unwrap();
+        let nsid = Nsid::from_slice(&[2; 20]).unwrap();
+        let pubkey: XOnlyPublicKey =
+            "d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f"
+                .parse()
+                .unwrap();
+        let claim = |txid: &str, height: usize, kind: NomenKind| {
+            let (pool, txid) = (pool.clone(), txid.repeat(32));
+            async move {
+                insert_blockchain(
+                    &mut pool.acquire().await.unwrap(),
+                    [1; 5],
+                    nsid,
+                    "00".repeat(32),
+                    txid,
+                    0,
+                    height,
+                    1,
+                    0,
+                    kind,
+                    None,
+                    TxStats {
+                        fee: None,
+                        size: 100,
+                        vsize: 100,
+                    },
+                    None,
+                )
+                .await
+            }
+        };
+        let anchor = |txid: &str, height: i64| RecordAnchor {
+            txid: txid.repeat(32).parse().unwrap(),
+            height,
+        };
+        let event = |id: &'static str, created_at: i64, anchor: Option<RecordAnchor>| {
+            let pool = pool.clone();
+            async move {
+                let current = current_anchor(&pool, [1; 5], nsid).await.unwrap();
+                insert_name_event(
+                    &pool,
+                    "smith".parse().unwrap(),
+                    [1; 5],
+                    nsid,
+                    pubkey,
+                    created_at,
+                    EventId::from_hex(id.repeat(32)).unwrap(),
+                    format!(r#"{{"ID":"{id}"}}"#),
+                    "{}".into(),
+                    None,
+                    anchor.as_ref(),
+                    current.as_ref(),
+                )
+                .await
+                .unwrap();
+            }
+        };
+        let records = || async {
+            sqlx::query_as::<_, (Option<String>,)>("SELECT records FROM records_vw;")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .0
+        };
+
+        claim("aa", 100, NomenKind::Create).await.unwrap();
+        event("01", 10, Some(anchor("aa", 100))).await;
+        sqlx::query("INSERT INTO name_owners VALUES ('smith', ?);")
+            .bind(pubkey.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(records().await.as_deref(), Some(r#"{"ID":"01"}"#));
+
+        // Transferred away and back: records bound to the first claim are not served.
+        claim("bb", 200, NomenKind::Transfer).await.unwrap();
+        assert_eq!(records().await, None);
+
+        // Neither is bound to the current anchor, so the newest wins.
+        event("02", 20, None).await;
+        assert_eq!(records().await.as_deref(), Some(r#"{"ID":"02"}"#));
+
+        // Bound to the current anchor wins over newer unbound events.
+        event("03", 15, Some(anchor("bb", 200))).await;
+        event("04", 30, None).await;
+        assert_eq!(records().await.as_deref(), Some(r#"{"ID":"03"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_records_at() {
+        let pool = scratch().await.unwrap();
+        let alice: XOnlyPublicKey =
+            "d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f"
+                .parse()
+                .unwrap();
+        let bob: XOnlyPublicKey =
+            "6b8e2f9b4d3d0d9e7c6b1f2f1d2b8f3e0c1a9a6c1a0f5d7f9d2e1c3b4a596877"
+                .parse()
+                .unwrap();
+        let (nsid_a, nsid_b) = (
+            Nsid::from_slice(&[1; 20]).unwrap(),
+            Nsid::from_slice(&[2; 20]).unwrap(),
+        );
+        for (nsid, txid, time, height, kind) in [
+            (nsid_a, "aa", 1000, 100, NomenKind::Create),
+            (nsid_b, "bb", 2000, 200, NomenKind::Transfer),
+        ] {
+            insert_blockchain(
+                &mut pool.acquire().await.unwrap(),
+                [1; 5],
+                nsid,
+                "00".repeat(32),
+                txid.repeat(32),
+                time,
+                height,
+                1,
+                0,
+                kind,
+                None,
+                TxStats {
+                    fee: None,
+                    size: 100,
+                    vsize: 100,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        }
+        sqlx::query("INSERT INTO transfer_events (nsid, name, fingerprint, pubkey, content) VALUES (?, 'smith', ?, ?, ?);")
+            .bind(nsid_b.to_string())
+            .bind(hex::encode([1; 5]))
+            .bind(alice.to_string())
+            .bind(bob.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (id, nsid, pubkey, created_at) in [
+            ("01", nsid_a, alice, 500),
+            ("02", nsid_a, alice, 1500),
+            ("03", nsid_b, bob, 2500),
+        ] {
+            insert_name_event(
+                &pool,
+                "smith".parse().unwrap(),
+                [1; 5],
+                nsid,
+                pubkey,
+                created_at,
+                EventId::from_hex(id.repeat(32)).unwrap(),
+                format!(r#"{{"ID":"{id}"}}"#),
+                "{}".into(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        let at = |height, time| {
+            let pool = pool.clone();
+            async move { records_at(&pool, "smith", height, time).await.unwrap() }
+        };
+        let before = at(Some(150), 1200).await.unwrap();
+        assert_eq!(before.pubkey, alice.to_string());
+        assert_eq!(before.owned_since, 100);
+        assert_eq!(before.records.as_deref(), Some(r#"{"ID":"01"}"#));
+        let replaced = at(Some(150), 2000).await.unwrap();
+        assert_eq!(replaced.records.as_deref(), Some(r#"{"ID":"02"}"#));
+        let transferred = at(Some(250), 3000).await.unwrap();
+        assert_eq!(transferred.pubkey, bob.to_string());
+        assert_eq!(transferred.owned_since, 200);
+        assert_eq!(transferred.records.as_deref(), Some(r#"{"ID":"03"}"#));
+        let no_records = at(Some(250), 2200).await.unwrap();
+        assert_eq!(no_records.pubkey, bob.to_string());
+        assert_eq!(no_records.records, None);
+        assert!(at(Some(50), 900).await.is_none());
+
+        // By time alone, the transfer at 2000 hasn't happened yet.
+        let by_time = at(None, 1800).await.unwrap();
+        assert_eq!(by_time.pubkey, alice.to_string());
+        assert_eq!(by_time.records.as_deref(), Some(r#"{"ID":"02"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_records_expiration() {
+        let pool = scratch().await.unwrap();
+        let pubkey: XOnlyPublicKey =
+            "d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f"
+                .parse()
+                .unwrap();
+        let nsid = Nsid::from_slice(&[1; 20]).unwrap();
+        insert_blockchain(
+            &mut pool.acquire().await.unwrap(),
+            [1; 5],
+            nsid,
+            "00".repeat(32),
+            "aa".repeat(32),
+            5,
+            100,
+            1,
+            0,
+            NomenKind::Create,
+            None,
+            TxStats {
+                fee: None,
+                size: 100,
+                vsize: 100,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO name_owners VALUES ('smith', ?);")
+            .bind(pubkey.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let event = |id: &'static str, created_at: i64, expires_at: i64| {
+            let pool = pool.clone();
+            async move {
+                insert_name_event(
+                    &pool,
+                    "smith".parse().unwrap(),
+                    [1; 5],
+                    nsid,
+                    pubkey,
+                    created_at,
+                    EventId::from_hex(id.repeat(32)).unwrap(),
+                    format!(r#"{{"ID":"{id}"}}"#),
+                    "{}".into(),
+                    Some(expires_at),
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            }
+        };
+        let records = || async {
+            sqlx::query_as::<_, (Option<String>,)>("SELECT records FROM records_vw;")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .0
+        };
+
+        // Expired records are not served, but stay in the history.
+        event("01", 10, 20).await;
+        assert_eq!(records().await, None);
+        let at = records_at(&pool, "smith", None, 15).await.unwrap().unwrap();
+        assert_eq!(at.records.as_deref(), Some(r#"{"ID":"01"}"#));
+        let at = records_at(&pool, "smith", None, 30).await.unwrap().unwrap();
+        assert_eq!(at.event_id, Some("01".repeat(32)));
+        assert_eq!(at.records, None);
+
+        let later = time::OffsetDateTime::now_utc().unix_timestamp() + 3600;
+        event("02", 40, later).await;
+        assert_eq!(records().await.as_deref(), Some(r#"{"ID":"02"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_deactivation() {
+        let pool = scratch().await.unwrap();
+        let pubkey: XOnlyPublicKey =
+            "d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f"
+                .parse()
+                .unwrap();
+        let nsid = Nsid::from_slice(&[1; 20]).unwrap();
+        insert_blockchain(
+            &mut pool.acquire().await.unwrap(),
+            [1; 5],
+            nsid,
+            "00".repeat(32),
+            "aa".repeat(32),
+            5,
+            100,
+            1,
+            0,
+            NomenKind::Create,
+            None,
+            TxStats {
+                fee: None,
+                size: 100,
+                vsize: 100,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO name_owners VALUES ('smith', ?);")
+            .bind(pubkey.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let event = |id: &'static str, created_at: i64| {
+            let pool = pool.clone();
+            async move {
+                insert_name_event(
+                    &pool,
+                    "smith".parse().unwrap(),
+                    [1; 5],
+                    nsid,
+                    pubkey,
+                    created_at,
+                    EventId::from_hex(id.repeat(32)).unwrap(),
+                    format!(r#"{{"ID":"{id}"}}"#),
+                    "{}".into(),
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            }
+        };
+        let records = || async {
+            sqlx::query_as::<_, (Option<String>,)>("SELECT records FROM records_vw;")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .0
+        };
+
+        let deactivate = |created_at: i64| {
+            let pool = pool.clone();
+            async move {
+                insert_deactivation(
+                    &pool,
+                    &Deactivation {
+                        event_id: EventId::from_hex("dd".repeat(32)).unwrap(),
+                        name: "smith".into(),
+                        pubkey,
+                        reason: String::new(),
+                        created_at,
+                        raw_event: "{}".into(),
+                    },
+                )
+                .await
+                .unwrap();
+            }
+        };
+
+        // A deactivation hides the records, which stay in the history.
+        event("01", 10).await;
+        deactivate(20).await;
+        assert_eq!(records().await, None);
+        assert!(name_deactivated(&pool, "smith").await.unwrap());
+        let at = records_at(&pool, "smith", None, 15).await.unwrap().unwrap();
+        assert_eq!(at.records.as_deref(), Some(r#"{"ID":"01"}"#));
+        let at = records_at(&pool, "smith", None, 25).await.unwrap().unwrap();
+        assert_eq!(at.records, None);
+
+        // An older deactivation doesn't replace a newer one.
+        deactivate(15).await;
+        assert_eq!(records().await, None);
+
+        // Newer records reactivate the name.
+        event("02", 30).await;
+        assert_eq!(records().await.as_deref(), Some(r#"{"ID":"02"}"#));
+        assert!(!name_deactivated(&pool, "smith").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_name_raw_events() {
+        let pool = scratch().await.unwrap();
+        let pubkey: XOnlyPublicKey =
+            "d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f"
+                .parse()
+                .unwrap();
+        let nsid = Nsid::from_slice(&[1; 20]).unwrap();
+        for (name, id, created_at) in [("smith", "02", 20), ("smith", "01", 10), ("jones", "03", 5)]
+        {
+            insert_name_event(
+                &pool,
+                name.parse().unwrap(),
+                [1; 5],
+                nsid,
+                pubkey,
+                created_at,
+                EventId::from_hex(id.repeat(32)).unwrap(),
+                "{}".into(),
+                format!(r#"{{"id":"{id}"}}"#),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+        insert_transfer_event(
+            &pool,
+            nsid,
+            pubkey,
+            30,
+            EventId::from_hex("04".repeat(32)).unwrap(),
+            "smith".parse().unwrap(),
+            [1; 5],
+            pubkey.to_string(),
+            r#"{"id":"04"}"#.into(),
+        )
+        .await
+        .unwrap();
+
+        // The latest records event is in both name_events and the history, but listed once.
+        let events = name_raw_events(&pool, "smith").await.unwrap();
+        assert_eq!(
+            events,
+            [r#"{"id":"01"}"#, r#"{"id":"02"}"#, r#"{"id":"04"}"#]
+        );
+
+        delete_name_events(&pool, "smith").await.unwrap();
+        assert!(name_raw_events(&pool, "smith").await.unwrap().is_empty());
+        assert_eq!(name_raw_events(&pool, "jones").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_event_sources() {
+        let pool = scratch().await.unwrap();
+        let pubkey: XOnlyPublicKey =
+            "d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f"
+                .parse()
+                .unwrap();
+        let nsid = Nsid::from_slice(&[1; 20]).unwrap();
+        let id = |id: &str| EventId::from_hex(id.repeat(32)).unwrap();
+        insert_name_event(
+            &pool,
+            "smith".parse().unwrap(),
+            [1; 5],
+            nsid,
+            pubkey,
+            10,
+            id("01"),
+            "{}".into(),
+            "{}".into(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        insert_transfer_event(
+            &pool,
+            nsid,
+            pubkey,
+            20,
+            id("02"),
+            "smith".parse().unwrap(),
+            [1; 5],
+            pubkey.to_string(),
+            "{}".into(),
+        )
+        .await
+        .unwrap();
+
+        // Events that aren't indexed, like "03", are not recorded.
+        save_event_sources(&pool, "wss://a", &[id("01"), id("02"), id("03")])
+            .await
+            .unwrap();
+        save_event_sources(&pool, "wss://b", &[id("01")])
+            .await
+            .unwrap();
+
+        let events = name_event_sources(&pool, "smith").await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, "transfer");
+        assert_eq!(events[0].relays, ["wss://a"]);
+        assert_eq!(events[1].event_id, "01".repeat(32));
+        assert_eq!(events[1].relays, ["wss://a", "wss://b"]);
+
+        let contributions = relay_contributions(&pool).await.unwrap();
+        let counts: Vec<_> = contributions
+            .iter()
+            .map(|c| (c.relay.as_str(), c.events, c.sole))
+            .collect();
+        assert_eq!(counts, [("wss://a", 2, 1), ("wss://b", 1, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_take_derivation_index() {
+        let pool = scratch().await.unwrap();
+        let (a, b) = ("wpkh(xpub-a/1/*)", "wpkh(xpub-b/1/*)");
+        assert_eq!(take_derivation_index(&pool, a).await.unwrap(), 0);
+        assert_eq!(take_derivation_index(&pool, a).await.unwrap(), 1);
+        assert_eq!(take_derivation_index(&pool, b).await.unwrap(), 0);
+        assert_eq!(take_derivation_index(&pool, a).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reset_index() {
+        use bitcoin::hashes::Hash;
+
+        let pool = scratch().await.unwrap();
+        assert_eq!(meta(&pool, "genesis").await.unwrap(), None);
+        set_meta(&pool, "genesis", "a").await.unwrap();
+        set_meta(&pool, "genesis", "b").await.unwrap();
+        assert_eq!(meta(&pool, "genesis").await.unwrap().as_deref(), Some("b"));
+
+        let mut conn = pool.acquire().await.unwrap();
+        insert_index_height(&mut conn, 100, &BlockHash::all_zeros())
+            .await
+            .unwrap();
+        drop(conn);
+        insert_api_key(&pool, "ops", "hash").await.unwrap();
+
+        reset_index(&pool).await.unwrap();
+        assert_eq!(index_tip(&pool).await.unwrap(), None);
+        assert_eq!(api_keys(&pool).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let pool = scratch().await.unwrap();
+        audit(&pool, LOCAL_ACTOR, "apikey add", "ops")
+            .await
+            .unwrap();
+        audit(&pool, "key:ops", "api tx", "00").await.unwrap();
+        audit(&pool, LOCAL_ACTOR, "watch add", "smith")
+            .await
+            .unwrap();
+
+        let actions =
+            |entries: Vec<AuditEntry>| entries.into_iter().map(|e| e.action).collect::<Vec<_>>();
+        assert_eq!(
+            actions(audit_log(&pool, None, 2).await.unwrap()),
+            ["api tx", "watch add"]
+        );
+        assert_eq!(
+            actions(audit_log(&pool, Some(LOCAL_ACTOR), 10).await.unwrap()),
+            ["apikey add", "watch add"]
+        );
+
+        // The audit log belongs to the operator, and survives a reset of the index.
+        reset_index(&pool).await.unwrap();
+        assert_eq!(audit_log(&pool, None, 10).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_seen_events() {
+        let pool = scratch().await.unwrap();
+        let seen = EventId::from_slice(&[1; 32]).unwrap();
+        let other = EventId::from_slice(&[2; 32]).unwrap();
+        save_seen_events(&pool, 38300, &[seen]).await.unwrap();
+        save_seen_events(&pool, 38300, &[seen]).await.unwrap();
+        let kind = NameKind::Name.into();
+        assert_eq!(
+            processed_events(&pool, kind, &[seen, other]).await.unwrap(),
+            HashSet::from([seen])
+        );
+
+        // Only events seen longer than the retention ago are forgotten.
+        assert_eq!(prune_seen_events(&pool, 60).await.unwrap(), 0);
+        sqlx::query("UPDATE seen_events SET seen_at = seen_at - 120;")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(prune_seen_events(&pool, 60).await.unwrap(), 1);
+        assert!(processed_events(&pool, kind, &[seen])
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Indexed events count as processed, without a seen_events row.
+        crate::subcommands::index_name(
+            &pool,
+            &nostr_sdk::Keys::generate(),
+            "smith",
+            &HashMap::new(),
+            100,
+        )
+        .await;
+        let (indexed,) = sqlx::query_as::<_, (String,)>("SELECT event_id FROM name_events;")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let indexed = EventId::from_hex(indexed).unwrap();
+        assert_eq!(
+            processed_events(&pool, kind, &[seen, indexed])
+                .await
+                .unwrap(),
+            HashSet::from([indexed])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint() {
+        let pool = scratch().await.unwrap();
+        assert!(checkpoint(&pool).await.unwrap().is_none());
+
+        let names = vec!["alice".to_string(), "bob".to_string(), "carol".to_string()];
+        save_checkpoint(&pool, 100, "{}", &names).await.unwrap();
+        save_checkpoint(&pool, 110, "{\"id\":1}", &names[..2])
+            .await
+            .unwrap();
+        let (height, event, stored) = checkpoint(&pool).await.unwrap().unwrap();
+        assert_eq!(height, 110);
+        assert_eq!(event, "{\"id\":1}");
+        assert_eq!(stored, names[..2]);
+    }
+
+    #[tokio::test]
+    async fn test_last_index_time() {
+        let pool = scratch().await.unwrap();
+        assert_eq!(last_index_time(&pool).await.unwrap(), None);
+        save_event(&pool, "index", "").await.unwrap();
+        assert!(last_index_time(&pool).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_schedule_event() {
+        let pool = scratch().await.unwrap();
+        let keys = nostr_sdk::Keys::generate();
+        let now = nostr_sdk::EventBuilder::new_text_note("now", &[])
+            .to_event(&keys)
+            .unwrap();
+        let later = nostr_sdk::EventBuilder::new_text_note("later", &[])
+            .to_event(&keys)
+            .unwrap();
+        let at = now.created_at.as_i64() + 3600;
+        queue_event(&pool, &now).await.unwrap();
+        schedule_event(&pool, &later, Some(at)).await.unwrap();
+
+        let due = outbox(&pool, true).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].event_id, now.id.to_hex());
+        assert_eq!(due[0].publish_at, None);
+
+        let all = outbox(&pool, false).await.unwrap();
+        let scheduled = all
+            .iter()
+            .find(|e| e.event_id == later.id.to_hex())
+            .unwrap();
+        assert_eq!(scheduled.publish_at, Some(at));
+        assert_eq!(scheduled.next_attempt_at, at);
+    }
+
+    #[tokio::test]
+    async fn test_subname_approved() {
+        let pool = scratch().await.unwrap();
+        for (name, pubkey) in [("smith", "a"), ("bob.smith", "b"), ("al.bob.smith", "c")] {
+            sqlx::query("INSERT INTO name_owners (name, pubkey) VALUES (?, ?);")
+                .bind(name)
+                .bind(pubkey)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let approve = |name: &'static str, parent, pubkey, approver| {
+            sqlx::query(
+                "INSERT INTO subname_approvals (name, parent, pubkey, approver) VALUES (?, ?, ?, ?);",
+            )
+            .bind(name)
+            .bind(parent)
+            .bind(pubkey)
+            .bind(approver)
+            .execute(&pool)
+        };
+
+        assert!(subname_approved(&pool, "smith").await.unwrap());
+        approve("al.bob.smith", "bob.smith", "c", "b")
+            .await
+            .unwrap();
+        assert!(!subname_approved(&pool, "al.bob.smith").await.unwrap());
+        // Approved by someone other than the owner of smith.
+        approve("bob.smith", "smith", "b", "x").await.unwrap();
+        assert!(!subname_approved(&pool, "bob.smith").await.unwrap());
+        approve("bob.smith", "smith", "b", "a").await.unwrap();
+        assert!(subname_approved(&pool, "bob.smith").await.unwrap());
+        assert!(subname_approved(&pool, "al.bob.smith").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_child_names() {
+        let pool = scratch().await.unwrap();
+        for (height, name) in [
+            (100, "smith"),
+            (101, "bob.smith"),
+            (102, "carol.smith"),
+            (103, "dave.smith"),
+            (104, "al.bob.smith"),
+            (105, "notsmith"),
+        ] {
+            let keys = nostr_sdk::Keys::generate();
+            crate::subcommands::index_name(&pool, &keys, name, &HashMap::new(), height).await;
+        }
+        let names = |page: Vec<(String, String)>| {
+            page.into_iter().map(|(_, name)| name).collect::<Vec<_>>()
+        };
+
+        let (first, total) = child_names(&pool, "smith", 2, 0).await.unwrap();
+        assert_eq!(names(first), ["bob.smith", "carol.smith"]);
+        assert_eq!(total, 3);
+        let (second, _) = child_names(&pool, "smith", 2, 2).await.unwrap();
+        assert_eq!(names(second), ["dave.smith"]);
+        let (grandchildren, total) = child_names(&pool, "bob.smith", 2, 0).await.unwrap();
+        assert_eq!(names(grandchildren), ["al.bob.smith"]);
+        assert_eq!(total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_inherited_records() {
+        let pool = scratch().await.unwrap();
+        for (height, name, records) in [
+            (
+                100,
+                "smith",
+                r#"{"LN":"smith@ln","WEB":"https://smith.example","EMAIL":"a@b"}"#,
+            ),
+            (101, "bob.smith", r#"{"LN":"bob@ln"}"#),
+            (102, "alice.bob.smith", "{}"),
+            (103, "notsmith", r#"{"WEB":"https://other.example"}"#),
+        ] {
+            let records: HashMap<String, String> = serde_json::from_str(records).unwrap();
+            let keys = nostr_sdk::Keys::generate();
+            crate::subcommands::index_name(&pool, &keys, name, &records, height).await;
+        }
+
+        let keys = ["LN".to_string(), "WEB".to_string()];
+        let records = inherited_records(&pool, "alice.bob.smith", &keys)
+            .await
+            .unwrap();
+        assert_eq!(
+            records,
+            HashMap::from([
+                ("LN".to_string(), "bob@ln".to_string()),
+                ("WEB".to_string(), "https://smith.example".to_string()),
+            ])
+        );
+        assert!(inherited_records(&pool, "smith", &keys)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_backup_to() {
+        let pool = scratch().await.unwrap();
+        insert_blockchain(
+            &mut pool.acquire().await.unwrap(),
+            [1; 5],
+            Nsid::from_slice(&[1; 20]).unwrap(),
+            "00".repeat(32),
+            "01".repeat(32),
+            0,
+            100,
+            1,
+            0,
+            NomenKind::Create,
+            None,
+            TxStats {
+                fee: None,
+                size: 100,
+                vsize: 100,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+        let path = std::env::temp_dir().join(format!("nomen-backup-{}.db", std::process::id()));
+        backup_to(&pool, &path).await.unwrap();
+        assert!(integrity_check(&path).await.unwrap().is_empty());
+
+        let backup = open_snapshot(&path).await.unwrap();
+        let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM blockchain;")
+            .fetch_one(&backup)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+        assert_eq!(
+            schema_version(&backup).await.unwrap(),
+            latest_schema_version()
+        );
+        backup.close().await;
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,9 @@
+INSERT INTO subname_approvals (event_id, name, parent, pubkey, approver, created_at, indexed_at, raw_event)
+VALUES (?, ?, ?, ?, ?, ?, unixepoch(), ?)
+ON CONFLICT (approver, name) DO UPDATE SET
+event_id = excluded.event_id,
+pubkey = excluded.pubkey,
+created_at = excluded.created_at,
+indexed_at = excluded.indexed_at,
+raw_event = excluded.raw_event
+where excluded.created_at > created_at;
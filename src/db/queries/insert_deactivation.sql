@@ -0,0 +1,9 @@
+INSERT INTO deactivations (event_id, name, pubkey, reason, created_at, indexed_at, raw_event)
+VALUES (?, ?, ?, ?, ?, unixepoch(), ?)
+ON CONFLICT (pubkey, name) DO UPDATE SET
+event_id = excluded.event_id,
+reason = excluded.reason,
+created_at = excluded.created_at,
+indexed_at = excluded.indexed_at,
+raw_event = excluded.raw_event
+where excluded.created_at > created_at;
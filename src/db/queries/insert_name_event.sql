@@ -1,8 +1,13 @@
-INSERT INTO name_events (name, fingerprint, nsid, pubkey, created_at, event_id, records, indexed_at, raw_event)
-VALUES (?, ?, ?, ?, ?, ?, ?, unixepoch(), ?)
+INSERT INTO name_events (name, fingerprint, nsid, pubkey, created_at, event_id, records, indexed_at, raw_event, anchor_txid, anchor_height, expires_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, unixepoch(), ?8, ?9, ?10, ?12)
 ON CONFLICT (name, pubkey) DO UPDATE SET
 created_at = excluded.created_at,
 event_id = excluded.event_id,
 records = excluded.records,
-raw_event = excluded.raw_event
-where excluded.created_at > created_at;
\ No newline at end of file
+raw_event = excluded.raw_event,
+anchor_txid = excluded.anchor_txid,
+anchor_height = excluded.anchor_height,
+expires_at = excluded.expires_at
+-- An event bound to the current anchor (?11) replaces one that isn't, otherwise the newest wins.
+where COALESCE(excluded.anchor_txid = ?11, 0) > COALESCE(anchor_txid = ?11, 0)
+OR (COALESCE(excluded.anchor_txid = ?11, 0) = COALESCE(anchor_txid = ?11, 0) AND excluded.created_at > created_at);
@@ -1 +1 @@
-INSERT INTO blockchain (fingerprint, nsid, blockhash, txid, blocktime, blockheight, txheight, vout, kind, indexed_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, unixepoch());
\ No newline at end of file
+INSERT INTO blockchain (fingerprint, nsid, blockhash, txid, blocktime, blockheight, txheight, vout, kind, fee, size, vsize, anchor_vout, anchor_value, anchor_script, commitment, indexed_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, unixepoch());
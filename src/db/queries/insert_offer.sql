@@ -0,0 +1,10 @@
+INSERT INTO offers (event_id, name, nsid, seller, buyer, price, psbt, created_at, indexed_at, raw_event)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, unixepoch(), ?)
+ON CONFLICT (seller, nsid) DO UPDATE SET
+event_id = excluded.event_id,
+price = excluded.price,
+psbt = excluded.psbt,
+created_at = excluded.created_at,
+indexed_at = excluded.indexed_at,
+raw_event = excluded.raw_event
+where excluded.created_at > created_at;
@@ -0,0 +1,10 @@
+INSERT INTO profiles (pubkey, name, display_name, picture, about, created_at, indexed_at)
+VALUES (?, ?, ?, ?, ?, ?, unixepoch())
+ON CONFLICT (pubkey) DO UPDATE SET
+name = excluded.name,
+display_name = excluded.display_name,
+picture = excluded.picture,
+about = excluded.about,
+created_at = excluded.created_at,
+indexed_at = excluded.indexed_at
+where excluded.created_at > created_at;
@@ -0,0 +1,109 @@
+use axum::http::StatusCode;
+use derive_more::Display;
+
+/// Classes of failure, so scripts (via exit code) and clients (via HTTP status) can tell them apart.
+///
+/// Functions still return `anyhow::Result`. Raise one of these with `bail!(NomenError::...)`, and
+/// use [`NomenError::classify`] to find the class of any error, including ones from our dependencies.
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+pub enum NomenError {
+    /// Bitcoin Core RPC, or the HWI signer, failed.
+    #[display(fmt = "{_0}")]
+    Rpc(String),
+
+    /// Nostr relays could not be reached, or rejected an event.
+    #[display(fmt = "{_0}")]
+    Relay(String),
+
+    /// Input, names, events or transactions that break the protocol rules.
+    #[display(fmt = "{_0}")]
+    Validation(String),
+
+    /// The requested name or record does not exist.
+    #[display(fmt = "{_0}")]
+    NotFound(String),
+
+    /// The index database failed.
+    #[display(fmt = "{_0}")]
+    Db(String),
+
+    /// Missing or invalid configuration.
+    #[display(fmt = "{_0}")]
+    Config(String),
+}
+
+impl std::error::Error for NomenError {}
+
+impl NomenError {
+    /// Find the class of `err` by walking its chain of causes. Returns `None` for unclassified errors.
+    pub fn classify(err: &anyhow::Error) -> Option<NomenError> {
+        let message = err.to_string();
+        err.chain().find_map(|cause| {
+            if let Some(e) = cause.downcast_ref::<NomenError>() {
+                Some(e.clone())
+            } else if cause.is::<nomen_core::Error>() {
+                Some(NomenError::Validation(message.clone()))
+            } else if cause.is::<sqlx::Error>() {
+                Some(NomenError::Db(message.clone()))
+            } else if cause.is::<bitcoincore_rpc::Error>() {
+                Some(NomenError::Rpc(message.clone()))
+            } else if cause.is::<nostr_sdk::client::Error>() {
+                Some(NomenError::Relay(message.clone()))
+            } else if cause.is::<toml::de::Error>() {
+                Some(NomenError::Config(message.clone()))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Process exit code. 1 is left for unclassified errors, and 2 is used by clap for usage errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            NomenError::Config(_) => 3,
+            NomenError::Validation(_) => 4,
+            NomenError::NotFound(_) => 5,
+            NomenError::Db(_) => 6,
+            NomenError::Rpc(_) => 7,
+            NomenError::Relay(_) => 8,
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            NomenError::Validation(_) => StatusCode::BAD_REQUEST,
+            NomenError::NotFound(_) => StatusCode::NOT_FOUND,
+            NomenError::Rpc(_) | NomenError::Relay(_) => StatusCode::BAD_GATEWAY,
+            NomenError::Db(_) | NomenError::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{bail, Context};
+
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let err = anyhow::anyhow!(NomenError::Validation("bad name".into()));
+        assert_eq!(
+            NomenError::classify(&err),
+            Some(NomenError::Validation("bad name".into()))
+        );
+
+        let err = Err::<(), _>(NomenError::NotFound("missing".into()))
+            .context("Lookup failed")
+            .unwrap_err();
+        assert_eq!(NomenError::classify(&err).unwrap().exit_code(), 5);
+
+        let err = "Smith".parse::<crate::util::Name>().unwrap_err();
+        assert_eq!(NomenError::classify(&err).unwrap().exit_code(), 4);
+        let err = "novalue".parse::<crate::util::KeyVal>().unwrap_err();
+        assert_eq!(NomenError::classify(&err).unwrap().exit_code(), 4);
+
+        let err = anyhow::anyhow!("something else");
+        assert_eq!(NomenError::classify(&err), None);
+    }
+}
@@ -2,20 +2,84 @@
 
 mod config;
 mod db;
+mod error;
+mod protocol;
+mod rpc;
 mod subcommands;
 mod util;
 
+use std::process::ExitCode;
+
 use clap::Parser;
 use config::Config;
+use error::NomenError;
 
 use crate::config::{Cli, ConfigFile};
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    env_logger::init();
+async fn main() -> ExitCode {
+    util::init_logger();
+
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            let code = NomenError::classify(&err).map_or(1, |e| e.exit_code());
+            ExitCode::from(code)
+        }
+    }
+}
+
+async fn run() -> anyhow::Result<()> {
     let config = parse_config()?;
 
-    let pool = db::initialize(&config).await?;
+    // Completions, man pages and example documents need no database.
+    match &config.cli.subcommand {
+        config::Subcommand::Util(config::UtilSubcommand::Completions { shell }) => {
+            return subcommands::util::completions(*shell);
+        }
+        config::Subcommand::Util(config::UtilSubcommand::Manpages { dir }) => {
+            return subcommands::util::manpages(dir);
+        }
+        config::Subcommand::Util(config::UtilSubcommand::Example { kind, schema }) => {
+            return subcommands::util::example(*kind, *schema);
+        }
+        _ => {}
+    }
+
+    // `util check-config` reports database problems itself, instead of failing here.
+    if let config::Subcommand::Util(config::UtilSubcommand::CheckConfig) = &config.cli.subcommand {
+        return subcommands::util::check_config(&config).await;
+    }
+
+    // `db snapshot import` replaces the database, so it must not be opened first.
+    if let config::Subcommand::Db(config::DbSubcommand::Snapshot(
+        config::SnapshotSubcommand::Import {
+            snapshot,
+            manifest,
+            trust,
+            sample,
+            skip_rpc,
+            force,
+        },
+    )) = &config.cli.subcommand
+    {
+        return subcommands::db::import_snapshot(
+            &config, snapshot, manifest, trust, *sample, *skip_rpc, *force,
+        )
+        .await;
+    }
+
+    // `dev` runs against its own throwaway index, so leave the configured one untouched.
+    if let config::Subcommand::Dev(dev) = &config.cli.subcommand {
+        return subcommands::dev(&config, dev).await;
+    }
+
+    // `db migrate` picks its own schema version, so don't migrate to latest first.
+    let pool = match &config.cli.subcommand {
+        config::Subcommand::Db(config::DbSubcommand::Migrate { .. }) => config.sqlite().await?,
+        _ => db::initialize(&config).await?,
+    };
 
     match &config.cli.subcommand {
         config::Subcommand::Noop => {}
@@ -28,31 +92,132 @@ async fn main() -> anyhow::Result<()> {
             config::UtilSubcommand::Lookup { name } => {
                 subcommands::util::lookup(&config, name).await?
             }
-            config::UtilSubcommand::OpReturn { name, pubkey, kind } => {
-                subcommands::util::op_return(name, pubkey, *kind)?
+            config::UtilSubcommand::OpReturn {
+                name,
+                pubkey,
+                kind,
+                commitment,
+            } => subcommands::util::op_return(name, pubkey, *kind, commitment)?,
+            config::UtilSubcommand::Relays => subcommands::util::relays(&config, &pool).await?,
+            config::UtilSubcommand::Verify { name } => {
+                subcommands::util::verify(&config, &pool, name).await?
+            }
+            config::UtilSubcommand::CheckConfig
+            | config::UtilSubcommand::Completions { .. }
+            | config::UtilSubcommand::Manpages { .. }
+            | config::UtilSubcommand::Example { .. } => unreachable!(),
+            config::UtilSubcommand::ListUtxos {
+                fee_rate,
+                min_conf,
+                json,
+            } => subcommands::util::list_utxos(&config, *fee_rate, *min_conf, *json).await?,
+            config::UtilSubcommand::DecodeOpReturn { hex } => {
+                subcommands::util::decode_op_return(hex)?
+            }
+            config::UtilSubcommand::TestVectors { verify, output } => {
+                subcommands::util::test_vectors(verify, output)?
+            }
+            config::UtilSubcommand::MergePsbt(merge) => {
+                subcommands::util::merge_psbt(&config, merge).await?
+            }
+        },
+        config::Subcommand::Name(name) => subcommands::name(&config, &pool, name).await?,
+        config::Subcommand::Index(config::IndexSubcommand {
+            command: Some(config::IndexCommand::Bench(bench)),
+            ..
+        }) => subcommands::bench(&config, &pool, bench).await?,
+        config::Subcommand::Index(config::IndexSubcommand {
+            command: Some(config::IndexCommand::Checkpoint(checkpoint)),
+            ..
+        }) => subcommands::checkpoint(&config, &pool, checkpoint).await?,
+        config::Subcommand::Index(config::IndexSubcommand {
+            command: Some(config::IndexCommand::RebuildName { name, skip_rpc }),
+            ..
+        }) => subcommands::rebuild_name(&config, &pool, name, *skip_rpc).await?,
+        config::Subcommand::Index(index) => {
+            if index.force_reset {
+                subcommands::force_reset(&config, &pool).await?;
+            }
+            if let Some(url) = &index.mirror {
+                subcommands::mirror(&config, &pool, url, index.mirror_sample).await?;
+            }
+            subcommands::index(&config, index.workers as usize).await?
+        }
+        config::Subcommand::Server(server) => match &server.command {
+            Some(config::ServerCommand::Apikey(apikey)) => match apikey {
+                config::ApiKeySubcommand::Add { name } => {
+                    subcommands::apikey::add(&pool, name).await?
+                }
+                config::ApiKeySubcommand::Revoke { name } => {
+                    subcommands::apikey::revoke(&pool, name).await?
+                }
+                config::ApiKeySubcommand::List => subcommands::apikey::list(&pool).await?,
+            },
+            Some(config::ServerCommand::Announce(announce)) => {
+                subcommands::announce::announce(&config, &pool, announce).await?
+            }
+            None => subcommands::start(&config, &pool, server).await?,
+        },
+        config::Subcommand::Dev(_) => unreachable!(),
+        config::Subcommand::Db(db) => match db {
+            config::DbSubcommand::ExportNames { format, output } => {
+                subcommands::db::export_names(&pool, *format, output).await?
+            }
+            config::DbSubcommand::Verify { fix, skip_rpc } => {
+                subcommands::db::verify(&config, &pool, *fix, *skip_rpc).await?
+            }
+            config::DbSubcommand::Migrate { to } => db::migrate(&config, &pool, *to).await?,
+            config::DbSubcommand::Snapshot(snapshot) => match snapshot {
+                config::SnapshotSubcommand::Export {
+                    output,
+                    privkey,
+                    bunker,
+                } => {
+                    subcommands::db::export_snapshot(&config, &pool, output, privkey, bunker)
+                        .await?
+                }
+                config::SnapshotSubcommand::Import { .. } => unreachable!(),
+            },
+            config::DbSubcommand::Backup { path, verify } => {
+                subcommands::db::backup(&pool, path, *verify).await?
+            }
+            config::DbSubcommand::Audit { actor, limit } => {
+                subcommands::db::audit(&pool, actor.as_deref(), *limit).await?
+            }
+        },
+        config::Subcommand::Watch(watch) => match watch {
+            config::WatchSubcommand::Add { name } => subcommands::watch::add(&pool, name).await?,
+            config::WatchSubcommand::Remove { name } => {
+                subcommands::watch::remove(&pool, name).await?
+            }
+            config::WatchSubcommand::List => subcommands::watch::list(&pool).await?,
+        },
+        config::Subcommand::Outbox(outbox) => match outbox {
+            config::OutboxSubcommand::Flush => {
+                subcommands::outbox::flush_cmd(&config, &pool).await?
+            }
+            config::OutboxSubcommand::List => subcommands::outbox::list(&pool).await?,
+        },
+        config::Subcommand::Batch(batch) => {
+            subcommands::batch::batch(&config, &pool, batch).await?
+        }
+        config::Subcommand::Market(market) => match market {
+            config::MarketSubcommand::Offer(offer) => {
+                subcommands::market::offer(&config, offer).await?
+            }
+            config::MarketSubcommand::Buy(buy) => subcommands::market::buy(&config, buy).await?,
+            config::MarketSubcommand::List { name, json } => {
+                subcommands::market::list(&pool, name.as_ref(), *json).await?
             }
         },
-        config::Subcommand::Name(name) => subcommands::name(&config, name).await?,
-        config::Subcommand::Index => subcommands::index(&config).await?,
-        config::Subcommand::Server(server) => subcommands::start(&config, &pool, server).await?,
     }
 
     Ok(())
 }
 
 fn parse_config() -> anyhow::Result<Config> {
-    let mut cli = config::Cli::parse();
-    let config_name = cli.config.clone().unwrap_or_else(|| "nomen.toml".into());
-
-    let file = if config_name.is_file() {
-        let config_str = std::fs::read_to_string(config_name)?;
-
-        toml::from_str(&config_str)?
-    } else {
-        log::info!("Config file not found. Skipping.");
-        ConfigFile::default()
-    };
-
+    let cli = config::Cli::parse();
+    let file = ConfigFile::load(&Config::config_file(&cli), cli.profile.as_deref())?;
     let config = Config::new(cli, file);
 
     log::debug!("Config loaded: {config:?}");
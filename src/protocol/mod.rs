@@ -0,0 +1 @@
+pub use nomen_core::{Grammar, LABEL_SEPARATOR, MAX_NAME_LENGTH, MIN_NAME_LENGTH};
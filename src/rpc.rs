@@ -0,0 +1,328 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::bail;
+use bitcoin::{address::NetworkUnchecked, base64, Address, BlockHash, Transaction, Txid};
+use bitcoincore_rpc::{
+    json::{GetBlockchainInfoResult, ListUnspentResultEntry},
+    jsonrpc, Client, RpcApi,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::NomenError;
+
+/// How long a connection to the node through a proxy may take to connect or answer.
+const RPC_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Bitcoin Core RPC for async code, shared by the indexer, the server and the CLI.
+///
+/// `bitcoincore_rpc` blocks, so every call runs on tokio's blocking thread pool. A sequence of
+/// calls, like scanning blocks, should go in one [`Rpc::run`] closure rather than one task each.
+#[derive(Clone)]
+pub struct Rpc(Arc<Client>);
+
+impl Rpc {
+    pub fn new(client: Client) -> Rpc {
+        Rpc(Arc::new(client))
+    }
+
+    /// Run `f` with the blocking client, off the async runtime.
+    pub async fn run<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Client) -> anyhow::Result<T> + Send + 'static,
+    {
+        let client = self.0.clone();
+        tokio::task::spawn_blocking(move || f(&client))
+            .await
+            .map_err(|e| NomenError::Rpc(format!("RPC task failed: {e}")))?
+    }
+
+    pub async fn get_block_count(&self) -> anyhow::Result<u64> {
+        self.run(|client| Ok(client.get_block_count()?)).await
+    }
+
+    pub async fn get_blockchain_info(&self) -> anyhow::Result<GetBlockchainInfoResult> {
+        self.run(|client| Ok(client.get_blockchain_info()?)).await
+    }
+
+    /// Timestamp of the block at `height` in the best chain.
+    pub async fn get_block_time(&self, height: u64) -> anyhow::Result<u32> {
+        self.run(move |client| {
+            let hash = client.get_block_hash(height)?;
+            Ok(client.get_block_header(&hash)?.time)
+        })
+        .await
+    }
+
+    pub async fn get_raw_transaction(
+        &self,
+        txid: Txid,
+        blockhash: Option<BlockHash>,
+    ) -> anyhow::Result<Transaction> {
+        self.run(move |client| Ok(client.get_raw_transaction(&txid, blockhash.as_ref())?))
+            .await
+    }
+
+    pub async fn send_raw_transaction(&self, tx: Transaction) -> anyhow::Result<Txid> {
+        self.run(move |client| Ok(client.send_raw_transaction(&tx)?))
+            .await
+    }
+
+    /// The wallet's spendable outputs with at least `min_conf` confirmations.
+    pub async fn list_unspent(
+        &self,
+        min_conf: usize,
+    ) -> anyhow::Result<Vec<ListUnspentResultEntry>> {
+        self.run(move |client| Ok(client.list_unspent(Some(min_conf), None, None, None, None)?))
+            .await
+    }
+
+    /// The node's fee rate estimate to confirm within `blocks`, in sat/vB, if it has one.
+    pub async fn estimate_fee_rate(&self, blocks: u16) -> anyhow::Result<Option<f64>> {
+        self.run(move |client| {
+            let estimate = client.estimate_smart_fee(blocks, None)?;
+            // Estimated in BTC per 1000 vbytes.
+            Ok(estimate.fee_rate.map(|rate| rate.to_sat() as f64 / 1000.0))
+        })
+        .await
+    }
+
+    /// The address of the ranged `descriptor` at `index`, derived by the node.
+    pub async fn derive_address(
+        &self,
+        descriptor: String,
+        index: u32,
+    ) -> anyhow::Result<Address<NetworkUnchecked>> {
+        self.run(move |client| {
+            let info = client.get_descriptor_info(&descriptor)?;
+            if !info.is_range {
+                bail!(NomenError::Validation(format!(
+                    "Descriptor {descriptor} is not ranged, it needs a /* step"
+                )));
+            }
+            // `deriveaddresses` only takes descriptors with their checksum.
+            let descriptor = match descriptor.contains('#') {
+                true => descriptor,
+                false => format!("{descriptor}#{}", info.checksum),
+            };
+            client
+                .derive_addresses(&descriptor, Some([index, index]))?
+                .into_iter()
+                .next()
+                .ok_or_else(|| NomenError::Rpc(format!("No address at index {index}")).into())
+        })
+        .await
+    }
+
+    pub async fn generate_to_address(
+        &self,
+        blocks: u64,
+        address: Address,
+    ) -> anyhow::Result<Vec<BlockHash>> {
+        self.run(move |client| Ok(client.generate_to_address(blocks, &address)?))
+            .await
+    }
+}
+
+/// JSON-RPC over HTTP through a SOCKS5 proxy, such as Tor, which resolves the node's host name.
+///
+/// Each request opens a new connection through the proxy and sends HTTP/1.0, so the response is
+/// simply read until the node closes it.
+pub struct SocksTransport {
+    proxy: SocketAddr,
+    host: String,
+    port: u16,
+    /// `Authorization` header value.
+    auth: Option<String>,
+}
+
+impl SocksTransport {
+    pub fn new(
+        proxy: SocketAddr,
+        host: &str,
+        port: u16,
+        auth: bitcoincore_rpc::Auth,
+    ) -> anyhow::Result<SocksTransport> {
+        if host.len() > 255 {
+            bail!(NomenError::Config(format!("RPC host {host} is too long")));
+        }
+        let auth = match auth.get_user_pass()? {
+            (Some(user), password) => Some(format!(
+                "Basic {}",
+                base64::encode(format!("{user}:{}", password.unwrap_or_default()))
+            )),
+            _ => None,
+        };
+        Ok(SocksTransport {
+            proxy,
+            host: host.to_string(),
+            port,
+            auth,
+        })
+    }
+
+    /// Open a connection to the node through the proxy (RFC 1928, without proxy authentication).
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut stream = TcpStream::connect_timeout(&self.proxy, RPC_TIMEOUT)?;
+        stream.set_read_timeout(Some(RPC_TIMEOUT))?;
+        stream.set_write_timeout(Some(RPC_TIMEOUT))?;
+
+        stream.write_all(&[5, 1, 0])?;
+        let mut method = [0; 2];
+        stream.read_exact(&mut method)?;
+        if method != [5, 0] {
+            return Err(io::Error::other("SOCKS5 proxy requires authentication"));
+        }
+
+        let mut request = vec![5, 1, 0, 3, self.host.len() as u8];
+        request.extend(self.host.as_bytes());
+        request.extend(self.port.to_be_bytes());
+        stream.write_all(&request)?;
+        let mut reply = [0; 4];
+        stream.read_exact(&mut reply)?;
+        if reply[1] != 0 {
+            return Err(io::Error::other(format!(
+                "SOCKS5 proxy failed to connect, reply code {}",
+                reply[1]
+            )));
+        }
+        // Skip the address the proxy bound, and its port.
+        let bound = match reply[3] {
+            1 => 4,
+            4 => 16,
+            _ => {
+                let mut len = [0];
+                stream.read_exact(&mut len)?;
+                len[0] as usize
+            }
+        };
+        stream.read_exact(&mut vec![0; bound + 2])?;
+        Ok(stream)
+    }
+
+    /// POST `body` to the node and return the response body.
+    fn post(&self, body: &[u8]) -> io::Result<Vec<u8>> {
+        let mut stream = self.connect()?;
+        let mut request = format!(
+            "POST / HTTP/1.0\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+            self.host,
+            self.port,
+            body.len()
+        );
+        if let Some(auth) = &self.auth {
+            request.push_str(&format!("Authorization: {auth}\r\n"));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes())?;
+        stream.write_all(body)?;
+
+        let mut response = vec![];
+        stream.read_to_end(&mut response)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid HTTP response");
+        let split = response
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(invalid)?;
+        let status = std::str::from_utf8(&response[..split])
+            .ok()
+            .and_then(|head| head.split(' ').nth(1))
+            .ok_or_else(invalid)?
+            .to_string();
+        let body = response.split_off(split + 4);
+        // The node answers RPC errors with an error status and a JSON body.
+        if body.is_empty() && status != "200" {
+            return Err(io::Error::other(format!(
+                "HTTP status {status} from the node"
+            )));
+        }
+        Ok(body)
+    }
+
+    fn call<T: DeserializeOwned>(&self, request: &impl Serialize) -> Result<T, jsonrpc::Error> {
+        let body = serde_json::to_vec(request)?;
+        let response = self
+            .post(&body)
+            .map_err(|e| jsonrpc::Error::Transport(Box::new(e)))?;
+        Ok(serde_json::from_slice(&response)?)
+    }
+}
+
+impl jsonrpc::Transport for SocksTransport {
+    fn send_request(&self, request: jsonrpc::Request) -> Result<jsonrpc::Response, jsonrpc::Error> {
+        self.call(&request)
+    }
+
+    fn send_batch(
+        &self,
+        requests: &[jsonrpc::Request],
+    ) -> Result<Vec<jsonrpc::Response>, jsonrpc::Error> {
+        self.call(&requests)
+    }
+
+    fn fmt_target(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "http://{}:{} via {}", self.host, self.port, self.proxy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_socks_transport() {
+        let proxy = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = proxy.local_addr().unwrap();
+        let node = std::thread::spawn(move || {
+            let (mut stream, _) = proxy.accept().unwrap();
+            let mut greeting = [0; 3];
+            stream.read_exact(&mut greeting).unwrap();
+            stream.write_all(&[5, 0]).unwrap();
+            let mut connect = [0; 5];
+            stream.read_exact(&mut connect).unwrap();
+            let mut host = vec![0; connect[4] as usize + 2];
+            stream.read_exact(&mut host).unwrap();
+            stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+
+            // Read until the end of the JSON body.
+            let mut request = vec![];
+            while !request.ends_with(b"}") {
+                let mut chunk = [0; 1024];
+                let len = stream.read(&mut chunk).unwrap();
+                request.extend(&chunk[..len]);
+            }
+            let request = String::from_utf8_lossy(&request).to_string();
+            let body = r#"{"result":800000,"error":null,"id":0}"#;
+            write!(
+                stream,
+                "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            (String::from_utf8_lossy(&host).to_string(), request)
+        });
+
+        let transport = SocksTransport::new(
+            address,
+            "node.onion",
+            8332,
+            bitcoincore_rpc::Auth::UserPass("user".into(), "pass".into()),
+        )
+        .unwrap();
+        let client = Client::from_jsonrpc(jsonrpc::Client::with_transport(transport));
+        assert_eq!(client.get_block_count().unwrap(), 800_000);
+
+        let (host, request) = node.join().unwrap();
+        assert!(host.starts_with("node.onion"));
+        assert!(request.starts_with("POST / HTTP/1.0\r\nHost: node.onion:8332\r\n"));
+        assert!(request.contains("Authorization: Basic dXNlcjpwYXNz\r\n"));
+        assert!(request.contains("\"method\":\"getblockcount\""));
+    }
+}
@@ -0,0 +1,52 @@
+use nostr_sdk::{prelude::ToBech32, Event};
+use sqlx::SqlitePool;
+use yansi::Paint;
+
+use crate::{
+    config::{Config, ServerAnnounceSubcommand},
+    db,
+    error::NomenError,
+    subcommands::{outbox, Signer},
+};
+
+/// Sign and publish the handler event announcing this server, with `server.signing_key` unless
+/// another key or a bunker is given.
+pub async fn announce(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &ServerAnnounceSubcommand,
+) -> anyhow::Result<()> {
+    let signer = match config.server_signing_keys()? {
+        Some(keys) if args.privkey.is_none() && args.bunker.is_none() => Signer::Keys(keys),
+        _ => Signer::new(config, &args.privkey, &args.bunker).await?,
+    };
+    let event = event(config, &signer).await?;
+
+    println!(
+        "Announcing {} signed by {}",
+        Paint::yellow(config.server_handler().map(|h| h.url).unwrap_or_default()),
+        event.pubkey.to_bech32()?
+    );
+    if outbox::publish(config, pool, &event).await? {
+        println!("Sent event {}", event.id);
+    }
+    Ok(())
+}
+
+/// Queue the handler event, signed with `server.signing_key`, for the outbox to send.
+pub async fn queue(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+    let keys = config.server_signing_keys()?.ok_or_else(|| {
+        NomenError::Config("server.signing_key is needed to announce the server".into())
+    })?;
+    let event = event(config, &Signer::Keys(keys)).await?;
+    db::queue_event(pool, &event).await?;
+    log::info!("Queued handler event {}", event.id);
+    Ok(())
+}
+
+async fn event(config: &Config, signer: &Signer) -> anyhow::Result<Event> {
+    let handler = config.server_handler().ok_or_else(|| {
+        NomenError::Config("server.announce.url is needed to announce the server".into())
+    })?;
+    signer.sign(handler.event(signer.public_key())?).await
+}
@@ -0,0 +1,79 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use time::{macros::format_description, OffsetDateTime};
+use yansi::Paint;
+
+use crate::db;
+
+/// Prefix of generated keys, so they are easy to recognize.
+const KEY_PREFIX: &str = "nomen_";
+
+/// The hex encoded SHA-256 hash of `key`, as stored in the database.
+pub fn hash(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+fn generate() -> String {
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{KEY_PREFIX}{}", hex::encode(bytes))
+}
+
+pub async fn add(pool: &SqlitePool, name: &str) -> anyhow::Result<()> {
+    let key = generate();
+    db::insert_api_key(pool, name, &hash(&key)).await?;
+    db::audit(pool, db::LOCAL_ACTOR, "apikey add", name).await?;
+    println!("Created API key {}:", Paint::yellow(name));
+    println!("{key}");
+    println!("Store it now, it cannot be shown again.");
+    Ok(())
+}
+
+pub async fn revoke(pool: &SqlitePool, name: &str) -> anyhow::Result<()> {
+    if db::revoke_api_key(pool, name).await? {
+        db::audit(pool, db::LOCAL_ACTOR, "apikey revoke", name).await?;
+        println!("Revoked API key {}.", Paint::yellow(name));
+    } else {
+        println!("No active API key named {}.", Paint::yellow(name));
+    }
+    Ok(())
+}
+
+pub async fn list(pool: &SqlitePool) -> anyhow::Result<()> {
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+    for key in db::api_keys(pool).await? {
+        let created = OffsetDateTime::from_unix_timestamp(key.created_at)?.format(format)?;
+        let requests: i64 = db::api_usage(pool, key.id)
+            .await?
+            .iter()
+            .map(|u| u.requests)
+            .sum();
+        let status = match key.revoked_at {
+            Some(revoked) => format!(
+                "revoked {}",
+                OffsetDateTime::from_unix_timestamp(revoked)?.format(format)?
+            ),
+            None => "active".to_string(),
+        };
+        println!(
+            "{}\tcreated {created}\t{status}\t{requests} requests",
+            Paint::yellow(&key.name)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let key = generate();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_ne!(key, generate());
+        assert_eq!(hash(&key).len(), 64);
+        assert_eq!(hash(&key), hash(&key));
+    }
+}
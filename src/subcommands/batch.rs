@@ -0,0 +1,301 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::bail;
+use bitcoin::{address::NetworkUnchecked, Address};
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use yansi::Paint;
+
+use crate::{
+    config::{
+        BatchSubcommand, Config, HwiArgs, NameNewSubcommand, NameRecordSubcomand,
+        NameTransferSignSubcommand,
+    },
+    error::NomenError,
+    subcommands::{claim, name::transfer, publish_records, read_records, Signer},
+};
+
+/// A batch document: operations run in order, all signed with the same key.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchDocument {
+    pub operations: Vec<BatchOperation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase", deny_unknown_fields)]
+pub enum BatchOperation {
+    /// `name new`: add a claim to a PSBT, written to `output` if given.
+    New {
+        name: String,
+        psbt: String,
+        #[serde(default)]
+        broadcast: bool,
+        #[serde(default)]
+        force: bool,
+        #[serde(default)]
+        commit_records: bool,
+        change_address: Option<Address<NetworkUnchecked>>,
+        fee_rate: Option<f64>,
+        output: Option<PathBuf>,
+    },
+
+    /// `name record`: publish a name's records.
+    Records {
+        name: String,
+        records: BTreeMap<String, String>,
+        #[serde(default)]
+        bind_anchor: bool,
+        at: Option<String>,
+        expires: Option<String>,
+    },
+
+    /// `name transfer sign`: sign a handover to `pubkey`, written to `output` if given.
+    Transfer {
+        name: String,
+        pubkey: XOnlyPublicKey,
+        #[serde(default)]
+        broadcast: bool,
+        #[serde(default)]
+        validate: bool,
+        output: Option<PathBuf>,
+    },
+}
+
+/// An operation parsed into the arguments of the command that runs it.
+enum Command {
+    New(Box<NameNewSubcommand>),
+    Records(NameRecordSubcomand),
+    Transfer(NameTransferSignSubcommand),
+}
+
+/// How one operation went, for the summary.
+#[derive(Serialize)]
+struct OperationReport {
+    op: &'static str,
+    name: String,
+    ok: bool,
+    /// The command's output if it succeeded, otherwise the error.
+    result: serde_json::Value,
+}
+
+impl BatchOperation {
+    fn op(&self) -> &'static str {
+        match self {
+            BatchOperation::New { .. } => "new",
+            BatchOperation::Records { .. } => "records",
+            BatchOperation::Transfer { .. } => "transfer",
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            BatchOperation::New { name, .. }
+            | BatchOperation::Records { name, .. }
+            | BatchOperation::Transfer { name, .. } => name,
+        }
+    }
+
+    /// Check the operation's values, so a bad document fails before anything is signed.
+    fn command(&self) -> anyhow::Result<Command> {
+        Ok(match self {
+            BatchOperation::New {
+                name,
+                psbt,
+                broadcast,
+                force,
+                commit_records,
+                change_address,
+                fee_rate,
+                output,
+            } => Command::New(Box::new(NameNewSubcommand {
+                name: name.parse()?,
+                psbt: psbt.clone(),
+                privkey: None,
+                bunker: None,
+                json: false,
+                broadcast: *broadcast,
+                validate: false,
+                force: *force,
+                commit_records: *commit_records,
+                change_address: change_address.clone(),
+                fee_rate: *fee_rate,
+                sponsor_psbt: false,
+                output: output.clone(),
+                hwi: HwiArgs {
+                    hwi: false,
+                    hwi_path: "hwi".into(),
+                    hwi_device: "coldcard".into(),
+                    hwi_fingerprint: None,
+                    hwi_chain: None,
+                    broadcast_tx: false,
+                },
+            })),
+            BatchOperation::Records {
+                name,
+                records,
+                bind_anchor,
+                at,
+                expires,
+            } => Command::Records(NameRecordSubcomand {
+                name: name.parse()?,
+                records: records
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}").parse())
+                    .collect::<anyhow::Result<_>>()?,
+                file: None,
+                bind_anchor: *bind_anchor,
+                at: at.as_deref().map(str::parse).transpose()?,
+                expires: expires.as_deref().map(str::parse).transpose()?,
+                privkey: None,
+                bunker: None,
+            }),
+            BatchOperation::Transfer {
+                name,
+                pubkey,
+                broadcast,
+                validate,
+                output,
+            } => Command::Transfer(NameTransferSignSubcommand {
+                name: name.parse()?,
+                pubkey: *pubkey,
+                privkey: None,
+                bunker: None,
+                json: false,
+                broadcast: *broadcast,
+                validate: *validate,
+                output: output.clone(),
+            }),
+        })
+    }
+}
+
+pub fn parse(json: &str) -> anyhow::Result<BatchDocument> {
+    let document: BatchDocument = serde_json::from_str(json)
+        .map_err(|e| NomenError::Validation(format!("Invalid batch document: {e}")))?;
+    if document.operations.is_empty() {
+        bail!(NomenError::Validation(
+            "Batch document has no operations".into()
+        ));
+    }
+    Ok(document)
+}
+
+pub async fn batch(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &BatchSubcommand,
+) -> anyhow::Result<()> {
+    let document = parse(&std::fs::read_to_string(&args.file)?)?;
+    let commands = document
+        .operations
+        .iter()
+        .enumerate()
+        .map(|(i, op)| {
+            op.command().map_err(|e| {
+                NomenError::Validation(format!("Operation {} ({}): {e}", i + 1, op.name()))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let signer = Signer::new(config, &args.privkey, &args.bunker).await?;
+    let mut reports = vec![];
+    for (op, command) in document.operations.iter().zip(&commands) {
+        let result = run(config, pool, command, &signer).await;
+        let failed = result.is_err();
+        reports.push(OperationReport {
+            op: op.op(),
+            name: op.name().to_string(),
+            ok: !failed,
+            result: result.unwrap_or_else(|e| serde_json::Value::String(e.to_string())),
+        });
+        if failed && args.stop_on_error {
+            break;
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string(&reports)?);
+    } else {
+        for report in &reports {
+            let status = if report.ok {
+                Paint::green("✓")
+            } else {
+                Paint::red("✗")
+            };
+            let detail = match &report.result {
+                serde_json::Value::String(error) => error.clone(),
+                result => result["nsid"]
+                    .as_str()
+                    .or_else(|| result["id"].as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+            println!("{status} {} {}: {detail}", report.op, report.name);
+        }
+    }
+
+    let failed = reports.iter().filter(|r| !r.ok).count();
+    let skipped = commands.len() - reports.len();
+    if failed > 0 {
+        bail!(NomenError::Validation(format!(
+            "{failed} of {} operations failed, {skipped} not run",
+            commands.len()
+        )));
+    }
+    Ok(())
+}
+
+async fn run(
+    config: &Config,
+    pool: &SqlitePool,
+    command: &Command,
+    signer: &Signer,
+) -> anyhow::Result<serde_json::Value> {
+    Ok(match command {
+        Command::New(args) => serde_json::to_value(claim(config, pool, args, signer).await?)?,
+        Command::Records(args) => {
+            let records = read_records(args)?;
+            serde_json::to_value(publish_records(config, pool, args, &records, signer).await?)?
+        }
+        Command::Transfer(args) => {
+            serde_json::to_value(transfer::handover(config, args, signer).await?)?
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let document = parse(
+            r#"{"operations": [
+                {"op": "new", "name": "smith", "psbt": "smith.psbt", "output": "out.psbt"},
+                {"op": "records", "name": "smith", "records": {"ip4": "192.0.2.1"}, "expires": "7d"},
+                {"op": "transfer", "name": "jones", "pubkey": "d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f"}
+            ]}"#,
+        )
+        .unwrap();
+        let ops: Vec<_> = document.operations.iter().map(|op| op.op()).collect();
+        assert_eq!(ops, ["new", "records", "transfer"]);
+        match document.operations[1].command().unwrap() {
+            Command::Records(args) => {
+                assert_eq!(args.records, ["IP4=192.0.2.1".parse().unwrap()]);
+                assert_eq!(args.expires.unwrap().as_secs(), 7 * 86400);
+            }
+            _ => panic!("Expected a records operation"),
+        }
+
+        assert!(parse(r#"{"operations": []}"#).is_err());
+        assert!(parse(r#"{"operations": [{"op": "burn", "name": "smith"}]}"#).is_err());
+
+        // Values are checked before anything runs.
+        let bad =
+            parse(r#"{"operations": [{"op": "records", "name": "Not A Name", "records": {}}]}"#)
+                .unwrap();
+        assert!(bad.operations[0].command().is_err());
+    }
+}
@@ -0,0 +1,485 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context};
+use bitcoin::{BlockHash, Txid};
+use bitcoincore_rpc::RpcApi;
+use futures::TryStreamExt;
+use nostr_sdk::{prelude::ToBech32, Event};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use time::{macros::format_description, OffsetDateTime};
+use yansi::Paint;
+
+use crate::{
+    config::{Config, ExportFormat},
+    db::{self, StoredAnchor, StoredEvent},
+    error::NomenError,
+    util::{NomenTx, NostrPk, NostrSk, SnapshotManifest},
+};
+
+use super::{check_block, check_output, BunkerUri, EventData, Signer};
+
+pub async fn export_names(
+    pool: &SqlitePool,
+    format: ExportFormat,
+    output: &Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(std::io::stdout().lock())),
+    };
+
+    let keys = db::record_keys(pool).await?;
+    if format == ExportFormat::Csv {
+        let header = ["name", "nsid", "pubkey", "blockheight"]
+            .into_iter()
+            .chain(keys.iter().map(String::as_str))
+            .map(csv_field)
+            .collect::<Vec<_>>();
+        writeln!(out, "{}", header.join(","))?;
+    }
+
+    let mut names = db::export_names(pool);
+    while let Some(row) = names.try_next().await? {
+        match format {
+            ExportFormat::Csv => {
+                let records: HashMap<String, String> = serde_json::from_str(&row.records)?;
+                let blockheight = row.blockheight.to_string();
+                let fields = [&row.name, &row.nsid, &row.pubkey, &blockheight]
+                    .into_iter()
+                    .map(|s| s.as_str())
+                    .chain(
+                        keys.iter()
+                            .map(|k| records.get(k).map(String::as_str).unwrap_or_default()),
+                    )
+                    .map(csv_field)
+                    .collect::<Vec<_>>();
+                writeln!(out, "{}", fields.join(","))?;
+            }
+            ExportFormat::Jsonl => writeln!(out, "{}", row.to_json()?)?,
+        }
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+/// A row that failed verification.
+struct Discrepancy {
+    table: &'static str,
+    rowid: i64,
+    row_data: String,
+    reason: String,
+}
+
+pub async fn verify(
+    config: &Config,
+    pool: &SqlitePool,
+    fix: bool,
+    skip_rpc: bool,
+) -> anyhow::Result<()> {
+    let mut discrepancies = vec![];
+
+    let name_events = db::stored_name_events(pool).await?;
+    log::info!("Verifying {} name events", name_events.len());
+    for row in name_events {
+        if let Err(e) = verify_event(&row, false) {
+            discrepancies.push(Discrepancy::new("name_events", row.rowid, &row, e)?);
+        }
+    }
+
+    let transfer_events = db::stored_transfer_events(pool).await?;
+    log::info!("Verifying {} transfer events", transfer_events.len());
+    for row in transfer_events {
+        if let Err(e) = verify_event(&row, true) {
+            discrepancies.push(Discrepancy::new("transfer_events", row.rowid, &row, e)?);
+        }
+    }
+
+    if !skip_rpc {
+        let anchors = db::stored_anchors(pool).await?;
+        log::info!("Verifying {} on-chain claims", anchors.len());
+        let failed = config
+            .rpc()?
+            .run(move |client| {
+                Ok(anchors
+                    .into_iter()
+                    .filter_map(|a| verify_anchor(client, &a).err().map(|e| (a, e)))
+                    .collect::<Vec<_>>())
+            })
+            .await?;
+        for (anchor, e) in failed {
+            discrepancies.push(Discrepancy::new("blockchain", anchor.id, &anchor, e)?);
+        }
+    }
+
+    for d in &discrepancies {
+        println!(
+            "{} {} row {}: {}",
+            Paint::red("Invalid"),
+            d.table,
+            d.rowid,
+            d.reason
+        );
+    }
+    println!(
+        "{} discrepancies found.",
+        Paint::yellow(discrepancies.len())
+    );
+
+    if fix && !discrepancies.is_empty() {
+        for d in &discrepancies {
+            db::quarantine(pool, d.table, d.rowid, &d.row_data, &d.reason).await?;
+        }
+        super::reindex_owners(pool).await?;
+        let detail = format!("{} rows quarantined", discrepancies.len());
+        db::audit(pool, db::LOCAL_ACTOR, "db verify --fix", &detail).await?;
+        println!("Moved {} rows to quarantine.", discrepancies.len());
+    }
+
+    Ok(())
+}
+
+impl Discrepancy {
+    fn new(
+        table: &'static str,
+        rowid: i64,
+        row: &impl serde::Serialize,
+        err: anyhow::Error,
+    ) -> anyhow::Result<Discrepancy> {
+        Ok(Discrepancy {
+            table,
+            rowid,
+            row_data: serde_json::to_string(row)?,
+            reason: err.to_string(),
+        })
+    }
+}
+
+/// Check the signature of a stored event, and that the stored columns match the event.
+fn verify_event(row: &StoredEvent, transfer: bool) -> anyhow::Result<()> {
+    let event = Event::from_json(&row.raw_event)?;
+    event.verify()?;
+    if event.id.to_hex() != row.event_id {
+        bail!(NomenError::Validation(
+            "Event id does not match raw event".into()
+        ));
+    }
+
+    let ed = EventData::from_event(&event)?;
+    if ed.name.to_string() != row.name || ed.pubkey.to_string() != row.pubkey {
+        bail!(NomenError::Validation(
+            "Name or pubkey does not match raw event".into()
+        ));
+    }
+
+    // Name events store the nsid derived from the name and pubkey. Transfer events store the
+    // nsid from the 'd' tag, which must match the one derived from the new owner.
+    ed.validate()?;
+    let stored = if transfer {
+        ed.nsid
+    } else {
+        ed.calculated_nsid
+    };
+    if stored.to_string() != row.nsid {
+        bail!(NomenError::Validation(
+            "Stored nsid does not match the derived nsid".into()
+        ));
+    }
+    Ok(())
+}
+
+/// Check that a claim's transaction is still in the active chain, with a matching Nomen output.
+pub(crate) fn verify_anchor(
+    client: &bitcoincore_rpc::Client,
+    anchor: &StoredAnchor,
+) -> anyhow::Result<()> {
+    let blockhash: BlockHash = anchor.blockhash.parse()?;
+    let txid: Txid = anchor.txid.parse()?;
+
+    let header = client.get_block_header_info(&blockhash)?;
+    if header.confirmations < 0 {
+        bail!(NomenError::Validation(format!(
+            "Block {blockhash} is no longer in the active chain"
+        )));
+    }
+
+    let tx = client.get_raw_transaction(&txid, Some(&blockhash))?;
+    let output = tx.output.get(anchor.vout as usize).ok_or_else(|| {
+        NomenError::Validation(format!("Output {} not found in {txid}", anchor.vout))
+    })?;
+    let data = output
+        .script_pubkey
+        .as_bytes()
+        .get(2..)
+        .filter(|_| output.script_pubkey.is_op_return())
+        .ok_or_else(|| NomenError::Validation("Output is not an OP_RETURN".into()))?;
+    let nomen = NomenTx::try_from(data)?;
+
+    if nomen.nsid.to_string() != anchor.nsid
+        || hex::encode(nomen.fingerprint) != anchor.fingerprint
+        || nomen.kind.to_string() != anchor.kind
+    {
+        bail!(NomenError::Validation(
+            "On-chain output does not match the stored claim".into()
+        ));
+    }
+    Ok(())
+}
+
+/// Write a snapshot of the index to `output`, and a manifest of it signed by the operator.
+pub async fn export_snapshot(
+    config: &Config,
+    pool: &SqlitePool,
+    output: &Path,
+    privkey: &Option<NostrSk>,
+    bunker: &Option<BunkerUri>,
+) -> anyhow::Result<()> {
+    if output.exists() {
+        bail!(NomenError::Validation(format!(
+            "{} already exists",
+            output.display()
+        )));
+    }
+    let signer = Signer::new(config, privkey, bunker).await?;
+
+    log::info!("Writing snapshot to {}", output.display());
+    db::snapshot(pool, output).await?;
+
+    // Read the tip from the snapshot itself, in case the indexer moved on while it was written.
+    let snapshot = db::open_snapshot(output).await?;
+    let tip = db::index_tip(&snapshot).await?;
+    snapshot.close().await;
+    let Some((index_height, index_blockhash)) = tip else {
+        std::fs::remove_file(output)?;
+        bail!(NomenError::NotFound(
+            "The index is empty, nothing to snapshot".into()
+        ));
+    };
+
+    let (sha256, size) = file_digest(output)?;
+    let manifest = SnapshotManifest {
+        version: 0,
+        network: config.network().to_string(),
+        index_height,
+        index_blockhash,
+        schema_version: db::schema_version(pool).await?,
+        sha256,
+        size,
+    };
+    let event = signer.sign(manifest.event(signer.public_key())?).await?;
+    let manifest_file = manifest_path(output);
+    std::fs::write(&manifest_file, event.as_json())?;
+
+    println!(
+        "Snapshot at height {} ({}) signed by {}",
+        Paint::yellow(manifest.index_height),
+        manifest.index_blockhash,
+        event.pubkey.to_bech32()?
+    );
+    println!("Manifest written to {}", manifest_file.display());
+    Ok(())
+}
+
+/// Back up the database to `path`, and check the copy's integrity with `verify`.
+pub async fn backup(pool: &SqlitePool, path: &Path, verify: bool) -> anyhow::Result<()> {
+    if path.exists() {
+        bail!(NomenError::Validation(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+    log::info!("Backing up database to {}", path.display());
+    db::backup_to(pool, path).await?;
+    println!("Backup written to {}", path.display());
+
+    if verify {
+        let problems = db::integrity_check(path).await?;
+        if !problems.is_empty() {
+            for problem in &problems {
+                println!("{}", Paint::red(problem));
+            }
+            bail!(NomenError::Db(format!(
+                "Backup {} failed the integrity check",
+                path.display()
+            )));
+        }
+        println!("Integrity check {}", Paint::green("ok"));
+    }
+    Ok(())
+}
+
+/// Print the latest `limit` entries of the audit log, optionally only those of `actor`.
+pub async fn audit(pool: &SqlitePool, actor: Option<&str>, limit: i64) -> anyhow::Result<()> {
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+    for entry in db::audit_log(pool, actor, limit).await? {
+        let created = OffsetDateTime::from_unix_timestamp(entry.created_at)?.format(format)?;
+        println!(
+            "{created}\t{}\t{}\t{}",
+            Paint::yellow(&entry.actor),
+            entry.action,
+            entry.detail
+        );
+    }
+    Ok(())
+}
+
+/// Check a snapshot against its signed manifest and a sample of it against the node, then make it
+/// the index. The snapshot is migrated to the latest schema once copied.
+pub async fn import_snapshot(
+    config: &Config,
+    snapshot: &Path,
+    manifest: &Option<PathBuf>,
+    trust: &[NostrPk],
+    sample: usize,
+    skip_rpc: bool,
+    force: bool,
+) -> anyhow::Result<()> {
+    if config.data_in_memory() {
+        bail!(NomenError::Config(
+            "Cannot import a snapshot into an in-memory index".into()
+        ));
+    }
+
+    let manifest_file = manifest.clone().unwrap_or_else(|| manifest_path(snapshot));
+    let event = std::fs::read_to_string(&manifest_file)
+        .with_context(|| format!("Could not read manifest {}", manifest_file.display()))?;
+    let event = Event::from_json(event)?;
+    let manifest = SnapshotManifest::from_event(&event)?;
+    let operator = event.pubkey.to_bech32()?;
+    if trust.is_empty() {
+        log::warn!(
+            "Snapshot is signed by {operator}, use --trust to only accept operators you know"
+        );
+    } else if !trust.iter().any(|pk| *pk.as_ref() == event.pubkey) {
+        bail!(NomenError::Validation(format!(
+            "Snapshot is signed by {operator}, which is not a trusted operator"
+        )));
+    }
+
+    if manifest.network != config.network().to_string() {
+        bail!(NomenError::Validation(format!(
+            "Snapshot is for {}, not {}",
+            manifest.network,
+            config.network()
+        )));
+    }
+    if manifest.schema_version > db::latest_schema_version() {
+        bail!(NomenError::Validation(format!(
+            "Snapshot schema version {} is newer than this version of Nomen supports",
+            manifest.schema_version
+        )));
+    }
+    if file_digest(snapshot)? != (manifest.sha256.clone(), manifest.size) {
+        bail!(NomenError::Validation(
+            "Snapshot file does not match its manifest".into()
+        ));
+    }
+
+    let pool = db::open_snapshot(snapshot).await?;
+    let tip = db::index_tip(&pool).await?;
+    let rows = db::sample_blockchain_rows(&pool, sample).await?;
+    pool.close().await;
+    if tip != Some((manifest.index_height, manifest.index_blockhash.clone())) {
+        bail!(NomenError::Validation(
+            "Snapshot index tip does not match its manifest".into()
+        ));
+    }
+
+    if !skip_rpc {
+        let checked = rows.len();
+        let (height, blockhash) = (manifest.index_height, manifest.index_blockhash.clone());
+        config
+            .rpc()?
+            .run(move |client| {
+                check_block(client, &blockhash, height)?;
+                for row in &rows {
+                    check_block(client, &row.blockhash, row.blockheight)?;
+                    check_output(client, row)?;
+                }
+                Ok(())
+            })
+            .await
+            .context("Not importing the snapshot")?;
+        log::info!(
+            "Verified the snapshot tip and {checked} blockchain entries against the local node"
+        );
+    }
+
+    let data = config.data();
+    if data.exists() {
+        if !force {
+            bail!(NomenError::Validation(format!(
+                "{} already exists, use --force to replace it",
+                data.display()
+            )));
+        }
+        // A write-ahead log left by the old index must not be applied to the new one.
+        for suffix in ["-wal", "-shm"] {
+            let mut path = data.clone().into_os_string();
+            path.push(suffix);
+            if Path::new(&path).exists() {
+                std::fs::remove_file(&path)?;
+            }
+        }
+    }
+    if let Some(parent) = data.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(snapshot, &data)?;
+    let pool = db::initialize(config).await?;
+    let detail = format!(
+        "{} at height {} signed by {operator}",
+        snapshot.display(),
+        manifest.index_height
+    );
+    db::audit(&pool, db::LOCAL_ACTOR, "db snapshot import", &detail).await?;
+    pool.close().await;
+
+    println!(
+        "Imported snapshot at height {} signed by {operator}",
+        Paint::yellow(manifest.index_height)
+    );
+    Ok(())
+}
+
+/// The manifest of `snapshot`: `<snapshot>.manifest.json`.
+fn manifest_path(snapshot: &Path) -> PathBuf {
+    let mut path = snapshot.to_path_buf().into_os_string();
+    path.push(".manifest.json");
+    path.into()
+}
+
+/// Hex encoded SHA-256 and size of a file.
+fn file_digest(path: &Path) -> anyhow::Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+/// Quote a CSV field if it contains a separator, quote, or newline.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("smith"), "smith");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+    }
+}
@@ -0,0 +1,200 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use bitcoin::{
+    absolute::LockTime, script::PushBytesBuf, Address, Network, ScriptBuf, Transaction, TxOut,
+};
+use bitcoincore_rpc::RpcApi;
+use nostr_sdk::Keys;
+use sqlx::SqlitePool;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::{
+    config::{self, Config, DevSubcommand, ServerSubcommand, Subcommand},
+    db,
+    error::NomenError,
+    rpc::Rpc,
+    util::{Hash160, NomenKind, NsidBuilder, RecordLimits},
+};
+
+use super::{name_event, op_return, EventData};
+
+/// Names registered, with sample records, when the dev environment starts.
+const SAMPLE_NAMES: [(&str, &[(&str, &str)]); 3] = [
+    (
+        "alice",
+        &[
+            ("WEB", "https://example.com"),
+            ("MOTD", "Hello from regtest"),
+        ],
+    ),
+    ("bob", &[("EMAIL", "bob@example.com")]),
+    ("carol", &[("TWITTER", "carol"), ("IP4", "127.0.0.1")]),
+];
+
+/// Start a throwaway regtest environment: a fresh database, sample names, a block miner and the server.
+pub async fn dev(config: &Config, args: &DevSubcommand) -> anyhow::Result<()> {
+    let config = dev_config(config, args);
+    log::info!("Using development database {:?}", config.data());
+    let pool = db::initialize(&config).await?;
+
+    let rpc = config.rpc()?;
+    let chain = rpc.get_blockchain_info().await?;
+    if chain.chain != "regtest" {
+        anyhow::bail!(NomenError::Config(format!(
+            "Development mode requires a regtest node, found {}",
+            chain.chain
+        )));
+    }
+
+    let address = rpc
+        .run(|client| {
+            Ok(client
+                .get_new_address(None, None)
+                .context(
+                    "Unable to get an address, make sure the regtest node has a wallet loaded",
+                )?
+                .require_network(Network::Regtest)?)
+        })
+        .await?;
+
+    // Coinbase outputs need 100 confirmations before they are spendable
+    if chain.blocks < 101 {
+        rpc.generate_to_address(101 - chain.blocks, address.clone())
+            .await?;
+    }
+
+    if !args.no_samples {
+        for (name, records) in SAMPLE_NAMES {
+            register_sample(&rpc, &pool, name, records).await?;
+        }
+    }
+    rpc.generate_to_address(config.confirmations()? as u64, address.clone())
+        .await?;
+
+    tokio::spawn(miner(config.clone(), address, args.block_interval));
+
+    let Subcommand::Server(server) = &config.cli.subcommand else {
+        unreachable!("dev_config always sets the server subcommand")
+    };
+    super::start(&config, &pool, server).await
+}
+
+/// The user's config, pointed at regtest and an in-memory database unless `--data` is given, with the server subcommand
+/// set so the server picks up the dev bind address.
+fn dev_config(config: &Config, args: &DevSubcommand) -> Config {
+    let mut config = config.clone();
+    config.cli.data = Some(
+        args.data
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(config::MEMORY_DATA)),
+    );
+    config.cli.network = Some(Network::Regtest);
+    if config.cli.rpcport.is_none() && config.file.rpc.port.is_none() {
+        config.cli.rpcport = Some(18443);
+    }
+    config.cli.subcommand = Subcommand::Server(ServerSubcommand {
+        command: None,
+        bind: vec![args.bind.clone()],
+        without_explorer: false,
+        without_api: false,
+        without_indexer: false,
+        indexer_delay: Some(5),
+    });
+    config
+}
+
+/// Claim `name` on chain with a new keypair, and store a signed record event for it directly in the index.
+async fn register_sample(
+    rpc: &Rpc,
+    pool: &SqlitePool,
+    name: &str,
+    records: &[(&str, &str)],
+) -> anyhow::Result<()> {
+    let keys = Keys::generate();
+    let pubkey = keys.public_key();
+    let fingerprint = Hash160::default()
+        .chain_update(name.as_bytes())
+        .fingerprint();
+    let nsid = NsidBuilder::new(name, &pubkey).finalize();
+
+    let data: PushBytesBuf = op_return(fingerprint, nsid, NomenKind::Create, None).try_into()?;
+    let tx = Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: ScriptBuf::new_op_return(&data),
+        }],
+    };
+    let txid = rpc
+        .run(move |client| {
+            let funded = client.fund_raw_transaction(&tx, None, None)?;
+            let signed = client.sign_raw_transaction_with_wallet(&funded.hex, None, None)?;
+            Ok(client.send_raw_transaction(&signed.hex)?)
+        })
+        .await?;
+    log::info!("Registered sample name {name} in tx {txid}");
+
+    let records: HashMap<String, String> = records
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let event = name_event(pubkey, &records, name, &RecordLimits::default(), None)?.sign(&keys)?;
+    let ed = EventData::from_event(&event)?;
+    db::insert_name_event(
+        pool,
+        ed.name,
+        ed.fingerprint,
+        ed.calculated_nsid,
+        ed.pubkey,
+        ed.created_at,
+        ed.event_id,
+        ed.raw_content,
+        ed.raw_event,
+        ed.expires_at,
+        None,
+        None,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Mine a block every `seconds`, so transactions submitted through the explorer confirm.
+async fn miner(config: Config, address: Address, seconds: u64) -> anyhow::Result<()> {
+    let rpc = config.rpc()?;
+    let mut interval = interval(Duration::from_secs(seconds));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+        match rpc.generate_to_address(1, address.clone()).await {
+            Ok(hashes) => log::debug!("Mined regtest block {:?}", hashes.first()),
+            Err(e) => log::error!("Regtest mining error: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::config::{Cli, ConfigFile};
+
+    #[test]
+    fn test_dev_config_in_memory() {
+        let config = Config::new(
+            Cli::parse_from(["nomen", "--data", "nomen.db", "dev"]),
+            ConfigFile::default(),
+        );
+        let Subcommand::Dev(args) = &config.cli.subcommand else {
+            unreachable!()
+        };
+        let dev = dev_config(&config, args);
+        assert!(dev.data_in_memory());
+        assert_eq!(dev.network(), Network::Regtest);
+    }
+}
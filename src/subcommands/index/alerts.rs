@@ -0,0 +1,112 @@
+use nostr_sdk::{prelude::FromPkStr, Kind};
+use secp256k1::XOnlyPublicKey;
+use serde::Serialize;
+
+use crate::{
+    config::{Config, RelayUse},
+    error::NomenError,
+};
+
+/// Something the operator should hear about, sent to every configured notification channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub title: String,
+    pub message: String,
+}
+
+#[derive(Clone)]
+enum Channel {
+    Stdout,
+    Webhook(String),
+
+    /// NIP-04 encrypted direct message to `to`, signed with `sk` or a random key.
+    NostrDm {
+        to: XOnlyPublicKey,
+        sk: Option<String>,
+    },
+}
+
+/// Sends alerts to the channels configured under `[notifications]`.
+#[derive(Clone)]
+pub struct Notifier {
+    config: Config,
+    channels: Vec<Channel>,
+    http: reqwest::Client,
+}
+
+impl Notifier {
+    pub fn new(config: &Config) -> anyhow::Result<Notifier> {
+        let mut channels = vec![];
+        if config.notifications_stdout() {
+            channels.push(Channel::Stdout);
+        }
+        if let Some(url) = config.notifications_webhook() {
+            channels.push(Channel::Webhook(url));
+        }
+        if let Some(dm) = config.notifications_dm() {
+            let to = nostr_sdk::Keys::from_pk_str(&dm.to)
+                .map_err(|e| NomenError::Config(format!("Invalid notifications.dm.to: {e}")))?
+                .public_key();
+            channels.push(Channel::NostrDm { to, sk: dm.privkey });
+        }
+
+        Ok(Notifier {
+            config: config.clone(),
+            channels,
+            http: config.http_client()?,
+        })
+    }
+
+    /// Send `alert` to every channel. A failing channel is logged, and does not stop the others.
+    pub async fn send(&self, alert: &Alert) {
+        for channel in &self.channels {
+            let result = match channel {
+                Channel::Stdout => {
+                    println!("{}: {}", alert.title, alert.message);
+                    Ok(())
+                }
+                Channel::Webhook(url) => self.webhook(url, alert).await,
+                Channel::NostrDm { to, sk } => self.nostr_dm(*to, sk.as_deref(), alert).await,
+            };
+            if let Err(e) = result {
+                log::error!("Alert {:?} could not be sent: {e}", alert.title);
+            }
+        }
+    }
+
+    async fn webhook(&self, url: &str, alert: &Alert) -> anyhow::Result<()> {
+        self.http
+            .post(url)
+            .json(alert)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn nostr_dm(
+        &self,
+        to: XOnlyPublicKey,
+        sk: Option<&str>,
+        alert: &Alert,
+    ) -> anyhow::Result<()> {
+        let (_keys, client) = match sk {
+            Some(sk) => {
+                self.config
+                    .nostr_client(sk, &[Kind::EncryptedDirectMessage], RelayUse::Write)
+                    .await?
+            }
+            None => {
+                self.config
+                    .nostr_random_client(&[Kind::EncryptedDirectMessage], RelayUse::Write)
+                    .await?
+            }
+        };
+        let sent = client
+            .send_direct_msg(to, format!("{}\n\n{}", alert.title, alert.message))
+            .await;
+        client.disconnect().await?;
+        sent?;
+        Ok(())
+    }
+}
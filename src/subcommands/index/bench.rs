@@ -0,0 +1,215 @@
+use std::time::{Duration, Instant};
+
+use bitcoin::BlockHash;
+use bitcoincore_rpc::RpcApi;
+use nostr_sdk::Event;
+use sqlx::SqlitePool;
+
+use crate::{
+    config::{Config, IndexBenchSubcommand},
+    db::{self, Anchor, TxStats},
+    util::{NameKind, NomenKind, NomenTx},
+};
+
+use super::{blockchain, events::records, BlockStore, EventData};
+
+/// Time spent in one stage of the pipeline, and how many items went through it.
+#[derive(Default)]
+struct Stage {
+    items: usize,
+    elapsed: Duration,
+}
+
+impl Stage {
+    /// Run `f`, adding its time to the stage. Items are counted by the caller.
+    fn time<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let t = f();
+        self.elapsed += start.elapsed();
+        t
+    }
+
+    fn print(&self, name: &str) {
+        let per_item = if self.items == 0 {
+            Duration::ZERO
+        } else {
+            self.elapsed / self.items as u32
+        };
+        println!(
+            "{name:<16}{:>8}{:>12.1?}{:>12.1?}",
+            self.items, self.elapsed, per_item
+        );
+    }
+}
+
+/// A Nomen output found in a replayed block.
+struct Output {
+    tx: bitcoin::Transaction,
+    nomen: NomenTx,
+    blockhash: BlockHash,
+    blocktime: usize,
+    blockheight: usize,
+    txheight: usize,
+    vout: usize,
+}
+
+/// Outputs found in the replayed blocks, with the RPC and parse stages.
+type Replayed = (Vec<(Output, TxStats)>, Stage, Stage);
+
+/// Replay indexed blocks and stored events through the indexing pipeline, into a scratch database,
+/// and print the time spent in each stage. The index itself is left unchanged.
+pub async fn bench(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &IndexBenchSubcommand,
+) -> anyhow::Result<()> {
+    let scratch = db::scratch().await?;
+
+    let blocks = db::indexed_blocks(pool, args.blocks).await?;
+    let mut rpc = Stage::default();
+    let mut parse = Stage::default();
+    let mut db_blocks = Stage::default();
+    if !blocks.is_empty() {
+        let (found, rpc_stage, parse_stage) = config
+            .rpc()?
+            .run(move |client| -> anyhow::Result<Replayed> {
+                let mut rpc = Stage::default();
+                let mut parse = Stage::default();
+                let mut found = Vec::new();
+                for (height, hash) in blocks {
+                    let blockhash: BlockHash = hash.parse()?;
+                    let (info, block) = rpc.time(|| -> anyhow::Result<_> {
+                        Ok((
+                            client.get_block_header_info(&blockhash)?,
+                            client.get_block(&blockhash)?,
+                        ))
+                    })?;
+                    rpc.items += 1;
+                    parse.items += 1;
+                    let outputs = parse.time(|| scan(&block, info.time, height as usize));
+                    for output in outputs {
+                        let stats = rpc.time(|| {
+                            blockchain::tx_stats(client, &BlockStore::disabled(), &output.tx)
+                        });
+                        found.push((output, stats));
+                    }
+                }
+                Ok((found, rpc, parse))
+            })
+            .await?;
+        rpc = rpc_stage;
+        parse = parse_stage;
+
+        for (output, stats) in found {
+            let start = Instant::now();
+            let anchor: Option<Anchor> = blockchain::anchor(&output.tx);
+            blockchain::index_output(
+                &mut *scratch.acquire().await?,
+                output.nomen.fingerprint,
+                output.nomen.nsid,
+                &output.blockhash,
+                &output.tx.txid(),
+                output.blocktime,
+                output.blockheight,
+                output.txheight,
+                output.vout,
+                output.nomen.kind,
+                output.nomen.commitment,
+                stats,
+                anchor,
+            )
+            .await?;
+            if output.nomen.kind == NomenKind::Create && output.nomen.commitment.is_some() {
+                records::quarantine_uncommitted(
+                    &scratch,
+                    output.nomen.fingerprint,
+                    output.nomen.nsid,
+                )
+                .await?;
+            }
+            db_blocks.elapsed += start.elapsed();
+            db_blocks.items += 1;
+        }
+    }
+
+    let mut parse_events = Stage::default();
+    let mut validate = Stage::default();
+    let mut db_events = Stage::default();
+    let limits = config.record_limits();
+    let events = db::raw_events_page(pool, NameKind::Name, 0, 0, args.events as i64).await?;
+    for (_, raw_event) in events {
+        parse_events.items += 1;
+        let Ok((ed, event)) = parse_events.time(|| -> anyhow::Result<_> {
+            let event: Event = serde_json::from_str(&raw_event)?;
+            Ok((EventData::from_event(&event)?, event))
+        }) else {
+            continue;
+        };
+        validate.items += 1;
+        let valid = validate.time(|| {
+            event.verify().is_ok()
+                && ed.validate().is_ok()
+                && limits.check(&ed.raw_content, ed.records.as_ref()).is_ok()
+        });
+        if valid {
+            let start = Instant::now();
+            records::save_event(&scratch, ed).await?;
+            db_events.elapsed += start.elapsed();
+            db_events.items += 1;
+        }
+    }
+
+    println!(
+        "{:<16}{:>8}{:>12}{:>12}",
+        "Stage", "Items", "Total", "Per item"
+    );
+    rpc.print("Block RPC");
+    parse.print("Block parse");
+    db_blocks.print("Block db");
+    parse_events.print("Event parse");
+    validate.print("Event validate");
+    db_events.print("Event db");
+    Ok(())
+}
+
+/// The Nomen outputs in `block`, as found by the indexer.
+fn scan(block: &bitcoin::Block, blocktime: usize, blockheight: usize) -> Vec<Output> {
+    let blockhash = block.block_hash();
+    let mut outputs = Vec::new();
+    for (txheight, tx) in block.txdata.iter().enumerate() {
+        for (vout, output) in tx.output.iter().enumerate() {
+            let Some(b) = blockchain::nomen_payload(&output.script_pubkey) else {
+                continue;
+            };
+            if let Ok(nomen) = NomenTx::try_from(b) {
+                outputs.push(Output {
+                    tx: tx.clone(),
+                    nomen,
+                    blockhash,
+                    blocktime,
+                    blockheight,
+                    txheight,
+                    vout,
+                });
+            }
+        }
+    }
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{blockdata::constants::genesis_block, Network, ScriptBuf, TxOut};
+
+    use super::*;
+
+    #[test]
+    fn test_scan_bare_op_return() {
+        let mut block = genesis_block(Network::Regtest);
+        block.txdata[0].output.push(TxOut {
+            value: 0,
+            script_pubkey: ScriptBuf::from(vec![0x6a]),
+        });
+        assert!(scan(&block, 0, 0).is_empty());
+    }
+}
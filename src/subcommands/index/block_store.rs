@@ -0,0 +1,211 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use bitcoin::{
+    consensus::{self, Decodable, Encodable},
+    Block, BlockHash, Transaction, Txid,
+};
+use bitcoincore_rpc::{Client, RpcApi};
+
+use crate::config::Config;
+
+/// Where blocks and transactions go in the store.
+const KINDS: [&str; 2] = ["blocks", "txs"];
+
+/// Raw blocks and transactions fetched from the node, kept on disk by hash so that scanning the
+/// chain again, e.g. after a reindex, doesn't fetch them all from the node again.
+///
+/// Each is a file named by its hash under `blocks/` or `txs/` in `[indexer].block_cache`. Once the
+/// files add up to more than `[indexer].block_cache_max_size`, the least recently used are removed
+/// until they are back under three quarters of it. Without `block_cache`, everything is fetched
+/// from the node.
+pub struct BlockStore {
+    dir: Option<PathBuf>,
+    max_size: u64,
+    /// Total size of the files in the store.
+    size: Mutex<u64>,
+}
+
+impl BlockStore {
+    /// The store configured under `[indexer]`, if any.
+    pub fn new(config: &Config) -> anyhow::Result<BlockStore> {
+        match config.indexer_block_cache() {
+            Some(dir) => BlockStore::open(dir, config.indexer_block_cache_max_size()),
+            None => Ok(BlockStore::disabled()),
+        }
+    }
+
+    /// A store that fetches everything from the node.
+    pub fn disabled() -> BlockStore {
+        BlockStore {
+            dir: None,
+            max_size: 0,
+            size: Mutex::new(0),
+        }
+    }
+
+    pub fn open(dir: PathBuf, max_size: u64) -> anyhow::Result<BlockStore> {
+        for kind in KINDS {
+            fs::create_dir_all(dir.join(kind))?;
+        }
+        let size = entries(&dir)?.iter().map(|(_, len, _)| len).sum();
+        log::info!(
+            "Block cache at {} holds {size} of {max_size} bytes",
+            dir.display()
+        );
+        Ok(BlockStore {
+            dir: Some(dir),
+            max_size,
+            size: Mutex::new(size),
+        })
+    }
+
+    pub fn block(&self, client: &Client, hash: &BlockHash) -> anyhow::Result<Block> {
+        self.fetch("blocks", &hash.to_string(), || Ok(client.get_block(hash)?))
+    }
+
+    pub fn transaction(&self, client: &Client, txid: &Txid) -> anyhow::Result<Transaction> {
+        self.fetch("txs", &txid.to_string(), || {
+            Ok(client.get_raw_transaction(txid, None)?)
+        })
+    }
+
+    /// The stored `kind` named `key`, or else the one `get` fetches, which is stored.
+    fn fetch<T: Encodable + Decodable>(
+        &self,
+        kind: &str,
+        key: &str,
+        get: impl FnOnce() -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let Some(dir) = &self.dir else {
+            return get();
+        };
+        let path = dir.join(kind).join(key);
+        if let Ok(bytes) = fs::read(&path) {
+            match consensus::deserialize(&bytes) {
+                Ok(value) => {
+                    // The modification time is the last use, for eviction.
+                    let touched = fs::File::options()
+                        .write(true)
+                        .open(&path)
+                        .and_then(|f| f.set_modified(SystemTime::now()));
+                    if let Err(e) = touched {
+                        log::debug!("Unable to touch {}: {e}", path.display());
+                    }
+                    return Ok(value);
+                }
+                Err(e) => log::warn!("Ignoring unreadable {}: {e}", path.display()),
+            }
+        }
+
+        let value = get()?;
+        if let Err(e) = self.insert(dir, &path, &consensus::serialize(&value)) {
+            log::warn!("Unable to cache {}: {e}", path.display());
+        }
+        Ok(value)
+    }
+
+    fn insert(&self, dir: &Path, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        let mut size = self.size.lock().expect("Block cache lock poisoned");
+        let replaced = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        // Written aside and renamed, so a concurrent read never sees half a file.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes)?;
+        fs::rename(&tmp, path)?;
+        *size = (*size + bytes.len() as u64).saturating_sub(replaced);
+
+        if *size > self.max_size {
+            let mut entries = entries(dir)?;
+            entries.sort_by_key(|(_, _, used)| *used);
+            let target = self.max_size / 4 * 3;
+            for (path, len, _) in entries {
+                if *size <= target {
+                    break;
+                }
+                fs::remove_file(&path)?;
+                *size = size.saturating_sub(len);
+            }
+            log::debug!("Block cache trimmed to {} bytes", *size);
+        }
+        Ok(())
+    }
+}
+
+/// Path, size and last use of every file in the store at `dir`.
+fn entries(dir: &Path) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+    let mut entries = vec![];
+    for kind in KINDS {
+        for entry in fs::read_dir(dir.join(kind))? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "tmp") {
+                continue;
+            }
+            let meta = entry.metadata()?;
+            entries.push((path, meta.len(), meta.modified()?));
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{absolute::LockTime, TxIn};
+
+    use super::*;
+
+    fn tx(lock_time: u32) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: LockTime::from_consensus(lock_time),
+            input: vec![TxIn::default()],
+            output: vec![],
+        }
+    }
+
+    #[test]
+    fn test_block_store() {
+        let dir = std::env::temp_dir().join(format!("nomen-block-store-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let size = consensus::serialize(&tx(0)).len() as u64;
+        let store = BlockStore::open(dir.clone(), size * 3).unwrap();
+        let fetch = |n: u32| {
+            store
+                .fetch("txs", &n.to_string(), || Ok(tx(n)))
+                .unwrap()
+                .lock_time
+                .to_consensus_u32()
+        };
+        let cached = |n: u32| dir.join("txs").join(n.to_string()).exists();
+
+        // A stored transaction is read back instead of fetched.
+        assert_eq!(fetch(1), 1);
+        let stored = store
+            .fetch::<Transaction>("txs", "1", || panic!("fetched again"))
+            .unwrap();
+        assert_eq!(stored.lock_time.to_consensus_u32(), 1);
+
+        // Going over the size evicts the least recently used.
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fetch(2);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fetch(3);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fetch(1);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fetch(4);
+        assert!(cached(1) && cached(4));
+        assert!(!cached(2) && !cached(3));
+
+        // Reopening counts what is already stored.
+        drop(store);
+        let store = BlockStore::open(dir.clone(), size * 3).unwrap();
+        assert_eq!(*store.size.lock().unwrap(), size * 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,102 +1,205 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use bitcoin::{BlockHash, Txid};
+use bitcoin::{BlockHash, OutPoint, Script, Txid};
 use bitcoincore_rpc::{Client, RpcApi};
-use sqlx::SqlitePool;
+use futures::StreamExt;
+use sqlx::{SqliteConnection, SqlitePool};
 
 use crate::{
     config::{Cli, Config},
-    db::{self, insert_index_height},
+    db::{self, Anchor, TxStats},
+    error::NomenError,
+    rpc::Rpc,
     util::{NomenKind, NomenTx, Nsid},
 };
 
-pub async fn index(config: &Config, pool: &sqlx::Pool<sqlx::Sqlite>) -> Result<(), anyhow::Error> {
+use super::{Alert, BlockStore, Hooks, IndexEvent, Notifier};
+
+/// Found while scanning a block. Everything found in a block is saved together.
+enum Found {
+    Output {
+        fingerprint: [u8; 5],
+        nsid: Nsid,
+        blockhash: BlockHash,
+        txid: Txid,
+        blocktime: usize,
+        blockheight: usize,
+        txheight: usize,
+        vout: usize,
+        kind: NomenKind,
+        commitment: Option<[u8; 32]>,
+        stats: TxStats,
+        anchor: Option<Anchor>,
+    },
+    /// An anchor output, spent by a transaction in this block.
+    AnchorSpent(OutPoint, Txid),
+}
+
+/// Blocks per chunk of a parallel scan. Each worker holds one chunk at a time.
+const CHUNK_BLOCKS: usize = 20;
+
+/// A block scanned by a worker of a parallel scan, committed in height order.
+struct ScannedBlock {
+    height: usize,
+    hash: BlockHash,
+    prev_hash: BlockHash,
+    txids: Vec<Txid>,
+    /// Every output spent in the block, with the position of the spending transaction. Whether
+    /// one is an anchor is only known when the block is committed.
+    inputs: Vec<(u32, OutPoint)>,
+    /// `Found::Output`s of the block, in order.
+    outputs: Vec<Found>,
+}
+
+/// Scan the blocks after the index tip for Nomen transactions, with `workers` parallel workers
+/// fetching blocks from the node if more than one. Blocks and transactions come from the
+/// `[indexer].block_cache` store when it has them.
+pub async fn index(
+    config: &Config,
+    pool: &sqlx::Pool<sqlx::Sqlite>,
+    hooks: &Hooks,
+    notifier: &Notifier,
+    workers: usize,
+) -> Result<(), anyhow::Error> {
     // Check if the index is on a stale chain, and rewind the index if necessary
-    rewind_invalid_chain(config.rpc_client()?, pool.clone()).await?;
+    let rpc = config.rpc()?;
+    if let Some(stale_block) = rewind_invalid_chain(&rpc, pool).await? {
+        notifier
+            .send(&Alert {
+                title: "Chain reorganization".into(),
+                message: format!("Stale blocks found, index rolled back to height {stale_block}"),
+            })
+            .await;
+    }
 
-    let client = config.rpc_client()?;
     let index_height = db::next_index_height(pool)
         .await?
         .max(config.starting_block_height());
+    let store = Arc::new(BlockStore::new(config)?);
     let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
 
     log::info!("Starting blockchain index at height {index_height}");
     let min_confirmations = config.confirmations()?;
+    let mut anchors: HashSet<OutPoint> = db::unspent_anchors(pool)
+        .await?
+        .into_iter()
+        .filter_map(|(txid, vout)| Some(OutPoint::new(txid.parse().ok()?, vout as u32)))
+        .collect();
 
-    let thread = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
-        let mut blockhash = client.get_block_hash(index_height as u64)?;
-        let mut blockinfo = client.get_block_header_info(&blockhash)?;
+    if workers > 1 {
+        return index_parallel(
+            &rpc,
+            &store,
+            pool,
+            hooks,
+            index_height,
+            min_confirmations,
+            anchors,
+            workers,
+        )
+        .await;
+    }
 
-        loop {
-            // If the channel is closed, let's stop
-            if sender.is_closed() {
-                log::info!("Stopping index operation.");
-                break;
-            }
+    let scan = tokio::spawn(async move {
+        rpc.run(move |client| {
+            let mut blockhash = client.get_block_hash(index_height as u64)?;
+            let mut blockinfo = client.get_block_header_info(&blockhash)?;
 
-            if (blockinfo.confirmations as usize) < min_confirmations {
-                log::info!(
-                    "Minimum confirmations not met at block height {}.",
-                    blockinfo.height
-                );
-                break;
-            }
+            loop {
+                // If the channel is closed, let's stop
+                if sender.is_closed() {
+                    log::info!("Stopping index operation.");
+                    break;
+                }
 
-            if blockinfo.height % 10 == 0 {
-                log::info!("Index block height {}", blockinfo.height);
-            }
+                if (blockinfo.confirmations as usize) < min_confirmations {
+                    log::info!(
+                        "Minimum confirmations not met at block height {}.",
+                        blockinfo.height
+                    );
+                    break;
+                }
+
+                if blockinfo.height % 10 == 0 {
+                    log::info!("Index block height {}", blockinfo.height);
+                }
 
-            let block = client.get_block(&blockhash)?;
+                let block = store.block(client, &blockhash)?;
 
-            for (txheight, tx) in block.txdata.iter().enumerate() {
-                for (vout, output) in tx.output.iter().enumerate() {
-                    if output.script_pubkey.is_op_return() {
-                        let b = &output.script_pubkey.as_bytes()[2..];
+                // Sent once the whole block is scanned, even if nothing was found, so the block is
+                // saved and marked indexed at once.
+                let mut found = vec![];
+                for (txheight, tx) in block.txdata.iter().enumerate() {
+                    if !tx.is_coin_base() {
+                        for input in &tx.input {
+                            if anchors.remove(&input.previous_output) {
+                                found.push(Found::AnchorSpent(input.previous_output, tx.txid()));
+                            }
+                        }
+                    }
 
-                        // Pre-check if it starts with NOM, so we can filter out some unnecessary errors from the logs
-                        if b.starts_with(b"NOM") {
-                            match NomenTx::try_from(b) {
-                                Ok(NomenTx {
+                    for (vout, output) in tx.output.iter().enumerate() {
+                        let Some(b) = nomen_payload(&output.script_pubkey) else {
+                            continue;
+                        };
+                        match NomenTx::try_from(b) {
+                            Ok(NomenTx {
+                                fingerprint,
+                                nsid,
+                                kind,
+                                commitment,
+                            }) => {
+                                let stats = tx_stats(client, &store, tx);
+                                let anchor = anchor(tx);
+                                if let Some(anchor) = &anchor {
+                                    anchors.insert(OutPoint::new(tx.txid(), anchor.vout as u32));
+                                }
+                                found.push(Found::Output {
                                     fingerprint,
                                     nsid,
+                                    blockhash,
+                                    txid: tx.txid(),
+                                    blocktime: blockinfo.time,
+                                    blockheight: blockinfo.height,
+                                    txheight,
+                                    vout,
                                     kind,
-                                }) => {
-                                    sender.blocking_send((
-                                        (blockinfo.height, blockhash),
-                                        Some((
-                                            fingerprint,
-                                            nsid,
-                                            blockhash,
-                                            tx.txid(),
-                                            blockinfo.time,
-                                            blockinfo.height,
-                                            txheight,
-                                            vout,
-                                            kind,
-                                        )),
-                                    ));
-                                }
-
-                                Err(e) => log::error!("Index error: {e}"),
+                                    commitment,
+                                    stats,
+                                    anchor,
+                                });
                             }
-                        } else {
-                            sender.blocking_send(((blockinfo.height, blockhash), None));
+
+                            Err(e) => log::error!("Index error: {e}"),
                         }
-                    } else {
-                        sender.blocking_send(((blockinfo.height, blockhash), None));
                     }
                 }
-            }
-            match blockinfo.next_block_hash {
-                Some(next_hash) => {
-                    blockhash = next_hash;
-                    blockinfo = client.get_block_header_info(&blockhash)?;
+                if sender
+                    .blocking_send((blockinfo.height, blockhash, found))
+                    .is_err()
+                {
+                    log::info!("Stopping index operation.");
+                    break;
+                }
+
+                match blockinfo.next_block_hash {
+                    Some(next_hash) => {
+                        blockhash = next_hash;
+                        blockinfo = client.get_block_header_info(&blockhash)?;
+                    }
+                    None => break,
                 }
-                None => break,
             }
-        }
 
-        Ok(())
+            Ok(())
+        })
+        .await
     });
 
     let guard = elegant_departure::get_shutdown_guard();
@@ -104,28 +207,7 @@ pub async fn index(config: &Config, pool: &sqlx::Pool<sqlx::Sqlite>) -> Result<(
         tokio::select! {
             msg = receiver.recv() => {
                 match msg {
-                    Some(((height, hash), Some((fingerprint, nsid, blockhash, txid, blocktime, blockheight, txheight, vout, kind)))) => {
-                        if let Err(e) = index_output(
-                            pool,
-                            fingerprint,
-                            nsid,
-                            &blockhash,
-                            &txid,
-                            blocktime,
-                            blockheight,
-                            txheight,
-                            vout,
-                            kind,
-                        )
-                        .await
-                        {
-                            log::error!("Index error: {e}");
-                        }
-                        insert_index_height(pool, height as i64, &hash).await?;
-                    }
-                    Some(((height, hash), None)) => {
-                        insert_index_height(pool, height as i64, &hash).await?;
-                    },
+                    Some((height, hash, found)) => save_block(pool, hooks, height, &hash, found).await?,
                     None => break 'select,
                 }
             }
@@ -136,13 +218,271 @@ pub async fn index(config: &Config, pool: &sqlx::Pool<sqlx::Sqlite>) -> Result<(
         }
     }
 
+    // Errors from the node stop the scan, and are returned once what was found is saved.
+    scan.await??;
+    log::info!("Blockchain index complete.");
+    Ok(())
+}
+
+impl ScannedBlock {
+    /// The outputs and anchor spends of the block, for each transaction in chain order as in the
+    /// sequential scan, given the unspent `anchors` before it. Updates `anchors`.
+    fn into_found(self, anchors: &mut HashSet<OutPoint>) -> Vec<Found> {
+        let mut found = vec![];
+        let mut inputs = self.inputs.into_iter().peekable();
+        let mut outputs = self.outputs.into_iter().peekable();
+        for (txheight, txid) in self.txids.into_iter().enumerate() {
+            while let Some((_, outpoint)) = inputs.next_if(|(i, _)| *i as usize == txheight) {
+                if anchors.remove(&outpoint) {
+                    found.push(Found::AnchorSpent(outpoint, txid));
+                }
+            }
+            while let Some(output) = outputs
+                .next_if(|o| matches!(o, Found::Output { txheight: t, .. } if *t == txheight))
+            {
+                if let Found::Output {
+                    anchor: Some(anchor),
+                    ..
+                } = &output
+                {
+                    anchors.insert(OutPoint::new(txid, anchor.vout as u32));
+                }
+                found.push(output);
+            }
+        }
+        found
+    }
+}
+
+/// Save what was found in the block at `height` and mark the block indexed, in one database
+/// transaction. If the indexer stops partway through a block, none of it is saved, and the whole
+/// block is scanned again on the next run instead of the rest of it being skipped.
+async fn save_block(
+    pool: &SqlitePool,
+    hooks: &Hooks,
+    height: usize,
+    hash: &BlockHash,
+    found: Vec<Found>,
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    // Claims with a commitment, and index events, handled once the block is saved.
+    let mut claims = vec![];
+    let mut events = vec![];
+    for found in found {
+        match found {
+            Found::Output {
+                fingerprint,
+                nsid,
+                blockhash,
+                txid,
+                blocktime,
+                blockheight,
+                txheight,
+                vout,
+                kind,
+                commitment,
+                stats,
+                anchor,
+            } => {
+                if let Err(e) = index_output(
+                    &mut tx,
+                    fingerprint,
+                    nsid,
+                    &blockhash,
+                    &txid,
+                    blocktime,
+                    blockheight,
+                    txheight,
+                    vout,
+                    kind,
+                    commitment,
+                    stats,
+                    anchor,
+                )
+                .await
+                {
+                    log::error!("Index error: {e}");
+                } else {
+                    if kind == NomenKind::Create && commitment.is_some() {
+                        claims.push((fingerprint, nsid));
+                    }
+                    events.push(IndexEvent::Blockchain {
+                        kind: kind.to_string(),
+                        fingerprint: hex::encode(fingerprint),
+                        nsid: nsid.to_string(),
+                        blockhash: blockhash.to_string(),
+                        txid: txid.to_string(),
+                        blockheight,
+                        txheight,
+                        vout,
+                    });
+                }
+            }
+            Found::AnchorSpent(anchor, txid) => {
+                log::info!("Anchor output {anchor} spent by {txid}");
+                db::spend_anchor(
+                    &mut tx,
+                    &anchor.txid.to_string(),
+                    anchor.vout as i64,
+                    &txid.to_string(),
+                    height as i64,
+                )
+                .await?;
+            }
+        }
+    }
+    db::insert_index_height(&mut tx, height as i64, hash).await?;
+    tx.commit().await?;
+
+    for (fingerprint, nsid) in claims {
+        super::events::records::quarantine_uncommitted(pool, fingerprint, nsid).await?;
+    }
+    for event in events {
+        hooks.dispatch(event).await;
+    }
+    Ok(())
+}
+
+/// Like the sequential scan, with the blocks up to the last confirmed one split into chunks that
+/// `workers` workers fetch and parse from the node in parallel. At most `workers` chunks are held
+/// at once, and they are committed in height order, so claims are saved in chain order and the
+/// earliest claim of a name still wins. Anchor spends are matched at commit, as only then are all
+/// earlier anchors known.
+///
+/// If the chain changes during the scan, it stops at the first block that doesn't follow the
+/// previous one, and the next run rewinds the index as usual.
+#[allow(clippy::too_many_arguments)]
+async fn index_parallel(
+    rpc: &Rpc,
+    store: &Arc<BlockStore>,
+    pool: &SqlitePool,
+    hooks: &Hooks,
+    start: usize,
+    min_confirmations: usize,
+    mut anchors: HashSet<OutPoint>,
+    workers: usize,
+) -> anyhow::Result<()> {
+    let tip = rpc.get_block_count().await? as usize;
+    let end = (tip + 1).saturating_sub(min_confirmations);
+    if end < start {
+        log::info!("Minimum confirmations not met at block height {start}.");
+        return Ok(());
+    }
+    log::info!("Scanning blocks {start} to {end} with {workers} workers");
+
+    let chunks = (start..=end).step_by(CHUNK_BLOCKS).map(|first| {
+        let rpc = rpc.clone();
+        let store = store.clone();
+        let last = (first + CHUNK_BLOCKS - 1).min(end);
+        async move {
+            rpc.run(move |client| scan_blocks(client, &store, first, last))
+                .await
+        }
+    });
+    let mut chunks = futures::stream::iter(chunks).buffered(workers);
+    let mut prev_hash = match db::index_tip(pool).await? {
+        Some((height, hash)) if height as usize + 1 == start => Some(hash.parse::<BlockHash>()?),
+        _ => None,
+    };
+
+    let guard = elegant_departure::get_shutdown_guard();
+    loop {
+        let chunk = tokio::select! {
+            chunk = chunks.next() => chunk,
+            _ = guard.wait() => None,
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        for block in chunk? {
+            if prev_hash.is_some_and(|prev| prev != block.prev_hash) {
+                log::warn!(
+                    "Block {} at height {} doesn't follow the previous block, stopping the scan",
+                    block.hash,
+                    block.height
+                );
+                return Ok(());
+            }
+            if block.height % 10 == 0 {
+                log::info!("Index block height {}", block.height);
+            }
+            let (height, hash) = (block.height, block.hash);
+            let found = block.into_found(&mut anchors);
+            save_block(pool, hooks, height, &hash, found).await?;
+            prev_hash = Some(hash);
+        }
+    }
+
     log::info!("Blockchain index complete.");
     Ok(())
 }
 
+/// Fetch and parse the blocks from height `first` to `last`, for a parallel scan.
+fn scan_blocks(
+    client: &Client,
+    store: &BlockStore,
+    first: usize,
+    last: usize,
+) -> anyhow::Result<Vec<ScannedBlock>> {
+    let mut blocks = Vec::with_capacity(last + 1 - first);
+    for height in first..=last {
+        let hash = client.get_block_hash(height as u64)?;
+        let block = store.block(client, &hash)?;
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        for (txheight, tx) in block.txdata.iter().enumerate() {
+            if !tx.is_coin_base() {
+                inputs.extend(
+                    tx.input
+                        .iter()
+                        .map(|input| (txheight as u32, input.previous_output)),
+                );
+            }
+            for (vout, output) in tx.output.iter().enumerate() {
+                let Some(data) = nomen_payload(&output.script_pubkey) else {
+                    continue;
+                };
+                match NomenTx::try_from(data) {
+                    Ok(NomenTx {
+                        fingerprint,
+                        nsid,
+                        kind,
+                        commitment,
+                    }) => outputs.push(Found::Output {
+                        fingerprint,
+                        nsid,
+                        blockhash: hash,
+                        txid: tx.txid(),
+                        blocktime: block.header.time as usize,
+                        blockheight: height,
+                        txheight,
+                        vout,
+                        kind,
+                        commitment,
+                        stats: tx_stats(client, store, tx),
+                        anchor: anchor(tx),
+                    }),
+                    Err(e) => log::error!("Index error: {e}"),
+                }
+            }
+        }
+        blocks.push(ScannedBlock {
+            height,
+            hash,
+            prev_hash: block.header.prev_blockhash,
+            txids: block.txdata.iter().map(|tx| tx.txid()).collect(),
+            inputs,
+            outputs,
+        });
+    }
+    Ok(blocks)
+}
+
+/// Save a Nomen output. Claims committing to their records also need
+/// [`quarantine_uncommitted`](super::events::records::quarantine_uncommitted) once saved.
 #[allow(clippy::too_many_arguments)]
-async fn index_output(
-    conn: &SqlitePool,
+pub(super) async fn index_output(
+    conn: &mut SqliteConnection,
     fingerprint: [u8; 5],
     nsid: Nsid,
     blockhash: &BlockHash,
@@ -152,10 +492,13 @@ async fn index_output(
     txheight: usize,
     vout: usize,
     kind: NomenKind,
+    commitment: Option<[u8; 32]>,
+    stats: TxStats,
+    anchor: Option<Anchor>,
 ) -> anyhow::Result<()> {
     log::info!("NOM output found: {}", nsid);
     if nsid.len() != 20 {
-        return Err(anyhow::anyhow!("Unexpected NOM length"));
+        anyhow::bail!(NomenError::Validation("Unexpected NOM length".into()));
     }
 
     db::insert_blockchain(
@@ -169,29 +512,76 @@ async fn index_output(
         txheight,
         vout,
         kind,
+        commitment,
+        stats,
+        anchor,
     )
     .await?;
     Ok(())
 }
 
-async fn rewind_invalid_chain(client: Client, pool: SqlitePool) -> anyhow::Result<()> {
+/// The first spendable output of `tx`.
+pub(super) fn anchor(tx: &bitcoin::Transaction) -> Option<Anchor> {
+    tx.output
+        .iter()
+        .enumerate()
+        .find(|(_, o)| !o.script_pubkey.is_op_return())
+        .map(|(vout, o)| Anchor {
+            vout,
+            value: o.value,
+            script: hex::encode(o.script_pubkey.as_bytes()),
+        })
+}
+
+/// Size and fee of `tx`. The fee needs every spent output, which is looked up from the node,
+/// so it is left empty if any of them can't be found (e.g. the node has no txindex).
+pub(super) fn tx_stats(client: &Client, store: &BlockStore, tx: &bitcoin::Transaction) -> TxStats {
+    let fee = if tx.is_coin_base() {
+        None
+    } else {
+        tx.input
+            .iter()
+            .map(|input| {
+                let prev = store
+                    .transaction(client, &input.previous_output.txid)
+                    .ok()?;
+                prev.output
+                    .get(input.previous_output.vout as usize)
+                    .map(|o| o.value)
+            })
+            .sum::<Option<u64>>()
+            .and_then(|input| input.checked_sub(tx.output.iter().map(|o| o.value).sum()))
+    };
+    if fee.is_none() {
+        log::debug!("Unable to calculate fee for {}", tx.txid());
+    }
+
+    TxStats {
+        fee,
+        size: tx.size(),
+        vsize: tx.vsize(),
+    }
+}
+
+/// Returns the height the index was rolled back to, if any blocks were stale.
+async fn rewind_invalid_chain(rpc: &Rpc, pool: &SqlitePool) -> anyhow::Result<Option<usize>> {
     // Get the latest indexed blockhash and blockheight
     let result = sqlx::query_as::<_, (i32, String)>(
         "SELECT blockheight, blockhash FROM index_height ORDER BY blockheight DESC LIMIT 1;",
     )
-    .fetch_optional(&pool)
+    .fetch_optional(pool)
     .await?;
 
     // No transactions indexed yet, skip the rest
     if result.is_none() {
-        return Ok(());
+        return Ok(None);
     }
 
     let (blockheight, blockhash) = result.unwrap();
 
     // Loop backwards from recently indexed block, continuing to the previous block, until we find the most recent ancestor which is not stale
-    let stale_block =
-        tokio::task::spawn_blocking(move || -> Result<Option<usize>, anyhow::Error> {
+    let stale_block = rpc
+        .run(move |client| {
             let mut next_block = Some(blockhash.parse()?);
             let mut stale_block = None;
 
@@ -212,22 +602,144 @@ async fn rewind_invalid_chain(client: Client, pool: SqlitePool) -> anyhow::Resul
 
             Ok(stale_block)
         })
-        .await??;
+        .await?;
 
-    // Delete entries from blockchain table
+    // Transactions from stale blocks wait to be mined again, see `conflicts::check`
     if let Some(stale_block) = stale_block {
         log::info!("Reindexing beginning at height {stale_block}");
         let mut tx = pool.begin().await?;
-        sqlx::query("DELETE FROM blockchain WHERE blockheight >= ?;")
-            .bind(stale_block as i32)
-            .execute(&mut tx)
-            .await?;
+        sqlx::query(
+            "UPDATE blockchain SET status = 'pending' WHERE blockheight >= ? AND status = 'valid';",
+        )
+        .bind(stale_block as i32)
+        .execute(&mut tx)
+        .await?;
         sqlx::query("DELETE FROM index_height WHERE blockheight >= ?;")
             .bind(stale_block as i32)
             .execute(&mut tx)
             .await?;
+        sqlx::query(
+            "UPDATE blockchain SET spent_txid = NULL, spent_height = NULL WHERE spent_height >= ?;",
+        )
+        .bind(stale_block as i32)
+        .execute(&mut tx)
+        .await?;
         tx.commit().await?;
     }
 
-    Ok(())
+    Ok(stale_block)
+}
+
+/// The data after `OP_RETURN <push>` of an output, if it starts like a Nomen output. Checking the
+/// prefix first keeps other OP_RETURN outputs out of the error log. A bare `OP_RETURN` has no data.
+pub(crate) fn nomen_payload(script: &Script) -> Option<&[u8]> {
+    if !script.is_op_return() {
+        return None;
+    }
+    script
+        .as_bytes()
+        .get(2..)
+        .filter(|data| data.starts_with(b"NOM"))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::hashes::Hash;
+
+    use super::*;
+
+    #[test]
+    fn test_nomen_payload() {
+        let script = |bytes: &[u8]| bitcoin::ScriptBuf::from(bytes.to_vec());
+        assert_eq!(nomen_payload(&script(&[0x6a])), None);
+        assert_eq!(nomen_payload(&script(&[0x6a, 0x01])), None);
+        assert_eq!(
+            nomen_payload(&script(&[0x6a, 0x04, b'a', b'b', b'c', b'd'])),
+            None
+        );
+        assert_eq!(
+            nomen_payload(&script(&[0x6a, 0x04, b'N', b'O', b'M', 0])),
+            Some(&b"NOM\0"[..])
+        );
+        // Not an OP_RETURN.
+        assert_eq!(
+            nomen_payload(&script(&[0x51, 0x04, b'N', b'O', b'M', 0])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_scanned_block_order() {
+        let txid = |n: u8| Txid::from_byte_array([n; 32]);
+        let hash = BlockHash::from_byte_array([0; 32]);
+        let known = OutPoint::new(txid(9), 1);
+        let output = |txheight: usize| Found::Output {
+            fingerprint: [1; 5],
+            nsid: Nsid::from_slice(&[1; 20]).unwrap(),
+            blockhash: hash,
+            txid: txid(txheight as u8),
+            blocktime: 0,
+            blockheight: 100,
+            txheight,
+            vout: 0,
+            kind: NomenKind::Create,
+            commitment: None,
+            stats: TxStats {
+                fee: None,
+                size: 100,
+                vsize: 100,
+            },
+            anchor: Some(Anchor {
+                vout: 1,
+                value: 1000,
+                script: String::new(),
+            }),
+        };
+        // Transaction 1 claims a name, 2 spends its anchor, and 3 spends an earlier anchor.
+        let block = ScannedBlock {
+            height: 100,
+            hash,
+            prev_hash: hash,
+            txids: (0..4).map(txid).collect(),
+            inputs: vec![
+                (1, OutPoint::new(txid(8), 0)),
+                (2, OutPoint::new(txid(1), 1)),
+                (3, known),
+            ],
+            outputs: vec![output(1)],
+        };
+        let mut anchors = HashSet::from([known]);
+        let found = block.into_found(&mut anchors);
+        assert!(anchors.is_empty());
+        assert_eq!(found.len(), 3);
+        assert!(matches!(found[0], Found::Output { txheight: 1, .. }));
+        assert!(
+            matches!(found[1], Found::AnchorSpent(o, t) if o == OutPoint::new(txid(1), 1) && t == txid(2))
+        );
+        assert!(matches!(found[2], Found::AnchorSpent(o, t) if o == known && t == txid(3)));
+    }
+
+    #[tokio::test]
+    async fn test_save_block() {
+        let pool = db::scratch().await.unwrap();
+        let hooks = Hooks::default();
+        let hash = BlockHash::from_byte_array([0; 32]);
+
+        // A block with nothing in it is still marked indexed.
+        save_block(&pool, &hooks, 100, &hash, vec![]).await.unwrap();
+        assert_eq!(db::next_index_height(&pool).await.unwrap(), 101);
+
+        // What was found is saved along with the height.
+        let spent = OutPoint::new(Txid::from_byte_array([1; 32]), 0);
+        save_block(
+            &pool,
+            &hooks,
+            101,
+            &hash,
+            vec![Found::AnchorSpent(spent, Txid::from_byte_array([2; 32]))],
+        )
+        .await
+        .unwrap();
+        assert_eq!(db::next_index_height(&pool).await.unwrap(), 102);
+    }
 }
@@ -0,0 +1,50 @@
+use anyhow::bail;
+use nostr_sdk::prelude::ToBech32;
+use sqlx::SqlitePool;
+use yansi::Paint;
+
+use crate::{
+    config::{Config, IndexCheckpointSubcommand},
+    db,
+    error::NomenError,
+    subcommands::{outbox, Signer},
+    util::Checkpoint,
+};
+
+/// Sign a checkpoint of every indexed name and store it, with the names, for `/api/name/absence`.
+/// Meant to be run periodically, e.g. from cron; each run replaces the previous checkpoint.
+pub async fn checkpoint(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &IndexCheckpointSubcommand,
+) -> anyhow::Result<()> {
+    let signer = Signer::new(config, &args.privkey, &args.bunker).await?;
+    let (tip, names) = db::names_at_tip(pool).await?;
+    let Some((index_height, index_blockhash)) = tip else {
+        bail!(NomenError::NotFound(
+            "The index is empty, nothing to checkpoint".into()
+        ));
+    };
+
+    let checkpoint = Checkpoint {
+        version: 0,
+        network: config.network().to_string(),
+        index_height,
+        index_blockhash,
+        count: names.len() as u64,
+        root: hex::encode(nomen_core::names_root(&names)),
+    };
+    let event = signer.sign(checkpoint.event(signer.public_key())?).await?;
+    db::save_checkpoint(pool, index_height, &event.as_json(), &names).await?;
+
+    println!(
+        "Checkpoint of {} names at height {} signed by {}",
+        Paint::yellow(checkpoint.count),
+        Paint::yellow(checkpoint.index_height),
+        event.pubkey.to_bech32()?
+    );
+    if args.publish && outbox::publish(config, pool, &event).await? {
+        println!("Sent event {}", event.id);
+    }
+    Ok(())
+}
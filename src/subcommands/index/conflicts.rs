@@ -0,0 +1,75 @@
+use bitcoin::{BlockHash, Txid};
+use bitcoincore_rpc::{Client, RpcApi};
+use sqlx::SqlitePool;
+
+use crate::{config::Config, db};
+
+use super::{Alert, Notifier};
+
+/// Revoke the pending transactions, from blocks that were reorganized away, that were conflicted
+/// out and can never be mined. The rest stay pending until the indexer finds them in a block again.
+pub async fn check(config: &Config, pool: &SqlitePool, notifier: &Notifier) -> anyhow::Result<()> {
+    let pending = db::pending_txs(pool).await?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let conflicts = config
+        .rpc()?
+        .run(move |client| {
+            let mut conflicts = vec![];
+            for tx in pending {
+                if let Some(reason) = conflict(client, &tx.txid.parse()?, &tx.blockhash.parse()?)? {
+                    conflicts.push((tx, reason));
+                }
+            }
+            Ok(conflicts)
+        })
+        .await?;
+
+    for (tx, reason) in conflicts {
+        log::warn!("Revoking {} transaction {}: {reason}", tx.kind, tx.txid);
+        db::revoke_tx(pool, tx.id, &reason).await?;
+        notifier
+            .send(&Alert {
+                title: "Nomen transaction conflicted out".into(),
+                message: format!(
+                    "The {} transaction {} was reorganized away and {reason}, it is revoked.",
+                    tx.kind, tx.txid
+                ),
+            })
+            .await;
+    }
+    Ok(())
+}
+
+/// Why `txid`, from the stale block `blockhash`, can never be mined, if it can't.
+fn conflict(client: &Client, txid: &Txid, blockhash: &BlockHash) -> anyhow::Result<Option<String>> {
+    // In the mempool, or mined again (found with -txindex) but not indexed yet.
+    if client.get_raw_transaction_info(txid, None).is_ok() {
+        return Ok(None);
+    }
+
+    // Stale blocks stay on disk, unless the node prunes them.
+    let tx = client.get_raw_transaction(txid, Some(blockhash))?;
+
+    // Without -txindex, an unspent output shows the transaction was mined again.
+    for vout in 0..tx.output.len() as u32 {
+        if client.get_tx_out(txid, vout, Some(true))?.is_some() {
+            return Ok(None);
+        }
+    }
+
+    for input in &tx.input {
+        let prev = input.previous_output;
+        if client
+            .get_tx_out(&prev.txid, prev.vout, Some(true))?
+            .is_none()
+        {
+            return Ok(Some(format!(
+                "its input {prev} was spent by another transaction"
+            )));
+        }
+    }
+    Ok(None)
+}
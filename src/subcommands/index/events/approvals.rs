@@ -0,0 +1,59 @@
+use sqlx::SqlitePool;
+
+use super::sync::RelaySync;
+use crate::{config::Config, db, util::Approval};
+
+pub async fn approvals(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+    log::info!("Beginning indexing subname approval events.");
+    let sync = RelaySync::fetch(config, pool, Approval::kind()).await?;
+    for event in &sync.events {
+        match Approval::from_event(event) {
+            Ok(approval) => {
+                log::info!(
+                    "Saving approval of {} ({})",
+                    approval.name,
+                    approval.event_id
+                );
+                db::insert_approval(pool, &approval).await?;
+            }
+            Err(err) => log::debug!("Invalid approval: {err}"),
+        }
+    }
+    sync.commit(config, pool).await?;
+
+    log::info!("Subname approval events indexing complete.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use nostr_sdk::Keys;
+
+    use super::*;
+    use crate::subcommands::index::index_name;
+
+    #[tokio::test]
+    async fn test_indexed_subname() {
+        let pool = db::scratch().await.unwrap();
+        let parent = Keys::generate();
+        let child = Keys::generate();
+        index_name(&pool, &parent, "smith", &HashMap::new(), 100).await;
+        index_name(&pool, &child, "bob.smith", &HashMap::new(), 101).await;
+        assert_eq!(
+            db::name_owner(&pool, "bob.smith").await.unwrap(),
+            Some(child.public_key())
+        );
+        assert!(!db::subname_approved(&pool, "bob.smith").await.unwrap());
+
+        let event = Approval::event(parent.public_key(), "bob.smith", &child.public_key())
+            .unwrap()
+            .sign(&parent)
+            .unwrap();
+        db::insert_approval(&pool, &Approval::from_event(&event).unwrap())
+            .await
+            .unwrap();
+        assert!(db::subname_approved(&pool, "bob.smith").await.unwrap());
+    }
+}
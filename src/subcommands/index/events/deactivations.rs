@@ -0,0 +1,26 @@
+use sqlx::SqlitePool;
+
+use super::sync::RelaySync;
+use crate::{config::Config, db, util::Deactivation};
+
+pub async fn deactivations(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+    log::info!("Beginning indexing deactivation events.");
+    let sync = RelaySync::fetch(config, pool, Deactivation::kind()).await?;
+    for event in &sync.events {
+        match Deactivation::from_event(event) {
+            Ok(deactivation) => {
+                log::info!(
+                    "Saving deactivation of {} ({})",
+                    deactivation.name,
+                    deactivation.event_id
+                );
+                db::insert_deactivation(pool, &deactivation).await?;
+            }
+            Err(err) => log::debug!("Invalid deactivation: {err}"),
+        }
+    }
+    sync.commit(config, pool).await?;
+
+    log::info!("Deactivation events indexing complete.");
+    Ok(())
+}
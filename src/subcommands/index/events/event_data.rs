@@ -1,10 +1,14 @@
 use std::collections::HashMap;
 
 use anyhow::bail;
-use nostr_sdk::{Event, EventId};
+use nomen_core::NameEvent;
+use nostr_sdk::{Event, EventId, Tag};
 use secp256k1::XOnlyPublicKey;
 
-use crate::util::{EventExtractor, Hash160, Name, Nsid};
+use crate::{
+    error::NomenError,
+    util::{EventExtractor, Name, Nsid, RecordAnchor},
+};
 
 #[derive(Debug, Clone)]
 pub struct EventData {
@@ -17,37 +21,70 @@ pub struct EventData {
     pub created_at: i64,
     pub raw_content: String,
     pub records: Option<HashMap<String, String>>,
+    pub anchor: Option<RecordAnchor>,
+    /// When the records stop being served, from a NIP-40 `expiration` tag.
+    pub expires_at: Option<i64>,
     pub raw_event: String,
 }
 
 impl EventData {
+    /// Read a name or transfer event with the protocol checks in `nomen_core`, shared with clients.
     pub fn from_event(event: &Event) -> anyhow::Result<Self> {
-        let nsid = event.extract_nsid()?;
-        let calculated_nsid = event.clone().try_into()?;
-        let name = event.extract_name()?;
-        let fingerprint = Hash160::default()
-            .chain_update(name.as_bytes())
-            .fingerprint();
+        let tags: Vec<Vec<String>> = event.tags.iter().map(Tag::as_vec).collect();
+        let name_event = NameEvent::from_parts(
+            event.kind.as_u64(),
+            &event.pubkey.serialize(),
+            &tags,
+            &event.content,
+        )?;
         let records = event.extract_records().ok();
+        let anchor = event.extract_anchor()?;
         let raw_event = serde_json::to_string(event)?;
 
         Ok(EventData {
             event_id: event.id,
-            fingerprint,
-            nsid,
-            calculated_nsid,
+            fingerprint: name_event.fingerprint,
+            nsid: name_event.nsid.into(),
+            calculated_nsid: name_event.calculated_nsid.into(),
             pubkey: event.pubkey,
-            name: name.parse()?,
+            name: name_event.name.parse()?,
             created_at: event.created_at.as_i64(),
             raw_content: event.content.clone(),
             records,
+            anchor,
+            expires_at: event.extract_expiration(),
             raw_event,
         })
     }
 
     pub fn validate(&self) -> anyhow::Result<()> {
         if self.nsid != self.calculated_nsid {
-            bail!("Invalid nsid")
+            bail!(NomenError::Validation("Invalid nsid".into()))
+        }
+        Ok(())
+    }
+
+    /// Check `created_at` is at most `max_skew` seconds after `now`, and at most `max_skew`
+    /// seconds before the block time of the name's anchor, if known.
+    pub fn validate_time(
+        &self,
+        now: i64,
+        anchor: Option<i64>,
+        max_skew: i64,
+    ) -> anyhow::Result<()> {
+        if self.created_at > now + max_skew {
+            bail!(NomenError::Validation(format!(
+                "Event created_at {} is too far in the future",
+                self.created_at
+            )))
+        }
+        if let Some(anchor) = anchor {
+            if self.created_at < anchor - max_skew {
+                bail!(NomenError::Validation(format!(
+                    "Event created_at {} predates the name's anchor at {anchor}",
+                    self.created_at
+                )))
+            }
         }
         Ok(())
     }
@@ -67,4 +104,21 @@ mod tests {
         ed.nsid = Nsid::from_slice(&[0; 20]).unwrap();
         assert!(ed.validate().is_err());
     }
+
+    #[test]
+    fn test_validate_time() {
+        let event = r#"{"id":"4fb5485ad12706f3ddbde1cdeab3199fcbef01b4c2456a7420ef5acb400d29e5","pubkey":"d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f","created_at":1682476154,"kind":38300,"tags":[["d","28d63a9a61c6c5ce6be37a830105c92cf7a8f365"],["nom","smith"]],"content":"{\"IP4\":\"127.0.0.1\",\"NPUB\":\"npub1234\"}","sig":"53a629c8169c29abc971653b71ebf8ceb185735170b702dd48377a3336819680577ef28a257b8e4db5e8101531232e1c886a35721b5af1399c32cb526fd61bb6"}"#;
+        let event = Event::from_json(event).unwrap();
+        let ed = EventData::from_event(&event).unwrap();
+        let created_at = ed.created_at;
+
+        assert!(ed.validate_time(created_at, None, 60).is_ok());
+        assert!(ed
+            .validate_time(created_at - 60, Some(created_at + 60), 60)
+            .is_ok());
+        assert!(ed.validate_time(created_at - 61, None, 60).is_err());
+        assert!(ed
+            .validate_time(created_at, Some(created_at + 61), 60)
+            .is_err());
+    }
 }
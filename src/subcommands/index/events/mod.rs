@@ -1,7 +1,14 @@
+mod approvals;
+mod deactivations;
 mod event_data;
-mod records;
-mod transfer;
+mod offers;
+pub(super) mod records;
+mod sync;
+pub(super) mod transfer;
 
+pub use approvals::*;
+pub use deactivations::*;
 pub use event_data::*;
+pub use offers::*;
 pub use records::*;
 pub use transfer::*;
@@ -0,0 +1,22 @@
+use sqlx::SqlitePool;
+
+use super::sync::RelaySync;
+use crate::{config::Config, db, util::Offer};
+
+pub async fn offers(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+    log::info!("Beginning indexing offer events.");
+    let sync = RelaySync::fetch(config, pool, Offer::kind()).await?;
+    for event in &sync.events {
+        match Offer::from_event(event) {
+            Ok(offer) => {
+                log::info!("Saving valid offer {}", offer.event_id);
+                db::insert_offer(pool, &offer).await?;
+            }
+            Err(err) => log::debug!("Invalid offer: {err}"),
+        }
+    }
+    sync.commit(config, pool).await?;
+
+    log::info!("Offer events indexing complete.");
+    Ok(())
+}
@@ -1,30 +1,91 @@
-use std::time::Duration;
-
-use nostr_sdk::{Event, Filter};
+use nomen_core::records_commitment;
+use nostr_sdk::{Event, Timestamp};
 use sqlx::SqlitePool;
 
+use super::sync::RelaySync;
 use crate::{
     config::{Cli, Config},
     db,
-    subcommands::index::events::EventData,
-    util::NameKind,
+    subcommands::index::{events::EventData, Hooks, IndexEvent},
+    util::{NameKind, Nsid},
 };
 
-pub async fn records(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+/// Why a records event was skipped or quarantined for its claim's commitment.
+const COMMITMENT_MISMATCH: &str = "records don't match the claim's commitment";
+
+pub async fn records(config: &Config, pool: &SqlitePool, hooks: &Hooks) -> anyhow::Result<()> {
     log::info!("Beginning indexing record events.");
-    let events = latest_events(config, pool).await?;
-    for event in events {
-        match EventData::from_event(&event) {
-            Ok(ed) => save_event(pool, ed).await?,
+    let mut sync = RelaySync::fetch(config, pool, NameKind::Name.into()).await?;
+    let max_skew = config.indexer_max_time_skew();
+    let grammar = config.grammar();
+    let limits = config.record_limits();
+    // Events that may be indexed later: ahead of time, or waiting on their claim.
+    let mut retry = vec![];
+    for event in &sync.events {
+        match EventData::from_event(event) {
+            Ok(ed) => {
+                let anchor = db::anchor_time(pool, ed.fingerprint).await?;
+                if let Err(err) = ed.validate_time(Timestamp::now().as_i64(), anchor, max_skew) {
+                    log::warn!("Skipping event {}: {err}", ed.event_id);
+                    retry.push(event.id);
+                    continue;
+                }
+                if let Err(err) = grammar
+                    .validate(ed.name.as_ref())
+                    .map_err(Into::into)
+                    .and_then(|_| limits.check(&ed.raw_content, ed.records.as_ref()))
+                {
+                    log::warn!("Skipping event {}: {err}", ed.event_id);
+                    retry.push(event.id);
+                    continue;
+                }
+                let index_event = IndexEvent::record(&ed);
+                if save_event(pool, ed).await? {
+                    hooks.dispatch(index_event).await;
+                } else {
+                    retry.push(event.id);
+                }
+            }
             Err(err) => log::debug!("Invalid event: {err}"),
         }
     }
+    for event_id in retry {
+        sync.retry(event_id);
+    }
+    sync.commit(config, pool).await?;
 
     log::info!("Records events indexing complete.");
     Ok(())
 }
 
-async fn save_event(pool: &SqlitePool, ed: EventData) -> anyhow::Result<()> {
+/// Save a records event, returning whether it was saved rather than skipped for its anchor or its
+/// claim's commitment.
+pub(crate) async fn save_event(pool: &SqlitePool, ed: EventData) -> anyhow::Result<bool> {
+    let current_anchor = db::current_anchor(pool, ed.fingerprint, ed.calculated_nsid).await?;
+    if let (Some(anchor), Some(current)) = (&ed.anchor, &current_anchor) {
+        if anchor.height < current.height {
+            log::warn!(
+                "Skipping event {}: bound to anchor {}, before the current anchor {}",
+                ed.event_id,
+                anchor.txid,
+                current.txid
+            );
+            return Ok(false);
+        }
+    }
+    if !matches_commitment(
+        pool,
+        ed.fingerprint,
+        ed.calculated_nsid,
+        ed.created_at,
+        &ed.raw_content,
+    )
+    .await?
+    {
+        log::warn!("Skipping event {}: {COMMITMENT_MISMATCH}", ed.event_id);
+        return Ok(false);
+    }
+
     log::info!("Saving valid event {}", ed.event_id);
     let EventData {
         event_id,
@@ -36,6 +97,8 @@ async fn save_event(pool: &SqlitePool, ed: EventData) -> anyhow::Result<()> {
         created_at,
         raw_content,
         records: _,
+        anchor,
+        expires_at,
         raw_event,
     } = ed;
     db::insert_name_event(
@@ -48,25 +111,161 @@ async fn save_event(pool: &SqlitePool, ed: EventData) -> anyhow::Result<()> {
         event_id,
         raw_content,
         raw_event,
+        expires_at,
+        anchor.as_ref(),
+        current_anchor.as_ref(),
     )
     .await?;
 
+    Ok(true)
+}
+
+/// Whether records `content` of `nsid`, created at `created_at`, may be indexed. A claim that
+/// commits to its first records event has no records until an event whose content hashes to the
+/// commitment is seen. Events created after it are indexed as usual.
+pub(crate) async fn matches_commitment(
+    pool: &SqlitePool,
+    fingerprint: [u8; 5],
+    nsid: Nsid,
+    created_at: i64,
+    content: &str,
+) -> anyhow::Result<bool> {
+    let Some(commitment) = db::claim_commitment(pool, fingerprint, nsid).await? else {
+        return Ok(true);
+    };
+    if records_commitment(content) == commitment {
+        return Ok(true);
+    }
+    Ok(
+        db::records_history_contents(pool, fingerprint, nsid, created_at)
+            .await?
+            .iter()
+            .any(|c| records_commitment(c) == commitment),
+    )
+}
+
+/// Quarantine the records events of a newly indexed claim of `nsid` that its commitment rules out,
+/// as they may have been indexed before the claim was mined.
+pub(crate) async fn quarantine_uncommitted(
+    pool: &SqlitePool,
+    fingerprint: [u8; 5],
+    nsid: Nsid,
+) -> anyhow::Result<()> {
+    for row in db::claim_name_events(pool, fingerprint, nsid).await? {
+        let event = Event::from_json(&row.raw_event)?;
+        if !matches_commitment(
+            pool,
+            fingerprint,
+            nsid,
+            event.created_at.as_i64(),
+            &event.content,
+        )
+        .await?
+        {
+            log::warn!("Quarantining event {}: {COMMITMENT_MISMATCH}", row.event_id);
+            let row_data = serde_json::to_string(&row)?;
+            db::quarantine(
+                pool,
+                "name_events",
+                row.rowid,
+                &row_data,
+                COMMITMENT_MISMATCH,
+            )
+            .await?;
+        }
+    }
     Ok(())
 }
 
-async fn latest_events(
-    config: &Config,
-    pool: &sqlx::Pool<sqlx::Sqlite>,
-) -> anyhow::Result<Vec<Event>> {
-    let index_height = db::last_records_time(pool).await?;
-    let filter = Filter::new()
-        .kind(NameKind::Name.into())
-        .since(index_height.into());
-
-    let (_keys, client) = config.nostr_random_client().await?;
-    let events = client
-        .get_events_of(vec![filter], Some(Duration::from_secs(10)))
-        .await?;
-    client.disconnect().await?;
-    Ok(events)
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use nostr_sdk::Keys;
+
+    use super::*;
+    use crate::{
+        db::TxStats,
+        subcommands::name_event,
+        util::{Hash160, NomenKind, NsidBuilder, RecordLimits},
+    };
+
+    #[tokio::test]
+    async fn test_records_commitment() {
+        let pool = db::scratch().await.unwrap();
+        let keys = Keys::generate();
+        let fingerprint = Hash160::default().chain_update(b"smith").fingerprint();
+        let nsid = NsidBuilder::new("smith", &keys.public_key()).finalize();
+        let committed = HashMap::from([("IP4".to_string(), "127.0.0.1".to_string())]);
+        let other = HashMap::from([("IP4".to_string(), "10.0.0.1".to_string())]);
+        let event = |records: &HashMap<String, String>| {
+            let event = name_event(
+                keys.public_key(),
+                records,
+                "smith",
+                &RecordLimits::default(),
+                None,
+            )
+            .unwrap()
+            .sign(&keys)
+            .unwrap();
+            EventData::from_event(&event).unwrap()
+        };
+
+        db::insert_blockchain(
+            &mut pool.acquire().await.unwrap(),
+            fingerprint,
+            nsid,
+            "00".repeat(32),
+            "11".repeat(32),
+            0,
+            100,
+            1,
+            0,
+            NomenKind::Create,
+            Some(records_commitment(
+                &serde_json::to_string(&committed).unwrap(),
+            )),
+            TxStats {
+                fee: None,
+                size: 100,
+                vsize: 100,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Nothing is indexed until the committed records are seen.
+        assert!(!save_event(&pool, event(&other)).await.unwrap());
+        assert!(db::claim_name_events(&pool, fingerprint, nsid)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let first = event(&committed);
+        let created_at = first.created_at;
+        assert!(save_event(&pool, first).await.unwrap());
+        assert_eq!(
+            db::claim_name_events(&pool, fingerprint, nsid)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(matches_commitment(
+            &pool,
+            fingerprint,
+            nsid,
+            created_at,
+            "{\"IP4\":\"10.0.0.1\"}"
+        )
+        .await
+        .unwrap());
+        assert!(
+            !matches_commitment(&pool, fingerprint, nsid, created_at - 1, "{}")
+                .await
+                .unwrap()
+        );
+    }
 }
@@ -0,0 +1,170 @@
+use std::{collections::HashSet, net::SocketAddr, time::Duration};
+
+use futures::future::join_all;
+use nostr_sdk::{Client, Event, EventId, Filter, Keys, Kind, Options, Timestamp};
+use sqlx::SqlitePool;
+
+use crate::{
+    config::{Config, RelayUse},
+    db,
+    util::NameKind,
+};
+
+/// New events of one kind from every relay, each fetched since that relay's own cursor.
+///
+/// A relay that lags behind the others is still asked for everything after the last event it
+/// returned, so its late events are not skipped. Cursors are only moved forward by [`commit`],
+/// once the events are saved.
+///
+/// [`commit`]: RelaySync::commit
+pub(super) struct RelaySync {
+    kind: Kind,
+    /// New events, without duplicates or events already processed, oldest first.
+    pub events: Vec<Event>,
+    /// Events to process again next time, e.g. ones that may become valid later.
+    retry: HashSet<EventId>,
+    cursors: Vec<(String, i64, EventId)>,
+    /// Ids of every event each relay returned, to record where indexed events came from.
+    sources: Vec<(String, Vec<EventId>)>,
+}
+
+impl RelaySync {
+    pub async fn fetch(
+        config: &Config,
+        pool: &SqlitePool,
+        kind: Kind,
+    ) -> anyhow::Result<RelaySync> {
+        let overlap = config.indexer_sync_overlap();
+        let mut windows = Vec::new();
+        for relay in config.relays_for(&[kind], RelayUse::Read) {
+            let cursor = db::relay_cursor(pool, &relay, kind.as_u64()).await?;
+            windows.push((relay, window_start(cursor.map(|(t, _)| t), overlap)));
+        }
+
+        let results = join_all(windows.into_iter().map(|(relay, since)| async move {
+            let events = fetch_relay(&relay, kind, since, config.network_proxy()).await;
+            (relay, events)
+        }))
+        .await;
+
+        let mut seen = HashSet::new();
+        let mut skipped = 0;
+        let mut events = Vec::new();
+        let mut cursors = Vec::new();
+        let mut sources = Vec::new();
+        for (relay, result) in results {
+            let relay_events = match result {
+                Ok(events) => events,
+                Err(err) => {
+                    log::warn!("Could not sync kind {} from {relay}: {err}", kind.as_u64());
+                    continue;
+                }
+            };
+            if let Some(newest) = relay_events.iter().max_by_key(|e| e.created_at) {
+                cursors.push((relay.clone(), newest.created_at.as_i64(), newest.id));
+            }
+            log::debug!(
+                "{} kind {} events from {relay}",
+                relay_events.len(),
+                kind.as_u64()
+            );
+            let ids: Vec<EventId> = relay_events.iter().map(|e| e.id).collect();
+            let processed = db::processed_events(pool, kind, &ids).await?;
+            sources.push((relay.clone(), ids));
+
+            for event in relay_events {
+                if !seen.insert(event.id) {
+                    continue;
+                }
+                if processed.contains(&event.id) {
+                    skipped += 1;
+                } else {
+                    events.push(event);
+                }
+            }
+        }
+        events.sort_by_key(|e| e.created_at);
+        log::debug!(
+            "Skipping {skipped} already processed kind {} events",
+            kind.as_u64()
+        );
+
+        Ok(RelaySync {
+            kind,
+            events,
+            retry: HashSet::new(),
+            cursors,
+            sources,
+        })
+    }
+
+    /// Process `event_id` again when it is next fetched, instead of skipping it as seen.
+    pub fn retry(&mut self, event_id: EventId) {
+        self.retry.insert(event_id);
+    }
+
+    /// Save each relay's newest seen event as its cursor, the relays of the saved events, and
+    /// the events processed, forgetting those processed longer than the retention ago.
+    pub async fn commit(self, config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+        for (relay, last_seen, event_id) in &self.cursors {
+            db::update_relay_cursor(pool, relay, self.kind.as_u64(), *last_seen, event_id).await?;
+        }
+        if NameKind::try_from(self.kind).is_ok() {
+            for (relay, event_ids) in &self.sources {
+                db::save_event_sources(pool, relay, event_ids).await?;
+            }
+        }
+
+        let processed: Vec<EventId> = self
+            .events
+            .iter()
+            .map(|e| e.id)
+            .filter(|id| !self.retry.contains(id))
+            .collect();
+        db::save_seen_events(pool, self.kind.as_u64(), &processed).await?;
+        let pruned = db::prune_seen_events(pool, config.indexer_seen_events_retention()).await?;
+        if pruned > 0 {
+            log::debug!("Forgot {pruned} processed events past their retention");
+        }
+        Ok(())
+    }
+}
+
+/// Start of the window to fetch from a relay: `overlap` seconds before its cursor, or the
+/// beginning for a relay that has not been synced yet.
+fn window_start(cursor: Option<i64>, overlap: i64) -> Timestamp {
+    let since = cursor.map(|t| (t - overlap).max(0)).unwrap_or(0);
+    Timestamp::from(since as u64)
+}
+
+async fn fetch_relay(
+    relay: &str,
+    kind: Kind,
+    since: Timestamp,
+    proxy: Option<SocketAddr>,
+) -> anyhow::Result<Vec<Event>> {
+    let client = Client::with_opts(&Keys::generate(), Options::new());
+    client.add_relay(relay, proxy).await?;
+    client.connect().await;
+    let filter = Filter::new().kind(kind).since(since);
+    let events = client
+        .get_events_of(vec![filter], Some(Duration::from_secs(10)))
+        .await;
+    client.disconnect().await?;
+    Ok(events?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_start() {
+        assert_eq!(window_start(None, 600), Timestamp::from(0));
+        assert_eq!(
+            window_start(Some(1_700_000_000), 600),
+            Timestamp::from(1_699_999_400)
+        );
+        assert_eq!(window_start(Some(100), 600), Timestamp::from(0));
+    }
+}
@@ -1,47 +1,33 @@
-use std::time::Duration;
-
-use nostr_sdk::{Event, Filter};
 use sqlx::SqlitePool;
 
+use super::sync::RelaySync;
 use crate::{
     config::{Cli, Config},
     db,
-    subcommands::index::events::EventData,
+    subcommands::index::{events::EventData, Hooks, IndexEvent},
     util::NameKind,
 };
 
-pub async fn transfer(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+pub async fn transfer(config: &Config, pool: &SqlitePool, hooks: &Hooks) -> anyhow::Result<()> {
     log::info!("Beginning indexing transfer events.");
-    let events = latest_events(config, pool).await?;
-    for event in events {
-        match EventData::from_event(&event) {
-            Ok(ed) => save_event(pool, ed).await?,
+    let sync = RelaySync::fetch(config, pool, NameKind::Transfer.into()).await?;
+    for event in &sync.events {
+        match EventData::from_event(event).and_then(|ed| ed.validate().map(|_| ed)) {
+            Ok(ed) => {
+                let index_event = IndexEvent::transfer(&ed);
+                save_event(pool, ed).await?;
+                hooks.dispatch(index_event).await;
+            }
             Err(err) => log::debug!("Invalid event: {err}"),
         }
     }
+    sync.commit(config, pool).await?;
 
     log::info!("Transfer events indexing complete.");
     Ok(())
 }
 
-async fn latest_events(
-    config: &Config,
-    pool: &sqlx::Pool<sqlx::Sqlite>,
-) -> anyhow::Result<Vec<Event>> {
-    let index_height = db::last_transfer_time(pool).await?;
-    let filter = Filter::new()
-        .kind(NameKind::Transfer.into())
-        .since(index_height.into());
-
-    let (_keys, client) = config.nostr_random_client().await?;
-    let events = client
-        .get_events_of(vec![filter], Some(Duration::from_secs(10)))
-        .await?;
-    client.disconnect().await?;
-    Ok(events)
-}
-
-async fn save_event(pool: &SqlitePool, ed: EventData) -> anyhow::Result<()> {
+pub(crate) async fn save_event(pool: &SqlitePool, ed: EventData) -> anyhow::Result<()> {
     log::info!("Saving valid event {}", ed.event_id);
     let EventData {
         event_id,
@@ -53,6 +39,8 @@ async fn save_event(pool: &SqlitePool, ed: EventData) -> anyhow::Result<()> {
         created_at,
         raw_content,
         records: _,
+        anchor: _,
+        expires_at: _,
         raw_event,
     } = ed;
 
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::config::Config;
+
+use super::{events::EventData, Notifier};
+
+/// A change committed to the index, handed to every registered [`EventProcessor`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum IndexEvent {
+    /// A Nomen OP_RETURN output found on chain.
+    Blockchain {
+        kind: String,
+        fingerprint: String,
+        nsid: String,
+        blockhash: String,
+        txid: String,
+        blockheight: usize,
+        txheight: usize,
+        vout: usize,
+    },
+
+    /// A name event with a record set.
+    Record {
+        name: String,
+        nsid: String,
+        pubkey: String,
+        event_id: String,
+        created_at: i64,
+        records: Option<HashMap<String, String>>,
+    },
+
+    /// A transfer event, handing the name over to `new_owner`.
+    Transfer {
+        name: String,
+        nsid: String,
+        pubkey: String,
+        new_owner: String,
+        event_id: String,
+        created_at: i64,
+    },
+}
+
+impl IndexEvent {
+    pub fn nsid(&self) -> &str {
+        match self {
+            IndexEvent::Blockchain { nsid, .. }
+            | IndexEvent::Record { nsid, .. }
+            | IndexEvent::Transfer { nsid, .. } => nsid,
+        }
+    }
+
+    pub fn record(ed: &EventData) -> IndexEvent {
+        IndexEvent::Record {
+            name: ed.name.to_string(),
+            nsid: ed.calculated_nsid.to_string(),
+            pubkey: ed.pubkey.to_string(),
+            event_id: ed.event_id.to_hex(),
+            created_at: ed.created_at,
+            records: ed.records.clone(),
+        }
+    }
+
+    pub fn transfer(ed: &EventData) -> IndexEvent {
+        IndexEvent::Transfer {
+            name: ed.name.to_string(),
+            nsid: ed.nsid.to_string(),
+            pubkey: ed.pubkey.to_string(),
+            new_owner: ed.raw_content.clone(),
+            event_id: ed.event_id.to_hex(),
+            created_at: ed.created_at,
+        }
+    }
+}
+
+/// Hook into the indexer. Implementations are registered in [`Hooks::new`], most of them
+/// behind feature flags.
+#[async_trait]
+pub trait EventProcessor: Send + Sync {
+    /// Name used in log messages.
+    fn name(&self) -> &'static str;
+
+    /// Called after `event` has been written to the index.
+    async fn process(&self, event: &IndexEvent) -> anyhow::Result<()>;
+}
+
+/// The set of processors enabled for an index run.
+#[derive(Default)]
+pub struct Hooks(Vec<Box<dyn EventProcessor>>);
+
+impl Hooks {
+    pub async fn new(
+        config: &Config,
+        pool: &SqlitePool,
+        notifier: &Notifier,
+    ) -> anyhow::Result<Hooks> {
+        let mut hooks = Hooks::default();
+        hooks.register(super::watch::WatchProcessor::new(
+            pool.clone(),
+            notifier.clone(),
+        ));
+
+        #[cfg(feature = "log-hook")]
+        hooks.register(LogProcessor);
+
+        #[cfg(any(feature = "nats", feature = "kafka"))]
+        if let Some(queue) = config.notifications_queue() {
+            hooks.register(super::queue::QueueProcessor::connect(&queue).await?);
+        }
+
+        Ok(hooks)
+    }
+
+    pub fn register(&mut self, processor: impl EventProcessor + 'static) {
+        log::debug!("Registered index hook {}", processor.name());
+        self.0.push(Box::new(processor));
+    }
+
+    /// Hand `event` to every processor. A failing processor is logged, and does not stop indexing.
+    pub async fn dispatch(&self, event: IndexEvent) {
+        for processor in &self.0 {
+            if let Err(e) = processor.process(&event).await {
+                log::error!("Index hook {} error: {e}", processor.name());
+            }
+        }
+    }
+}
+
+/// Logs every index event as JSON. Mostly useful as an example.
+#[cfg(feature = "log-hook")]
+pub struct LogProcessor;
+
+#[cfg(feature = "log-hook")]
+#[async_trait]
+impl EventProcessor for LogProcessor {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    async fn process(&self, event: &IndexEvent) -> anyhow::Result<()> {
+        log::info!("Index event: {}", serde_json::to_string(event)?);
+        Ok(())
+    }
+}
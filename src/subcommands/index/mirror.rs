@@ -0,0 +1,193 @@
+use anyhow::{anyhow, bail, Context};
+use bitcoin::{BlockHash, Txid};
+use bitcoincore_rpc::{Client, RpcApi};
+use nomen_core::{api_types::MirrorQuery, client::Client as ApiClient};
+use nostr_sdk::{Event, Timestamp};
+use rand::seq::SliceRandom;
+use sqlx::SqlitePool;
+
+use crate::{
+    config::Config,
+    db::{self, BlockchainRow},
+    error::NomenError,
+    util::{NameKind, NomenTx},
+};
+
+use super::events::{self, EventData};
+
+/// Page size requested from the remote indexer.
+const PAGE_SIZE: i64 = 1000;
+
+/// Bootstrap or continue the index from another indexer's API at `url`.
+///
+/// Blockchain rows are only saved once `sample` of them, and the remote tip, are found on the
+/// local node. Events are signed, so every one is checked like the indexer checks relay events.
+pub async fn mirror(
+    config: &Config,
+    pool: &SqlitePool,
+    url: &str,
+    sample: usize,
+) -> anyhow::Result<()> {
+    super::network::check(config, pool).await?;
+    let api = ApiClient::with_http(config.http_client()?, url);
+
+    let from = db::next_index_height(pool).await?;
+    log::info!("Mirroring blockchain index from {url}, starting at height {from}");
+    let mut rows = Vec::new();
+    let mut cursor = 0;
+    let tip = loop {
+        let page = api
+            .mirror_blockchain(&MirrorQuery {
+                from: from as i64,
+                cursor,
+                limit: Some(PAGE_SIZE),
+                kind: None,
+            })
+            .await?;
+        rows.extend(page.items);
+        match page.next_cursor {
+            Some(next) => cursor = next,
+            None => break page.index_height.zip(page.index_blockhash),
+        }
+    };
+
+    let checked: Vec<BlockchainRow> = rows
+        .choose_multiple(&mut rand::thread_rng(), sample)
+        .cloned()
+        .collect();
+    let tip_check = tip.clone();
+    config
+        .rpc()?
+        .run(move |client| {
+            if let Some((height, blockhash)) = &tip_check {
+                check_block(client, blockhash, *height)?;
+            }
+            for row in &checked {
+                check_block(client, &row.blockhash, row.blockheight)?;
+                check_output(client, row)?;
+            }
+            Ok(())
+        })
+        .await
+        .context("Not saving the mirrored index")?;
+    log::info!(
+        "Verified {} of {} mirrored blockchain entries against the local node",
+        sample.min(rows.len()),
+        rows.len()
+    );
+
+    for row in &rows {
+        db::insert_blockchain_row(pool, row).await?;
+    }
+    if let Some((height, blockhash)) = tip {
+        db::insert_index_height(&mut *pool.acquire().await?, height, &blockhash.parse()?).await?;
+    }
+
+    mirror_events(config, pool, &api, NameKind::Name).await?;
+    mirror_events(config, pool, &api, NameKind::Transfer).await?;
+
+    log::info!("Mirror complete.");
+    Ok(())
+}
+
+async fn mirror_events(
+    config: &Config,
+    pool: &SqlitePool,
+    api: &ApiClient,
+    kind: NameKind,
+) -> anyhow::Result<()> {
+    let (kind_param, since) = match kind {
+        NameKind::Name => ("name", db::last_records_time(pool).await?),
+        NameKind::Transfer => ("transfer", db::last_transfer_time(pool).await?),
+    };
+    let max_skew = config.indexer_max_time_skew();
+    let mut cursor = 0;
+    let mut saved = 0;
+    loop {
+        let page = api
+            .mirror_events(&MirrorQuery {
+                from: since as i64,
+                cursor,
+                limit: Some(PAGE_SIZE),
+                kind: Some(kind_param.into()),
+            })
+            .await?;
+
+        for raw in page.items {
+            let ed = match check_event(&raw, kind) {
+                Ok(ed) => ed,
+                Err(err) => {
+                    log::warn!("Skipping mirrored event: {err}");
+                    continue;
+                }
+            };
+            if kind == NameKind::Name {
+                let anchor = db::anchor_time(pool, ed.fingerprint).await?;
+                if let Err(err) = ed.validate_time(Timestamp::now().as_i64(), anchor, max_skew) {
+                    log::warn!("Skipping mirrored event {}: {err}", ed.event_id);
+                    continue;
+                }
+                events::records::save_event(pool, ed).await?;
+            } else {
+                events::transfer::save_event(pool, ed).await?;
+            }
+            saved += 1;
+        }
+
+        match page.next_cursor {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+
+    log::info!("Mirrored {saved} {kind_param} events");
+    Ok(())
+}
+
+fn check_event(raw: &str, kind: NameKind) -> anyhow::Result<EventData> {
+    let event = Event::from_json(raw)?;
+    if NameKind::try_from(event.kind)? != kind {
+        bail!(NomenError::Validation("Unexpected event kind".into()));
+    }
+    event.verify()?;
+    let ed = EventData::from_event(&event)?;
+    ed.validate()?;
+    Ok(ed)
+}
+
+/// Check `blockhash` is in the local node's best chain at `height`.
+pub(crate) fn check_block(client: &Client, blockhash: &str, height: i64) -> anyhow::Result<()> {
+    let hash: BlockHash = blockhash.parse()?;
+    let info = client.get_block_header_info(&hash)?;
+    if info.confirmations < 0 || info.height as i64 != height {
+        bail!(mismatch(&format!("Block {blockhash}")));
+    }
+    Ok(())
+}
+
+/// Check the row's output is a Nomen output with the same fingerprint, nsid and kind.
+pub(crate) fn check_output(client: &Client, row: &BlockchainRow) -> anyhow::Result<()> {
+    let outpoint = format!("Output {}:{}", row.txid, row.vout);
+    let tx = client.get_raw_transaction(&row.txid.parse()?, Some(&row.blockhash.parse()?))?;
+    let script = tx
+        .output
+        .get(row.vout as usize)
+        .map(|o| o.script_pubkey.as_bytes())
+        .filter(|b| b.len() > 2)
+        .ok_or_else(|| mismatch(&outpoint))?;
+    match NomenTx::try_from(&script[2..]) {
+        Ok(tx)
+            if hex::encode(tx.fingerprint) == row.fingerprint
+                && tx.nsid.to_string() == row.nsid
+                && tx.kind.to_string() == row.kind
+                && tx.commitment.map(hex::encode) == row.commitment =>
+        {
+            Ok(())
+        }
+        _ => bail!(mismatch(&outpoint)),
+    }
+}
+
+fn mismatch(what: &str) -> NomenError {
+    NomenError::Validation(format!("{what} does not match the local node"))
+}
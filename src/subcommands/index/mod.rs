@@ -5,17 +5,113 @@ use crate::{
     db,
 };
 
+mod alerts;
+mod bench;
+mod block_store;
 mod blockchain;
+mod checkpoint;
+mod conflicts;
 mod events;
+mod hooks;
+mod mirror;
+mod network;
 mod owners;
+mod profiles;
+#[cfg(any(feature = "nats", feature = "kafka"))]
+mod queue;
+mod rebuild;
+mod similar;
+mod watch;
 
-pub async fn index(config: &Config) -> anyhow::Result<()> {
+pub use alerts::{Alert, Notifier};
+pub use bench::bench;
+pub use block_store::BlockStore;
+pub use checkpoint::checkpoint;
+pub use events::EventData;
+pub use hooks::{EventProcessor, Hooks, IndexEvent};
+pub use mirror::mirror;
+pub(crate) use mirror::{check_block, check_output};
+pub use network::force_reset;
+pub use owners::reindex as reindex_owners;
+pub use rebuild::rebuild_name;
+
+/// Index the blockchain and the relays. `workers` parallel workers scan the blockchain.
+pub async fn index(config: &Config, workers: usize) -> anyhow::Result<()> {
     let pool = config.sqlite().await?;
-    blockchain::index(config, &pool).await?;
-    events::records(config, &pool).await?;
-    events::transfer(config, &pool).await?;
+    network::check(config, &pool).await?;
+    let notifier = Notifier::new(config)?;
+    let hooks = Hooks::new(config, &pool, &notifier).await?;
+    blockchain::index(config, &pool, &hooks, &notifier, workers).await?;
+    conflicts::check(config, &pool, &notifier).await?;
+    events::records(config, &pool, &hooks).await?;
+    events::transfer(config, &pool, &hooks).await?;
+    events::offers(config, &pool).await?;
+    events::deactivations(config, &pool).await?;
+    if config.protocol_subname_approval() {
+        events::approvals(config, &pool).await?;
+    }
     owners::reindex(&pool).await?;
+    similar::reindex(&pool).await?;
+    if config.indexer_profiles() {
+        profiles::index(config, &pool).await?;
+    }
 
     db::save_event(&pool, "index", "").await?;
     Ok(())
 }
+
+/// Claim `name` for `keys` at `height`, and index a records event for it, the way the indexer
+/// does.
+#[cfg(test)]
+pub(crate) async fn index_name(
+    pool: &SqlitePool,
+    keys: &nostr_sdk::Keys,
+    name: &str,
+    records: &std::collections::HashMap<String, String>,
+    height: usize,
+) {
+    use crate::{
+        db::TxStats,
+        subcommands::name_event,
+        util::{Hash160, NomenKind, NsidBuilder, RecordLimits},
+    };
+
+    let fingerprint = Hash160::default()
+        .chain_update(name.as_bytes())
+        .fingerprint();
+    let nsid = NsidBuilder::new(name, &keys.public_key()).finalize();
+    db::insert_blockchain(
+        &mut pool.acquire().await.unwrap(),
+        fingerprint,
+        nsid,
+        "00".repeat(32),
+        format!("{:0>64}", nsid.to_string()),
+        0,
+        height,
+        1,
+        0,
+        NomenKind::Create,
+        None,
+        TxStats {
+            fee: None,
+            size: 100,
+            vsize: 100,
+        },
+        None,
+    )
+    .await
+    .unwrap();
+    let event = name_event(
+        keys.public_key(),
+        records,
+        name,
+        &RecordLimits::default(),
+        None,
+    )
+    .unwrap()
+    .sign(keys)
+    .unwrap();
+    let ed = EventData::from_event(&event).unwrap();
+    assert!(events::records::save_event(pool, ed).await.unwrap());
+    owners::reindex(pool).await.unwrap();
+}
@@ -0,0 +1,60 @@
+use anyhow::bail;
+use bitcoincore_rpc::RpcApi;
+use sqlx::SqlitePool;
+
+use crate::{config::Config, db, error::NomenError};
+
+/// `meta` keys of the chain the index was built from.
+const NETWORK: &str = "network";
+const GENESIS: &str = "genesis";
+
+/// Check the node is on the chain the index was built from, as indexing another one into it
+/// would mix up both. An index without a chain recorded yet, new or from before it was recorded,
+/// takes the node's.
+pub async fn check(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+    let (network, genesis) = node_chain(config).await?;
+    match db::meta(pool, GENESIS).await? {
+        None => record(pool, &network, &genesis).await,
+        Some(indexed) if indexed == genesis => Ok(()),
+        Some(indexed) => {
+            let indexed_network = db::meta(pool, NETWORK).await?.unwrap_or_default();
+            bail!(NomenError::Config(format!(
+                "The index was built from {indexed_network} (genesis block {indexed}), but the node \
+                is on {network} (genesis block {genesis}). Use a {indexed_network} node, or run \
+                `nomen index --force-reset` to discard the index and build it from {network}."
+            )))
+        }
+    }
+}
+
+/// Discard the index if it was built from another chain than the node's, and record the node's.
+pub async fn force_reset(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+    let (network, genesis) = node_chain(config).await?;
+    if let Some(indexed) = db::meta(pool, GENESIS).await? {
+        if indexed != genesis {
+            log::warn!(
+                "Discarding the index built from genesis block {indexed}, the node is on {network}"
+            );
+            db::reset_index(pool).await?;
+            let detail = format!("index of genesis block {indexed} discarded for {network}");
+            db::audit(pool, db::LOCAL_ACTOR, "index reset", &detail).await?;
+        }
+    }
+    record(pool, &network, &genesis).await
+}
+
+/// The node's chain (`main`, `test`, `signet` or `regtest`) and genesis block hash.
+async fn node_chain(config: &Config) -> anyhow::Result<(String, String)> {
+    config
+        .rpc()?
+        .run(|client| {
+            let network = client.get_blockchain_info()?.chain;
+            Ok((network, client.get_block_hash(0)?.to_string()))
+        })
+        .await
+}
+
+async fn record(pool: &SqlitePool, network: &str, genesis: &str) -> anyhow::Result<()> {
+    db::set_meta(pool, NETWORK, network).await?;
+    db::set_meta(pool, GENESIS, genesis).await
+}
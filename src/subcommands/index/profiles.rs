@@ -0,0 +1,60 @@
+use std::{collections::HashMap, time::Duration};
+
+use nostr_sdk::{prelude::Metadata, Event, Filter, Kind};
+use sqlx::SqlitePool;
+
+use crate::{
+    config::{Config, RelayUse},
+    db::{self, Profile},
+};
+
+/// Authors per relay request, to stay under relay filter limits.
+const BATCH_SIZE: usize = 100;
+
+/// Fetch and store the latest Nostr profile of every name owner.
+pub async fn index(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+    log::info!("Beginning profile indexing.");
+    let pubkeys = db::owner_pubkeys(pool).await?;
+
+    let (_keys, client) = config
+        .nostr_random_client(&[Kind::Metadata], RelayUse::Read)
+        .await?;
+    let mut latest: HashMap<String, Event> = HashMap::new();
+    for batch in pubkeys.chunks(BATCH_SIZE) {
+        let filter = Filter::new().kind(Kind::Metadata).authors(batch.to_vec());
+        let events = client
+            .get_events_of(vec![filter], Some(Duration::from_secs(10)))
+            .await?;
+        for event in events.into_iter().filter(|e| e.verify().is_ok()) {
+            match latest.get(&event.pubkey.to_string()) {
+                Some(e) if e.created_at >= event.created_at => {}
+                _ => {
+                    latest.insert(event.pubkey.to_string(), event);
+                }
+            }
+        }
+    }
+    client.disconnect().await?;
+
+    for (pubkey, event) in latest {
+        match Metadata::from_json(&event.content) {
+            Ok(metadata) => {
+                let profile = Profile {
+                    pubkey,
+                    name: metadata.name,
+                    display_name: metadata.display_name,
+                    picture: metadata
+                        .picture
+                        .filter(|p| p.starts_with("https://") || p.starts_with("http://")),
+                    about: metadata.about,
+                    created_at: event.created_at.as_i64(),
+                };
+                db::insert_profile(pool, &profile).await?;
+            }
+            Err(err) => log::debug!("Invalid profile {}: {err}", event.id),
+        }
+    }
+
+    log::info!("Profile indexing complete.");
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use anyhow::bail;
+use async_trait::async_trait;
+
+use crate::config::{QueueBackend, QueueConfig};
+
+use super::{EventProcessor, IndexEvent};
+
+/// Publishes every index change as JSON to a message queue.
+pub enum QueueProcessor {
+    #[cfg(feature = "nats")]
+    Nats {
+        client: async_nats::Client,
+        subject: String,
+    },
+
+    #[cfg(feature = "kafka")]
+    Kafka {
+        producer: rdkafka::producer::FutureProducer,
+        topic: String,
+    },
+}
+
+impl QueueProcessor {
+    pub async fn connect(config: &QueueConfig) -> anyhow::Result<QueueProcessor> {
+        let topic = config.topic.clone().unwrap_or_else(|| "nomen.index".into());
+
+        match config.backend {
+            #[cfg(feature = "nats")]
+            QueueBackend::Nats => {
+                let client = async_nats::connect(&config.url).await?;
+                Ok(QueueProcessor::Nats {
+                    client,
+                    subject: topic,
+                })
+            }
+
+            #[cfg(feature = "kafka")]
+            QueueBackend::Kafka => {
+                let producer = rdkafka::ClientConfig::new()
+                    .set("bootstrap.servers", &config.url)
+                    .set("message.timeout.ms", "5000")
+                    .create()?;
+                Ok(QueueProcessor::Kafka { producer, topic })
+            }
+
+            #[allow(unreachable_patterns)]
+            backend => bail!(NomenError::Config(format!(
+                "Queue backend {backend:?} was not enabled at compile time"
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl EventProcessor for QueueProcessor {
+    fn name(&self) -> &'static str {
+        "queue"
+    }
+
+    async fn process(&self, event: &IndexEvent) -> anyhow::Result<()> {
+        let payload = serde_json::to_vec(event)?;
+
+        match self {
+            #[cfg(feature = "nats")]
+            QueueProcessor::Nats { client, subject } => {
+                client.publish(subject.clone(), payload.into()).await?;
+                client.flush().await?;
+            }
+
+            #[cfg(feature = "kafka")]
+            QueueProcessor::Kafka { producer, topic } => {
+                let record = rdkafka::producer::FutureRecord::to(topic)
+                    .key(event.nsid())
+                    .payload(&payload);
+                producer
+                    .send(record, std::time::Duration::from_secs(0))
+                    .await
+                    .map_err(|(e, _)| e)?;
+            }
+        }
+
+        Ok(())
+    }
+}
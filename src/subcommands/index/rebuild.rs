@@ -0,0 +1,97 @@
+use nostr_sdk::{Event, Timestamp};
+use sqlx::SqlitePool;
+use yansi::Paint;
+
+use crate::{
+    config::Config,
+    db,
+    subcommands::db::verify_anchor,
+    util::{Hash160, Name, NameKind},
+};
+
+use super::{events, owners, similar, EventData};
+
+/// Rebuild the index of one name: quarantine its claims that no longer check out against the
+/// node, then drop its records and transfer events and index them again from their raw events.
+pub async fn rebuild_name(
+    config: &Config,
+    pool: &SqlitePool,
+    name: &Name,
+    skip_rpc: bool,
+) -> anyhow::Result<()> {
+    let fingerprint = Hash160::default()
+        .chain_update(name.as_ref().as_bytes())
+        .fingerprint();
+
+    if !skip_rpc {
+        let anchors = db::name_anchors(pool, fingerprint).await?;
+        log::info!("Verifying {} on-chain claims of {name}", anchors.len());
+        let failed = config
+            .rpc()?
+            .run(move |client| {
+                Ok(anchors
+                    .into_iter()
+                    .filter_map(|a| verify_anchor(client, &a).err().map(|e| (a, e)))
+                    .collect::<Vec<_>>())
+            })
+            .await?;
+        for (anchor, e) in failed {
+            println!("{} claim {}: {e}", Paint::red("Invalid"), anchor.txid);
+            let row = serde_json::to_string(&anchor)?;
+            db::quarantine(pool, "blockchain", anchor.id, &row, &e.to_string()).await?;
+        }
+    }
+
+    let raw_events = db::name_raw_events(pool, name.as_ref()).await?;
+    db::delete_name_events(pool, name.as_ref()).await?;
+    let max_skew = config.indexer_max_time_skew();
+    let grammar = config.grammar();
+    let limits = config.record_limits();
+    let mut saved = 0;
+    for raw in &raw_events {
+        let ed = match Event::from_json(raw)
+            .map_err(anyhow::Error::from)
+            .and_then(|event| event.verify().map(|_| event).map_err(Into::into))
+            .and_then(|event| EventData::from_event(&event).map(|ed| (event.kind, ed)))
+        {
+            Ok(ed) => ed,
+            Err(err) => {
+                log::warn!("Dropping stored event: {err}");
+                continue;
+            }
+        };
+        match ed {
+            (kind, ed) if kind == NameKind::Transfer.into() => {
+                if let Err(err) = ed.validate() {
+                    log::warn!("Dropping event {}: {err}", ed.event_id);
+                    continue;
+                }
+                events::transfer::save_event(pool, ed).await?;
+            }
+            (_, ed) => {
+                let anchor = db::anchor_time(pool, ed.fingerprint).await?;
+                if let Err(err) = ed
+                    .validate_time(Timestamp::now().as_i64(), anchor, max_skew)
+                    .and_then(|_| Ok(grammar.validate(ed.name.as_ref())?))
+                    .and_then(|_| limits.check(&ed.raw_content, ed.records.as_ref()))
+                {
+                    log::warn!("Dropping event {}: {err}", ed.event_id);
+                    continue;
+                }
+                events::records::save_event(pool, ed).await?;
+            }
+        }
+        saved += 1;
+    }
+
+    owners::reindex(pool).await?;
+    similar::reindex(pool).await?;
+    let detail = format!("{name}, {saved} of {} events", raw_events.len());
+    db::audit(pool, db::LOCAL_ACTOR, "index rebuild-name", &detail).await?;
+    println!(
+        "Rebuilt {name} from {} of {} stored events.",
+        Paint::green(saved),
+        raw_events.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use sqlx::SqlitePool;
+
+use crate::{
+    db,
+    util::{similarity, skeleton},
+};
+
+/// Rebuild the list of indexed names that look alike, to flag likely squats.
+pub async fn reindex(conn: &SqlitePool) -> anyhow::Result<()> {
+    log::info!("Beginning similar names rebuild.");
+    let names: Vec<String> = db::top_level_names(conn, None)
+        .await?
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    let pairs = similar_pairs(&names);
+    db::replace_similar_names(conn, &pairs).await?;
+    log::info!(
+        "Similar names rebuild complete, {} pairs found.",
+        pairs.len() / 2
+    );
+    Ok(())
+}
+
+/// Each name paired with every other name of the same skeleton, in both directions, with their
+/// similarity.
+fn similar_pairs(names: &[String]) -> Vec<(String, String, f64)> {
+    let mut groups: HashMap<String, Vec<&String>> = HashMap::new();
+    for name in names {
+        groups.entry(skeleton(name)).or_default().push(name);
+    }
+    let mut pairs = vec![];
+    for group in groups.values().filter(|g| g.len() > 1) {
+        for a in group {
+            for b in group.iter().filter(|b| *b != a) {
+                pairs.push((a.to_string(), b.to_string(), similarity(a, b)));
+            }
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similar_pairs() {
+        let names = ["google", "g00gle", "goo-gle", "smith"].map(String::from);
+        let mut pairs = similar_pairs(&names);
+        pairs.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        assert_eq!(pairs.len(), 6);
+        assert_eq!(
+            pairs[0],
+            (
+                "g00gle".into(),
+                "goo-gle".into(),
+                similarity("g00gle", "goo-gle")
+            )
+        );
+        assert!(pairs.iter().all(|(a, b, _)| a != "smith" && b != "smith"));
+    }
+}
@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+
+use crate::{db, util::Hash160};
+
+use super::{Alert, EventProcessor, IndexEvent, Notifier};
+
+/// Sends an alert when a name on the watch list is claimed, transferred, or gets new records.
+pub struct WatchProcessor {
+    pool: SqlitePool,
+    notifier: Notifier,
+}
+
+impl WatchProcessor {
+    pub fn new(pool: SqlitePool, notifier: Notifier) -> WatchProcessor {
+        WatchProcessor { pool, notifier }
+    }
+}
+
+#[async_trait]
+impl EventProcessor for WatchProcessor {
+    fn name(&self) -> &'static str {
+        "watch"
+    }
+
+    async fn process(&self, event: &IndexEvent) -> anyhow::Result<()> {
+        let fingerprint = match event {
+            IndexEvent::Blockchain { fingerprint, .. } => fingerprint.clone(),
+            IndexEvent::Record { name, .. } | IndexEvent::Transfer { name, .. } => hex::encode(
+                Hash160::default()
+                    .chain_update(name.as_bytes())
+                    .fingerprint(),
+            ),
+        };
+        let Some(name) = db::watched_name(&self.pool, &fingerprint).await? else {
+            return Ok(());
+        };
+
+        let alert = match event {
+            IndexEvent::Blockchain {
+                kind,
+                txid,
+                blockheight,
+                ..
+            } => Alert {
+                title: format!("Watched name {name}: on-chain {kind}"),
+                message: format!("Transaction {txid} at block height {blockheight}"),
+            },
+            IndexEvent::Record {
+                pubkey, records, ..
+            } => {
+                let mut records = records
+                    .iter()
+                    .flatten()
+                    .map(|(k, v)| format!("{k}={v}"))
+                    .collect::<Vec<_>>();
+                records.sort();
+                Alert {
+                    title: format!("Watched name {name}: records changed"),
+                    message: format!("Published by {pubkey}: {}", records.join(", ")),
+                }
+            }
+            IndexEvent::Transfer {
+                pubkey, new_owner, ..
+            } => Alert {
+                title: format!("Watched name {name}: ownership transfer"),
+                message: format!("From {pubkey} to {new_owner}"),
+            },
+        };
+
+        self.notifier.send(&alert).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use nostr_sdk::{Event, EventId, Filter};
+use sqlx::SqlitePool;
+use time::{macros::format_description, OffsetDateTime};
+use yansi::Paint;
+
+use crate::{
+    config::{Config, MarketBuySubcommand, MarketOfferSubcommand, RelayUse},
+    db,
+    error::NomenError,
+    util::{self, tag_print, Hash160, Name, NomenKind, NsidBuilder, Offer},
+};
+
+use super::name::{check_owner, hwi, insert_outputs, parse_event, parse_psbt, Signer};
+
+#[derive(serde::Serialize)]
+struct OfferOutput {
+    nsid: String,
+    nsid_bech32: String,
+    price: u64,
+    unsigned_tx: String,
+    /// The PSBT as signed by the hardware wallet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signed_psbt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct BuyOutput {
+    nsid: String,
+    nsid_bech32: String,
+    price: u64,
+    fee: u64,
+    unsigned_tx: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signed_tx: Option<String>,
+}
+
+/// Add the transfer output to the seller's transaction, sign it, and publish the offer.
+pub async fn offer(config: &Config, args: &MarketOfferSubcommand) -> anyhow::Result<()> {
+    if args.hwi.broadcast_tx {
+        bail!(NomenError::Validation(
+            "An offer can only be broadcast once the buyer completes it".into()
+        ));
+    }
+    let name = args.name.as_ref();
+    let signer = Signer::new(config, &args.privkey, &args.bunker).await?;
+    if args.validate {
+        check_owner(config, name, &signer.public_key()).await?;
+    }
+
+    let mut psbt = parse_psbt(&args.psbt)?;
+    let nsid = NsidBuilder::new(name, &args.buyer).finalize();
+    let fingerprint = Hash160::default()
+        .chain_update(name.as_bytes())
+        .fingerprint();
+    // The seller may run this again with the transaction signed elsewhere.
+    if !util::has_transfer_output(&psbt, fingerprint, nsid) {
+        insert_outputs(&mut psbt, fingerprint, nsid, NomenKind::Transfer, None)?;
+    }
+    for input in &mut psbt.inputs {
+        if input.final_script_witness.is_none() && input.final_script_sig.is_none() {
+            input.sighash_type = Some(util::offer_sighash_type());
+        }
+    }
+    let price = util::price(&psbt)?;
+
+    let unsigned_tx = psbt.to_string();
+    let signed_psbt = if args.hwi.hwi {
+        psbt = hwi::sign(config, &args.hwi, &psbt).await?;
+        Some(psbt.to_string())
+    } else {
+        None
+    };
+
+    let event = if args.broadcast {
+        let event = signer
+            .sign(Offer::event(
+                signer.public_key(),
+                &args.name,
+                &args.buyer,
+                &psbt,
+            ))
+            .await?;
+        Offer::from_event(&event)?;
+        let (_k, nostr) = config
+            .nostr_random_client(&[event.kind], RelayUse::Write)
+            .await?;
+        nostr.send_event(event.clone()).await?;
+        log::info!("Nost event transmitted");
+        Some(serde_json::to_string(&event)?)
+    } else {
+        None
+    };
+
+    let output = OfferOutput {
+        nsid: nsid.to_string(),
+        nsid_bech32: nsid.to_bech32(),
+        price,
+        unsigned_tx,
+        signed_psbt,
+        event,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        tag_print("Nsid", &output.nsid);
+        tag_print("Nsid (bech32)", &output.nsid_bech32);
+        tag_print("Price", &format!("{} sats", output.price));
+        tag_print("Unsigned Tx", &output.unsigned_tx);
+        if let Some(signed_psbt) = &output.signed_psbt {
+            tag_print("Signed PSBT", signed_psbt);
+        }
+        if let Some(event) = &output.event {
+            tag_print("Event", event);
+        }
+    }
+
+    if let Some(output) = &args.output {
+        std::fs::write(output, psbt.serialize())?;
+    }
+
+    Ok(())
+}
+
+/// Add the buyer's inputs to an offer's transaction, and sign it.
+pub async fn buy(config: &Config, args: &MarketBuySubcommand) -> anyhow::Result<()> {
+    let event = fetch_offer(config, &args.offer).await?;
+    event
+        .verify()
+        .map_err(|e| NomenError::Validation(format!("Invalid offer signature: {e}")))?;
+    let offer = Offer::from_event(&event)?;
+    check_owner(config, offer.name.as_ref(), &offer.seller).await?;
+
+    let buyer_psbt = parse_psbt(&args.psbt)?;
+    if !buyer_psbt.unsigned_tx.output.is_empty() {
+        bail!(NomenError::Validation(
+            "The buyer's transaction must not have outputs".into()
+        ));
+    }
+    let mut psbt = offer.psbt.clone();
+    psbt.unsigned_tx.input.extend(buyer_psbt.unsigned_tx.input);
+    psbt.inputs.extend(buyer_psbt.inputs);
+
+    let paid = util::input_value(&psbt)?;
+    let fee = paid.checked_sub(util::output_value(&psbt)).ok_or_else(|| {
+        NomenError::Validation(format!(
+            "Inputs must pay at least the price of {} sats, plus the fee",
+            offer.price
+        ))
+    })?;
+
+    let unsigned_tx = psbt.to_string();
+    let signed_tx = if args.hwi.hwi {
+        psbt = hwi::sign(config, &args.hwi, &psbt).await?;
+        Some(hwi::finalize(config, &psbt, args.hwi.broadcast_tx).await?)
+    } else {
+        None
+    };
+
+    let output = BuyOutput {
+        nsid: offer.nsid.to_string(),
+        nsid_bech32: offer.nsid.to_bech32(),
+        price: offer.price,
+        fee,
+        unsigned_tx,
+        signed_tx,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        tag_print("Nsid", &output.nsid);
+        tag_print("Nsid (bech32)", &output.nsid_bech32);
+        tag_print("Price", &format!("{} sats", output.price));
+        tag_print("Fee", &format!("{} sats", output.fee));
+        tag_print("Unsigned Tx", &output.unsigned_tx);
+        if let Some(signed_tx) = &output.signed_tx {
+            tag_print("Signed Tx", signed_tx);
+        }
+        println!(
+            "Once the transaction confirms, the seller completes the transfer with `nomen name transfer sign {} {}`.",
+            offer.name, offer.buyer
+        );
+    }
+
+    if let Some(output) = &args.output {
+        std::fs::write(output, psbt.serialize())?;
+    }
+
+    Ok(())
+}
+
+pub async fn list(pool: &SqlitePool, name: Option<&Name>, json: bool) -> anyhow::Result<()> {
+    let offers = db::open_offers(pool, name.map(|n| n.as_ref().as_str())).await?;
+    if json {
+        println!("{}", serde_json::to_string(&offers)?);
+        return Ok(());
+    }
+
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+    for offer in offers {
+        let created = OffsetDateTime::from_unix_timestamp(offer.created_at)?.format(format)?;
+        println!(
+            "{}\t{} sats\tto {}\t{} {created}",
+            Paint::yellow(&offer.name),
+            offer.price,
+            offer.buyer,
+            offer.event_id
+        );
+    }
+    Ok(())
+}
+
+/// The offer event, fetched from the relays if `offer` is an event id.
+async fn fetch_offer(config: &Config, offer: &str) -> anyhow::Result<Event> {
+    let Ok(id) = EventId::from_hex(offer) else {
+        return parse_event(offer);
+    };
+
+    let filter = Filter::new().id(id.to_hex()).kind(Offer::kind());
+    let (_keys, client) = config
+        .nostr_random_client(&[Offer::kind()], RelayUse::Read)
+        .await?;
+    let events = client
+        .get_events_of(vec![filter], Some(Duration::from_secs(10)))
+        .await?;
+    client.disconnect().await?;
+    events
+        .into_iter()
+        .next()
+        .ok_or_else(|| NomenError::NotFound(format!("Offer {id} not found")).into())
+}
@@ -1,8 +1,17 @@
+pub mod announce;
+pub mod apikey;
+pub mod batch;
+pub mod db;
+mod dev;
 mod index;
+pub mod market;
 mod name;
+pub mod outbox;
 mod server;
 pub mod util;
+pub mod watch;
 
+pub use dev::*;
 pub use index::*;
 pub use name::*;
 pub use server::*;
@@ -0,0 +1,34 @@
+use sqlx::SqlitePool;
+
+use crate::{
+    config::{Config, NameApproveSubcommand},
+    subcommands::{outbox, Signer},
+    util::{self, Approval},
+};
+
+/// Approve the owner of a subname of a name you own, for `protocol.subname_approval`.
+pub async fn approve(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &NameApproveSubcommand,
+) -> anyhow::Result<()> {
+    let parent = util::parent(&args.name)?;
+    let signer = Signer::new(config, &args.privkey, &args.bunker).await?;
+    super::check_owner(config, parent, &signer.public_key()).await?;
+
+    let event = signer
+        .sign(Approval::event(
+            signer.public_key(),
+            &args.name,
+            args.pubkey.as_ref(),
+        )?)
+        .await?;
+
+    if outbox::publish(config, pool, &event).await? {
+        println!("Sent event {}", event.id);
+    } else {
+        println!("Queued event {}", event.id);
+    }
+
+    Ok(())
+}
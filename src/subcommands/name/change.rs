@@ -0,0 +1,124 @@
+use anyhow::anyhow;
+use bitcoin::{address::NetworkUnchecked, psbt::Output, psbt::Psbt, Address, TxOut};
+use sqlx::SqlitePool;
+
+use crate::{
+    config::Config,
+    db,
+    error::NomenError,
+    subcommands::util::{DUST_LIMIT, FALLBACK_FEE_RATE},
+};
+
+/// Virtual size a P2WPKH input's witness adds once signed, rounded up.
+const INPUT_WITNESS_VSIZE: u64 = 27;
+
+/// Virtual size of the change output, enough for any standard script.
+const CHANGE_OUTPUT_VSIZE: u64 = 43;
+
+/// Pay what the inputs of `psbt` leave over, less the fee at `fee_rate` (or the node's estimate),
+/// to `address`, or else to the next address of `wallet.descriptor`. A PSBT that leaves no more
+/// than the fee and a dust output is left as it is. Returns the change address, if one was added.
+pub(super) async fn add_change(
+    config: &Config,
+    pool: &SqlitePool,
+    psbt: &mut Psbt,
+    address: Option<&Address<NetworkUnchecked>>,
+    fee_rate: Option<f64>,
+) -> anyhow::Result<Option<Address>> {
+    let inputs = input_value(config, psbt).await?;
+    let outputs: u64 = psbt.unsigned_tx.output.iter().map(|o| o.value).sum();
+    let surplus = inputs.checked_sub(outputs).ok_or_else(|| {
+        NomenError::Validation(format!(
+            "PSBT outputs ({outputs} sats) are more than its inputs ({inputs} sats)"
+        ))
+    })?;
+    let fee_rate = match fee_rate {
+        Some(rate) => rate,
+        None => config
+            .rpc()?
+            .estimate_fee_rate(6)
+            .await?
+            .unwrap_or(FALLBACK_FEE_RATE),
+    };
+    let vsize = psbt.unsigned_tx.vsize() as u64
+        + INPUT_WITNESS_VSIZE * psbt.unsigned_tx.input.len() as u64
+        + CHANGE_OUTPUT_VSIZE;
+    let Some(value) = change_value(surplus, vsize, fee_rate) else {
+        log::info!("PSBT leaves {surplus} sats, too little for change");
+        return Ok(None);
+    };
+
+    let address = match address {
+        Some(address) => address.clone(),
+        None => derive_address(config, pool).await?,
+    };
+    let address = address.require_network(config.network()).map_err(|_| {
+        NomenError::Validation(format!(
+            "Change address is not a {} address",
+            config.network()
+        ))
+    })?;
+    psbt.unsigned_tx.output.push(TxOut {
+        value,
+        script_pubkey: address.script_pubkey(),
+    });
+    psbt.outputs.push(Output::default());
+    Ok(Some(address))
+}
+
+/// Change left from `surplus` sats once a transaction of `vsize` pays `fee_rate`, if it is more
+/// than dust.
+fn change_value(surplus: u64, vsize: u64, fee_rate: f64) -> Option<u64> {
+    let fee = (fee_rate * vsize as f64).ceil() as u64;
+    surplus
+        .checked_sub(fee)
+        .filter(|change| *change >= DUST_LIMIT)
+}
+
+/// The next unused address of `wallet.descriptor`.
+async fn derive_address(
+    config: &Config,
+    pool: &SqlitePool,
+) -> anyhow::Result<Address<NetworkUnchecked>> {
+    let descriptor = config.wallet_descriptor().ok_or_else(|| {
+        NomenError::Config("Set wallet.descriptor or use --change-address".into())
+    })?;
+    let index = db::take_derivation_index(pool, &descriptor).await?;
+    log::info!("Deriving change address at index {index}");
+    config.rpc()?.derive_address(descriptor, index as u32).await
+}
+
+/// Total value of the outputs `psbt` spends, from its UTXO fields or else the node.
+async fn input_value(config: &Config, psbt: &Psbt) -> anyhow::Result<u64> {
+    let mut total = 0;
+    for (txin, input) in psbt.unsigned_tx.input.iter().zip(&psbt.inputs) {
+        let outpoint = txin.previous_output;
+        let value = match (&input.witness_utxo, &input.non_witness_utxo) {
+            (Some(utxo), _) => Some(utxo.value),
+            (None, Some(tx)) => tx.output.get(outpoint.vout as usize).map(|o| o.value),
+            (None, None) => super::get_transaction(config, &outpoint.txid)
+                .await?
+                .output
+                .get(outpoint.vout as usize)
+                .map(|o| o.value),
+        };
+        total += value.ok_or_else(|| {
+            NomenError::Validation(format!("PSBT input {outpoint} does not exist"))
+        })?;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_value() {
+        assert_eq!(change_value(10_000, 200, 1.0), Some(9_800));
+        assert_eq!(change_value(10_000, 200, 2.5), Some(9_500));
+        assert_eq!(change_value(746, 200, 1.0), Some(546));
+        assert_eq!(change_value(745, 200, 1.0), None);
+        assert_eq!(change_value(100, 200, 1.0), None);
+    }
+}
@@ -0,0 +1,34 @@
+use sqlx::SqlitePool;
+
+use crate::{
+    config::{Config, NameDeactivateSubcommand},
+    subcommands::{outbox, Signer},
+    util::Deactivation,
+};
+
+/// Deactivate a name you own, hiding its records until you publish new ones.
+pub async fn deactivate(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &NameDeactivateSubcommand,
+) -> anyhow::Result<()> {
+    let name = args.name.to_string();
+    let signer = Signer::new(config, &args.privkey, &args.bunker).await?;
+    super::check_owner(config, &name, &signer.public_key()).await?;
+
+    let event = signer
+        .sign(Deactivation::event(
+            signer.public_key(),
+            &name,
+            &args.reason,
+        ))
+        .await?;
+
+    if outbox::publish(config, pool, &event).await? {
+        println!("Sent event {}", event.id);
+    } else {
+        println!("Queued event {}", event.id);
+    }
+
+    Ok(())
+}
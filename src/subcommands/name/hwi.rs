@@ -0,0 +1,141 @@
+use anyhow::{anyhow, bail, Context};
+use bitcoin::{psbt::Psbt, Network};
+use bitcoincore_rpc::RpcApi;
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::{
+    config::{Config, HwiArgs},
+    error::NomenError,
+};
+
+#[derive(Deserialize)]
+struct SignTxOutput {
+    psbt: Option<String>,
+    signed: Option<bool>,
+    error: Option<String>,
+}
+
+/// Sign `psbt` with a hardware wallet, using the HWI command line tool. The tool runs until the
+/// signature is confirmed on the device, without blocking the runtime meanwhile.
+pub async fn sign(config: &Config, args: &HwiArgs, psbt: &Psbt) -> anyhow::Result<Psbt> {
+    let mut cmd = Command::new(&args.hwi_path);
+    if let Some(fingerprint) = &args.hwi_fingerprint {
+        cmd.args(["--fingerprint", fingerprint]);
+    } else {
+        cmd.arg("--device-type").arg(&args.hwi_device);
+    }
+    let chain = match &args.hwi_chain {
+        Some(chain) => chain.as_str(),
+        None => hwi_chain(config.network()),
+    };
+    cmd.args(["--chain", chain]);
+    cmd.arg("signtx").arg(psbt.to_string());
+
+    log::info!("Waiting for hardware wallet to sign the transaction...");
+    let output = cmd
+        .output()
+        .await
+        .with_context(|| format!("Unable to run {:?}", args.hwi_path))?;
+    if !output.status.success() {
+        bail!(NomenError::Rpc(format!(
+            "HWI failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let result: SignTxOutput = serde_json::from_slice(&output.stdout)?;
+    if let Some(error) = result.error {
+        bail!(NomenError::Rpc(format!("HWI error: {error}")));
+    }
+    if result.signed == Some(false) {
+        bail!(NomenError::Validation(
+            "Hardware wallet did not sign the transaction".into()
+        ));
+    }
+    let psbt = result
+        .psbt
+        .ok_or_else(|| NomenError::Rpc("HWI returned no PSBT".into()))?
+        .parse()?;
+    Ok(psbt)
+}
+
+/// HWI's name for `network`.
+fn hwi_chain(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "main",
+        Network::Testnet => "test",
+        Network::Signet => "signet",
+        _ => "regtest",
+    }
+}
+
+/// Finalize a signed PSBT with the node, and broadcast it if requested. Returns the hex encoded transaction.
+pub async fn finalize(config: &Config, psbt: &Psbt, broadcast: bool) -> anyhow::Result<String> {
+    let psbt = psbt.to_string();
+    let hex = config
+        .rpc()?
+        .run(move |client| {
+            let finalized = client.finalize_psbt(&psbt, Some(true))?;
+            let hex = match (finalized.complete, finalized.hex) {
+                (true, Some(hex)) => hex,
+                _ => bail!(NomenError::Validation(
+                    "Transaction is not fully signed".into()
+                )),
+            };
+            if broadcast {
+                let txid = client.send_raw_transaction(&hex)?;
+                log::info!("Broadcast transaction {txid}");
+            }
+            Ok(hex)
+        })
+        .await?;
+
+    Ok(hex::encode(hex))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use bitcoin::{absolute::LockTime, Transaction};
+    use clap::Parser;
+
+    use super::*;
+    use crate::config::{Cli, ConfigFile};
+
+    #[tokio::test]
+    async fn test_sign_failure() {
+        let hwi = std::env::temp_dir().join(format!("nomen-hwi-{}", std::process::id()));
+        std::fs::write(&hwi, "#!/bin/sh\necho \"$@\" >&2\nexit 1\n").unwrap();
+        std::fs::set_permissions(&hwi, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let config = Config::new(
+            Cli::parse_from(["nomen", "--network", "regtest", "watch", "list"]),
+            ConfigFile::default(),
+        );
+        let args = HwiArgs {
+            hwi: true,
+            hwi_path: hwi.clone(),
+            hwi_device: "coldcard".into(),
+            hwi_fingerprint: None,
+            hwi_chain: None,
+            broadcast_tx: false,
+        };
+        let psbt = Psbt::from_unsigned_tx(Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        })
+        .unwrap();
+        let err = sign(&config, &args, &psbt).await.unwrap_err();
+        std::fs::remove_file(hwi).unwrap();
+
+        assert!(err.to_string().contains("--chain regtest"), "{err}");
+        assert!(matches!(
+            NomenError::classify(&err),
+            Some(NomenError::Rpc(_))
+        ));
+    }
+}
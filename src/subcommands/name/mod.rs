@@ -1,10 +1,18 @@
+mod approve;
+mod change;
+mod deactivate;
+pub(crate) mod hwi;
 mod new;
+mod nip46;
+mod preflight;
 mod record;
-mod transfer;
+mod show;
+pub(crate) mod transfer;
 
 use std::{collections::HashMap, io::Write, path::PathBuf, str::FromStr};
 
 pub use anyhow::anyhow;
+use anyhow::bail;
 use bitcoin::{
     psbt::{Output, Psbt},
     script::PushBytesBuf,
@@ -13,21 +21,38 @@ use bitcoin::{
 };
 use bitcoincore_rpc::RpcApi;
 pub use new::*;
-use nostr_sdk::{prelude::TagKind, EventBuilder, Keys, Tag, UnsignedEvent};
+pub use nip46::BunkerUri;
+use nostr_sdk::{prelude::TagKind, Event, EventBuilder, Keys, Tag, UnsignedEvent};
 pub use record::*;
 use secp256k1::XOnlyPublicKey;
+use sqlx::SqlitePool;
 
 use crate::{
-    config::{Cli, Config, NameSubcommand, TxInfo},
-    util::{NameKind, NomenKind, NostrSk, Nsid, NsidBuilder},
+    config::{Cli, Config, NameSubcommand, NameTransferSubcommand, TxInfo},
+    db,
+    error::NomenError,
+    util::{
+        validate_record, NameKind, NomenKind, NostrSk, Nsid, NsidBuilder, RecordAnchor,
+        RecordLimits,
+    },
 };
 
-pub async fn name(config: &Config, cmd: &NameSubcommand) -> anyhow::Result<()> {
+pub async fn name(config: &Config, pool: &SqlitePool, cmd: &NameSubcommand) -> anyhow::Result<()> {
     match cmd {
-        NameSubcommand::New(new_data) => new::new(config, new_data).await?,
-        NameSubcommand::Record(record_data) => record::record(config, record_data).await?,
-        NameSubcommand::Transfer(transfer_data) => {
-            transfer::transfer(config, transfer_data).await?
+        NameSubcommand::New(new_data) => new::new(config, pool, new_data).await?,
+        NameSubcommand::Record(record_data) => record::record(config, pool, record_data).await?,
+        NameSubcommand::Approve(approve_data) => {
+            approve::approve(config, pool, approve_data).await?
+        }
+        NameSubcommand::Deactivate(deactivate_data) => {
+            deactivate::deactivate(config, pool, deactivate_data).await?
+        }
+        NameSubcommand::Show(show_data) => show::show(config, pool, show_data).await?,
+        NameSubcommand::Transfer(NameTransferSubcommand::Sign(sign_data)) => {
+            transfer::sign(config, sign_data).await?
+        }
+        NameSubcommand::Transfer(NameTransferSubcommand::Finalize(finalize_data)) => {
+            transfer::finalize(config, finalize_data).await?
         }
     }
 
@@ -49,13 +74,51 @@ pub(crate) fn get_keys(privkey: &Option<NostrSk>) -> Result<Keys, anyhow::Error>
     Ok(keys)
 }
 
+/// Signs events either with a local private key, or with a NIP-46 remote signer.
+pub(crate) enum Signer {
+    Keys(Keys),
+    Bunker(Box<nip46::Bunker>),
+}
+
+impl Signer {
+    /// Use the bunker if one is given, otherwise the private key (prompting if necessary).
+    pub async fn new(
+        config: &Config,
+        privkey: &Option<NostrSk>,
+        bunker: &Option<BunkerUri>,
+    ) -> anyhow::Result<Signer> {
+        match bunker {
+            Some(uri) => Ok(Signer::Bunker(Box::new(
+                nip46::Bunker::connect(uri, config.network_proxy()).await?,
+            ))),
+            None => Ok(Signer::Keys(get_keys(privkey)?)),
+        }
+    }
+
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        match self {
+            Signer::Keys(keys) => keys.public_key(),
+            Signer::Bunker(bunker) => bunker.public_key(),
+        }
+    }
+
+    pub async fn sign(&self, event: UnsignedEvent) -> anyhow::Result<Event> {
+        match self {
+            Signer::Keys(keys) => Ok(event.sign(keys)?),
+            Signer::Bunker(bunker) => bunker.sign(event).await,
+        }
+    }
+}
+
 pub(crate) fn insert_outputs(
     psbt: &mut Psbt,
     fingerprint: [u8; 5],
     nsid: Nsid,
     kind: NomenKind,
+    commitment: Option<[u8; 32]>,
 ) -> anyhow::Result<()> {
-    let op_return: PushBytesBuf = super::op_return(fingerprint, nsid, kind).try_into()?;
+    let op_return: PushBytesBuf =
+        super::op_return(fingerprint, nsid, kind, commitment).try_into()?;
     let op_return = ScriptBuf::new_op_return(&op_return);
     psbt.unsigned_tx.output.push(TxOut {
         value: 0,
@@ -73,36 +136,43 @@ pub(crate) async fn get_transaction(
     config: &Config,
     txid: &bitcoin::Txid,
 ) -> Result<bitcoin::Transaction, anyhow::Error> {
-    let client = config.rpc_client()?;
-    let txid = *txid;
-    Ok(tokio::task::spawn_blocking(move || client.get_raw_transaction(&txid, None)).await??)
+    config.rpc()?.get_raw_transaction(*txid, None).await
 }
 
-pub(crate) fn op_return(fingerprint: [u8; 5], nsid: Nsid, kind: NomenKind) -> Vec<u8> {
-    let mut v = Vec::with_capacity(25);
-    v.extend(b"NOM\x00");
-    v.push(kind.into());
-    v.extend(fingerprint);
-    v.extend(nsid.as_ref());
-    v
+pub(crate) fn op_return(
+    fingerprint: [u8; 5],
+    nsid: Nsid,
+    kind: NomenKind,
+    commitment: Option<[u8; 32]>,
+) -> Vec<u8> {
+    nomen_core::OpReturn {
+        kind,
+        fingerprint,
+        nsid: *nsid,
+        commitment,
+    }
+    .to_bytes()
 }
 
 pub(crate) fn name_event(
     pubkey: XOnlyPublicKey,
     records: &HashMap<String, String>,
     name: &str,
+    limits: &RecordLimits,
+    anchor: Option<&RecordAnchor>,
 ) -> anyhow::Result<UnsignedEvent> {
-    let records = serde_json::to_string(&records)?;
+    for (key, value) in records {
+        validate_record(key, value)?;
+    }
+    let content = serde_json::to_string(&records)?;
+    limits.check(&content, Some(records))?;
     let nsid = NsidBuilder::new(name, &pubkey).finalize();
-    let event = EventBuilder::new(
-        NameKind::Name.into(),
-        records,
-        &[
-            Tag::Identifier(nsid.to_string()),
-            Tag::Generic(TagKind::Custom("nom".to_owned()), vec![name.to_owned()]),
-        ],
-    )
-    .to_unsigned_event(pubkey);
+    let mut tags = vec![
+        Tag::Identifier(nsid.to_string()),
+        Tag::Generic(TagKind::Custom("nom".to_owned()), vec![name.to_owned()]),
+    ];
+    tags.extend(anchor.map(RecordAnchor::tag));
+    let event = EventBuilder::new(NameKind::Name.into(), content, &tags).to_unsigned_event(pubkey);
 
     Ok(event)
 }
@@ -113,3 +183,33 @@ pub(crate) fn parse_psbt(psbt: &str) -> anyhow::Result<Psbt> {
         _ => Psbt::from_str(psbt)?,
     })
 }
+
+/// Parse an event, read from a file if `event` is a path.
+pub(crate) fn parse_event(event: &str) -> anyhow::Result<Event> {
+    let json = match PathBuf::from_str(event) {
+        Ok(path) if path.is_file() => std::fs::read_to_string(path)?,
+        _ => event.to_string(),
+    };
+    Event::from_json(json.trim())
+        .map_err(|e| NomenError::Validation(format!("Invalid event: {e}")).into())
+}
+
+/// Check the index agrees that `pubkey` currently owns `name`.
+pub(crate) async fn check_owner(
+    config: &Config,
+    name: &str,
+    pubkey: &XOnlyPublicKey,
+) -> anyhow::Result<()> {
+    let conn = config.sqlite().await?;
+    match db::name_owner(&conn, name).await? {
+        Some(pk) if *pubkey != pk => {
+            bail!(NomenError::Validation(
+                "The specified key does not own the domain".into()
+            ))
+        }
+        Some(_) => Ok(()),
+        None => {
+            bail!(NomenError::NotFound("That name does not exist".into()))
+        }
+    }
+}
@@ -11,18 +11,29 @@ use bitcoincore_rpc::{RawTx, RpcApi};
 
 use nostr_sdk::{prelude::TagKind, EventBuilder, Keys, Tag};
 use secp256k1::{SecretKey, XOnlyPublicKey};
+use sqlx::SqlitePool;
+use yansi::Paint;
 
 use crate::{
     config::{Cli, Config, NameNewSubcommand},
     db::{self},
-    subcommands::name::get_keys,
-    util::{check_name_availability, tag_print, Hash160, NameKind, NomenKind, Nsid, NsidBuilder},
+    error::NomenError,
+    subcommands::{name::Signer, outbox},
+    util::{self, tag_print, Hash160, NameKind, NomenKind, Nsid, NsidBuilder},
 };
 
 #[derive(serde::Serialize)]
-struct CmdOutput {
-    nsid: String,
+pub(crate) struct CmdOutput {
+    pub nsid: String,
+    nsid_bech32: String,
     unsigned_tx: String,
+    /// The PSBT as signed by the hardware wallet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signed_psbt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signed_tx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    change_address: Option<String>,
     event: String,
 }
 
@@ -46,49 +57,135 @@ fn create_event(
     Ok(event)
 }
 
-pub(crate) async fn new(config: &Config, args: &NameNewSubcommand) -> anyhow::Result<()> {
+pub(crate) async fn new(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &NameNewSubcommand,
+) -> anyhow::Result<()> {
+    let signer = Signer::new(config, &args.privkey, &args.bunker).await?;
+    let output = claim(config, pool, args, &signer).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        tag_print("Nsid", &output.nsid);
+        tag_print("Nsid (bech32)", &output.nsid_bech32);
+        tag_print("Unsigned Tx", &output.unsigned_tx);
+        if let Some(signed_psbt) = &output.signed_psbt {
+            tag_print("Signed PSBT", signed_psbt);
+        }
+        if let Some(signed_tx) = &output.signed_tx {
+            tag_print("Signed Tx", signed_tx);
+        }
+        if let Some(address) = &output.change_address {
+            tag_print("Change address", address);
+        }
+        tag_print("Event", &output.event);
+    }
+
+    Ok(())
+}
+
+/// Add the claim of `args.name` by `signer` to the PSBT, and sign the name's first event.
+pub(crate) async fn claim(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &NameNewSubcommand,
+    signer: &Signer,
+) -> anyhow::Result<CmdOutput> {
     let name = args.name.as_ref();
-    validate(config, args).await?;
     let mut psbt = super::parse_psbt(&args.psbt)?;
-    let keys = get_keys(&args.privkey)?;
-    let nsid = NsidBuilder::new(name, &keys.public_key()).finalize();
+    preflight(config, pool, args, signer.public_key()).await?;
+    let nsid = NsidBuilder::new(name, &signer.public_key()).finalize();
     let fingerprint = Hash160::default()
         .chain_update(name.as_bytes())
         .fingerprint();
 
-    super::insert_outputs(&mut psbt, fingerprint, nsid, NomenKind::Create)?;
+    let event = super::name_event(
+        signer.public_key(),
+        &HashMap::new(),
+        name,
+        &config.record_limits(),
+        None,
+    )?;
+    let commitment = args
+        .commit_records
+        .then(|| nomen_core::records_commitment(&event.content));
+    super::insert_outputs(&mut psbt, fingerprint, nsid, NomenKind::Create, commitment)?;
+    if args.sponsor_psbt {
+        // The sponsor adds their inputs after these are signed, so the signatures can't cover them.
+        for input in &mut psbt.inputs {
+            if input.final_script_witness.is_none() && input.final_script_sig.is_none() {
+                input.sighash_type = Some(util::offer_sighash_type());
+            }
+        }
+    }
+    let change = if args.sponsor_psbt {
+        None
+    } else if args.change_address.is_some() || config.wallet_descriptor().is_some() {
+        super::change::add_change(
+            config,
+            pool,
+            &mut psbt,
+            args.change_address.as_ref(),
+            args.fee_rate,
+        )
+        .await?
+    } else {
+        None
+    };
 
-    let event = super::name_event(keys.public_key(), &HashMap::new(), name)?.sign(&keys)?;
-    if args.broadcast {
-        let (_k, nostr) = config.nostr_random_client().await?;
-        nostr.send_event(event.clone()).await?;
-        log::info!("Nost event transmitted");
+    let event = signer.sign(event).await?;
+    if args.broadcast && outbox::publish(config, pool, &event).await? {
+        log::info!("Nostr event transmitted");
     }
 
-    let output = CmdOutput {
-        nsid: nsid.to_string(),
-        unsigned_tx: psbt.to_string(),
-        event: serde_json::to_string(&event)?,
+    let unsigned_tx = psbt.to_string();
+    let signed_psbt = if args.hwi.hwi {
+        psbt = super::hwi::sign(config, &args.hwi, &psbt).await?;
+        Some(psbt.to_string())
+    } else {
+        None
     };
-
-    if args.json {
-        println!("{}", serde_json::to_string(&output)?);
+    // A sponsored claim is finalized once the sponsor has signed too.
+    let signed_tx = if args.hwi.hwi && !args.sponsor_psbt {
+        Some(super::hwi::finalize(config, &psbt, args.hwi.broadcast_tx).await?)
     } else {
-        tag_print("Nsid", &output.nsid);
-        tag_print("Unsigned Tx", &output.unsigned_tx);
-        tag_print("Event", &output.event);
-    }
+        None
+    };
 
     if let Some(output) = &args.output {
         std::fs::write(output, psbt.serialize())?;
     }
 
-    Ok(())
+    Ok(CmdOutput {
+        nsid: nsid.to_string(),
+        nsid_bech32: nsid.to_bech32(),
+        unsigned_tx,
+        signed_psbt,
+        signed_tx,
+        change_address: change.map(|a| a.to_string()),
+        event: serde_json::to_string(&event)?,
+    })
 }
 
-pub async fn validate(config: &Config, args: &NameNewSubcommand) -> anyhow::Result<()> {
-    if args.validate {
-        check_name_availability(config, args.name.as_ref()).await?;
+/// Look for competing claims before building the claim transaction. They are an error, unless `--force`.
+async fn preflight(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &NameNewSubcommand,
+    pubkey: XOnlyPublicKey,
+) -> anyhow::Result<()> {
+    config.grammar().validate(args.name.as_ref())?;
+    let conflicts = super::preflight::conflicts(config, pool, args.name.as_ref(), pubkey).await?;
+    for conflict in &conflicts {
+        eprintln!("{} {conflict}", Paint::yellow("Warning:"));
+    }
+    if !conflicts.is_empty() && !args.force {
+        bail!(NomenError::Validation(format!(
+            "Found competing claims to {}, use --force to claim it anyway",
+            args.name
+        )));
     }
     Ok(())
 }
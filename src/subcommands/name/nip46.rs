@@ -0,0 +1,238 @@
+use std::{net::SocketAddr, str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail, Context};
+use nostr_sdk::{
+    nips::nip04, Client, Event, EventBuilder, Filter, Keys, Kind, RelayPoolNotification, Tag,
+    Timestamp, UnsignedEvent,
+};
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::error::NomenError;
+
+/// Event kind used for NIP-46 requests and responses.
+const NOSTR_CONNECT_KIND: u64 = 24133;
+
+/// How long to wait for the remote signer. Signers may ask the user to approve each request.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A `bunker://<remote pubkey>?relay=<relay>&secret=<secret>` connection string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BunkerUri {
+    pub remote_pubkey: XOnlyPublicKey,
+    pub relays: Vec<String>,
+    pub secret: Option<String>,
+}
+
+impl FromStr for BunkerUri {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix("bunker://")
+            .ok_or_else(|| NomenError::Validation("Bunker URI must start with bunker://".into()))?;
+        let (pubkey, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let remote_pubkey = pubkey.parse().context("Invalid bunker pubkey")?;
+
+        let mut relays = vec![];
+        let mut secret = None;
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value);
+            match key {
+                "relay" => relays.push(value),
+                "secret" => secret = Some(value),
+                _ => {}
+            }
+        }
+        if relays.is_empty() {
+            bail!(NomenError::Validation(
+                "Bunker URI must include at least one relay".into()
+            ));
+        }
+
+        Ok(BunkerUri {
+            remote_pubkey,
+            relays,
+            secret,
+        })
+    }
+}
+
+/// Decode `%XX` escapes, as used for relay URLs in the query string.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) => match u8::from_str_radix(&String::from_utf8_lossy(hex), 16) {
+                Ok(b) => {
+                    out.push(b);
+                    i += 3;
+                    continue;
+                }
+                Err(_) => out.push(b'%'),
+            },
+            (b, _) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    id: String,
+    method: &'a str,
+    params: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: String,
+    result: Option<String>,
+    error: Option<String>,
+}
+
+/// A connection to a NIP-46 remote signer.
+pub struct Bunker {
+    app_keys: Keys,
+    client: Client,
+    uri: BunkerUri,
+    user_pubkey: XOnlyPublicKey,
+}
+
+impl Bunker {
+    /// Connect to the signer's relays and perform the `connect` handshake.
+    pub async fn connect(uri: &BunkerUri, proxy: Option<SocketAddr>) -> anyhow::Result<Bunker> {
+        let app_keys = Keys::generate();
+        let client = Client::new(&app_keys);
+        for relay in &uri.relays {
+            client.add_relay(relay.as_str(), proxy).await?;
+        }
+        client.connect().await;
+        client
+            .subscribe(vec![Filter::new()
+                .kind(Kind::Custom(NOSTR_CONNECT_KIND))
+                .pubkey(app_keys.public_key())
+                .since(Timestamp::now())])
+            .await;
+
+        let mut bunker = Bunker {
+            app_keys,
+            client,
+            uri: uri.clone(),
+            user_pubkey: uri.remote_pubkey,
+        };
+
+        let mut params = vec![uri.remote_pubkey.to_string()];
+        params.extend(uri.secret.clone());
+        bunker.request("connect", params).await?;
+
+        bunker.user_pubkey = bunker.request("get_public_key", vec![]).await?.parse()?;
+        log::info!("Connected to remote signer for {}", bunker.user_pubkey);
+        Ok(bunker)
+    }
+
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        self.user_pubkey
+    }
+
+    pub async fn sign(&self, event: UnsignedEvent) -> anyhow::Result<Event> {
+        let signed = self
+            .request("sign_event", vec![serde_json::to_string(&event)?])
+            .await?;
+        let event = Event::from_json(signed)?;
+        event.verify()?;
+        if event.pubkey != self.user_pubkey {
+            bail!(NomenError::Validation(
+                "Remote signer signed with an unexpected key".into()
+            ));
+        }
+        Ok(event)
+    }
+
+    async fn request(&self, method: &str, params: Vec<String>) -> anyhow::Result<String> {
+        let id = hex::encode(rand::random::<[u8; 8]>());
+        let request = serde_json::to_string(&Request {
+            id: id.clone(),
+            method,
+            params,
+        })?;
+        let content = nip04::encrypt(
+            &self.app_keys.secret_key()?,
+            &self.uri.remote_pubkey,
+            request,
+        )?;
+
+        let mut notifications = self.client.notifications();
+        let event = EventBuilder::new(
+            Kind::Custom(NOSTR_CONNECT_KIND),
+            content,
+            &[Tag::PubKey(self.uri.remote_pubkey, None)],
+        )
+        .to_event(&self.app_keys)?;
+        self.client.send_event(event).await?;
+        log::debug!("Sent NIP-46 {method} request {id}");
+
+        let response = tokio::time::timeout(RESPONSE_TIMEOUT, async {
+            while let Ok(notification) = notifications.recv().await {
+                let RelayPoolNotification::Event(_, event) = notification else {
+                    continue;
+                };
+                if event.pubkey != self.uri.remote_pubkey {
+                    continue;
+                }
+                let Ok(content) = nip04::decrypt(
+                    &self.app_keys.secret_key()?,
+                    &self.uri.remote_pubkey,
+                    &event.content,
+                ) else {
+                    continue;
+                };
+                match serde_json::from_str::<Response>(&content) {
+                    Ok(response) if response.id == id => return Ok(response),
+                    _ => continue,
+                }
+            }
+            bail!(NomenError::Relay("Relay connection closed".into()))
+        })
+        .await
+        .map_err(|_| NomenError::Relay("Timed out waiting for remote signer".into()))??;
+
+        match (response.result, response.error) {
+            (_, Some(error)) if !error.is_empty() => {
+                bail!(NomenError::Relay(format!("Remote signer error: {error}")))
+            }
+            (Some(result), _) => Ok(result),
+            _ => bail!(NomenError::Relay(
+                "Remote signer returned an empty response".into()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bunker_uri() {
+        let uri: BunkerUri = "bunker://d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f?relay=wss%3A%2F%2Frelay.damus.io&secret=abc"
+            .parse()
+            .unwrap();
+        assert_eq!(uri.relays, vec!["wss://relay.damus.io".to_string()]);
+        assert_eq!(uri.secret.as_deref(), Some("abc"));
+
+        assert!(
+            "bunker://d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f"
+                .parse::<BunkerUri>()
+                .is_err()
+        );
+        assert!("nostrconnect://abc?relay=wss://x"
+            .parse::<BunkerUri>()
+            .is_err());
+    }
+}
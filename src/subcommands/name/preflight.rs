@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use bitcoincore_rpc::{Client, RpcApi};
+use nostr_sdk::{Filter, Timestamp};
+use secp256k1::XOnlyPublicKey;
+use sqlx::SqlitePool;
+
+use crate::{
+    config::{Config, RelayUse},
+    db,
+    subcommands::EventData,
+    util::{Hash160, NameKind, NomenKind, NomenTx, Nsid, NsidBuilder},
+};
+
+/// How long to wait for relays to answer.
+const RELAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Competing claims to `name` by anyone but `pubkey`: on chain in the local index, in the node's
+/// mempool, and name events on the relays the index has not seen yet.
+///
+/// The mempool and relays are checked on a best effort basis, a source that can't be reached is
+/// skipped with a warning.
+pub async fn conflicts(
+    config: &Config,
+    pool: &SqlitePool,
+    name: &str,
+    pubkey: XOnlyPublicKey,
+) -> anyhow::Result<Vec<String>> {
+    let fingerprint = Hash160::default()
+        .chain_update(name.as_bytes())
+        .fingerprint();
+    let nsid = NsidBuilder::new(name, &pubkey).finalize();
+    let mut conflicts = vec![];
+
+    if !db::name_available(pool, name).await? {
+        conflicts.push(format!("{name} is already claimed on chain"));
+    }
+
+    match mempool(config, fingerprint, nsid).await {
+        Ok(txids) => conflicts.extend(
+            txids
+                .into_iter()
+                .map(|txid| format!("Transaction {txid} in the mempool claims {name}")),
+        ),
+        Err(e) => log::warn!("Skipping the mempool check: {e}"),
+    }
+
+    match relays(config, pool, name, pubkey).await {
+        Ok(pubkeys) => conflicts.extend(pubkeys.into_iter().map(|pubkey| {
+            format!("{pubkey} published a name event for {name}, not in the index yet")
+        })),
+        Err(e) => log::warn!("Skipping the relay check: {e}"),
+    }
+
+    Ok(conflicts)
+}
+
+/// Mempool transactions claiming `fingerprint` for another nsid.
+async fn mempool(config: &Config, fingerprint: [u8; 5], nsid: Nsid) -> anyhow::Result<Vec<String>> {
+    config
+        .rpc()?
+        .run(move |client| {
+            let txids = client.get_raw_mempool()?;
+            log::info!("Checking {} mempool transactions", txids.len());
+            Ok(txids
+                .into_iter()
+                .filter(|txid| claims(client, txid, fingerprint, nsid))
+                .map(|txid| txid.to_string())
+                .collect())
+        })
+        .await
+}
+
+fn claims(client: &Client, txid: &bitcoin::Txid, fingerprint: [u8; 5], nsid: Nsid) -> bool {
+    // Transactions can leave the mempool while it is scanned.
+    let Ok(tx) = client.get_raw_transaction(txid, None) else {
+        return false;
+    };
+    tx.output.iter().any(|output| {
+        let script = output.script_pubkey.as_bytes();
+        output.script_pubkey.is_op_return()
+            && script.len() > 2
+            && matches!(
+                NomenTx::try_from(&script[2..]),
+                Ok(NomenTx { fingerprint: f, nsid: n, kind: NomenKind::Create, .. })
+                    if f == fingerprint && n != nsid
+            )
+    })
+}
+
+/// Pubkeys, other than `pubkey`, of name events for `name` published after the newest one in
+/// the index.
+async fn relays(
+    config: &Config,
+    pool: &SqlitePool,
+    name: &str,
+    pubkey: XOnlyPublicKey,
+) -> anyhow::Result<Vec<String>> {
+    let since = db::last_create_event_time(pool).await?;
+    let (_keys, client) = config
+        .nostr_random_client(&[NameKind::Name.into()], RelayUse::Read)
+        .await?;
+    let events = client
+        .get_events_of(
+            vec![Filter::new()
+                .kind(NameKind::Name.into())
+                .since(Timestamp::from(since))],
+            Some(RELAY_TIMEOUT),
+        )
+        .await;
+    client.disconnect().await.ok();
+
+    let mut pubkeys: Vec<String> = events?
+        .iter()
+        .filter_map(|event| EventData::from_event(event).ok())
+        .filter(|ed| ed.name.as_ref() == name && ed.pubkey != pubkey && ed.validate().is_ok())
+        .map(|ed| ed.pubkey.to_string())
+        .collect();
+    pubkeys.sort();
+    pubkeys.dedup();
+    Ok(pubkeys)
+}
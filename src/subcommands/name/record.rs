@@ -1,29 +1,120 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, io::Read, sync::Arc};
 
-use nostr_sdk::{prelude::TagKind, EventBuilder, Tag};
+use anyhow::bail;
+use nostr_sdk::{prelude::TagKind, Event, EventBuilder, EventId, Tag, Timestamp};
+use sqlx::SqlitePool;
 
 use crate::{
     config::{Cli, Config, NameRecordSubcomand},
-    subcommands::get_keys,
-    util::{NameKind, NsidBuilder},
+    db,
+    error::NomenError,
+    subcommands::{outbox, Signer},
+    util::{records_from_json, Hash160, NameKind, NsidBuilder},
 };
 
-pub async fn record(config: &Config, record_data: &NameRecordSubcomand) -> anyhow::Result<()> {
-    let keys = get_keys(&record_data.privkey)?;
+pub async fn record(
+    config: &Config,
+    pool: &SqlitePool,
+    record_data: &NameRecordSubcomand,
+) -> anyhow::Result<()> {
+    // Read and checked before asking for a key, which may also come from stdin.
+    let records = read_records(record_data)?;
+    let signer = Signer::new(config, &record_data.privkey, &record_data.bunker).await?;
+    publish_records(config, pool, record_data, &records, &signer).await?;
+    Ok(())
+}
+
+/// The records of `record_data`, from its `--file` or else its "key=value" arguments.
+pub(crate) fn read_records(
+    record_data: &NameRecordSubcomand,
+) -> anyhow::Result<HashMap<String, String>> {
+    let Some(path) = &record_data.file else {
+        return Ok(record_data
+            .records
+            .iter()
+            .map(|p| p.clone().pair())
+            .collect());
+    };
+    let json = if path.as_os_str() == "-" {
+        let mut json = String::new();
+        std::io::stdin().read_to_string(&mut json)?;
+        json
+    } else {
+        std::fs::read_to_string(path).map_err(|e| {
+            NomenError::Validation(format!("Unable to read {}: {e}", path.display()))
+        })?
+    };
+    records_from_json(&json)
+}
+
+/// Sign the records event of `record_data`, with `records`, with `signer`, and send or schedule it.
+pub(crate) async fn publish_records(
+    config: &Config,
+    pool: &SqlitePool,
+    record_data: &NameRecordSubcomand,
+    records: &HashMap<String, String>,
+    signer: &Signer,
+) -> anyhow::Result<Event> {
     let name = record_data.name.as_ref();
-    let nsid = NsidBuilder::new(name, &keys.public_key()).finalize();
-    let map: HashMap<String, String> = record_data
-        .records
-        .iter()
-        .map(|p| p.clone().pair())
-        .collect();
-    let records = serde_json::to_string(&map)?;
+    let nsid = NsidBuilder::new(name, &signer.public_key()).finalize();
 
-    let event = super::name_event(keys.public_key(), &map, name)?.sign(&keys)?;
+    let anchor = if record_data.bind_anchor {
+        let fingerprint = Hash160::default()
+            .chain_update(name.as_bytes())
+            .fingerprint();
+        let anchor = db::current_anchor(pool, fingerprint, nsid)
+            .await?
+            .ok_or_else(|| {
+                NomenError::NotFound(format!(
+                    "No transaction for {name} with this key in the index, run `nomen index` first"
+                ))
+            })?;
+        Some(anchor)
+    } else {
+        None
+    };
 
-    let (_keys, client) = config.nostr_random_client().await?;
-    let event_id = client.send_event(event).await?;
-    println!("Sent event {event_id}");
+    config.grammar().validate(name)?;
+    let mut unsigned = super::name_event(
+        signer.public_key(),
+        records,
+        name,
+        &config.record_limits(),
+        anchor.as_ref(),
+    )?;
+    if let Some(at) = &record_data.at {
+        if at.unix_timestamp() <= Timestamp::now().as_i64() {
+            bail!(NomenError::Validation(format!("{at} is not in the future")));
+        }
+        // Dated at the scheduled time, so it replaces any records published before then.
+        unsigned.created_at = Timestamp::from(at.unix_timestamp() as u64);
+    }
+    if let Some(expires) = &record_data.expires {
+        let expiration = unsigned.created_at.as_i64() + expires.as_secs();
+        unsigned
+            .tags
+            .push(Tag::Expiration(Timestamp::from(expiration as u64)));
+    }
+    unsigned.id = EventId::new(
+        &unsigned.pubkey,
+        unsigned.created_at,
+        &unsigned.kind,
+        &unsigned.tags,
+        &unsigned.content,
+    );
+    let event = signer.sign(unsigned).await?;
 
-    Ok(())
+    if let Some(at) = &record_data.at {
+        outbox::schedule(pool, &event, at.unix_timestamp()).await?;
+        println!(
+            "Scheduled event {} for {at}, it will be sent by `nomen server` then.",
+            event.id
+        );
+    } else if outbox::publish(config, pool, &event).await? {
+        println!("Sent event {}", event.id);
+    } else {
+        println!("Queued event {}", event.id);
+    }
+
+    Ok(event)
 }
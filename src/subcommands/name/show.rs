@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use sqlx::SqlitePool;
+
+use crate::{
+    config::{Config, NameShowSubcommand},
+    db,
+    error::NomenError,
+    util::{self, tag_print},
+};
+
+/// Print the owner and records of a name, from the index or its history with `--at`.
+pub async fn show(
+    config: &Config,
+    pool: &SqlitePool,
+    args: &NameShowSubcommand,
+) -> anyhow::Result<()> {
+    let name = args.name.as_ref();
+    let (pubkey, since, records) = match args.at {
+        Some(moment) => {
+            let history = util::records_at(config, pool, name, moment).await?;
+            (history.pubkey, history.owned_since, history.records)
+        }
+        None => {
+            let details = db::name_details(pool, name)
+                .await?
+                .ok_or_else(|| NomenError::NotFound(format!("{name} is not in the index")))?;
+            (details.pubkey, details.blockheight, Some(details.records))
+        }
+    };
+
+    tag_print("Name", name);
+    if let Some(moment) = args.at {
+        tag_print("At", &moment.to_string());
+    }
+    tag_print("Owner", &pubkey);
+    tag_print("Owned since", &format!("height {since}"));
+    let records: BTreeMap<String, String> = match records {
+        Some(records) => serde_json::from_str(&records)?,
+        None => BTreeMap::new(),
+    };
+    if records.is_empty() {
+        tag_print("Records", "none");
+    }
+    for (key, value) in records {
+        println!("{key}={value}");
+    }
+    Ok(())
+}
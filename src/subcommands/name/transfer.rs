@@ -1,42 +1,124 @@
 use anyhow::bail;
 use bitcoincore_rpc::RawTx;
-use nostr_sdk::{prelude::TagKind, EventBuilder, Keys, Tag};
+use nostr_sdk::{prelude::TagKind, Event, EventBuilder, Keys, Tag, UnsignedEvent};
+use secp256k1::XOnlyPublicKey;
 
 use crate::{
-    config::{Cli, Config, NameTransferSubcommand},
+    config::{Cli, Config, NameTransferFinalizeSubcommand, NameTransferSignSubcommand, RelayUse},
     db,
+    error::NomenError,
+    subcommands::EventData,
     util::{check_name_availability, tag_print, Hash160, NameKind, NomenKind, Nsid, NsidBuilder},
 };
 
+use super::Signer;
+
 #[derive(serde::Serialize)]
-struct CmdOutput {
+pub(crate) struct SignOutput {
+    pub nsid: String,
+    nsid_bech32: String,
+    event: String,
+}
+
+#[derive(serde::Serialize)]
+struct FinalizeOutput {
     nsid: String,
+    nsid_bech32: String,
     unsigned_tx: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signed_tx: Option<String>,
     event: String,
 }
 
-pub async fn transfer(config: &Config, args: &NameTransferSubcommand) -> anyhow::Result<()> {
-    let name = args.name.as_ref();
-    let keys = super::get_keys(&args.privkey)?;
-    validate(config, args, &keys).await?;
+/// Sign the handover event, as the current owner.
+pub async fn sign(config: &Config, args: &NameTransferSignSubcommand) -> anyhow::Result<()> {
+    let signer = Signer::new(config, &args.privkey, &args.bunker).await?;
+    let output = handover(config, args, &signer).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        tag_print("Nsid", &output.nsid);
+        tag_print("Nsid (bech32)", &output.nsid_bech32);
+        tag_print("Event", &output.event);
+    }
+
+    Ok(())
+}
+
+/// Sign the handover event of `args.name` to `args.pubkey` with `signer`, the current owner.
+pub(crate) async fn handover(
+    config: &Config,
+    args: &NameTransferSignSubcommand,
+    signer: &Signer,
+) -> anyhow::Result<SignOutput> {
+    if args.validate {
+        super::check_owner(config, args.name.as_ref(), &signer.public_key()).await?;
+    }
+    let nsid = NsidBuilder::new(args.name.as_ref(), &args.pubkey).finalize();
+
+    let event = signer
+        .sign(create_event(nsid, signer.public_key(), args))
+        .await?;
+    if args.broadcast {
+        let (_k, nostr) = config
+            .nostr_random_client(&[event.kind], RelayUse::Write)
+            .await?;
+        nostr.send_event(event.clone()).await?;
+        log::info!("Nost event transmitted");
+    }
+
+    let output = SignOutput {
+        nsid: nsid.to_string(),
+        nsid_bech32: nsid.to_bech32(),
+        event: serde_json::to_string(&event)?,
+    };
+
+    if let Some(path) = &args.output {
+        std::fs::write(path, &output.event)?;
+    }
+
+    Ok(output)
+}
+
+/// Check the handover event, and add the transfer output to the transaction, as the new owner.
+pub async fn finalize(
+    config: &Config,
+    args: &NameTransferFinalizeSubcommand,
+) -> anyhow::Result<()> {
+    let event = super::parse_event(&args.event)?;
+    let ed = check_handover(&event)?;
+    if args.validate {
+        super::check_owner(config, ed.name.as_ref(), &ed.pubkey).await?;
+    }
+
     let mut psbt = super::parse_psbt(&args.psbt)?;
-    let nsid = NsidBuilder::new(name, &args.pubkey).finalize();
     let fingerprint = Hash160::default()
-        .chain_update(name.as_bytes())
+        .chain_update(ed.name.as_ref().as_bytes())
         .fingerprint();
+    super::insert_outputs(&mut psbt, fingerprint, ed.nsid, NomenKind::Transfer, None)?;
 
-    super::insert_outputs(&mut psbt, fingerprint, nsid, NomenKind::Transfer)?;
-
-    let event = create_event(nsid, &keys, args)?;
     if args.broadcast {
-        let (_k, nostr) = config.nostr_random_client().await?;
+        let (_k, nostr) = config
+            .nostr_random_client(&[event.kind], RelayUse::Write)
+            .await?;
         nostr.send_event(event.clone()).await?;
         log::info!("Nost event transmitted");
     }
 
-    let output = CmdOutput {
-        nsid: nsid.to_string(),
-        unsigned_tx: psbt.to_string(),
+    let unsigned_tx = psbt.to_string();
+    let signed_tx = if args.hwi.hwi {
+        psbt = super::hwi::sign(config, &args.hwi, &psbt).await?;
+        Some(super::hwi::finalize(config, &psbt, args.hwi.broadcast_tx).await?)
+    } else {
+        None
+    };
+
+    let output = FinalizeOutput {
+        nsid: ed.nsid.to_string(),
+        nsid_bech32: ed.nsid.to_bech32(),
+        unsigned_tx,
+        signed_tx,
         event: serde_json::to_string(&event)?,
     };
 
@@ -44,7 +126,11 @@ pub async fn transfer(config: &Config, args: &NameTransferSubcommand) -> anyhow:
         println!("{}", serde_json::to_string(&output)?);
     } else {
         tag_print("Nsid", &output.nsid);
+        tag_print("Nsid (bech32)", &output.nsid_bech32);
         tag_print("Unsigned Tx", &output.unsigned_tx);
+        if let Some(signed_tx) = &output.signed_tx {
+            tag_print("Signed Tx", signed_tx);
+        }
         tag_print("Event", &output.event);
     }
 
@@ -57,10 +143,10 @@ pub async fn transfer(config: &Config, args: &NameTransferSubcommand) -> anyhow:
 
 fn create_event(
     nsid: Nsid,
-    keys: &Keys,
-    args: &NameTransferSubcommand,
-) -> Result<nostr_sdk::Event, anyhow::Error> {
-    let event = EventBuilder::new(
+    pubkey: XOnlyPublicKey,
+    args: &NameTransferSignSubcommand,
+) -> UnsignedEvent {
+    EventBuilder::new(
         NameKind::Transfer.into(),
         args.pubkey.to_string(),
         &[
@@ -71,27 +157,52 @@ fn create_event(
             ),
         ],
     )
-    .to_event(keys)?;
-    Ok(event)
+    .to_unsigned_event(pubkey)
 }
 
-async fn validate(
-    config: &Config,
-    args: &NameTransferSubcommand,
-    keys: &Keys,
-) -> anyhow::Result<()> {
-    if args.validate {
-        let conn = config.sqlite().await?;
-        match db::name_owner(&conn, args.name.as_ref()).await? {
-            Some(pk) if keys.public_key() != pk => {
-                bail!("The specified key does not own the domain")
-            }
-            Some(_) => {}
-            None => {
-                bail!("That name does not exist")
-            }
-        }
+/// A handover event must be a signed transfer event whose nsid is derived from the new owner.
+pub(crate) fn check_handover(event: &Event) -> anyhow::Result<EventData> {
+    if !matches!(NameKind::try_from(event.kind), Ok(NameKind::Transfer)) {
+        bail!(NomenError::Validation(
+            "Handover event is not a transfer event".into()
+        ));
     }
+    event
+        .verify()
+        .map_err(|e| NomenError::Validation(format!("Invalid handover signature: {e}")))?;
+    let ed = EventData::from_event(event)?;
+    ed.validate()?;
+    Ok(ed)
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_handover() {
+        let old = Keys::generate();
+        let new = Keys::generate();
+        let nsid = NsidBuilder::new("smith", &new.public_key()).finalize();
+        let handover = |nsid: Nsid| {
+            EventBuilder::new(
+                NameKind::Transfer.into(),
+                new.public_key().to_string(),
+                &[
+                    Tag::Identifier(nsid.to_string()),
+                    Tag::Generic(TagKind::Custom("nom".to_owned()), vec!["smith".into()]),
+                ],
+            )
+            .to_event(&old)
+            .unwrap()
+        };
+
+        let ed = check_handover(&handover(nsid)).unwrap();
+        assert_eq!(ed.pubkey, old.public_key());
+        assert_eq!(ed.nsid, nsid);
+
+        // The nsid must be derived from the new owner, not the old one.
+        let wrong = NsidBuilder::new("smith", &old.public_key()).finalize();
+        assert!(check_handover(&handover(wrong)).is_err());
+    }
 }
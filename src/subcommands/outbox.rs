@@ -0,0 +1,169 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
+
+use nostr_sdk::{Event, Filter, Kind};
+use sqlx::SqlitePool;
+use time::{macros::format_description, OffsetDateTime};
+use yansi::Paint;
+
+use crate::{
+    config::{Config, RelayUse},
+    db,
+};
+
+/// Seconds before retrying an event after its first failed attempt, doubled after each one.
+pub const RETRY_BASE: i64 = 30;
+
+/// Longest wait between attempts, in seconds.
+const RETRY_MAX: i64 = 60 * 60;
+
+/// How long relays have to return the events just sent to them.
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Seconds to wait after the failed attempt number `attempts` (counting from 1).
+fn retry_delay(attempts: i64) -> i64 {
+    let doublings = (attempts - 1).clamp(0, 20) as u32;
+    (RETRY_BASE << doublings).min(RETRY_MAX)
+}
+
+/// Result of one flush of the outbox.
+pub struct Flushed {
+    /// Ids of the events acknowledged by a relay.
+    pub sent: Vec<String>,
+    /// Number of attempted events left in the outbox.
+    pub pending: usize,
+}
+
+/// Send the queued events to the relays, only those due for a retry with `due_only`. Events
+/// scheduled for later are never sent early.
+///
+/// An event is acknowledged once a relay returns it when asked for its id, and is then removed
+/// from the outbox. The others are retried later with exponential backoff.
+pub async fn flush(config: &Config, pool: &SqlitePool, due_only: bool) -> anyhow::Result<Flushed> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let mut queued = db::outbox(pool, due_only).await?;
+    queued.retain(|e| e.publish_at.unwrap_or_default() <= now);
+    let mut flushed = Flushed {
+        sent: vec![],
+        pending: 0,
+    };
+    if queued.is_empty() {
+        return Ok(flushed);
+    }
+
+    let acknowledged = send(config, &queued).await;
+    for event in queued {
+        let error = match &acknowledged {
+            Ok(ids) if ids.contains(&event.event_id) => {
+                db::outbox_sent(pool, &event.event_id).await?;
+                flushed.sent.push(event.event_id);
+                continue;
+            }
+            Ok(_) => "No relay acknowledged the event".to_string(),
+            Err(err) => err.to_string(),
+        };
+        let retry_in = retry_delay(event.attempts + 1);
+        log::warn!(
+            "Event {} not sent ({error}), retrying in {retry_in}s",
+            event.event_id
+        );
+        db::outbox_failed(pool, &event.event_id, &error, retry_in).await?;
+        flushed.pending += 1;
+    }
+    Ok(flushed)
+}
+
+/// Send `queued` to the relays routed for each event's kind, and return the ids of those the
+/// relays have stored.
+async fn send(config: &Config, queued: &[db::OutboxEvent]) -> anyhow::Result<HashSet<String>> {
+    let mut by_kind: BTreeMap<u64, Vec<Event>> = BTreeMap::new();
+    for queued in queued {
+        let event = Event::from_json(&queued.event)?;
+        by_kind.entry(event.kind.as_u64()).or_default().push(event);
+    }
+
+    let mut stored = HashSet::new();
+    for (kind, events) in by_kind {
+        let (_keys, client) = config
+            .nostr_random_client(&[Kind::from(kind)], RelayUse::Write)
+            .await?;
+        for event in &events {
+            client.send_event(event.clone()).await?;
+        }
+        let ids = events.iter().map(|e| e.id.to_hex()).collect();
+        let acknowledged = client
+            .get_events_of(vec![Filter::new().ids(ids)], Some(ACK_TIMEOUT))
+            .await;
+        client.disconnect().await.ok();
+        stored.extend(acknowledged?.into_iter().map(|e| e.id.to_hex()));
+    }
+    Ok(stored)
+}
+
+/// Queue `event` to be sent by the server's outbox flusher once `at` has passed.
+pub async fn schedule(pool: &SqlitePool, event: &Event, at: i64) -> anyhow::Result<()> {
+    db::schedule_event(pool, event, Some(at)).await
+}
+
+/// Queue `event`, and make a first attempt to send it. Returns whether a relay acknowledged it.
+pub async fn publish(config: &Config, pool: &SqlitePool, event: &Event) -> anyhow::Result<bool> {
+    db::queue_event(pool, event).await?;
+    let flushed = flush(config, pool, true).await?;
+    let sent = flushed.sent.contains(&event.id.to_hex());
+    if !sent {
+        eprintln!(
+            "No relay acknowledged event {}, it is queued and will be retried by the server or `nomen outbox flush`.",
+            event.id
+        );
+    }
+    Ok(sent)
+}
+
+pub async fn flush_cmd(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+    let flushed = flush(config, pool, false).await?;
+    for event_id in &flushed.sent {
+        println!("Sent {}", Paint::yellow(event_id));
+    }
+    println!(
+        "{} sent, {} still queued.",
+        flushed.sent.len(),
+        flushed.pending
+    );
+    Ok(())
+}
+
+pub async fn list(pool: &SqlitePool) -> anyhow::Result<()> {
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+    for queued in db::outbox(pool, false).await? {
+        let next = OffsetDateTime::from_unix_timestamp(queued.next_attempt_at)?.format(format)?;
+        if queued.attempts == 0 && queued.publish_at.is_some() {
+            println!("{}\tscheduled\tat {next}", Paint::yellow(&queued.event_id));
+            continue;
+        }
+        println!(
+            "{}\t{} attempts\tnext {next}",
+            Paint::yellow(&queued.event_id),
+            queued.attempts
+        );
+        if let Some(error) = &queued.last_error {
+            println!("  {error}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), 30);
+        assert_eq!(retry_delay(2), 60);
+        assert_eq!(retry_delay(4), 240);
+        assert_eq!(retry_delay(8), RETRY_MAX);
+        assert_eq!(retry_delay(1000), RETRY_MAX);
+    }
+}
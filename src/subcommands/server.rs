@@ -1,35 +1,120 @@
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
+use anyhow::{bail, Context};
 use askama_axum::IntoResponse;
 use axum::{
-    http::StatusCode,
+    extract::ConnectInfo,
+    http::{header, HeaderName, HeaderValue, Request, StatusCode},
+    middleware::{self, Next},
+    response::Response,
     routing::{get, post},
-    Router,
+    Extension, Router,
 };
 use sqlx::SqlitePool;
-use tokio::time::{interval, MissedTickBehavior};
+use tokio::{
+    sync::watch,
+    time::{interval, MissedTickBehavior},
+};
+use tower_http::{
+    compression::CompressionLayer,
+    trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer},
+    LatencyUnit,
+};
+use tracing::Level;
 
 use crate::{
-    config::{Cli, Config, ServerSubcommand},
+    config::{BindAddr, Cli, Config, ServerSubcommand},
+    db,
+    error::NomenError,
     subcommands,
+    util::{request_id, with_request_id, RecordScript},
 };
 
 use self::site::ErrorTemplate;
 
-pub struct WebError(anyhow::Error, Option<StatusCode>);
+/// Header carrying the id the server gave a request.
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// An error page or API error: the error, its status if not found by classifying the error, and
+/// any extra response headers.
+pub struct WebError(anyhow::Error, Option<StatusCode>, Vec<(HeaderName, String)>);
 
 impl WebError {
     pub fn not_found(err: anyhow::Error) -> WebError {
-        WebError(err, Some(StatusCode::NOT_FOUND))
+        WebError(err, Some(StatusCode::NOT_FOUND), vec![])
+    }
+
+    pub fn bad_request(err: anyhow::Error) -> WebError {
+        WebError(err, Some(StatusCode::BAD_REQUEST), vec![])
+    }
+
+    pub fn unauthorized(message: &str) -> WebError {
+        WebError(
+            anyhow::anyhow!(message.to_string()),
+            Some(StatusCode::UNAUTHORIZED),
+            vec![],
+        )
+    }
+
+    pub fn gone(err: anyhow::Error) -> WebError {
+        WebError(err, Some(StatusCode::GONE), vec![])
+    }
+
+    pub fn too_many_requests() -> WebError {
+        WebError(
+            anyhow::anyhow!("Too many requests, try again later"),
+            Some(StatusCode::TOO_MANY_REQUESTS),
+            vec![],
+        )
+    }
+
+    pub fn unavailable(err: anyhow::Error, retry_after: u64) -> WebError {
+        WebError(err, Some(StatusCode::SERVICE_UNAVAILABLE), vec![])
+            .with_header(header::RETRY_AFTER, retry_after.to_string())
+    }
+
+    pub fn with_header(mut self, name: HeaderName, value: String) -> WebError {
+        self.2.push((name, value));
+        self
+    }
+
+    /// The status the error is answered with.
+    pub fn status(&self) -> StatusCode {
+        self.1.unwrap_or_else(|| {
+            NomenError::classify(&self.0)
+                .map(|e| e.status_code())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+        })
     }
 }
 
 impl IntoResponse for WebError {
     fn into_response(self) -> askama_axum::Response {
-        ErrorTemplate {
+        let template = ErrorTemplate {
+            lang: i18n::Lang::current(),
+            branding: Default::default(),
             message: self.0.to_string(),
+            request_id: request_id(),
+        };
+        let status = self.status();
+        if status.is_server_error() {
+            log::error!("Request failed: {:#}", self.0);
         }
-        .into_response()
+        let mut response = (status, template).into_response();
+        for (name, value) in self.2 {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        response
     }
 }
 
@@ -38,14 +123,141 @@ where
     E: Into<anyhow::Error>,
 {
     fn from(err: E) -> Self {
-        Self(err.into(), None)
+        Self(err.into(), None, vec![])
+    }
+}
+
+/// Give each request a random id. Every line logged while handling the request includes it, and it
+/// is returned in the `X-Request-Id` header and on error pages, so users reporting a failed request
+/// can give operators an id to look for.
+async fn tag_request<B>(request: Request<B>, next: Next<B>) -> Response {
+    let id = uuid::Uuid::new_v4().to_string();
+    let mut response = with_request_id(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
     }
+    response
 }
 
 #[derive(Clone)]
 pub struct AppState {
-    config: Config,
+    config: watch::Receiver<Config>,
     pool: SqlitePool,
+    limiter: Arc<RateLimiter>,
+    script: Option<Arc<RecordScript>>,
+}
+
+impl AppState {
+    /// The current config, including any settings reloaded since the server started.
+    fn config(&self) -> Config {
+        self.config.borrow().clone()
+    }
+
+    /// The records of a name as served, and whether they were inherited from its parents
+    /// because it has none of its own.
+    async fn records(
+        &self,
+        details: &db::NameDetails,
+    ) -> anyhow::Result<(HashMap<String, String>, bool)> {
+        let mut records: HashMap<String, String> = serde_json::from_str(&details.records)?;
+        let config = self.config();
+        let keys = config.server_inherit_records();
+        let mut inherited = false;
+        // An unapproved subname could otherwise pose as its parent by taking on its records, and a
+        // deactivated name has none on purpose.
+        if records.is_empty()
+            && !keys.is_empty()
+            && (!config.protocol_subname_approval()
+                || db::subname_approved(&self.pool, &details.name).await?)
+            && !db::name_deactivated(&self.pool, &details.name).await?
+        {
+            records = db::inherited_records(&self.pool, &details.name, &keys).await?;
+            inherited = !records.is_empty();
+        }
+        let records = self.transform_records(&details.name, records).await?;
+        Ok((records, inherited))
+    }
+
+    /// With `[server.privacy]` in private mode, owners and transaction details are only served to
+    /// requests with an API key.
+    fn check_private(&self, has_key: bool) -> Result<(), WebError> {
+        if !has_key && self.config().server_private() {
+            return Err(WebError::unauthorized(
+                "Owners and transactions need an API key on this server",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the index is more than `server.max_index_lag` blocks behind the node's `tip`. With
+    /// `server.reject_stale`, a stale index is an error instead, to retry after the next sync.
+    async fn stale(&self, tip: u64) -> Result<bool, WebError> {
+        let config = self.config();
+        let indexed = db::index_tip(&self.pool)
+            .await?
+            .map_or(0, |(height, _)| height.max(0) as u64);
+        let lag = tip.saturating_sub(indexed);
+        if lag <= config.server_max_index_lag() {
+            return Ok(false);
+        }
+        if config.server_reject_stale() {
+            return Err(WebError::unavailable(
+                anyhow::anyhow!("The index is {lag} blocks behind the node, try again later"),
+                config.server_indexer_delay(),
+            ));
+        }
+        Ok(true)
+    }
+
+    /// The records of `name` as served, after the configured record script (if any) has run.
+    async fn transform_records(
+        &self,
+        name: &str,
+        records: HashMap<String, String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        match &self.script {
+            Some(script) => {
+                let (script, name) = (script.clone(), name.to_string());
+                tokio::task::spawn_blocking(move || script.transform(&name, records)).await?
+            }
+            None => Ok(records),
+        }
+    }
+}
+
+/// Fixed window, per IP address rate limiter for endpoints that touch relays or the node.
+pub struct RateLimiter {
+    limit: AtomicU32,
+    window: Duration,
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> RateLimiter {
+        RateLimiter {
+            limit: AtomicU32::new(limit),
+            window,
+            clients: Default::default(),
+        }
+    }
+
+    pub fn set_limit(&self, limit: u32) {
+        self.limit.store(limit, Ordering::Relaxed);
+    }
+
+    /// Count a request from `ip`, returning an error if it is over the limit.
+    pub fn check(&self, ip: IpAddr) -> Result<(), WebError> {
+        let now = Instant::now();
+        let mut clients = self.clients.lock().expect("Rate limiter lock poisoned");
+        clients.retain(|_, (start, _)| now.duration_since(*start) < self.window);
+
+        let (_, count) = clients.entry(ip).or_insert((now, 0));
+        if *count >= self.limit.load(Ordering::Relaxed) {
+            return Err(WebError::too_many_requests());
+        }
+        *count += 1;
+        Ok(())
+    }
 }
 
 pub async fn start(
@@ -53,16 +265,52 @@ pub async fn start(
     conn: &SqlitePool,
     server: &ServerSubcommand,
 ) -> anyhow::Result<()> {
-    if !server.without_indexer {
-        let _indexer = tokio::spawn(indexer(config.clone(), server.clone()));
+    let limiter = Arc::new(RateLimiter::new(
+        config.server_rate_limit(),
+        Duration::from_secs(60),
+    ));
+    let script = config
+        .server_records_script()
+        .map(|path| RecordScript::load(&path, config.server_script_timeout()))
+        .transpose()?
+        .map(Arc::new);
+    config.server_trusted_proxies()?;
+    let (config_tx, config_rx) = watch::channel(config.clone());
+    let _watcher = reload::watch(config.clone(), config_tx, limiter.clone(), conn.clone())
+        .map_err(|e| log::warn!("Config file will not be reloaded: {e}"))
+        .ok();
+
+    if !config.server_without_indexer() {
+        let _indexer = tokio::spawn(indexer(config_rx.clone(), server.clone()));
     }
+    if config.server_announce_on_start() {
+        // Queued for the outbox flusher to send.
+        if let Err(e) = subcommands::announce::queue(config, conn).await {
+            log::warn!("Not announcing the server: {e}");
+        }
+    }
+    let _outbox = tokio::spawn(outbox_flusher(config_rx.clone(), conn.clone()));
+    let state = AppState {
+        config: config_rx,
+        pool: conn.clone(),
+        limiter,
+        script,
+    };
     let mut app = Router::new();
 
     if !server.without_explorer {
         app = app
             .route("/", get(site::index))
             .route("/explorer", get(site::explorer))
+            .route("/faqs", get(site::faqs))
+            .route("/fees", get(site::fees))
+            .route("/activity", get(site::activity))
+            .route("/theme.css", get(assets::theme))
+            .route("/logo", get(branding::logo))
+            .route("/assets/*file", get(assets::asset))
             .route("/explorer/:nsid", get(site::explore_nsid))
+            .route("/n/:name", get(site::redirect))
+            .route("/pubkey/:npub", get(site::owner))
             .route("/newname", get(site::new_name_form))
             .route("/newname", post(site::new_name_submit))
             .route("/updaterecords", get(site::new_records_form))
@@ -70,83 +318,864 @@ pub async fn start(
     }
 
     if !server.without_api {
-        app = app.route("/api/name", get(api::name));
+        app = app.merge(api_routes(&state));
     }
 
-    let state = AppState {
-        config: config.clone(),
-        pool: conn.clone(),
-    };
-    let app = app.with_state(state);
+    let mut app = app.with_state(state.clone());
 
-    let addr = config
-        .server_bind()
-        .expect("Server bind unconfigured")
-        .parse()?;
+    if let Some(path) = config.server_access_log() {
+        let access_log = access_log::AccessLog::open(
+            path,
+            config.server_access_log_max_size(),
+            config.server_access_log_max_files(),
+        )?;
+        app = app.layer(middleware::from_fn_with_state(
+            Arc::new(access_log),
+            access_log::record,
+        ));
+    }
+    if config.server_trace_requests() {
+        app = app.layer(
+            TraceLayer::new_for_http()
+                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                .on_response(
+                    DefaultOnResponse::new()
+                        .level(Level::INFO)
+                        .latency_unit(LatencyUnit::Millis),
+                ),
+        );
+    }
+    if config.server_compression() {
+        app = app.layer(CompressionLayer::new());
+    }
+    let app = app
+        .layer(middleware::from_fn_with_state(
+            state.config.clone(),
+            forwarded::client_addr,
+        ))
+        .layer(middleware::from_fn(i18n::negotiate))
+        .layer(middleware::from_fn(tag_request));
 
-    log::info!("Starting server on {addr}");
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .with_graceful_shutdown(elegant_departure::tokio::depart().on_termination())
-        .await?;
+    let mut listeners = activated()?;
+    if listeners.is_empty() {
+        let binds = config.server_bind()?;
+        if binds.is_empty() {
+            bail!(NomenError::Config("Server bind unconfigured".into()));
+        }
+        listeners = binds.iter().map(bind).collect::<anyhow::Result<_>>()?;
+    } else {
+        log::info!("Using {} sockets passed by systemd", listeners.len());
+    }
+    futures::future::try_join_all(listeners.into_iter().map(|l| serve(l, app.clone()))).await?;
 
     log::info!("Server shutdown complete.");
     elegant_departure::shutdown();
     Ok(())
 }
 
-async fn indexer(config: Config, server: ServerSubcommand) -> anyhow::Result<()> {
-    let mut interval = interval(Duration::from_secs(config.server_indexer_delay()));
+/// A socket the server accepts connections on.
+enum Listener {
+    Tcp(std::net::TcpListener),
+    /// A Unix domain socket, and its path if the server created it and should remove it.
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, Option<std::path::PathBuf>),
+}
+
+/// The API endpoints, with API key authentication and response signing.
+fn api_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/api/name", get(api::name))
+        .route("/api/v1/name", get(api::name_v1))
+        .route("/api/name/proof", get(api::name_proof))
+        .route("/api/name/absence", get(api::name_absence))
+        .route("/api/name/diff", get(api::name_diff))
+        .route("/api/name/similar", get(api::name_similar))
+        .route("/api/names", get(api::names))
+        .route("/api/dump", get(api::dump))
+        .route("/api/children", get(api::children))
+        .route("/api/search", get(api::search))
+        .route("/api/offers", get(api::offers))
+        .route("/api/nsid/:nsid", get(api::nsid))
+        .route("/api/nsid/:nsid/raw", get(api::nsid_raw))
+        .route("/api/stats/fees", get(api::fees))
+        .route("/api/stats/relays", get(api::relay_stats))
+        .route("/api/mirror/blockchain", get(api::mirror_blockchain))
+        .route("/api/mirror/events", get(api::mirror_events))
+        .route("/api/event", post(api::event))
+        .route("/api/tx", post(api::tx))
+        .route("/api/usage", get(api::usage))
+        .route("/rpc", post(rpc::rpc))
+        .route("/.well-known/nomen.json", get(signing::well_known))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            api::authenticate,
+        ))
+        .route_layer(middleware::from_fn_with_state(state.clone(), signing::sign))
+}
+
+fn bind(bind: &BindAddr) -> anyhow::Result<Listener> {
+    match bind {
+        BindAddr::Tcp(addr) => Ok(Listener::Tcp(
+            std::net::TcpListener::bind(addr).with_context(|| format!("Unable to bind {addr}"))?,
+        )),
+        #[cfg(unix)]
+        BindAddr::Unix(path) => {
+            // A socket left behind by a server that didn't shut down would fail the bind. One that
+            // still accepts connections belongs to a running server, and is left alone.
+            if std::fs::metadata(path)
+                .is_ok_and(|m| std::os::unix::fs::FileTypeExt::is_socket(&m.file_type()))
+            {
+                if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                    bail!(NomenError::Config(format!(
+                        "Unable to bind {}, another server is listening on it",
+                        path.display()
+                    )));
+                }
+                std::fs::remove_file(path)?;
+            }
+            let listener = tokio::net::UnixListener::bind(path)
+                .with_context(|| format!("Unable to bind {}", path.display()))?;
+            Ok(Listener::Unix(listener, Some(path.clone())))
+        }
+        #[cfg(not(unix))]
+        BindAddr::Unix(path) => bail!(NomenError::Config(format!(
+            "Unable to bind {}, Unix domain sockets are not supported on this platform",
+            path.display()
+        ))),
+    }
+}
+
+/// First descriptor passed with systemd socket activation, after stdin, stdout and stderr.
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Sockets passed by systemd socket activation (`LISTEN_PID` and `LISTEN_FDS`), if any.
+#[cfg(unix)]
+fn activated() -> anyhow::Result<Vec<Listener>> {
+    use std::os::fd::{FromRawFd, IntoRawFd};
+
+    let pid = std::env::var("LISTEN_PID").ok();
+    let fds = std::env::var("LISTEN_FDS").ok();
+    // Processes started by the server must not take the sockets for theirs.
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+    if pid.and_then(|pid| pid.parse().ok()) != Some(std::process::id()) {
+        return Ok(vec![]);
+    }
+    let fds: i32 = fds
+        .as_deref()
+        .unwrap_or("0")
+        .parse()
+        .map_err(|_| NomenError::Config(format!("Invalid LISTEN_FDS {fds:?}")))?;
+
+    let mut listeners = vec![];
+    for fd in LISTEN_FDS_START..LISTEN_FDS_START + fds {
+        // SAFETY: systemd passed these descriptors to this process to listen on, and they are
+        // taken only once, as the variables naming them are removed above.
+        let unix = unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) };
+        // Reading a Unix socket address from a TCP socket fails.
+        let listener = if unix.local_addr().is_ok() {
+            unix.set_nonblocking(true)?;
+            Listener::Unix(tokio::net::UnixListener::from_std(unix)?, None)
+        } else {
+            // SAFETY: as above, the descriptor was only released by the Unix listener.
+            Listener::Tcp(unsafe { std::net::TcpListener::from_raw_fd(unix.into_raw_fd()) })
+        };
+        listeners.push(listener);
+    }
+    Ok(listeners)
+}
+
+#[cfg(not(unix))]
+fn activated() -> anyhow::Result<Vec<Listener>> {
+    Ok(vec![])
+}
+
+/// Serve `app` on `listener` until the server is shut down.
+async fn serve(listener: Listener, app: Router) -> anyhow::Result<()> {
+    let shutdown = elegant_departure::tokio::depart().on_termination();
+    match listener {
+        Listener::Tcp(listener) => {
+            log::info!("Starting server on {}", listener.local_addr()?);
+            axum::Server::from_tcp(listener)?
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(shutdown)
+                .await?;
+        }
+        #[cfg(unix)]
+        Listener::Unix(listener, path) => {
+            match listener.local_addr()?.as_pathname() {
+                Some(path) => log::info!("Starting server on unix:{}", path.display()),
+                None => log::info!("Starting server on an unnamed Unix socket"),
+            }
+            // Clients of a Unix socket have no address. They are taken to be a reverse proxy on
+            // this machine, and share its rate limit, unless `server.trusted_proxies` has `unix`
+            // and they give the client's address in `X-Forwarded-For`.
+            let app = app
+                .layer(Extension(forwarded::UnixClient))
+                .layer(Extension(ConnectInfo(SocketAddr::from((
+                    Ipv4Addr::LOCALHOST,
+                    0,
+                )))));
+            let served = axum::Server::builder(UnixAccept(listener))
+                .serve(app.into_make_service())
+                .with_graceful_shutdown(shutdown)
+                .await;
+            if let Some(path) = path {
+                let _ = std::fs::remove_file(path);
+            }
+            served?;
+        }
+    }
+    Ok(())
+}
+
+/// Connections accepted on a Unix domain socket, for hyper.
+#[cfg(unix)]
+struct UnixAccept(tokio::net::UnixListener);
+
+#[cfg(unix)]
+impl hyper::server::accept::Accept for UnixAccept {
+    type Conn = tokio::net::UnixStream;
+    type Error = std::io::Error;
+
+    fn poll_accept(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Result<Self::Conn, Self::Error>>> {
+        self.0
+            .poll_accept(cx)
+            .map(|accepted| Some(accepted.map(|(stream, _)| stream)))
+    }
+}
+
+async fn indexer(config: watch::Receiver<Config>, server: ServerSubcommand) -> anyhow::Result<()> {
+    let mut delay = config.borrow().server_indexer_delay();
+    let mut interval = interval(Duration::from_secs(delay));
     interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
+    let mut failures = 0;
     loop {
-        match subcommands::index(&config).await {
-            Ok(_) => {}
-            Err(err) => log::error!("Indexing error: {}", err),
+        let config = config.borrow().clone();
+        if config.server_indexer_delay() != delay {
+            delay = config.server_indexer_delay();
+            interval = tokio::time::interval(Duration::from_secs(delay));
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            interval.tick().await;
+        }
+
+        match subcommands::index(&config, 1).await {
+            Ok(_) => failures = 0,
+            Err(err) => {
+                log::error!("Indexing error: {}", err);
+                failures += 1;
+                if failures == config.notifications_error_streak() {
+                    alert_error_streak(&config, failures, &err).await;
+                }
+            }
         }
         interval.tick().await;
     }
     Ok(())
 }
 
+/// Retry the events in the outbox as they become due.
+async fn outbox_flusher(config: watch::Receiver<Config>, pool: SqlitePool) {
+    let mut interval = interval(Duration::from_secs(subcommands::outbox::RETRY_BASE as u64));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        let config = config.borrow().clone();
+        match subcommands::outbox::flush(&config, &pool, true).await {
+            Ok(flushed) if !flushed.sent.is_empty() => {
+                log::info!("Sent {} queued events", flushed.sent.len())
+            }
+            Ok(_) => {}
+            Err(err) => log::error!("Outbox error: {err}"),
+        }
+    }
+}
+
+async fn alert_error_streak(config: &Config, failures: u32, err: &anyhow::Error) {
+    match subcommands::Notifier::new(config) {
+        Ok(notifier) => {
+            notifier
+                .send(&subcommands::Alert {
+                    title: "Indexing failing".into(),
+                    message: format!("{failures} index runs in a row failed, last error: {err}"),
+                })
+                .await
+        }
+        Err(e) => log::error!("Alert could not be sent: {e}"),
+    }
+}
+
+/// Client addresses given by reverse proxies.
+mod forwarded {
+    use std::net::{IpAddr, SocketAddr};
+
+    use axum::{
+        extract::{ConnectInfo, State},
+        http::{HeaderMap, HeaderName, Request},
+        middleware::Next,
+        response::Response,
+    };
+    use tokio::sync::watch;
+
+    use crate::config::{Config, TrustedProxy};
+
+    const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+    /// Marks requests over a Unix domain socket, whose clients have no address of their own.
+    #[derive(Clone, Copy)]
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub struct UnixClient;
+
+    /// Middleware replacing the peer address of requests from a trusted proxy with the client
+    /// address it gives in `X-Forwarded-For`, so rate limits and the access log see the client.
+    pub async fn client_addr<B>(
+        State(config): State<watch::Receiver<Config>>,
+        mut request: Request<B>,
+        next: Next<B>,
+    ) -> Response {
+        let proxies = config.borrow().server_trusted_proxies().unwrap_or_default();
+        if let Some(ConnectInfo(peer)) = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .copied()
+        {
+            let unix = request.extensions().get::<UnixClient>().is_some();
+            if let Some(client) = client(&proxies, peer, unix, request.headers()) {
+                request.extensions_mut().insert(ConnectInfo(client));
+            }
+        }
+        next.run(request).await
+    }
+
+    /// The client address, if the peer is a trusted proxy: reading `X-Forwarded-For` from the
+    /// end, the first address that isn't a trusted proxy itself.
+    fn client(
+        proxies: &[TrustedProxy],
+        peer: SocketAddr,
+        unix: bool,
+        headers: &HeaderMap,
+    ) -> Option<SocketAddr> {
+        let trusted = |ip: IpAddr| proxies.contains(&TrustedProxy::Ip(ip));
+        let peer_trusted = if unix {
+            proxies.contains(&TrustedProxy::Unix)
+        } else {
+            trusted(peer.ip())
+        };
+        if !peer_trusted {
+            return None;
+        }
+        let hops = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+        let mut client = None;
+        for hop in hops.into_iter().rev() {
+            let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = Some(ip);
+            if !trusted(ip) {
+                break;
+            }
+        }
+        client.map(|ip| SocketAddr::new(ip, 0))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use axum::http::HeaderValue;
+
+        use super::*;
+
+        fn headers(forwarded: &str) -> HeaderMap {
+            let mut headers = HeaderMap::new();
+            headers.insert(X_FORWARDED_FOR, HeaderValue::from_str(forwarded).unwrap());
+            headers
+        }
+
+        #[test]
+        fn test_client() {
+            let proxy: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+            let proxies = [TrustedProxy::Ip(proxy.ip())];
+            let client = |peer, unix, forwarded| {
+                super::client(&proxies, peer, unix, &headers(forwarded)).map(|a| a.to_string())
+            };
+
+            assert_eq!(
+                client(proxy, false, "203.0.113.7"),
+                Some("203.0.113.7:0".into())
+            );
+            assert_eq!(
+                client(proxy, false, "198.51.100.1, 203.0.113.7, 10.0.0.1"),
+                Some("203.0.113.7:0".into())
+            );
+            assert_eq!(client(proxy, false, "garbage"), None);
+            // Only trusted peers may set the client address.
+            let other = "192.0.2.1:4000".parse().unwrap();
+            assert_eq!(client(other, false, "203.0.113.7"), None);
+            // Unix socket clients are trusted only with `unix`.
+            assert_eq!(client(proxy, true, "203.0.113.7"), None);
+            let unix = [TrustedProxy::Unix];
+            assert_eq!(
+                super::client(&unix, proxy, true, &headers("203.0.113.7")),
+                Some("203.0.113.7:0".parse().unwrap())
+            );
+        }
+    }
+}
+
+/// Access log of the requests served.
+mod access_log {
+    use std::{
+        fs::{File, OpenOptions},
+        io::Write,
+        net::SocketAddr,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        extract::{ConnectInfo, State},
+        http::{header, HeaderMap, HeaderName, Request},
+        middleware::Next,
+        response::Response,
+    };
+    use time::{macros::format_description, OffsetDateTime};
+
+    /// Access log in the combined log format, rotated once it reaches `max_size` bytes.
+    pub struct AccessLog {
+        path: PathBuf,
+        max_size: u64,
+        max_files: usize,
+        file: Mutex<(File, u64)>,
+    }
+
+    impl AccessLog {
+        pub fn open(path: PathBuf, max_size: u64, max_files: usize) -> anyhow::Result<AccessLog> {
+            let file = append(&path)?;
+            let size = file.metadata()?.len();
+            log::info!("Writing access log to {}", path.display());
+            Ok(AccessLog {
+                path,
+                max_size,
+                max_files,
+                file: Mutex::new((file, size)),
+            })
+        }
+
+        fn write(&self, line: &str) -> std::io::Result<()> {
+            let mut file = self.file.lock().expect("Access log lock poisoned");
+            if file.1 > 0 && file.1 + line.len() as u64 > self.max_size {
+                self.rotate()?;
+                *file = (append(&self.path)?, 0);
+            }
+            file.0.write_all(line.as_bytes())?;
+            file.1 += line.len() as u64;
+            Ok(())
+        }
+
+        /// Shift `<path>.1` to `<path>.2` and so on, dropping the oldest, and move the log to `<path>.1`.
+        fn rotate(&self) -> std::io::Result<()> {
+            if self.max_files == 0 {
+                return std::fs::remove_file(&self.path);
+            }
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated(1))
+        }
+
+        fn rotated(&self, n: usize) -> PathBuf {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{n}"));
+            path.into()
+        }
+    }
+
+    fn append(path: &Path) -> std::io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    /// Middleware writing one line per request to the access log.
+    pub async fn record<B>(
+        State(access_log): State<Arc<AccessLog>>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        request: Request<B>,
+        next: Next<B>,
+    ) -> Response {
+        let time = OffsetDateTime::now_utc();
+        let request_line = format!(
+            "{} {} {:?}",
+            request.method(),
+            request.uri(),
+            request.version()
+        );
+        let referer = header_value(request.headers(), header::REFERER);
+        let user_agent = header_value(request.headers(), header::USER_AGENT);
+
+        let response = next.run(request).await;
+
+        let line = combined_line(
+            &addr,
+            time,
+            &request_line,
+            response.status().as_u16(),
+            response
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok()),
+            &referer,
+            &user_agent,
+        );
+        if let Err(e) = access_log.write(&line) {
+            log::error!("Access log write failed: {e}");
+        }
+        response
+    }
+
+    fn header_value(headers: &HeaderMap, name: HeaderName) -> String {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.replace('"', "\\\""))
+            .unwrap_or_else(|| "-".into())
+    }
+
+    fn combined_line(
+        addr: &SocketAddr,
+        time: OffsetDateTime,
+        request_line: &str,
+        status: u16,
+        bytes: Option<&str>,
+        referer: &str,
+        user_agent: &str,
+    ) -> String {
+        let format =
+            format_description!("[day]/[month repr:short]/[year]:[hour]:[minute]:[second] +0000");
+        let time = time.format(format).unwrap_or_default();
+        format!(
+            "{} - - [{time}] \"{request_line}\" {status} {} \"{referer}\" \"{user_agent}\"\n",
+            addr.ip(),
+            bytes.unwrap_or("-"),
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use time::macros::datetime;
+
+        use super::*;
+
+        #[test]
+        fn test_combined_line() {
+            let line = combined_line(
+                &"192.0.2.1:5000".parse().unwrap(),
+                datetime!(2023-06-01 13:55:36 UTC),
+                "GET /api/name?name=smith HTTP/1.1",
+                200,
+                Some("42"),
+                "-",
+                "curl/8.0",
+            );
+            assert_eq!(
+                line,
+                "192.0.2.1 - - [01/Jun/2023:13:55:36 +0000] \"GET /api/name?name=smith HTTP/1.1\" 200 42 \"-\" \"curl/8.0\"\n"
+            );
+        }
+
+        #[test]
+        fn test_rotate() {
+            let dir = std::env::temp_dir().join(format!("nomen-access-log-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("access.log");
+            let log = AccessLog::open(path.clone(), 10, 2).unwrap();
+            for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+                log.write(line).unwrap();
+            }
+
+            let read = |p: &Path| std::fs::read_to_string(p).unwrap();
+            assert_eq!(read(&path), "fourth\n");
+            assert_eq!(read(&log.rotated(1)), "third\n");
+            assert_eq!(read(&log.rotated(2)), "second\n");
+            assert!(!log.rotated(3).exists());
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+}
+
+/// Reload the config file while the server is running.
+mod reload {
+    use std::{sync::Arc, time::Duration};
+
+    use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+    use sqlx::SqlitePool;
+    use tokio::sync::{mpsc, watch};
+
+    use crate::{
+        config::{Config, ConfigFile},
+        db,
+        error::NomenError,
+    };
+
+    use super::RateLimiter;
+
+    /// Watch the config file, and publish a new config to `sender` each time it changes. The
+    /// settings reloaded are added to the audit log. The watcher stops when the returned value is
+    /// dropped.
+    pub fn watch(
+        config: Config,
+        sender: watch::Sender<Config>,
+        limiter: Arc<RateLimiter>,
+        pool: SqlitePool,
+    ) -> anyhow::Result<RecommendedWatcher> {
+        let path = Config::config_file(&config.cli);
+        if !path.is_file() {
+            anyhow::bail!(NomenError::Config(format!(
+                "{} does not exist",
+                path.display()
+            )));
+        }
+        let file_name = path.file_name().map(ToOwned::to_owned);
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => ".".into(),
+        };
+
+        // Editors often replace the file instead of writing to it, so watch the directory.
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    if event
+                        .paths
+                        .iter()
+                        .any(|p| p.file_name() == file_name.as_deref())
+                    {
+                        tx.send(()).ok();
+                    }
+                }
+            })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        log::info!("Watching {} for changes", path.display());
+
+        tokio::spawn(async move {
+            while rx.recv().await.is_some() {
+                // A save usually fires several events, wait for them to settle.
+                tokio::time::sleep(Duration::from_millis(250)).await;
+                while rx.try_recv().is_ok() {}
+
+                let current = sender.borrow().clone();
+                match reload(&current) {
+                    Ok((config, changed)) => {
+                        limiter.set_limit(config.server_rate_limit());
+                        sender.send_replace(config);
+                        if !changed.is_empty() {
+                            let detail = changed.join(", ");
+                            if let Err(e) =
+                                db::audit(&pool, db::LOCAL_ACTOR, "config reload", &detail).await
+                            {
+                                log::warn!("Config reload not added to the audit log: {e}");
+                            }
+                        }
+                    }
+                    Err(e) => log::error!("Config reload failed, keeping the current config: {e}"),
+                }
+            }
+        });
+
+        Ok(watcher)
+    }
+
+    /// Read the config file again, with the settings that changed. Settings that only take effect
+    /// at startup keep their current value.
+    fn reload(current: &Config) -> anyhow::Result<(Config, Vec<&'static str>)> {
+        let path = Config::config_file(&current.cli);
+        let mut file = ConfigFile::load(&path, current.cli.profile.as_deref())?;
+        let old = &current.file;
+
+        let reloaded = [
+            ("nostr.relays", old.nostr.relays != file.nostr.relays),
+            (
+                "nostr.read_quorum",
+                old.nostr.read_quorum != file.nostr.read_quorum,
+            ),
+            ("nostr.routes", old.nostr.routes != file.nostr.routes),
+            (
+                "server.indexer_delay",
+                old.server.indexer_delay != file.server.indexer_delay,
+            ),
+            (
+                "server.rate_limit",
+                old.server.rate_limit != file.server.rate_limit,
+            ),
+            (
+                "server.confirmations",
+                old.server.confirmations != file.server.confirmations,
+            ),
+            ("server.theme", old.server.theme != file.server.theme),
+            ("server.privacy", old.server.privacy != file.server.privacy),
+            (
+                "server.branding",
+                old.server.branding != file.server.branding,
+            ),
+            ("server.links", old.server.links != file.server.links),
+            (
+                "server.redirect_interstitial",
+                old.server.redirect_interstitial != file.server.redirect_interstitial,
+            ),
+            (
+                "server.inherit_records",
+                old.server.inherit_records != file.server.inherit_records,
+            ),
+            (
+                "server.max_index_lag",
+                old.server.max_index_lag != file.server.max_index_lag,
+            ),
+            (
+                "server.reject_stale",
+                old.server.reject_stale != file.server.reject_stale,
+            ),
+            (
+                "server.signing_key",
+                old.server.signing_key != file.server.signing_key,
+            ),
+            (
+                "server.max_response_size",
+                old.server.max_response_size != file.server.max_response_size,
+            ),
+            ("indexer", old.indexer != file.indexer),
+            ("protocol", old.protocol != file.protocol),
+        ];
+        let restart = [
+            ("data", old.data != file.data),
+            ("rpc", old.rpc != file.rpc),
+            ("notifications", old.notifications != file.notifications),
+            ("network", old.network != file.network),
+            ("server.bind", old.server.bind != file.server.bind),
+            ("server.script", old.server.script != file.server.script),
+            (
+                "server.announce",
+                old.server.announce != file.server.announce,
+            ),
+            (
+                "server.without_explorer",
+                old.server.without_explorer != file.server.without_explorer,
+            ),
+            (
+                "server.without_api",
+                old.server.without_api != file.server.without_api,
+            ),
+            (
+                "server.without_indexer",
+                old.server.without_indexer != file.server.without_indexer,
+            ),
+            (
+                "server.compression",
+                old.server.compression != file.server.compression,
+            ),
+            ("server.logging", old.server.logging != file.server.logging),
+        ];
+
+        for (setting, _) in reloaded.iter().filter(|(_, changed)| *changed) {
+            log::info!("Config setting {setting} reloaded");
+        }
+        for (setting, _) in restart.iter().filter(|(_, changed)| *changed) {
+            log::warn!("Config setting {setting} changed, restart the server to apply it");
+        }
+
+        file.data = old.data.clone();
+        file.rpc = old.rpc.clone();
+        file.notifications = old.notifications.clone();
+        file.network = old.network.clone();
+        file.server.bind = old.server.bind.clone();
+        file.server.script = old.server.script.clone();
+        file.server.without_explorer = old.server.without_explorer;
+        file.server.without_api = old.server.without_api;
+        file.server.without_indexer = old.server.without_indexer;
+        file.server.compression = old.server.compression;
+        file.server.logging = old.server.logging.clone();
+
+        let changed = reloaded
+            .iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(setting, _)| *setting)
+            .collect();
+        Ok((current.with_file(file), changed))
+    }
+}
+
 mod site {
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
     use anyhow::{anyhow, bail};
     use axum::{
         extract::{rejection::FailedToDeserializeForm, Path, Query, State},
-        http::StatusCode,
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
         Form,
     };
     use axum_extra::extract::WithRejection;
     use bitcoin::{address::NetworkUnchecked, psbt::Psbt, Address, Transaction, Txid};
     use bitcoincore_rpc::RawTx;
     use itertools::Itertools;
+    use nostr_sdk::{prelude::ToBech32, Url};
     use secp256k1::XOnlyPublicKey;
     use serde::Deserialize;
     use sqlx::SqlitePool;
 
     use crate::{
-        config::{Cli, TxInfo},
-        db::{self, name_available, NameDetails},
+        config::{Cli, RelayUse, TxInfo},
+        db::{self, name_available, NameDetails, Profile},
+        error::NomenError,
         subcommands::{insert_outputs, name_event},
-        util::{check_name_availability, Hash160, KeyVal, Name, NomenKind, NsidBuilder},
+        util::{
+            check_name_availability, Hash160, KeyVal, Name, NameKind, NomenKind, NostrPk, Nsid,
+            NsidBuilder,
+        },
     };
 
-    use super::{util, AppState, WebError};
+    use super::{branding::Branding, i18n::Lang, util, AppState, WebError};
 
     #[derive(askama::Template)]
     #[template(path = "error.html")]
     pub struct ErrorTemplate {
+        pub lang: Lang,
+        pub branding: Branding,
         pub message: String,
+        pub request_id: Option<String>,
     }
 
     #[derive(askama::Template)]
     #[template(path = "index.html")]
-    pub struct IndexTemplate {}
+    pub struct IndexTemplate {
+        lang: Lang,
+        branding: Branding,
+    }
+
+    pub async fn index(lang: Lang, branding: Branding) -> IndexTemplate {
+        IndexTemplate { lang, branding }
+    }
+
+    #[derive(askama::Template)]
+    #[template(path = "faqs.html")]
+    pub struct FaqsTemplate {
+        lang: Lang,
+        branding: Branding,
+    }
 
-    pub async fn index() -> IndexTemplate {
-        IndexTemplate {}
+    pub async fn faqs(lang: Lang, branding: Branding) -> FaqsTemplate {
+        FaqsTemplate { lang, branding }
     }
 
     #[derive(Deserialize)]
@@ -157,63 +1186,286 @@ mod site {
     #[derive(askama::Template)]
     #[template(path = "explorer.html")]
     pub struct ExplorerTemplate {
+        lang: Lang,
+        branding: Branding,
         q: String,
         names: Vec<(String, String)>,
-        last_index_time: String,
+        last_index_time: Option<String>,
     }
 
     pub async fn explorer(
         State(state): State<AppState>,
         Query(query): Query<ExplorerQuery>,
+        lang: Lang,
+        branding: Branding,
     ) -> Result<ExplorerTemplate, WebError> {
         let conn = state.pool;
-        let last_index_time = db::last_index_time(&conn).await?;
-        let last_index_time = util::format_time(last_index_time)?;
+        let last_index_time = db::last_index_time(&conn)
+            .await?
+            .map(util::format_time)
+            .transpose()?;
         let q = query.q.map(|s| s.trim().to_string());
 
         Ok(ExplorerTemplate {
+            lang,
+            branding,
             q: q.clone().unwrap_or_default(),
             names: db::top_level_names(&conn, q).await?,
             last_index_time,
         })
     }
 
+    #[derive(askama::Template)]
+    #[template(path = "fees.html")]
+    pub struct FeesTemplate {
+        lang: Lang,
+        branding: Branding,
+        days: String,
+        overall: util::FeeStats,
+        chart: Option<util::FeeChart>,
+        periods: Vec<(String, util::FeeStats)>,
+    }
+
+    pub async fn fees(
+        State(state): State<AppState>,
+        Query(query): Query<util::FeesQuery>,
+        lang: Lang,
+        branding: Branding,
+    ) -> Result<FeesTemplate, WebError> {
+        let report = util::FeeReport::load(&state.pool, &query).await?;
+        let chart = util::FeeChart::new(&report.periods);
+        let periods = report
+            .periods
+            .into_iter()
+            .rev()
+            .map(|p| Ok((util::format_date(p.start)?, p.stats)))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(FeesTemplate {
+            lang,
+            branding,
+            days: report.days.to_string(),
+            overall: report.overall,
+            chart,
+            periods,
+        })
+    }
+
+    #[derive(Deserialize)]
+    pub struct ActivityQuery {
+        pub page: Option<i64>,
+    }
+
+    pub struct ActivityItem {
+        event: String,
+        name: String,
+        npub: String,
+        time: String,
+        ago: String,
+    }
+
+    #[derive(askama::Template)]
+    #[template(path = "activity.html")]
+    pub struct ActivityTemplate {
+        lang: Lang,
+        branding: Branding,
+        items: Vec<ActivityItem>,
+        page: util::Page,
+        /// With `[server.privacy]` in private mode, owners are left out.
+        private: bool,
+    }
+
+    /// Registrations, transfers and records updates, newest first.
+    pub async fn activity(
+        State(state): State<AppState>,
+        Query(query): Query<ActivityQuery>,
+        lang: Lang,
+        branding: Branding,
+    ) -> Result<ActivityTemplate, WebError> {
+        let page = util::Page::new(query.page, db::activity_count(&state.pool).await?);
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        let items = db::activity(&state.pool, util::PAGE_SIZE, page.offset())
+            .await?
+            .into_iter()
+            .map(|a| {
+                let npub = a
+                    .pubkey
+                    .parse::<XOnlyPublicKey>()
+                    .ok()
+                    .and_then(|pk| pk.to_bech32().ok())
+                    .unwrap_or(a.pubkey);
+                let (ago, count) = util::time_ago(now - a.time);
+                Ok(ActivityItem {
+                    event: lang.t(&format!("activity-{}", a.kind)),
+                    name: a.name,
+                    npub,
+                    time: util::format_time(a.time)?,
+                    ago: lang.t_count(ago, count),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(ActivityTemplate {
+            lang,
+            branding,
+            items,
+            page,
+            private: state.config().server_private(),
+        })
+    }
+
+    #[derive(Deserialize)]
+    pub struct NsidQuery {
+        pub records_page: Option<i64>,
+        pub children_page: Option<i64>,
+    }
+
     #[derive(askama::Template)]
     #[template(path = "nsid.html")]
     pub struct NsidTemplate {
+        lang: Lang,
+        branding: Branding,
+        confirmations: u32,
+        confirmed: bool,
         name: String,
-        record_keys: Vec<String>,
-        records: HashMap<String, String>,
+        /// False for a subname not approved by its parent's owner, with `protocol.subname_approval`.
+        approved: bool,
+        /// Whether the owner deactivated the name, hiding its records.
+        deactivated: bool,
+        records: Vec<(String, String)>,
+        records_page: util::Page,
         records_created_at: String,
+        /// All of the records, as pretty printed JSON.
+        records_json: String,
+        /// Nsid, name, and whether it is approved.
+        children: Vec<(String, String, bool)>,
+        children_page: util::Page,
         blockhash: String,
+        blockhash_url: Option<String>,
         blocktime: String,
         txid: String,
+        txid_url: Option<String>,
         vout: i64,
         height: i64,
+        txheight: i64,
+        size: Option<i64>,
+        vsize: Option<i64>,
+        fee: Option<i64>,
+        fee_rate: Option<String>,
+        nsid: String,
+        nsid_bech32: String,
         pubkey: String,
+        profile: Option<Profile>,
+        /// schema.org description of the name, for the page's `application/ld+json` script.
+        json_ld: String,
+        /// Names that look like this one, and how alike they are in percent.
+        similar: Vec<(String, i64)>,
+        /// Relays the current records event was fetched from.
+        sources: Vec<String>,
+        /// With `[server.privacy]` in private mode, the owner and transaction are left out.
+        private: bool,
     }
 
-    impl TryFrom<NameDetails> for NsidTemplate {
-        type Error = anyhow::Error;
+    /// Characters of a record value shown before it is cut short, with the rest expandable.
+    const RECORD_PREVIEW_CHARS: usize = 80;
 
-        fn try_from(value: NameDetails) -> Result<Self, Self::Error> {
-            let records: HashMap<String, String> = serde_json::from_str(&value.records)?;
-            let mut record_keys = records.keys().cloned().collect_vec();
-            record_keys.sort();
-            let blocktime = util::format_time(value.blocktime)?;
-            let records_created_at = util::format_time(value.records_created_at)?;
+    impl NsidTemplate {
+        /// The start of a record `value` too long to show whole, or `None` if it fits.
+        fn preview(&self, value: &str) -> Option<String> {
+            (value.chars().count() > RECORD_PREVIEW_CHARS)
+                .then(|| value.chars().take(RECORD_PREVIEW_CHARS).collect())
+        }
 
-            Ok(NsidTemplate {
+        /// A schema.org JSON-LD description of the name, its owner and all of its `records`, safe
+        /// to embed in a `<script>` element. A private page leaves out the owner and txid.
+        fn json_ld(&self, records: &[(String, String)]) -> anyhow::Result<String> {
+            let mut properties = vec![serde_json::json!({
+                "@type": "PropertyValue",
+                "propertyID": "blockHeight",
+                "value": self.height,
+            })];
+            if !self.private {
+                properties.push(serde_json::json!({
+                    "@type": "PropertyValue",
+                    "propertyID": "txid",
+                    "value": self.txid,
+                }));
+            }
+            properties.extend(records.iter().map(|(key, value)| {
+                serde_json::json!({
+                    "@type": "PropertyValue",
+                    "propertyID": "record",
+                    "name": key,
+                    "value": value,
+                })
+            }));
+            let mut json_ld = serde_json::json!({
+                "@context": "https://schema.org",
+                "@type": "Thing",
+                "name": self.name,
+                "identifier": self.nsid_bech32,
+                "additionalProperty": properties,
+            });
+            if !self.private {
+                let owner = self
+                    .pubkey
+                    .parse::<XOnlyPublicKey>()
+                    .ok()
+                    .and_then(|pk| pk.to_bech32().ok())
+                    .unwrap_or_else(|| self.pubkey.clone());
+                json_ld["owner"] = serde_json::json!({ "@type": "Person", "identifier": owner });
+            }
+            // A record value containing `</script>` must not end the script element.
+            Ok(serde_json::to_string(&json_ld)?.replace("</", "<\\/"))
+        }
+    }
+
+    impl TryFrom<NameDetails> for NsidTemplate {
+        type Error = anyhow::Error;
+
+        fn try_from(value: NameDetails) -> Result<Self, Self::Error> {
+            let blocktime = util::format_time(value.blocktime)?;
+            let records_created_at = util::format_time(value.records_created_at)?;
+            let fee_rate = value
+                .fee
+                .zip(value.vsize)
+                .filter(|(_, vsize)| *vsize > 0)
+                .map(|(fee, vsize)| format!("{:.1}", fee as f64 / vsize as f64));
+
+            Ok(NsidTemplate {
+                lang: Default::default(),
+                branding: Default::default(),
+                confirmations: 0,
+                confirmed: false,
                 name: value.name,
-                record_keys,
-                records,
+                approved: true,
+                deactivated: false,
+                records: Default::default(),
+                records_page: Default::default(),
                 records_created_at,
+                records_json: Default::default(),
+                children: Default::default(),
+                children_page: Default::default(),
                 blockhash: value.blockhash,
+                blockhash_url: None,
                 blocktime,
                 txid: value.txid,
+                txid_url: None,
                 vout: value.vout,
                 height: value.blockheight,
+                txheight: value.txheight,
+                size: value.size,
+                vsize: value.vsize,
+                fee: value.fee,
+                fee_rate,
+                nsid_bech32: value.nsid.parse::<Nsid>()?.to_bech32(),
+                nsid: value.nsid,
                 pubkey: value.pubkey,
+                profile: None,
+                json_ld: Default::default(),
+                similar: vec![],
+                sources: vec![],
+                private: false,
             })
         }
     }
@@ -221,16 +1473,199 @@ mod site {
     pub async fn explore_nsid(
         State(state): State<AppState>,
         Path(nsid): Path<String>,
+        Query(query): Query<NsidQuery>,
+        lang: Lang,
+        branding: Branding,
     ) -> Result<NsidTemplate, WebError> {
-        let conn = state.pool;
-        let details = db::name_details(&conn, &nsid).await?;
+        let config = state.config();
+        let conn = state.pool.clone();
+        let details = db::name_details(&conn, &nsid)
+            .await?
+            .ok_or_else(|| NomenError::NotFound(format!("Name {nsid} not found")))?;
+        let (confirmations, confirmed) =
+            util::confirmations(&config, &state.pool, details.blockheight).await?;
+        let confirmed = confirmed && details.status == "valid";
+
+        let raw_records: HashMap<String, String> = serde_json::from_str(&details.records)?;
+        let mut template = NsidTemplate::try_from(details)?;
+
+        let all_records: Vec<(String, String)>;
+        if state.script.is_some() {
+            // Scripted records aren't in the database, so page through them here.
+            let records = state.transform_records(&template.name, raw_records).await?;
+            all_records = records.into_iter().sorted().collect();
+            let records_page = util::Page::new(query.records_page, all_records.len() as i64);
+            template.records = all_records
+                .iter()
+                .skip(records_page.offset() as usize)
+                .take(util::PAGE_SIZE as usize)
+                .cloned()
+                .collect();
+            template.records_page = records_page;
+        } else {
+            all_records = raw_records.into_iter().sorted().collect();
+            let (_, total) = db::name_records_page(&conn, &template.name, 0, 0).await?;
+            let records_page = util::Page::new(query.records_page, total);
+            (template.records, _) = db::name_records_page(
+                &conn,
+                &template.name,
+                util::PAGE_SIZE,
+                records_page.offset(),
+            )
+            .await?;
+            template.records_page = records_page;
+        }
+
+        let (_, total) = db::child_names(&conn, &template.name, 0, 0).await?;
+        let children_page = util::Page::new(query.children_page, total);
+        let (children, _) = db::child_names(
+            &conn,
+            &template.name,
+            util::PAGE_SIZE,
+            children_page.offset(),
+        )
+        .await?;
+        let approval = config.protocol_subname_approval();
+        for (nsid, name) in children {
+            let approved = !approval || db::subname_approved(&conn, &name).await?;
+            template.children.push((nsid, name, approved));
+        }
+        template.children_page = children_page;
+        template.approved = !approval || db::subname_approved(&conn, &template.name).await?;
+        template.deactivated = db::name_deactivated(&conn, &template.name).await?;
+        template.private = config.server_private();
+        if !template.private {
+            template.profile = db::profile(&conn, &template.pubkey).await?;
+        }
+        template.blockhash_url = config.server_block_link(&template.blockhash);
+        template.txid_url = config.server_tx_link(&template.txid);
+        template.json_ld = template.json_ld(&all_records)?;
+        template.records_json =
+            serde_json::to_string_pretty(&all_records.iter().cloned().collect::<BTreeMap<_, _>>())?;
+        template.similar = db::similar_names(&conn, &template.name)
+            .await?
+            .into_iter()
+            .map(|(name, score)| (name, (score * 100.0).round() as i64))
+            .collect();
+        template.sources = db::records_event_sources(&conn, &template.name).await?;
+
+        template.lang = lang;
+        template.branding = branding;
+        template.confirmations = confirmations;
+        template.confirmed = confirmed;
+        Ok(template)
+    }
+
+    pub struct OwnerName {
+        name: String,
+        nsid: String,
+        confirmed: bool,
+        records_created_at: String,
+    }
+
+    #[derive(askama::Template)]
+    #[template(path = "owner.html")]
+    pub struct OwnerTemplate {
+        lang: Lang,
+        branding: Branding,
+        npub: String,
+        profile: Option<Profile>,
+        names: Vec<OwnerName>,
+    }
+
+    /// Names owned by a pubkey, given as an npub or hex.
+    pub async fn owner(
+        State(state): State<AppState>,
+        Path(npub): Path<String>,
+        lang: Lang,
+        branding: Branding,
+    ) -> Result<OwnerTemplate, WebError> {
+        let config = state.config();
+        if config.server_private() {
+            return Err(WebError::not_found(anyhow!("Owner pages are disabled")));
+        }
+        let pubkey: NostrPk = npub.parse().map_err(WebError::bad_request)?;
+        let pubkey: XOnlyPublicKey = pubkey.into();
+        let tip = util::block_count(&config, &state.pool).await?;
+
+        let names = db::names_for_pubkey(&state.pool, &pubkey.to_string())
+            .await?
+            .into_iter()
+            .map(|n| {
+                Ok(OwnerName {
+                    confirmed: util::confirmations_at(&config, tip, n.blockheight)?.1,
+                    records_created_at: util::format_time(n.records_created_at)?,
+                    name: n.name,
+                    nsid: n.nsid,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(OwnerTemplate {
+            lang,
+            branding,
+            npub: pubkey.to_bech32()?,
+            profile: db::profile(&state.pool, &pubkey.to_string()).await?,
+            names,
+        })
+    }
+
+    #[derive(askama::Template)]
+    #[template(path = "redirect.html")]
+    pub struct RedirectTemplate {
+        lang: Lang,
+        branding: Branding,
+        name: String,
+        url: String,
+    }
+
+    /// Redirect to the URL in a name's `WEB` record, or show a page linking to it if
+    /// `server.redirect_interstitial` is set.
+    pub async fn redirect(
+        State(state): State<AppState>,
+        Path(name): Path<String>,
+        lang: Lang,
+        branding: Branding,
+    ) -> Result<Response, WebError> {
+        let name: Name = name.parse()?;
+        let details = db::name_details(&state.pool, name.as_ref())
+            .await?
+            .ok_or_else(|| NomenError::NotFound(format!("Name {name} not found")))?;
+        let (records, _) = state.records(&details).await?;
+        let url = records
+            .into_iter()
+            .find_map(|(k, v)| k.eq_ignore_ascii_case("WEB").then_some(v))
+            .ok_or_else(|| NomenError::NotFound(format!("Name {name} has no WEB record")))?;
+        let url = web_url(&url).map_err(WebError::bad_request)?;
 
-        Ok(details.try_into()?)
+        if state.config().server_redirect_interstitial() {
+            let template = RedirectTemplate {
+                lang,
+                branding,
+                name: name.to_string(),
+                url,
+            };
+            return Ok(template.into_response());
+        }
+        Ok((StatusCode::FOUND, [(header::LOCATION, url)]).into_response())
+    }
+
+    /// Only redirect to web pages, never to `javascript:` or other schemes.
+    fn web_url(url: &str) -> anyhow::Result<String> {
+        let url = Url::parse(url)?;
+        if !matches!(url.scheme(), "http" | "https") {
+            bail!(NomenError::Validation(format!(
+                "WEB record {url} is not an http(s) URL"
+            )));
+        }
+        Ok(url.to_string())
     }
 
     #[derive(askama::Template, Default)]
     #[template(path = "newname.html")]
     pub struct NewNameTemplate {
+        lang: Lang,
+        branding: Branding,
         psbt: String,
         name: String,
         pubkey: String,
@@ -244,36 +1679,48 @@ mod site {
         pubkey: XOnlyPublicKey,
     }
 
-    pub async fn new_name_form(State(state): State<AppState>) -> Result<NewNameTemplate, WebError> {
+    pub async fn new_name_form(
+        State(state): State<AppState>,
+        lang: Lang,
+        branding: Branding,
+    ) -> Result<NewNameTemplate, WebError> {
         Ok(NewNameTemplate {
-            confirmations: state.config.confirmations()?,
+            lang,
+            branding,
+            confirmations: state.config().confirmations()?,
             ..Default::default()
         })
     }
 
     pub async fn new_name_submit(
         State(state): State<AppState>,
+        lang: Lang,
+        branding: Branding,
         WithRejection(Form(mut form), _): WithRejection<Form<NewNameForm>, WebError>,
     ) -> Result<NewNameTemplate, WebError> {
         let name: Name = form.name.parse()?;
-        check_name_availability(&state.config, form.name.as_ref()).await?;
+        check_name_availability(&state.config(), form.name.as_ref()).await?;
         let fingerprint = Hash160::default()
             .chain_update(name.as_ref().as_bytes())
             .fingerprint();
         let nsid = NsidBuilder::new(form.name.as_ref(), &form.pubkey).finalize();
         let mut psbt: Psbt = form.psbt.parse()?;
-        insert_outputs(&mut psbt, fingerprint, nsid, NomenKind::Create)?;
+        insert_outputs(&mut psbt, fingerprint, nsid, NomenKind::Create, None)?;
         Ok(NewNameTemplate {
+            lang,
+            branding,
             psbt: psbt.to_string(),
             name: form.name,
             pubkey: form.pubkey.to_string(),
-            confirmations: state.config.confirmations()?,
+            confirmations: state.config().confirmations()?,
         })
     }
 
     #[derive(askama::Template)]
     #[template(path = "updaterecords.html")]
     pub struct NewRecordsTemplate {
+        lang: Lang,
+        branding: Branding,
         name: String,
         pubkey: String,
         unsigned_event: String,
@@ -290,13 +1737,19 @@ mod site {
     pub async fn new_records_form(
         State(state): State<AppState>,
         Query(query): Query<NewRecordsQuery>,
+        lang: Lang,
+        branding: Branding,
     ) -> Result<NewRecordsTemplate, WebError> {
         let records = records_from_query(&query, &state).await?;
         Ok(NewRecordsTemplate {
+            lang,
+            branding,
             name: query.name.unwrap_or_default(),
             pubkey: query.pubkey.map(|s| s.to_string()).unwrap_or_default(),
             unsigned_event: Default::default(),
-            relays: state.config.relays(),
+            relays: state
+                .config()
+                .relays_for(&[NameKind::Name.into()], RelayUse::Write),
             records,
         })
     }
@@ -334,9 +1787,12 @@ mod site {
 
     pub async fn new_records_submit(
         State(state): State<AppState>,
+        lang: Lang,
+        branding: Branding,
         Form(form): Form<NewRecordsForm>,
     ) -> Result<NewRecordsTemplate, WebError> {
         let name: Name = form.name.parse()?;
+        state.config().grammar().validate(name.as_ref())?;
         let records = form
             .records
             .lines()
@@ -345,57 +1801,2065 @@ mod site {
             .iter()
             .map(|kv| kv.clone().pair())
             .collect::<HashMap<_, _>>();
-        let event = name_event(form.pubkey, &records, &form.name)?;
+        let event = name_event(
+            form.pubkey,
+            &records,
+            &form.name,
+            &state.config().record_limits(),
+            None,
+        )?;
         let unsigned_event = serde_json::to_string_pretty(&event)?;
         Ok(NewRecordsTemplate {
+            lang,
+            branding,
             name: form.name.to_string(),
             pubkey: form.pubkey.to_string(),
             unsigned_event,
-            relays: state.config.relays(),
+            relays: state
+                .config()
+                .relays_for(&[NameKind::Name.into()], RelayUse::Write),
             records: "KEY=value".into(),
         })
     }
+
+    #[cfg(test)]
+    mod tests {
+        use askama::Template;
+
+        use super::*;
+
+        fn name_details() -> NameDetails {
+            NameDetails {
+                nsid: "02".repeat(20),
+                blockhash: "00".repeat(32),
+                txid: "11".repeat(32),
+                blocktime: 1_700_000_000,
+                vout: 0,
+                blockheight: 800_000,
+                txheight: 1,
+                fee: None,
+                size: None,
+                vsize: None,
+                anchor_vout: None,
+                anchor_value: None,
+                anchor_script: None,
+                spent_txid: None,
+                spent_height: None,
+                status: "valid".into(),
+                name: "smith".into(),
+                records: "{}".into(),
+                pubkey: "pk".into(),
+                records_created_at: 1_700_000_000,
+            }
+        }
+
+        #[test]
+        fn test_nsid_metadata() {
+            let mut template = NsidTemplate::try_from(name_details()).unwrap();
+            let records = vec![("MOTD".to_string(), "</script><b>hi</b>".to_string())];
+            template.json_ld = template.json_ld(&records).unwrap();
+            template.similar = vec![("5mith".into(), 80)];
+
+            assert!(!template.json_ld.contains("</"));
+            let json_ld: serde_json::Value = serde_json::from_str(&template.json_ld).unwrap();
+            assert_eq!(json_ld["name"], "smith");
+            assert_eq!(json_ld["additionalProperty"][0]["value"], 800_000);
+            assert_eq!(
+                json_ld["additionalProperty"][2]["value"],
+                "</script><b>hi</b>"
+            );
+
+            let html = template.render().unwrap();
+            assert!(html.contains("<meta property=\"og:title\" content=\"smith\">"));
+            assert!(html.contains("<script type=\"application/ld+json\">{"));
+            assert!(html.contains("<a href=\"/explorer/5mith\">5mith</a> (80%)"));
+            assert!(html.contains(&"11".repeat(32)));
+
+            // A private page leaves out the owner and the transaction.
+            template.private = true;
+            template.json_ld = template.json_ld(&records).unwrap();
+            let json_ld: serde_json::Value = serde_json::from_str(&template.json_ld).unwrap();
+            assert!(json_ld.get("owner").is_none());
+            assert_eq!(json_ld["additionalProperty"][1]["name"], "MOTD");
+            let html = template.render().unwrap();
+            assert!(!html.contains(&"11".repeat(32)));
+            assert!(!html.contains("pubkey=pk"));
+        }
+
+        #[test]
+        fn test_long_record() {
+            let mut template = NsidTemplate {
+                records: vec![
+                    ("KEY".into(), "short".into()),
+                    ("NOTE".into(), "é".repeat(100)),
+                ],
+                records_json: "{\"NOTE\": \"...\"}".into(),
+                ..NsidTemplate::try_from(name_details()).unwrap()
+            };
+            assert_eq!(template.preview("short"), None);
+            assert_eq!(
+                template.preview(&"é".repeat(100)),
+                Some("é".repeat(RECORD_PREVIEW_CHARS))
+            );
+
+            let html = template.render().unwrap();
+            assert!(html.contains("<td>short</td>"));
+            assert!(html.contains(&format!(
+                "<summary>{}…</summary>{}</details>",
+                "é".repeat(RECORD_PREVIEW_CHARS),
+                "é".repeat(100)
+            )));
+            assert!(html.contains("<pre id=\"raw-records\">"));
+            assert!(html.contains("/api/nsid/0202"));
+
+            template.private = true;
+            assert!(!template.render().unwrap().contains("/api/nsid/"));
+        }
+    }
 }
 
 mod api {
-    use std::collections::HashMap;
+    use std::{
+        collections::{BTreeMap, HashMap},
+        net::SocketAddr,
+    };
 
-    use anyhow::anyhow;
+    use anyhow::{anyhow, bail};
 
     use axum::{
-        extract::{Query, State},
-        Json,
+        body::StreamBody,
+        extract::{ConnectInfo, MatchedPath, Path, Query, State},
+        http::{header, HeaderMap, HeaderName, Request, StatusCode},
+        middleware::Next,
+        response::{IntoResponse, Response},
+        Extension, Json,
+    };
+    use bitcoin::{
+        consensus::encode::serialize_hex,
+        hashes::{sha256, Hash},
+        BlockHash, Transaction, Txid,
+    };
+    use bitcoincore_rpc::RpcApi;
+    use futures::TryStreamExt;
+    use nomen_core::{api_types::*, AbsenceProof};
+    use nostr_sdk::{Event, EventId};
+    use serde::Serialize;
+
+    use crate::{
+        config::RelayUse,
+        db::{self, ApiKey},
+        error::NomenError,
+        subcommands::{apikey, EventData},
+        util::{
+            history_records, quorum_latest, records_at, replacements_filter, skeleton, typed_value,
+            Hash160, Moment, Name, NameKind, NomenTx, Nsid,
+        },
     };
-    use serde::Deserialize;
-    use sqlx::SqlitePool;
 
-    use crate::db;
+    use super::{util, AppState, WebError};
 
-    use super::{AppState, WebError};
+    /// Largest raw transaction accepted by `/api/tx`, in bytes. Matches the standardness limit.
+    const MAX_TX_SIZE: usize = 100_000;
 
-    #[derive(Deserialize)]
-    pub struct NameQuery {
-        name: String,
-    }
+    /// Header carrying an API key from `server apikey add`.
+    const API_KEY_HEADER: &str = "x-api-key";
+
+    /// Header carrying the index height a name was found missing at.
+    const INDEX_HEIGHT_HEADER: HeaderName = HeaderName::from_static("x-index-height");
 
+    /// Default and largest page size of the list endpoints.
+    const DEFAULT_LIMIT: i64 = 100;
+    const MAX_LIMIT: i64 = 1000;
+
+    /// The current records of a name, or with `at_height` or `at`, those it had at that point.
     pub async fn name(
+        Query(query): Query<NameAtQuery>,
+        State(state): State<AppState>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Response, WebError> {
+        let name: Name = query.name.parse()?;
+        let moment = match (query.at_height, query.at) {
+            (None, None) => return Ok(Json(current_name(&state, &name).await?).into_response()),
+            (Some(height), None) => Moment::Height(height),
+            (None, Some(at)) => Moment::Time(at.parse().map_err(WebError::bad_request)?),
+            (Some(_), Some(_)) => {
+                return Err(WebError::bad_request(anyhow!(
+                    "Use either at_height or at, not both"
+                )))
+            }
+        };
+        // Past records come with the owner of the time.
+        state.check_private(key.is_some())?;
+        let history = records_at(&state.config(), &state.pool, name.as_ref(), moment).await?;
+        let records = match &history.records {
+            Some(records) => serde_json::from_str(records)?,
+            None => HashMap::new(),
+        };
+        Ok(Json(NameAtResponse {
+            records,
+            pubkey: history.pubkey,
+            owned_since: history.owned_since,
+            event_id: history.event_id,
+            records_created_at: history.created_at,
+        })
+        .into_response())
+    }
+
+    /// Records added, removed and changed between two records events of a name, from its history.
+    pub async fn name_diff(
+        Query(query): Query<NameDiffQuery>,
+        State(state): State<AppState>,
+    ) -> Result<Json<NameDiffResponse>, WebError> {
+        let name: Name = query.name.parse()?;
+        let from = EventId::from_hex(&query.from).map_err(|e| WebError::bad_request(e.into()))?;
+        let to = EventId::from_hex(&query.to).map_err(|e| WebError::bad_request(e.into()))?;
+        let (from, from_created_at) = history_records(&state.pool, name.as_ref(), from).await?;
+        let (to, to_created_at) = history_records(&state.pool, name.as_ref(), to).await?;
+        Ok(Json(NameDiffResponse {
+            from_created_at,
+            to_created_at,
+            diff: RecordsDiff::between(&from, &to),
+        }))
+    }
+
+    /// Indexed names that look like `name`, as found by the indexer's last run.
+    pub async fn name_similar(
+        Query(query): Query<NameQuery>,
+        State(state): State<AppState>,
+    ) -> Result<Json<NameSimilarResponse>, WebError> {
+        let name: Name = query.name.to_lowercase().parse()?;
+        let similar = db::similar_names(&state.pool, name.as_ref())
+            .await?
+            .into_iter()
+            .map(|(name, score)| SimilarName { name, score })
+            .collect();
+        Ok(Json(NameSimilarResponse {
+            skeleton: skeleton(name.as_ref()),
+            name: name.to_string(),
+            similar,
+        }))
+    }
+
+    pub async fn current_name(state: &AppState, name: &Name) -> Result<NameResponse, WebError> {
+        let details = name_details(state, name).await?;
+        let (records, inherited) = state.records(&details).await?;
+        let config = state.config();
+        let tip = util::block_count(&config, &state.pool).await?;
+        let stale = state.stale(tip).await?;
+        let (confirmations, confirmed) = util::confirmations_at(&config, tip, details.blockheight)?;
+        let confirmed = confirmed && details.status == "valid";
+
+        Ok(NameResponse {
+            records,
+            confirmations,
+            confirmed,
+            status: details.status,
+            anchor_spent: details.anchor_vout.map(|_| details.spent_txid.is_some()),
+            inherited,
+            deactivated: db::name_deactivated(&state.pool, &details.name).await?,
+            stale,
+        })
+    }
+
+    /// Details of `name`. Gone if its claim was conflicted out, rather than just not found.
+    ///
+    /// Not found answers carry the index height in `X-Index-Height`, and may be cached until the
+    /// indexer's next run. `/api/name/absence` proves them against a signed checkpoint.
+    async fn name_details(state: &AppState, name: &Name) -> Result<db::NameDetails, WebError> {
+        let pool = &state.pool;
+        if let Some(details) = db::name_details(pool, name.as_ref()).await? {
+            return Ok(details);
+        }
+        let fingerprint = Hash160::default()
+            .chain_update(name.as_ref().as_bytes())
+            .fingerprint();
+        if let Some(reason) = db::revoked_claim(pool, &hex::encode(fingerprint)).await? {
+            return Err(WebError::gone(
+                NomenError::NotFound(format!("The claim of {name} was revoked: {reason}")).into(),
+            ));
+        }
+        let height = db::index_tip(pool).await?.map_or(0, |(height, _)| height);
+        Err(WebError::not_found(
+            NomenError::NotFound(format!("{name} is not registered at height {height}")).into(),
+        )
+        .with_header(INDEX_HEIGHT_HEADER, height.to_string())
+        .with_header(
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", state.config().server_indexer_delay()),
+        ))
+    }
+
+    /// Proof that `name` is not registered, against the latest checkpoint from `nomen index checkpoint`.
+    pub async fn name_absence(
         Query(name): Query<NameQuery>,
         State(state): State<AppState>,
-    ) -> Result<Json<HashMap<String, String>>, WebError> {
-        let conn = state.pool;
-        let name = db::name_records(&conn, name.name).await?;
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Json<NameAbsenceResponse>, WebError> {
+        if key.is_none() {
+            state.limiter.check(addr.ip())?;
+        }
+        let name: Name = name.name.parse()?;
+        if db::name_details(&state.pool, name.as_ref())
+            .await?
+            .is_some()
+        {
+            return Err(WebError::bad_request(anyhow!("{name} is registered")));
+        }
+        let Some((height, event, names)) = db::checkpoint(&state.pool).await? else {
+            return Err(WebError::not_found(
+                NomenError::NotFound("This server has no names checkpoint".into()).into(),
+            ));
+        };
+        let proof = AbsenceProof::new(&names, name.as_ref()).ok_or_else(|| {
+            WebError::bad_request(anyhow!(
+                "{name} was registered at the checkpoint height {height}"
+            ))
+        })?;
 
-        name.map(Json)
-            .ok_or_else(|| WebError::not_found(anyhow!("Not found")))
+        Ok(Json(NameAbsenceResponse {
+            checkpoint: serde_json::from_str(&event)?,
+            proof,
+            index_height: db::index_tip(&state.pool).await?.map(|(height, _)| height),
+        }))
     }
-}
 
-mod util {
-    use time::{macros::format_description, OffsetDateTime};
+    pub async fn name_v1(
+        Query(name): Query<NameQuery>,
+        State(state): State<AppState>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Json<NameV1Response>, WebError> {
+        state.check_private(key.is_some())?;
+        let name: Name = name.name.parse()?;
+        let details = name_details(&state, &name).await?;
+        let (records, inherited) = state.records(&details).await?;
+        let deactivated = db::name_deactivated(&state.pool, &details.name).await?;
+        let config = state.config();
+        let tip = util::block_count(&config, &state.pool).await?;
+        let stale = state.stale(tip).await?;
+        let (confirmations, confirmed) = util::confirmations_at(&config, tip, details.blockheight)?;
+        let confirmed = confirmed && details.status == "valid";
 
-    pub fn format_time(timestamp: i64) -> anyhow::Result<String> {
-        let dt = OffsetDateTime::from_unix_timestamp(timestamp)?;
-        let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
-        Ok(dt.format(format)?)
+        Ok(Json(NameV1Response {
+            name: details.name,
+            nsid_bech32: details.nsid.parse::<Nsid>()?.to_bech32(),
+            nsid: details.nsid,
+            pubkey: details.pubkey,
+            records: records
+                .into_iter()
+                .map(|(k, v)| {
+                    let v = typed_value(&k, v);
+                    (k, v)
+                })
+                .collect(),
+            confirmations,
+            confirmed,
+            status: details.status,
+            anchor_spent: details.anchor_vout.map(|_| details.spent_txid.is_some()),
+            inherited,
+            deactivated,
+            stale,
+        }))
+    }
+
+    /// Merkle proof, OP_RETURN and records event of a name. `nomen_core::verify_proof` checks the
+    /// event against the OP_RETURN. Answers 304 if the proof has the request's `If-None-Match` ETag.
+    pub async fn name_proof(
+        Query(name): Query<NameQuery>,
+        State(state): State<AppState>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        key: Option<Extension<ApiKey>>,
+        headers: HeaderMap,
+    ) -> Result<Response, WebError> {
+        let name: Name = name.name.parse()?;
+        let (details, event) = proof_event(&state, &name, key.is_some(), addr).await?;
+
+        // The proof only changes with the anchoring transaction or the records event.
+        let etag = format!(
+            "\"{}\"",
+            sha256::Hash::hash(
+                format!("{}:{}:{event}", details.blockhash, details.txid).as_bytes()
+            )
+        );
+        if headers
+            .get(header::IF_NONE_MATCH)
+            .is_some_and(|tag| tag.as_bytes() == etag.as_bytes())
+        {
+            return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+        }
+
+        if key.is_none() && state.config().nostr_read_quorum() == 1 {
+            state.limiter.check(addr.ip())?;
+        }
+        let proof = proof(&state, details, &event).await?;
+        Ok(([(header::ETAG, etag)], Json(proof)).into_response())
+    }
+
+    /// Details of `name` and the records event its proof serves. With `nostr.read_quorum` above
+    /// one, the event is the one the relays agree on, which counts against the rate limit of
+    /// requests without an API key.
+    pub async fn proof_event(
+        state: &AppState,
+        name: &Name,
+        has_key: bool,
+        addr: SocketAddr,
+    ) -> Result<(db::NameDetails, String), WebError> {
+        state.check_private(has_key)?;
+        let details = name_details(state, name).await?;
+        if details.status != "valid" {
+            return Err(WebError::not_found(
+                NomenError::NotFound(format!(
+                    "The transaction of {name} is pending after a reorg, try again later"
+                ))
+                .into(),
+            ));
+        }
+        let mut event = db::name_event_json(&state.pool, &details.name, &details.pubkey)
+            .await?
+            .ok_or_else(|| {
+                WebError::not_found(
+                    NomenError::NotFound(format!("No signed records event for {name}")).into(),
+                )
+            })?;
+        if state.config().nostr_read_quorum() > 1 {
+            if !has_key {
+                state.limiter.check(addr.ip())?;
+            }
+            event = quorum_event(state, name, &event).await?;
+        }
+        Ok((details, event))
+    }
+
+    /// The proof of a name with the records `event`, from the node.
+    pub async fn proof(
+        state: &AppState,
+        details: db::NameDetails,
+        event: &str,
+    ) -> Result<NameProofResponse, WebError> {
+        let stale = state
+            .stale(util::block_count(&state.config(), &state.pool).await?)
+            .await?;
+        let txid: Txid = details.txid.parse()?;
+        let blockhash: BlockHash = details.blockhash.parse()?;
+        let (tx, txoutproof) = state
+            .config()
+            .rpc()?
+            .run(move |client| {
+                Ok((
+                    client.get_raw_transaction(&txid, Some(&blockhash))?,
+                    client.get_tx_out_proof(&[txid], Some(&blockhash))?,
+                ))
+            })
+            .await?;
+        let output = tx.output.get(details.vout as usize).ok_or_else(|| {
+            NomenError::Rpc(format!("Transaction {txid} has no output {}", details.vout))
+        })?;
+        let op_return = output
+            .script_pubkey
+            .as_bytes()
+            .get(2..)
+            .map(hex::encode)
+            .ok_or_else(|| {
+                NomenError::Rpc(format!(
+                    "Output {txid}:{} has no OP_RETURN data",
+                    details.vout
+                ))
+            })?;
+
+        Ok(NameProofResponse {
+            name: details.name,
+            nsid: details.nsid,
+            blockhash: details.blockhash,
+            blockheight: details.blockheight,
+            txid: details.txid,
+            txoutproof: hex::encode(txoutproof),
+            tx: serialize_hex(&tx),
+            vout: details.vout,
+            op_return,
+            event: serde_json::from_str(event)?,
+            stale,
+        })
+    }
+
+    /// The latest records event replacing `indexed` that `nostr.read_quorum` relays agree on,
+    /// instead of trusting whichever relay the indexer heard it from.
+    async fn quorum_event(
+        state: &AppState,
+        name: &Name,
+        indexed: &str,
+    ) -> Result<String, WebError> {
+        let indexed = Event::from_json(indexed)?;
+        let config = state.config();
+        let latest = quorum_latest(&config, replacements_filter(&indexed))
+            .await
+            .map_err(|err| WebError::unavailable(err, config.server_indexer_delay()))?;
+        match latest {
+            Some(event) => Ok(event.as_json()),
+            None => Err(WebError::not_found(
+                NomenError::NotFound(format!(
+                    "The relays agree there is no records event for {name}"
+                ))
+                .into(),
+            )),
+        }
+    }
+
+    /// Every indexed name, optionally filtered by `q`.
+    pub async fn names(
+        Query(query): Query<ListQuery>,
+        State(state): State<AppState>,
+    ) -> Result<Json<ListResponse<NameItem>>, WebError> {
+        let q = query.name.clone().unwrap_or_default();
+        list(&state, db::NameFilter::Search(&q), &query).await
+    }
+
+    /// Names directly below `name`.
+    pub async fn children(
+        Query(query): Query<ListQuery>,
+        State(state): State<AppState>,
+    ) -> Result<Json<ListResponse<NameItem>>, WebError> {
+        let name: Name = query
+            .name
+            .as_deref()
+            .ok_or_else(|| anyhow!("Missing name"))
+            .and_then(str::parse)
+            .map_err(WebError::bad_request)?;
+        list(&state, db::NameFilter::Children(name.as_ref()), &query).await
+    }
+
+    /// Names containing `q`.
+    pub async fn search(
+        Query(query): Query<ListQuery>,
+        State(state): State<AppState>,
+    ) -> Result<Json<ListResponse<NameItem>>, WebError> {
+        let q = query
+            .name
+            .clone()
+            .filter(|q| !q.is_empty())
+            .ok_or_else(|| WebError::bad_request(anyhow!("Missing q")))?;
+        list(&state, db::NameFilter::Search(&q), &query).await
+    }
+
+    pub async fn list(
+        state: &AppState,
+        filter: db::NameFilter<'_>,
+        query: &ListQuery,
+    ) -> Result<Json<ListResponse<NameItem>>, WebError> {
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        // Fetch one extra row to find out if there is another page.
+        let (mut names, total) =
+            db::list_names(&state.pool, filter, query.cursor.as_deref(), limit + 1).await?;
+        let mut more = names.len() as i64 > limit;
+        names.truncate(limit as usize);
+        let mut items: Vec<_> = names
+            .into_iter()
+            .map(|(nsid, name)| NameItem { name, nsid })
+            .collect();
+        more |= fit_response(&mut items, state.config().server_max_response_size())?;
+        let next_cursor = items.last().filter(|_| more).map(|item| item.name.clone());
+
+        Ok(Json(ListResponse {
+            items,
+            next_cursor,
+            total,
+        }))
+    }
+
+    /// Drop items from the end of a page until it serializes to at most `max_size` bytes, keeping
+    /// at least one item. Returns whether any were dropped.
+    fn fit_response<T: Serialize>(items: &mut Vec<T>, max_size: usize) -> anyhow::Result<bool> {
+        // Brackets, then each item and its separating comma.
+        let mut size = 2;
+        for (i, item) in items.iter().enumerate() {
+            size += serde_json::to_vec(item)?.len() + 1;
+            if size > max_size && i > 0 {
+                items.truncate(i);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Names sent ahead of a slow client, before reading further from the database.
+    const DUMP_BUFFER: usize = 64;
+
+    /// Every indexed name with its records, as newline-delimited JSON, for full syncs.
+    /// Rows are read from the database as the client consumes them.
+    pub async fn dump(
+        State(state): State<AppState>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Response, WebError> {
+        state.check_private(key.is_some())?;
+        if key.is_none() {
+            state.limiter.check(addr.ip())?;
+        }
+
+        let (sender, receiver) = tokio::sync::mpsc::channel::<anyhow::Result<String>>(DUMP_BUFFER);
+        let pool = state.pool.clone();
+        tokio::spawn(async move {
+            let mut names = db::export_names(&pool);
+            loop {
+                let line = match names.try_next().await {
+                    Ok(Some(row)) => row.to_json().map(|json| format!("{json}\n")),
+                    Ok(None) => break,
+                    Err(e) => Err(e.into()),
+                };
+                let failed = line.is_err();
+                // Stop reading once the client has gone away, or after an error.
+                if sender.send(line).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let lines = futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|line| (line, receiver))
+        })
+        .map_err(|e| {
+            log::error!("Dump error: {e}");
+            e
+        });
+        Ok((
+            [(header::CONTENT_TYPE, "application/x-ndjson")],
+            StreamBody::new(lines),
+        )
+            .into_response())
+    }
+
+    /// Open offers to sell a name, optionally only those for `name`.
+    pub async fn offers(
+        Query(query): Query<OffersQuery>,
+        State(state): State<AppState>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Json<Vec<db::OpenOffer>>, WebError> {
+        state.check_private(key.is_some())?;
+        if let Some(name) = &query.name {
+            name.parse::<Name>().map_err(WebError::bad_request)?;
+        }
+        let offers = db::open_offers(&state.pool, query.name.as_deref()).await?;
+        Ok(Json(offers))
+    }
+
+    /// Raw `blockchain` rows, for `index --mirror`.
+    pub async fn mirror_blockchain(
+        Query(query): Query<MirrorQuery>,
+        State(state): State<AppState>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Json<MirrorBlockchainResponse>, WebError> {
+        state.check_private(key.is_some())?;
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let tip = db::index_tip(&state.pool).await?;
+        let mut items = db::blockchain_page(&state.pool, query.from, query.cursor, limit).await?;
+        let mut more = items.len() as i64 == limit;
+        more |= fit_response(&mut items, state.config().server_max_response_size())?;
+        let next_cursor = items.last().filter(|_| more).map(|row| row.id);
+
+        Ok(Json(MirrorBlockchainResponse {
+            items,
+            next_cursor,
+            index_height: tip.as_ref().map(|(height, _)| *height),
+            index_blockhash: tip.map(|(_, hash)| hash),
+        }))
+    }
+
+    /// Raw name or transfer events, for `index --mirror`.
+    pub async fn mirror_events(
+        Query(query): Query<MirrorQuery>,
+        State(state): State<AppState>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Json<MirrorEventsResponse>, WebError> {
+        state.check_private(key.is_some())?;
+        let kind = match query.kind.as_deref() {
+            Some("name") => NameKind::Name,
+            Some("transfer") => NameKind::Transfer,
+            _ => {
+                return Err(WebError::bad_request(anyhow!(
+                    "kind must be name or transfer"
+                )))
+            }
+        };
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let mut events =
+            db::raw_events_page(&state.pool, kind, query.from, query.cursor, limit).await?;
+        let mut more = events.len() as i64 == limit;
+        more |= fit_response(&mut events, state.config().server_max_response_size())?;
+        let next_cursor = events.last().filter(|_| more).map(|(rowid, _)| *rowid);
+
+        Ok(Json(MirrorEventsResponse {
+            items: events.into_iter().map(|(_, event)| event).collect(),
+            next_cursor,
+        }))
+    }
+
+    /// Relay a signed name or transfer event to the configured relays.
+    pub async fn event(
+        State(state): State<AppState>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        key: Option<Extension<ApiKey>>,
+        Json(event): Json<Event>,
+    ) -> Result<Json<EventResponse>, WebError> {
+        if key.is_none() {
+            state.limiter.check(addr.ip())?;
+        }
+        validate_event(&event).map_err(WebError::bad_request)?;
+
+        let (_keys, client) = state
+            .config()
+            .nostr_random_client(&[event.kind], RelayUse::Write)
+            .await?;
+        let event_id = client.send_event(event).await?;
+        client.disconnect().await?;
+        log::info!("Relayed event {event_id} for {}", addr.ip());
+        audit(&state, &key, addr, "api event", &event_id.to_hex()).await;
+
+        Ok(Json(EventResponse {
+            event_id: event_id.to_hex(),
+        }))
+    }
+
+    /// Add an action taken through the API to the audit log, by its API key or else the client's
+    /// address. The action is already done, so a failure is only logged.
+    async fn audit(
+        state: &AppState,
+        key: &Option<Extension<ApiKey>>,
+        addr: SocketAddr,
+        action: &str,
+        detail: &str,
+    ) {
+        let actor = match key {
+            Some(Extension(key)) => format!("key:{}", key.name),
+            None => format!("ip:{}", addr.ip()),
+        };
+        if let Err(e) = db::audit(&state.pool, &actor, action, detail).await {
+            log::warn!("{action} {detail} not added to the audit log: {e}");
+        }
+    }
+
+    fn validate_event(event: &Event) -> anyhow::Result<()> {
+        NameKind::try_from(event.kind)?;
+        event.verify()?;
+        EventData::from_event(event)?.validate()?;
+        Ok(())
+    }
+
+    /// Submit a signed transaction containing a Nomen output to the node.
+    pub async fn tx(
+        State(state): State<AppState>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        key: Option<Extension<ApiKey>>,
+        Json(request): Json<TxRequest>,
+    ) -> Result<Json<TxResponse>, WebError> {
+        if key.is_none() {
+            state.limiter.check(addr.ip())?;
+        }
+        let tx = decode_tx(&request.tx).map_err(WebError::bad_request)?;
+
+        let txid = state.config().rpc()?.send_raw_transaction(tx).await?;
+        log::info!("Broadcast transaction {txid} for {}", addr.ip());
+        audit(&state, &key, addr, "api tx", &txid.to_string()).await;
+
+        Ok(Json(TxResponse {
+            txid: txid.to_string(),
+        }))
+    }
+
+    /// Check the API key of a request, if it has one, and count the request against it.
+    /// Requests with a key are not rate limited.
+    pub async fn authenticate<B>(
+        State(state): State<AppState>,
+        mut request: Request<B>,
+        next: Next<B>,
+    ) -> Result<Response, WebError> {
+        let Some(key) = request.headers().get(API_KEY_HEADER) else {
+            return Ok(next.run(request).await);
+        };
+        let key_hash = apikey::hash(key.to_str().unwrap_or_default());
+        let key = db::active_api_key(&state.pool, &key_hash)
+            .await?
+            .ok_or_else(|| WebError::unauthorized("Invalid or revoked API key"))?;
+
+        let endpoint = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|p| p.as_str().to_string())
+            .unwrap_or_else(|| request.uri().path().to_string());
+        db::record_api_usage(&state.pool, key.id, &endpoint).await?;
+
+        request.extensions_mut().insert(key);
+        Ok(next.run(request).await)
+    }
+
+    /// Requests made with the caller's API key, per day and endpoint.
+    pub async fn usage(
+        State(state): State<AppState>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Json<UsageResponse>, WebError> {
+        let Some(Extension(key)) = key else {
+            return Err(WebError::unauthorized(
+                "An API key is required, in the X-Api-Key header",
+            ));
+        };
+        let usage = db::api_usage(&state.pool, key.id).await?;
+        Ok(Json(UsageResponse {
+            name: key.name,
+            created_at: key.created_at,
+            total: usage.iter().map(|u| u.requests).sum(),
+            usage,
+        }))
+    }
+
+    /// Fee rate percentiles of name claims confirmed in the last `days` days (default 90), overall
+    /// and per `interval` (`day` or `week`).
+    pub async fn fees(
+        State(state): State<AppState>,
+        Query(query): Query<util::FeesQuery>,
+    ) -> Result<Json<FeesResponse>, WebError> {
+        let report = util::FeeReport::load(&state.pool, &query).await?;
+        Ok(Json(FeesResponse {
+            days: report.days,
+            interval: report.interval,
+            overall: report.overall,
+            periods: report.periods,
+        }))
+    }
+
+    /// A claim by nsid (hex or bech32), with where its events came from.
+    pub async fn nsid(
+        Path(nsid): Path<String>,
+        State(state): State<AppState>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Json<NsidResponse>, WebError> {
+        state.check_private(key.is_some())?;
+        let details = db::name_details(&state.pool, &nsid)
+            .await?
+            .ok_or_else(|| NomenError::NotFound(format!("Name {nsid} not found")))?;
+        let events = db::name_event_sources(&state.pool, &details.name).await?;
+        Ok(Json(NsidResponse {
+            nsid: details.nsid,
+            name: details.name,
+            pubkey: details.pubkey,
+            txid: details.txid,
+            blockheight: details.blockheight,
+            status: details.status,
+            events,
+        }))
+    }
+
+    /// The signed records event of a claim by nsid (hex or bech32), as a file to download.
+    pub async fn nsid_raw(
+        Path(nsid): Path<String>,
+        State(state): State<AppState>,
+        key: Option<Extension<ApiKey>>,
+    ) -> Result<Response, WebError> {
+        state.check_private(key.is_some())?;
+        let details = db::name_details(&state.pool, &nsid)
+            .await?
+            .ok_or_else(|| NomenError::NotFound(format!("Name {nsid} not found")))?;
+        let event = db::name_event_json(&state.pool, &details.name, &details.pubkey)
+            .await?
+            .ok_or_else(|| {
+                NomenError::NotFound(format!("No signed records event for {}", details.name))
+            })?;
+        Ok((
+            [
+                (header::CONTENT_TYPE, "application/json".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.json\"", details.name),
+                ),
+            ],
+            event,
+        )
+            .into_response())
+    }
+
+    /// Indexed events supplied by each relay, and how many no other relay supplied.
+    pub async fn relay_stats(
+        State(state): State<AppState>,
+    ) -> Result<Json<Vec<db::RelayContribution>>, WebError> {
+        Ok(Json(db::relay_contributions(&state.pool).await?))
+    }
+
+    fn decode_tx(hex_tx: &str) -> anyhow::Result<Transaction> {
+        let hex_tx = hex_tx.trim();
+        if hex_tx.len() > MAX_TX_SIZE * 2 {
+            bail!(NomenError::Validation(format!(
+                "Transaction is larger than {MAX_TX_SIZE} bytes"
+            )));
+        }
+        let bytes = hex::decode(hex_tx)?;
+        let tx: Transaction = bitcoin::consensus::deserialize(&bytes)?;
+
+        let nomen_outputs = tx
+            .output
+            .iter()
+            .filter(|o| o.script_pubkey.is_op_return())
+            .filter_map(|o| o.script_pubkey.as_bytes().get(2..))
+            .filter(|b| NomenTx::try_from(*b).is_ok())
+            .count();
+        if nomen_outputs == 0 {
+            bail!(NomenError::Validation(
+                "Transaction does not contain a valid Nomen output".into()
+            ));
+        }
+
+        Ok(tx)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{net::Ipv4Addr, sync::Arc, time::Duration};
+
+        use axum::{body::Body, Router};
+        use clap::Parser;
+        use nostr_sdk::Keys;
+        use sqlx::SqlitePool;
+        use tokio::sync::watch;
+        use tower::ServiceExt;
+
+        use super::{
+            super::{api_routes, RateLimiter},
+            *,
+        };
+        use crate::{
+            config::{Cli, Config, ConfigFile, PrivacyConfig, PrivacyMode},
+            subcommands::index_name,
+        };
+
+        #[test]
+        fn test_fit_response() {
+            // Each item serializes to 5 bytes, so 3 items are 2 + 3 * 6 = 20 bytes.
+            let mut items = vec!["abc"; 5];
+            assert!(fit_response(&mut items, 20).unwrap());
+            assert_eq!(items.len(), 3);
+            assert!(!fit_response(&mut items, 20).unwrap());
+
+            // A single item larger than the limit is still sent.
+            assert!(fit_response(&mut items, 1).unwrap());
+            assert_eq!(items.len(), 1);
+        }
+
+        #[test]
+        fn test_decode_tx_size() {
+            // Surrounding whitespace does not count towards the limit.
+            let padded = format!("  {}\n", "00".repeat(MAX_TX_SIZE));
+            let err = decode_tx(&padded).unwrap_err().to_string();
+            assert!(!err.contains("larger than"), "{err}");
+
+            let oversize = "00".repeat(MAX_TX_SIZE + 1);
+            let err = decode_tx(&oversize).unwrap_err().to_string();
+            assert!(err.contains("larger than"), "{err}");
+        }
+
+        /// The API of a server with the `file` config, serving the index in `pool`.
+        fn app(file: ConfigFile, pool: SqlitePool) -> Router {
+            let config = Config::new(
+                Cli::parse_from(["nomen", "--data", ":memory:", "server", "--without-indexer"]),
+                file,
+            );
+            let state = AppState {
+                config: watch::channel(config).1,
+                pool,
+                limiter: Arc::new(RateLimiter::new(100, Duration::from_secs(60))),
+                script: None,
+            };
+            api_routes(&state)
+                .with_state(state)
+                .layer(Extension(ConnectInfo(SocketAddr::from((
+                    Ipv4Addr::LOCALHOST,
+                    0,
+                )))))
+        }
+
+        /// The JSON response to a GET request for `uri`, which must succeed.
+        async fn get<T: serde::de::DeserializeOwned>(app: &Router, uri: &str) -> T {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        }
+
+        #[tokio::test]
+        async fn test_children() {
+            let pool = db::scratch().await.unwrap();
+            for (height, name) in [
+                (100, "smith"),
+                (101, "bob.smith"),
+                (102, "al.bob.smith"),
+                (103, "carol.smith"),
+            ] {
+                index_name(&pool, &Keys::generate(), name, &HashMap::new(), height).await;
+            }
+            let app = app(ConfigFile::default(), pool);
+            let names = |page: &ListResponse<NameItem>| {
+                page.items
+                    .iter()
+                    .map(|i| i.name.clone())
+                    .collect::<Vec<_>>()
+            };
+
+            let page: ListResponse<NameItem> = get(&app, "/api/children?name=smith&limit=1").await;
+            assert_eq!(names(&page), ["bob.smith"]);
+            assert_eq!(page.total, 2);
+            let cursor = page.next_cursor.unwrap();
+            let page: ListResponse<NameItem> = get(
+                &app,
+                &format!("/api/children?name=smith&limit=1&cursor={cursor}"),
+            )
+            .await;
+            assert_eq!(names(&page), ["carol.smith"]);
+            assert_eq!(page.next_cursor, None);
+
+            let page: ListResponse<NameItem> = get(&app, "/api/children?name=bob.smith").await;
+            assert_eq!(names(&page), ["al.bob.smith"]);
+        }
+
+        #[tokio::test]
+        async fn test_inherited() {
+            let pool = db::scratch().await.unwrap();
+            let smith = HashMap::from([("LN".to_string(), "smith@ln".to_string())]);
+            index_name(&pool, &Keys::generate(), "smith", &smith, 100).await;
+            index_name(&pool, &Keys::generate(), "bob.smith", &HashMap::new(), 101).await;
+            let mut file = ConfigFile::default();
+            file.server.inherit_records = Some(vec!["LN".into()]);
+            let app = app(file, pool);
+
+            let bob: NameResponse = get(&app, "/api/name?name=bob.smith").await;
+            assert!(bob.inherited);
+            assert_eq!(bob.records, smith);
+            let smith: NameResponse = get(&app, "/api/name?name=smith").await;
+            assert!(!smith.inherited);
+        }
+
+        #[tokio::test]
+        async fn test_private() {
+            let mut file = ConfigFile::default();
+            file.server.privacy = Some(PrivacyConfig {
+                mode: Some(PrivacyMode::Private),
+            });
+            let pool = db::scratch().await.unwrap();
+            db::insert_api_key(&pool, "test", &apikey::hash("secret"))
+                .await
+                .unwrap();
+            let app = app(file, pool);
+
+            let nsid = "00".repeat(20);
+            // Past the check, unknown names and nsids are not found.
+            let endpoints = [
+                ("/api/v1/name?name=smith".to_string(), StatusCode::NOT_FOUND),
+                (
+                    "/api/name?name=smith&at=2024-01-01T00:00:00Z".into(),
+                    StatusCode::NOT_FOUND,
+                ),
+                ("/api/name/proof?name=smith".into(), StatusCode::NOT_FOUND),
+                ("/api/dump".into(), StatusCode::OK),
+                (format!("/api/nsid/{nsid}"), StatusCode::NOT_FOUND),
+                (format!("/api/nsid/{nsid}/raw"), StatusCode::NOT_FOUND),
+                ("/api/mirror/blockchain".into(), StatusCode::OK),
+                ("/api/mirror/events?kind=name".into(), StatusCode::OK),
+                ("/api/offers".into(), StatusCode::OK),
+            ];
+            for (uri, status) in endpoints {
+                let request = |key: Option<&str>| {
+                    let mut request = Request::get(&uri);
+                    if let Some(key) = key {
+                        request = request.header(API_KEY_HEADER, key);
+                    }
+                    request.body(Body::empty()).unwrap()
+                };
+                let response = app.clone().oneshot(request(None)).await.unwrap();
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+                let response = app.clone().oneshot(request(Some("secret"))).await.unwrap();
+                assert_eq!(response.status(), status, "{uri}");
+            }
+        }
+    }
+}
+
+/// JSON-RPC 2.0 at `/rpc`, for wallets that prefer it to the HTTP API. Each method answers like
+/// its endpoint: `resolve` like `/api/name`, `list_names` like `/api/names`, `get_proof` like
+/// `/api/name/proof`, and `get_status` with how far the index is behind the node.
+mod rpc {
+    use std::net::SocketAddr;
+
+    use axum::{
+        extract::{ConnectInfo, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        Extension, Json,
+    };
+    use nomen_core::api_types::{
+        ListQuery, NameQuery, RpcError, RpcRequest, RpcResponse, StatusResponse,
+    };
+    use serde::de::DeserializeOwned;
+    use serde_json::Value;
+
+    use crate::{
+        db::{self, ApiKey},
+        util::Name,
+    };
+
+    use super::{api, util, AppState, WebError};
+
+    /// Largest number of calls in a batch.
+    const MAX_BATCH: usize = 100;
+
+    const PARSE_ERROR: i64 = -32700;
+    const INVALID_REQUEST: i64 = -32600;
+    const METHOD_NOT_FOUND: i64 = -32601;
+    const INVALID_PARAMS: i64 = -32602;
+
+    fn error(code: i64, message: impl ToString) -> RpcError {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    impl From<WebError> for RpcError {
+        fn from(err: WebError) -> Self {
+            let status = err.status();
+            if status.is_server_error() {
+                log::error!("RPC call failed: {:#}", err.0);
+            }
+            error(status.as_u16() as i64, err.0)
+        }
+    }
+
+    /// Answer a call or a batch of calls. Notifications are run but not answered, so a batch of
+    /// only notifications gets an empty response.
+    pub async fn rpc(
+        State(state): State<AppState>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        key: Option<Extension<ApiKey>>,
+        body: String,
+    ) -> Response {
+        let caller = Caller {
+            state,
+            addr,
+            has_key: key.is_some(),
+        };
+        let request: Value = match serde_json::from_str(&body) {
+            Ok(request) => request,
+            Err(e) => return Json(answer(Value::Null, Err(error(PARSE_ERROR, e)))).into_response(),
+        };
+        match request {
+            Value::Array(calls) if calls.is_empty() || calls.len() > MAX_BATCH => {
+                let message = format!("A batch has 1 to {MAX_BATCH} calls");
+                Json(answer(Value::Null, Err(error(INVALID_REQUEST, message)))).into_response()
+            }
+            Value::Array(calls) => {
+                let mut responses = vec![];
+                for call in calls {
+                    responses.extend(caller.call(call).await);
+                }
+                if responses.is_empty() {
+                    return StatusCode::NO_CONTENT.into_response();
+                }
+                Json(responses).into_response()
+            }
+            call => match caller.call(call).await {
+                Some(response) => Json(response).into_response(),
+                None => StatusCode::NO_CONTENT.into_response(),
+            },
+        }
+    }
+
+    fn answer(id: Value, result: Result<Value, RpcError>) -> RpcResponse {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        RpcResponse {
+            jsonrpc: "2.0".into(),
+            result,
+            error,
+            id,
+        }
+    }
+
+    struct Caller {
+        state: AppState,
+        addr: SocketAddr,
+        has_key: bool,
+    }
+
+    impl Caller {
+        /// The response to one call, unless it is a notification.
+        async fn call(&self, call: Value) -> Option<RpcResponse> {
+            let request: RpcRequest = match serde_json::from_value(call) {
+                Ok(request) => request,
+                Err(e) => return Some(answer(Value::Null, Err(error(INVALID_REQUEST, e)))),
+            };
+            let result = if request.jsonrpc == "2.0" {
+                self.run(&request.method, request.params).await
+            } else {
+                Err(error(INVALID_REQUEST, "jsonrpc must be 2.0"))
+            };
+            request.id.map(|id| answer(id, result))
+        }
+
+        async fn run(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+            let state = &self.state;
+            let result = match method {
+                "resolve" => {
+                    let query: NameQuery = parse_params(params, &["name"])?;
+                    let name: Name = query.name.parse().map_err(WebError::bad_request)?;
+                    serde_json::to_value(api::current_name(state, &name).await?)
+                }
+                "list_names" => {
+                    let query: ListQuery = parse_params(params, &["name", "cursor", "limit"])?;
+                    let q = query.name.clone().unwrap_or_default();
+                    let page = api::list(state, db::NameFilter::Search(&q), &query).await?;
+                    serde_json::to_value(page.0)
+                }
+                "get_proof" => {
+                    let query: NameQuery = parse_params(params, &["name"])?;
+                    let name: Name = query.name.parse().map_err(WebError::bad_request)?;
+                    let (details, event) =
+                        api::proof_event(state, &name, self.has_key, self.addr).await?;
+                    if !self.has_key && state.config().nostr_read_quorum() == 1 {
+                        state.limiter.check(self.addr.ip())?;
+                    }
+                    serde_json::to_value(api::proof(state, details, &event).await?)
+                }
+                "get_status" => serde_json::to_value(status(state).await?),
+                _ => return Err(error(METHOD_NOT_FOUND, format!("No method {method}"))),
+            };
+            result.map_err(|e| WebError::from(e).into())
+        }
+    }
+
+    /// Parameters by name, or by position in the order of `names`.
+    fn parse_params<T: DeserializeOwned>(params: Value, names: &[&str]) -> Result<T, RpcError> {
+        let params = match params {
+            Value::Null => Value::Object(Default::default()),
+            Value::Array(values) if values.len() <= names.len() => Value::Object(
+                names
+                    .iter()
+                    .map(|name| name.to_string())
+                    .zip(values)
+                    .collect(),
+            ),
+            params => params,
+        };
+        serde_json::from_value(params).map_err(|e| error(INVALID_PARAMS, e))
+    }
+
+    async fn status(state: &AppState) -> Result<StatusResponse, WebError> {
+        let config = state.config();
+        let tip = db::index_tip(&state.pool).await?;
+        let node_height = util::block_count(&config, &state.pool).await?;
+        let indexed = tip
+            .as_ref()
+            .map_or(0, |(height, _)| (*height).max(0) as u64);
+        Ok(StatusResponse {
+            index_height: tip.as_ref().map(|(height, _)| *height),
+            index_blockhash: tip.map(|(_, hash)| hash),
+            node_height,
+            stale: node_height.saturating_sub(indexed) > config.server_max_index_lag(),
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use nomen_core::api_types::NameDiffQuery;
+
+        use super::*;
+
+        #[test]
+        fn test_parse_params() {
+            let by_name: NameQuery =
+                parse_params(serde_json::json!({"name": "smith"}), &["name"]).unwrap();
+            let by_position: NameQuery =
+                parse_params(serde_json::json!(["smith"]), &["name"]).unwrap();
+            assert_eq!(by_name, by_position);
+
+            let list: ListQuery = parse_params(Value::Null, &["name", "cursor", "limit"]).unwrap();
+            assert_eq!(list, ListQuery::default());
+
+            let err = parse_params::<NameDiffQuery>(serde_json::json!(["a", "b"]), &["name"])
+                .unwrap_err();
+            assert_eq!(err.code, INVALID_PARAMS);
+        }
+    }
+}
+
+/// Sign API responses with HTTP Message Signatures (RFC 9421) and `server.signing_key`, so a
+/// client can authenticate a response that came through a cache or proxy.
+mod signing {
+    use axum::{
+        body::{self, Full, HttpBody},
+        extract::State,
+        http::{header, HeaderValue, Request},
+        middleware::Next,
+        response::{IntoResponse, Response},
+        Json,
+    };
+    use bitcoin::{
+        base64,
+        hashes::{sha256, Hash},
+    };
+    use nomen_core::api_types::WellKnown;
+    use nostr_sdk::{prelude::ToBech32, Keys};
+    use secp256k1::{Message, Secp256k1};
+
+    use super::{AppState, WebError};
+
+    /// Label of the signature in the `Signature-Input` and `Signature` headers.
+    const LABEL: &str = "nomen";
+
+    /// Signature algorithm: BIP-340 Schnorr over the SHA-256 of the signature base.
+    const ALGORITHM: &str = "bip340-schnorr-sha256";
+
+    /// Covered components: the response status and body, and the path and query of the request.
+    const COMPONENTS: [&str; 4] = [
+        "\"@status\"",
+        "\"content-digest\"",
+        "\"@path\";req",
+        "\"@query\";req",
+    ];
+
+    pub async fn sign<B>(
+        State(state): State<AppState>,
+        request: Request<B>,
+        next: Next<B>,
+    ) -> Result<Response, WebError> {
+        let Some(keys) = state.config().server_signing_keys()? else {
+            return Ok(next.run(request).await);
+        };
+        let path = request.uri().path().to_string();
+        let query = format!("?{}", request.uri().query().unwrap_or_default());
+        let response = next.run(request).await;
+        // Streamed responses (`/api/dump`) can be too large to hold, so they go unsigned.
+        if response.body().size_hint().exact().is_none() {
+            return Ok(response);
+        }
+
+        let (mut parts, mut body) = response.into_parts();
+        let mut content = vec![];
+        while let Some(chunk) = body.data().await {
+            content.extend_from_slice(&chunk?);
+        }
+        let digest = content_digest(&content);
+        let params = signature_params(&keys, time::OffsetDateTime::now_utc().unix_timestamp());
+        let base = signature_base(parts.status.as_u16(), &digest, &path, &query, &params);
+        let signature = sign_base(&keys, &base)?;
+
+        let headers = &mut parts.headers;
+        headers.insert("content-digest", HeaderValue::from_str(&digest)?);
+        headers.insert(
+            "signature-input",
+            HeaderValue::from_str(&format!("{LABEL}={params}"))?,
+        );
+        headers.insert(
+            "signature",
+            HeaderValue::from_str(&format!("{LABEL}=:{signature}:"))?,
+        );
+        Ok(Response::from_parts(
+            parts,
+            body::boxed(Full::from(content)),
+        ))
+    }
+
+    /// The key API responses are signed with, for clients to check signatures against.
+    pub async fn well_known(State(state): State<AppState>) -> Result<Response, WebError> {
+        let keys = state.config().server_signing_keys()?.ok_or_else(|| {
+            WebError::not_found(anyhow::anyhow!("This server does not sign responses"))
+        })?;
+        let pubkey = keys.public_key();
+        let mut response = Json(WellKnown {
+            pubkey: pubkey.to_string(),
+            npub: pubkey.to_bech32()?,
+            algorithm: ALGORITHM.into(),
+            label: LABEL.into(),
+            components: COMPONENTS.iter().map(|c| c.to_string()).collect(),
+        })
+        .into_response();
+        response.headers_mut().insert(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        );
+        Ok(response)
+    }
+
+    /// `Content-Digest` of a body (RFC 9530).
+    fn content_digest(content: &[u8]) -> String {
+        let hash = sha256::Hash::hash(content);
+        format!("sha-256=:{}:", base64::encode(hash.as_byte_array()))
+    }
+
+    /// The `@signature-params` value: the covered components, creation time and key id.
+    fn signature_params(keys: &Keys, created: i64) -> String {
+        format!(
+            "({});created={created};keyid=\"{}\"",
+            COMPONENTS.join(" "),
+            keys.public_key()
+        )
+    }
+
+    /// The signature base, one line per covered component and `@signature-params` last.
+    fn signature_base(status: u16, digest: &str, path: &str, query: &str, params: &str) -> String {
+        let values = [status.to_string(), digest.into(), path.into(), query.into()];
+        let mut lines: Vec<String> = COMPONENTS
+            .iter()
+            .zip(values)
+            .map(|(component, value)| format!("{component}: {value}"))
+            .collect();
+        lines.push(format!("\"@signature-params\": {params}"));
+        lines.join("\n")
+    }
+
+    fn sign_base(keys: &Keys, base: &str) -> anyhow::Result<String> {
+        let hash = sha256::Hash::hash(base.as_bytes());
+        let message = Message::from_slice(hash.as_byte_array())?;
+        let signature = Secp256k1::signing_only().sign_schnorr(&message, &keys.key_pair()?);
+        Ok(base64::encode(signature.as_ref()))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use secp256k1::schnorr::Signature;
+
+        use super::*;
+
+        #[test]
+        fn test_sign_base() {
+            let keys = Keys::generate();
+            let digest = content_digest(b"{}");
+            assert_eq!(
+                digest,
+                "sha-256=:RBNvo1WzZ4oRRq0W9+hknpT7T8If536DEMBg9hyq/4o=:"
+            );
+
+            let params = signature_params(&keys, 1700000000);
+            let base = signature_base(200, &digest, "/api/name", "?name=smith", &params);
+            assert_eq!(
+                base,
+                format!(
+                    "\"@status\": 200\n\
+                     \"content-digest\": {digest}\n\
+                     \"@path\";req: /api/name\n\
+                     \"@query\";req: ?name=smith\n\
+                     \"@signature-params\": (\"@status\" \"content-digest\" \"@path\";req \
+                     \"@query\";req);created=1700000000;keyid=\"{}\"",
+                    keys.public_key()
+                )
+            );
+
+            let signature = base64::decode(sign_base(&keys, &base).unwrap()).unwrap();
+            let signature = Signature::from_slice(&signature).unwrap();
+            let hash = sha256::Hash::hash(base.as_bytes());
+            let message = Message::from_slice(hash.as_byte_array()).unwrap();
+            let secp = Secp256k1::verification_only();
+            assert!(secp
+                .verify_schnorr(&signature, &message, &keys.public_key())
+                .is_ok());
+            let other = Message::from_slice(sha256::Hash::hash(b"other").as_byte_array()).unwrap();
+            assert!(secp
+                .verify_schnorr(&signature, &other, &keys.public_key())
+                .is_err());
+        }
+    }
+}
+
+mod assets {
+    use axum::{
+        extract::{Path, State},
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+    };
+    use rust_embed::RustEmbed;
+
+    use crate::config::ThemeMode;
+
+    use super::AppState;
+
+    /// Static files for the explorer, compiled into the binary so no CDN is needed.
+    #[derive(RustEmbed)]
+    #[folder = "assets/"]
+    struct Assets;
+
+    pub(super) fn content_type(file: &str) -> &'static str {
+        match file.rsplit_once('.').map(|(_, ext)| ext) {
+            Some("css") => "text/css",
+            Some("js") => "application/javascript",
+            Some("svg") => "image/svg+xml",
+            Some("png") => "image/png",
+            Some("ico") => "image/x-icon",
+            _ => "application/octet-stream",
+        }
+    }
+
+    pub async fn asset(Path(file): Path<String>) -> Response {
+        match Assets::get(&file) {
+            Some(content) => (
+                [(header::CONTENT_TYPE, content_type(&file))],
+                content.data.into_owned(),
+            )
+                .into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    /// Stylesheet with the color palette picked by the `[server.theme]` config.
+    pub async fn theme(State(state): State<AppState>) -> impl IntoResponse {
+        let palette = match state.config().server_theme_mode() {
+            ThemeMode::Auto => concat!(
+                "@import url(\"/assets/light.css\");\n",
+                "@import url(\"/assets/dark.css\") (prefers-color-scheme: dark);\n"
+            ),
+            ThemeMode::Light => "@import url(\"/assets/light.css\");\n",
+            ThemeMode::Dark => "@import url(\"/assets/dark.css\");\n",
+        };
+        let accent = state.config().server_theme_accent();
+        let css = format!("{palette}\n:root {{\n  --accent: {accent};\n}}\n");
+
+        ([(header::CONTENT_TYPE, "text/css")], css)
+    }
+}
+
+mod branding {
+    use axum::{
+        async_trait,
+        extract::{FromRequestParts, State},
+        http::{header, request::Parts, StatusCode},
+        response::{IntoResponse, Response},
+    };
+
+    use super::{assets::content_type, AppState};
+
+    /// The operator's `[server.branding]`, as shown by the base template.
+    #[derive(Clone, Debug, Default)]
+    pub struct Branding {
+        pub title: Option<String>,
+        pub logo: bool,
+        pub footer: Option<String>,
+        pub announcement: Option<String>,
+    }
+
+    #[async_trait]
+    impl FromRequestParts<AppState> for Branding {
+        type Rejection = std::convert::Infallible;
+
+        async fn from_request_parts(
+            _parts: &mut Parts,
+            state: &AppState,
+        ) -> Result<Self, Self::Rejection> {
+            let branding = state.config().server_branding();
+            Ok(Branding {
+                title: branding.title,
+                logo: branding.logo.is_some(),
+                footer: branding.footer,
+                announcement: branding.announcement,
+            })
+        }
+    }
+
+    /// The configured logo file, read on every request so it can be replaced without a restart.
+    pub async fn logo(State(state): State<AppState>) -> Response {
+        let Some(path) = state.config().server_branding().logo else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        match tokio::fs::read(&path).await {
+            Ok(logo) => (
+                [(header::CONTENT_TYPE, content_type(&path.to_string_lossy()))],
+                logo,
+            )
+                .into_response(),
+            Err(e) => {
+                log::error!("Unable to read logo {}: {e}", path.display());
+                StatusCode::NOT_FOUND.into_response()
+            }
+        }
+    }
+}
+
+mod i18n {
+    use std::{collections::HashMap, convert::Infallible};
+
+    use axum::{
+        async_trait,
+        extract::{FromRequestParts, Query},
+        http::{header::ACCEPT_LANGUAGE, request::Parts, Request},
+        middleware::Next,
+        response::Response,
+    };
+    use fluent_templates::{fluent_bundle::FluentValue, LanguageIdentifier, Loader};
+    use serde::Deserialize;
+
+    fluent_templates::static_loader! {
+        static LOCALES = {
+            locales: "./locales",
+            fallback_language: "en-US",
+            // Isolation marks (U+2068/U+2069) around arguments show up as stray characters in
+            // browsers and copied text, and every page is rendered in a single direction anyway.
+            customise: |bundle| bundle.set_use_isolating(false),
+        };
+    }
+
+    tokio::task_local! {
+        static REQUEST_LANG: Lang;
+    }
+
+    /// The language negotiated for a request, used by templates to look up translated text.
+    #[derive(Clone, Debug)]
+    pub struct Lang(LanguageIdentifier);
+
+    impl Lang {
+        /// Translated text for `id`. Ids missing from every locale show as themselves.
+        pub fn t(&self, id: &str) -> String {
+            LOCALES.lookup(&self.0, id).unwrap_or_else(|| id.to_owned())
+        }
+
+        /// Translated text for `id`, with a single `$arg` substituted.
+        pub fn t_with(&self, id: &str, arg: &str, value: &str) -> String {
+            let args = HashMap::from([(arg, FluentValue::from(value))]);
+            LOCALES
+                .lookup_with_args(&self.0, id, &args)
+                .unwrap_or_else(|| id.to_owned())
+        }
+
+        /// Translated text for `id`, with `$count` substituted as a number, for plurals.
+        pub fn t_count(&self, id: &str, count: i64) -> String {
+            let args = HashMap::from([("count", FluentValue::from(count))]);
+            LOCALES
+                .lookup_with_args(&self.0, id, &args)
+                .unwrap_or_else(|| id.to_owned())
+        }
+
+        /// The language negotiated for the request being handled by the current task, for responses
+        /// built outside a handler, such as error pages.
+        pub fn current() -> Lang {
+            REQUEST_LANG.try_with(Lang::clone).unwrap_or_default()
+        }
+
+        /// The language code, for use in the `lang` attribute of the page.
+        pub fn code(&self) -> String {
+            self.0.to_string()
+        }
+
+        /// Pick the best supported language, given a list of requested languages in order of preference.
+        fn negotiate<'a>(requested: impl IntoIterator<Item = &'a str>) -> Lang {
+            let supported: Vec<&LanguageIdentifier> = LOCALES.locales().collect();
+            for requested in requested {
+                let Ok(requested) = requested.trim().parse::<LanguageIdentifier>() else {
+                    continue;
+                };
+                if let Some(exact) = supported.iter().copied().find(|l| **l == requested) {
+                    return Lang(exact.clone());
+                }
+                if let Some(partial) = supported
+                    .iter()
+                    .copied()
+                    .find(|l| l.language == requested.language)
+                {
+                    return Lang(partial.clone());
+                }
+            }
+            Lang::default()
+        }
+    }
+
+    impl Default for Lang {
+        fn default() -> Self {
+            Lang("en-US".parse().expect("Invalid fallback language"))
+        }
+    }
+
+    /// Parse an `Accept-Language` header into language tags, sorted by quality.
+    fn accept_language(header: &str) -> Vec<&str> {
+        let mut langs = header
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.trim().split(';');
+                let tag = parts.next()?.trim();
+                let q = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (!tag.is_empty() && tag != "*").then_some((tag, q))
+            })
+            .collect::<Vec<_>>();
+        langs.sort_by(|a, b| b.1.total_cmp(&a.1));
+        langs.into_iter().map(|(tag, _)| tag).collect()
+    }
+
+    #[derive(Deserialize)]
+    struct LangQuery {
+        lang: Option<String>,
+    }
+
+    #[async_trait]
+    impl<S> FromRequestParts<S> for Lang
+    where
+        S: Send + Sync,
+    {
+        type Rejection = Infallible;
+
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+            let query = Query::<LangQuery>::from_request_parts(parts, state)
+                .await
+                .ok()
+                .and_then(|Query(q)| q.lang);
+            let header = parts
+                .headers
+                .get(ACCEPT_LANGUAGE)
+                .and_then(|h| h.to_str().ok())
+                .map(accept_language)
+                .unwrap_or_default();
+
+            Ok(Lang::negotiate(query.as_deref().into_iter().chain(header)))
+        }
+    }
+
+    /// Negotiate the language of each request up front, so error pages are translated too.
+    pub async fn negotiate<B>(lang: Lang, request: Request<B>, next: Next<B>) -> Response {
+        REQUEST_LANG.scope(lang, next.run(request)).await
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_accept_language() {
+            assert_eq!(
+                accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5"),
+                vec!["fr-CH", "fr", "en", "de"]
+            );
+            assert_eq!(accept_language("en;q=0.5, es"), vec!["es", "en"]);
+        }
+
+        #[test]
+        fn test_negotiate() {
+            assert_eq!(Lang::negotiate(["es-MX"]).code(), "es");
+            assert_eq!(Lang::negotiate(["xx", "es"]).code(), "es");
+            assert_eq!(Lang::negotiate(["xx"]).code(), "en-US");
+        }
+
+        #[test]
+        fn test_missing_id() {
+            let lang = Lang::default();
+            assert_eq!(lang.t("no-such-id"), "no-such-id");
+            assert_eq!(lang.t_with("no-such-id", "name", "smith"), "no-such-id");
+        }
+
+        #[test]
+        fn test_no_isolation_marks() {
+            let lang = Lang::default();
+            assert_eq!(
+                lang.t_with("explorer-last-indexed", "time", "never"),
+                "Last indexed at never."
+            );
+        }
+
+        #[tokio::test]
+        async fn test_current() {
+            assert_eq!(Lang::current().code(), "en-US");
+            let lang = Lang::negotiate(["es"]);
+            let code = REQUEST_LANG
+                .scope(lang, async { Lang::current().code() })
+                .await;
+            assert_eq!(code, "es");
+        }
+    }
+}
+
+mod util {
+    use bitcoincore_rpc::RpcApi;
+    use serde::{Deserialize, Serialize};
+    use sqlx::SqlitePool;
+    use time::{macros::format_description, OffsetDateTime};
+
+    use crate::{config::Config, db};
+
+    /// Number of rows per page on paginated explorer sections.
+    pub const PAGE_SIZE: i64 = 50;
+
+    /// Position in a paginated listing. Page numbers start at 1.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Page {
+        pub number: i64,
+        pub pages: i64,
+        pub total: i64,
+    }
+
+    impl Default for Page {
+        fn default() -> Self {
+            Page {
+                number: 1,
+                pages: 1,
+                total: 0,
+            }
+        }
+    }
+
+    impl Page {
+        /// Clamp the requested page number between the first and last page.
+        pub fn new(number: Option<i64>, total: i64) -> Page {
+            let pages = ((total + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+            Page {
+                number: number.unwrap_or(1).clamp(1, pages),
+                pages,
+                total,
+            }
+        }
+
+        pub fn offset(&self) -> i64 {
+            (self.number - 1) * PAGE_SIZE
+        }
+
+        pub fn has_prev(&self) -> bool {
+            self.number > 1
+        }
+
+        pub fn has_next(&self) -> bool {
+            self.number < self.pages
+        }
+
+        pub fn prev(&self) -> i64 {
+            self.number - 1
+        }
+
+        pub fn next(&self) -> i64 {
+            self.number + 1
+        }
+    }
+
+    /// Number of confirmations of a block at `blockheight`, and whether it meets the configured threshold.
+    pub async fn confirmations(
+        config: &Config,
+        pool: &SqlitePool,
+        blockheight: i64,
+    ) -> anyhow::Result<(u32, bool)> {
+        confirmations_at(config, block_count(config, pool).await?, blockheight)
+    }
+
+    /// Height of the node's best block. Without the indexer, e.g. serving a snapshot on a machine
+    /// with no node, the height of the last indexed block instead.
+    pub async fn block_count(config: &Config, pool: &SqlitePool) -> anyhow::Result<u64> {
+        if config.server_without_indexer() {
+            return Ok(db::index_tip(pool)
+                .await?
+                .map_or(0, |(height, _)| height.max(0) as u64));
+        }
+        config.rpc()?.get_block_count().await
+    }
+
+    /// Like `confirmations`, with the node's best block height already known.
+    pub fn confirmations_at(
+        config: &Config,
+        tip: u64,
+        blockheight: i64,
+    ) -> anyhow::Result<(u32, bool)> {
+        let confirmations = (tip as i64 - blockheight + 1).max(0) as u32;
+        let confirmed = confirmations as usize >= config.confirmations()?;
+        Ok((confirmations, confirmed))
+    }
+
+    pub fn format_time(timestamp: i64) -> anyhow::Result<String> {
+        let dt = OffsetDateTime::from_unix_timestamp(timestamp)?;
+        let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+        Ok(dt.format(format)?)
+    }
+
+    /// The locale message id and `$count` to show a time `seconds` ago with. Times in the future,
+    /// from clock skew, are shown as just now.
+    pub fn time_ago(seconds: i64) -> (&'static str, i64) {
+        match seconds.max(0) {
+            s if s < 60 => ("time-just-now", 0),
+            s if s < 3_600 => ("time-minutes-ago", s / 60),
+            s if s < 86_400 => ("time-hours-ago", s / 3_600),
+            s => ("time-days-ago", s / 86_400),
+        }
+    }
+
+    pub fn format_date(timestamp: i64) -> anyhow::Result<String> {
+        let dt = OffsetDateTime::from_unix_timestamp(timestamp)?;
+        Ok(dt.format(format_description!("[year]-[month]-[day]"))?)
+    }
+
+    /// Default and largest number of days covered by fee statistics.
+    pub const DEFAULT_FEE_DAYS: i64 = 90;
+    pub const MAX_FEE_DAYS: i64 = 730;
+
+    pub use nomen_core::api_types::{FeeInterval, FeePeriod, FeeStats, FeesQuery};
+
+    /// Fee statistics of the claims confirmed in the last `days` days: over the whole window, and per period.
+    pub struct FeeReport {
+        pub days: i64,
+        pub interval: FeeInterval,
+        pub overall: FeeStats,
+        pub periods: Vec<FeePeriod>,
+    }
+
+    impl FeeReport {
+        pub async fn load(pool: &SqlitePool, query: &FeesQuery) -> anyhow::Result<FeeReport> {
+            let days = query
+                .days
+                .unwrap_or(DEFAULT_FEE_DAYS)
+                .clamp(1, MAX_FEE_DAYS);
+            let interval = query.interval.unwrap_or(FeeInterval::for_days(days));
+            let since = OffsetDateTime::now_utc().unix_timestamp() - days * 86_400;
+            let claims = db::claim_fee_rates(pool, since).await?;
+            Ok(FeeReport {
+                days,
+                interval,
+                overall: FeeStats::new(claims.iter().map(|(_, rate)| *rate).collect()),
+                periods: fee_periods(&claims, interval),
+            })
+        }
+    }
+
+    /// Group `(blocktime, fee rate)` claims, oldest first, into UTC days or weeks.
+    /// Periods without claims are left out.
+    pub fn fee_periods(claims: &[(i64, f64)], interval: FeeInterval) -> Vec<FeePeriod> {
+        let interval = interval.seconds();
+        let mut periods: Vec<(i64, Vec<f64>)> = Vec::new();
+        for (blocktime, rate) in claims {
+            let start = blocktime.div_euclid(interval) * interval;
+            match periods.last_mut() {
+                Some((last, rates)) if *last == start => rates.push(*rate),
+                _ => periods.push((start, vec![*rate])),
+            }
+        }
+        periods
+            .into_iter()
+            .map(|(start, rates)| FeePeriod {
+                start,
+                stats: FeeStats::new(rates),
+            })
+            .collect()
+    }
+
+    /// SVG points of a percentile chart, in a 600 by 200 view box: the 10th to 90th and
+    /// 25th to 75th percentile bands, and the median line.
+    pub struct FeeChart {
+        pub outer: String,
+        pub inner: String,
+        pub median: String,
+        /// Fee rate at the top of the chart.
+        pub max: f64,
+    }
+
+    impl FeeChart {
+        const WIDTH: f64 = 600.0;
+        const HEIGHT: f64 = 200.0;
+
+        pub fn new(periods: &[FeePeriod]) -> Option<FeeChart> {
+            if periods.is_empty() {
+                return None;
+            }
+            let max = periods
+                .iter()
+                .map(|p| p.stats.p90)
+                .fold(1.0, f64::max)
+                .ceil();
+            let first = periods[0].start;
+            let span = (periods[periods.len() - 1].start - first).max(1) as f64;
+            let points = |rate: fn(&FeeStats) -> f64| {
+                periods
+                    .iter()
+                    .map(|p| {
+                        let x = if periods.len() == 1 {
+                            Self::WIDTH / 2.0
+                        } else {
+                            (p.start - first) as f64 / span * Self::WIDTH
+                        };
+                        let y = Self::HEIGHT - rate(&p.stats) / max * Self::HEIGHT;
+                        format!("{x:.1},{y:.1}")
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let band = |low: fn(&FeeStats) -> f64, high: fn(&FeeStats) -> f64| {
+                let mut band = points(high);
+                band.extend(points(low).into_iter().rev());
+                band.join(" ")
+            };
+
+            Some(FeeChart {
+                outer: band(|s| s.p10, |s| s.p90),
+                inner: band(|s| s.p25, |s| s.p75),
+                median: points(|s| s.p50).join(" "),
+                max,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_page() {
+            let page = Page::new(None, 0);
+            assert_eq!((page.number, page.pages, page.offset()), (1, 1, 0));
+
+            let page = Page::new(Some(3), 120);
+            assert_eq!((page.number, page.pages, page.offset()), (3, 3, 100));
+            assert!(page.has_prev() && !page.has_next());
+
+            assert_eq!(Page::new(Some(9), 120).number, 3);
+            assert_eq!(Page::new(Some(-1), 120).number, 1);
+        }
+
+        #[tokio::test]
+        async fn test_block_count_without_indexer() {
+            use bitcoin::{hashes::Hash, BlockHash};
+            use clap::Parser;
+
+            use crate::config::{Cli, ConfigFile};
+
+            let config = Config::new(
+                Cli::parse_from(["nomen", "server", "--without-indexer"]),
+                ConfigFile::default(),
+            );
+            let pool = db::scratch().await.unwrap();
+            assert_eq!(block_count(&config, &pool).await.unwrap(), 0);
+
+            let mut conn = pool.acquire().await.unwrap();
+            db::insert_index_height(&mut conn, 100, &BlockHash::all_zeros())
+                .await
+                .unwrap();
+            drop(conn);
+            assert_eq!(block_count(&config, &pool).await.unwrap(), 100);
+            assert_eq!(confirmations(&config, &pool, 98).await.unwrap(), (3, true));
+        }
+
+        #[test]
+        fn test_time_ago() {
+            assert_eq!(time_ago(-30), ("time-just-now", 0));
+            assert_eq!(time_ago(59), ("time-just-now", 0));
+            assert_eq!(time_ago(150), ("time-minutes-ago", 2));
+            assert_eq!(time_ago(7_200), ("time-hours-ago", 2));
+            assert_eq!(time_ago(3 * 86_400 + 5), ("time-days-ago", 3));
+        }
+
+        #[test]
+        fn test_fee_stats() {
+            let stats = FeeStats::new((1..=10).rev().map(f64::from).collect());
+            assert_eq!(stats.claims, 10);
+            assert_eq!((stats.p10, stats.p50, stats.p90), (1.0, 5.0, 9.0));
+            assert_eq!(FeeStats::new(vec![]), FeeStats::default());
+
+            let claims = [(0, 2.0), (100, 4.0), (86_400, 8.0), (3 * 86_400, 1.5)];
+            let periods = fee_periods(&claims, FeeInterval::Day);
+            assert_eq!(
+                periods
+                    .iter()
+                    .map(|p| (p.start, p.stats.claims))
+                    .collect::<Vec<_>>(),
+                vec![(0, 2), (86_400, 1), (3 * 86_400, 1)]
+            );
+            assert_eq!(fee_periods(&claims, FeeInterval::Week).len(), 1);
+
+            let chart = FeeChart::new(&periods).unwrap();
+            assert_eq!(chart.max, 8.0);
+            assert!(chart.median.starts_with("0.0,150.0 200.0,"));
+        }
     }
 }
@@ -1,16 +1,40 @@
-use std::{io::Write, path::PathBuf};
+use std::{
+    collections::HashMap,
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use anyhow::bail;
-use nostr_sdk::UnsignedEvent;
+use bitcoin::psbt::Psbt;
+use bitcoincore_rpc::{json::ListUnspentResultEntry, RpcApi};
+use clap::{Command, CommandFactory};
+use itertools::Itertools;
+use nostr_sdk::{
+    nips::nip11::RelayInformationDocument, Client, Event, EventBuilder, Filter, Keys, Kind,
+    Options, UnsignedEvent, Url,
+};
 use secp256k1::{Secp256k1, XOnlyPublicKey};
+use sqlx::SqlitePool;
 use yansi::Paint;
 
 use crate::{
-    config::{Config, ConfigFile, SignEventCommand},
-    util::{check_name_availability, Hash160, NomenKind, NsidBuilder},
+    config::{
+        paths, route_kind, Cli, Config, ConfigFile, MergePsbtSubcommand, RelayUse, SignEventCommand,
+    },
+    db,
+    error::NomenError,
+    util::{
+        self, check_name_availability, tag_print, DocumentKind, Hash160, Name, NomenKind, NomenTx,
+        NsidBuilder, RecordScript, TestVectors,
+    },
 };
 
-use super::get_keys;
+use super::{
+    get_keys,
+    name::{hwi, parse_psbt},
+};
 
 pub fn generate_keypair() {
     let secp = Secp256k1::new();
@@ -26,6 +50,7 @@ pub fn generate_keypair() {
 
 pub async fn lookup(config: &Config, name: &str) -> anyhow::Result<()> {
     let name = name.to_lowercase();
+    name.parse::<Name>()?;
     let (name, msg) = match check_name_availability(config, &name).await {
         Ok(_) => (Paint::yellow(&name), Paint::green("available")),
         Err(_) => (Paint::yellow(&name), Paint::red("unavailable")),
@@ -36,16 +61,22 @@ pub async fn lookup(config: &Config, name: &str) -> anyhow::Result<()> {
 }
 
 pub fn init_config(path: &Option<PathBuf>) -> anyhow::Result<()> {
-    let file = path.clone().unwrap_or_else(|| "nomen.toml".into());
+    let file = path
+        .clone()
+        .or_else(paths::xdg_config_file)
+        .unwrap_or_else(|| "nomen.toml".into());
     if file.exists() {
-        bail!("Config file already exists.");
+        bail!(NomenError::Config("Config file already exists.".into()));
+    }
+    if let Some(parent) = file.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
     }
 
-    let mut file = std::fs::File::create(&file)?;
     let config_file = ConfigFile::init();
-
     let strout = toml::to_string_pretty(&config_file)?;
-    file.write_all(strout.as_bytes())?;
+    std::fs::File::create(&file)?.write_all(strout.as_bytes())?;
+
+    println!("Config file written to {}", file.display());
     Ok(())
 }
 
@@ -55,7 +86,9 @@ pub async fn sign_event(config: &Config, args: &SignEventCommand) -> anyhow::Res
     let event = event.sign(&keys)?;
 
     if args.broadcast {
-        let (_k, nostr) = config.nostr_random_client().await?;
+        let (_k, nostr) = config
+            .nostr_random_client(&[event.kind], RelayUse::Write)
+            .await?;
         let event_id = nostr.send_event(event).await?;
         println!("Broadcast event {event_id}");
     } else {
@@ -65,17 +98,795 @@ pub async fn sign_event(config: &Config, args: &SignEventCommand) -> anyhow::Res
 }
 
 pub(crate) fn op_return(
-    name: &str,
+    name: &Name,
     pubkey: &XOnlyPublicKey,
     kind: NomenKind,
+    commitment: &Option<String>,
 ) -> anyhow::Result<()> {
+    let name = name.as_ref();
     let fingerprint = Hash160::default()
         .chain_update(name.as_bytes())
         .fingerprint();
     let nsid = NsidBuilder::new(name, pubkey).finalize();
-    let data = super::op_return(fingerprint, nsid, kind);
+    let commitment = match commitment {
+        Some(_) if kind != NomenKind::Create => bail!(NomenError::Validation(
+            "Only create transactions can carry a commitment".into()
+        )),
+        Some(commitment) => Some(
+            hex::decode(commitment)
+                .ok()
+                .and_then(|c| <[u8; 32]>::try_from(c).ok())
+                .ok_or_else(|| {
+                    NomenError::Validation("Commitment must be 32 hex encoded bytes".into())
+                })?,
+        ),
+        None => None,
+    };
+    let data = super::op_return(fingerprint, nsid, kind, commitment);
 
     println!("{}", hex::encode(data));
 
     Ok(())
 }
+
+/// Number of recent indexed events to look for on each relay.
+const RELAY_SAMPLE_SIZE: i64 = 100;
+
+const RELAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Virtual size of a name claim funded by one P2WPKH input, with a change output and an OP_RETURN
+/// that has room for a records commitment, rounded up.
+const CLAIM_VSIZE: u64 = 200;
+
+/// Smallest change output nodes relay by default, in sats.
+pub(crate) const DUST_LIMIT: u64 = 546;
+
+/// Fee rate when the node has no estimate, as on regtest, in sat/vB.
+pub(crate) const FALLBACK_FEE_RATE: f64 = 1.0;
+
+struct RelayReport {
+    url: String,
+    latency: Option<Duration>,
+    read: bool,
+    write: bool,
+    info: Option<RelayInformationDocument>,
+    stored: usize,
+}
+
+pub async fn relays(config: &Config, pool: &SqlitePool) -> anyhow::Result<()> {
+    let ids = db::recent_event_ids(pool, RELAY_SAMPLE_SIZE).await?;
+    let reports = futures::future::join_all(
+        config
+            .all_relays()
+            .into_iter()
+            .map(|url| check_relay(url, ids.clone(), config.network_proxy())),
+    )
+    .await;
+    let contributions: HashMap<String, db::RelayContribution> = db::relay_contributions(pool)
+        .await?
+        .into_iter()
+        .map(|c| (c.relay.clone(), c))
+        .collect();
+
+    for report in reports {
+        let report = match report {
+            Ok(report) => report,
+            Err((url, err)) => {
+                println!("{} {}", Paint::red("✗"), Paint::yellow(url));
+                println!("  {err}");
+                continue;
+            }
+        };
+
+        let mark = if report.read && report.write {
+            Paint::green("✓")
+        } else {
+            Paint::red("✗")
+        };
+        println!("{mark} {}", Paint::yellow(&report.url));
+        match report.latency {
+            Some(latency) => println!("  Latency: {}ms", latency.as_millis()),
+            None => println!("  Latency: {}", Paint::red("timed out")),
+        }
+        println!("  Read:    {}", yes_no(report.read));
+        println!("  Write:   {}", yes_no(report.write));
+        match &report.info {
+            Some(info) => {
+                let nips = info
+                    .supported_nips
+                    .iter()
+                    .flatten()
+                    .map(|n| n.to_string())
+                    .join(", ");
+                println!(
+                    "  NIP-11:  {} ({}), NIPs: {}",
+                    info.name.as_deref().unwrap_or("unnamed"),
+                    info.software.as_deref().unwrap_or("unknown software"),
+                    if nips.is_empty() { "-" } else { &nips }
+                );
+            }
+            None => println!("  NIP-11:  {}", Paint::red("unavailable")),
+        }
+        println!(
+            "  Stored:  {}/{} recent name events",
+            report.stored,
+            ids.len()
+        );
+        match contributions.get(&report.url) {
+            Some(c) => println!(
+                "  Indexed: {} events from here, {} from no other relay",
+                c.events, c.sole
+            ),
+            None => println!("  Indexed: no events from here yet"),
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn verify(config: &Config, pool: &SqlitePool, name: &str) -> anyhow::Result<()> {
+    let name = name.to_lowercase();
+    let owner = db::name_owner(pool, &name)
+        .await?
+        .ok_or_else(|| NomenError::NotFound(format!("{name} is not in the index")))?;
+    let indexed = db::name_event_json(pool, &name, &owner.to_string())
+        .await?
+        .ok_or_else(|| NomenError::NotFound(format!("No signed records event for {name}")))?;
+    nomen_core::verify_name_event(&indexed)?;
+    let indexed = Event::from_json(&indexed)?;
+
+    let latest = util::quorum_latest(config, util::replacements_filter(&indexed)).await?;
+    let agreement = format!(
+        "at least {} of {} relays",
+        config.nostr_read_quorum(),
+        config.relays_for(&[indexed.kind], RelayUse::Read).len()
+    );
+    match latest {
+        Some(event) if event.id == indexed.id => {
+            println!(
+                "{} Records event {} of {} is the latest, according to {agreement}.",
+                Paint::green("✓"),
+                indexed.id,
+                Paint::yellow(&name)
+            );
+            Ok(())
+        }
+        Some(event) if event.created_at > indexed.created_at => bail!(NomenError::Validation(
+            format!(
+                "The index is behind: {agreement} have a newer records event {} for {name}, run `nomen index`",
+                event.id
+            )
+        )),
+        Some(event) => bail!(NomenError::Relay(format!(
+            "{agreement} serve the older records event {} for {name}, instead of {}",
+            event.id, indexed.id
+        ))),
+        None => bail!(NomenError::Relay(format!(
+            "{agreement} have no records event for {name}"
+        ))),
+    }
+}
+
+fn yes_no(ok: bool) -> Paint<&'static str> {
+    if ok {
+        Paint::green("yes")
+    } else {
+        Paint::red("no")
+    }
+}
+
+async fn check_relay(
+    url: String,
+    ids: Vec<String>,
+    proxy: Option<SocketAddr>,
+) -> Result<RelayReport, (String, anyhow::Error)> {
+    let info = match Url::parse(&url) {
+        Ok(u) => RelayInformationDocument::get(u, proxy).await.ok(),
+        Err(e) => return Err((url, e.into())),
+    };
+
+    let keys = Keys::generate();
+    let client = Client::with_opts(&keys, Options::new().wait_for_send(true));
+    if let Err(e) = client.add_relay(url.as_str(), proxy).await {
+        return Err((url, e.into()));
+    }
+    client.connect().await;
+
+    // Latency is the time for the relay to answer a minimal query, including the connection.
+    let start = Instant::now();
+    let latest = client
+        .get_events_of(vec![Filter::new().limit(1)], Some(RELAY_TIMEOUT))
+        .await;
+    let elapsed = start.elapsed();
+    let read = matches!(&latest, Ok(events) if !events.is_empty());
+    let latency = (elapsed < RELAY_TIMEOUT && latest.is_ok()).then_some(elapsed);
+
+    // Ephemeral events are not stored by relays, so this leaves nothing behind.
+    let write = match EventBuilder::new(Kind::Ephemeral(20_000), "nomen relay check", &[])
+        .to_event(&keys)
+    {
+        Ok(event) => client.send_event(event).await.is_ok(),
+        Err(_) => false,
+    };
+
+    let stored = if ids.is_empty() {
+        0
+    } else {
+        client
+            .get_events_of(vec![Filter::new().ids(ids)], Some(RELAY_TIMEOUT))
+            .await
+            .map(|events| events.len())
+            .unwrap_or_default()
+    };
+
+    client.disconnect().await.ok();
+
+    Ok(RelayReport {
+        url,
+        latency,
+        read,
+        write,
+        info,
+        stored,
+    })
+}
+
+/// Result of one `check-config` check: a short description on success, or what to fix.
+type Check = Result<String, String>;
+
+pub async fn check_config(config: &Config) -> anyhow::Result<()> {
+    let mut checks: Vec<(String, Check)> = vec![
+        ("Config file".into(), check_config_file(config)),
+        ("Settings".into(), check_settings(config)),
+        ("Bitcoin RPC".into(), check_rpc(config).await),
+        ("Database".into(), check_database(config).await),
+    ];
+
+    let relays = futures::future::join_all(
+        config
+            .all_relays()
+            .into_iter()
+            .map(|url| check_relay(url, vec![], config.network_proxy())),
+    )
+    .await;
+    for report in relays {
+        checks.push(match report {
+            Ok(r) if r.latency.is_some() => (
+                format!("Relay {}", r.url),
+                Ok(format!(
+                    "{}ms, write {}",
+                    r.latency.unwrap_or_default().as_millis(),
+                    if r.write { "ok" } else { "rejected" }
+                )),
+            ),
+            Ok(r) => (
+                format!("Relay {}", r.url),
+                Err("No response, check the URL or remove it from nostr.relays".into()),
+            ),
+            Err((url, err)) => (format!("Relay {url}"), Err(err.to_string())),
+        });
+    }
+
+    for (name, check) in &checks {
+        match check {
+            Ok(detail) => println!("{} {name}: {detail}", Paint::green("✓")),
+            Err(problem) => println!("{} {name}: {problem}", Paint::red("✗")),
+        }
+    }
+
+    let failed = checks.iter().filter(|(_, c)| c.is_err()).count();
+    if failed > 0 {
+        bail!(NomenError::Config(format!("{failed} config checks failed")));
+    }
+    Ok(())
+}
+
+fn check_config_file(config: &Config) -> Check {
+    let path = Config::config_file(&config.cli);
+    let profile = config
+        .cli
+        .profile
+        .as_ref()
+        .map(|p| format!(", profile {p}"))
+        .unwrap_or_default();
+    if path.is_file() {
+        Ok(format!("{}{profile}", path.display()))
+    } else if config.cli.config.is_some() {
+        Err(format!("{} does not exist", path.display()))
+    } else {
+        Ok(format!(
+            "none found, using defaults (create one with `nomen util init`){profile}"
+        ))
+    }
+}
+
+fn check_settings(config: &Config) -> Check {
+    let mut problems = vec![];
+
+    if config.relays().is_empty() {
+        problems.push("nostr.relays is empty".to_string());
+    }
+    for relay in config.all_relays() {
+        match Url::parse(&relay) {
+            Ok(url) if matches!(url.scheme(), "ws" | "wss") => {}
+            _ => problems.push(format!("Relay {relay} is not a ws:// or wss:// URL")),
+        }
+    }
+    for key in config.file.nostr.routes.iter().flat_map(|r| r.keys()) {
+        if key != "default" && route_kind(key).is_none() {
+            problems.push(format!("nostr.routes.{key} is not an event kind"));
+        }
+    }
+    if config.nostr_read_quorum() > config.relays().len() {
+        problems.push(format!(
+            "nostr.read_quorum {} is more than the {} configured relays",
+            config.nostr_read_quorum(),
+            config.relays().len()
+        ));
+    }
+    if let Err(e) = config.server_bind() {
+        problems.push(format!("server.bind: {e}"));
+    }
+    if let Some(descriptor) = config.wallet_descriptor() {
+        if !descriptor.contains('*') {
+            problems.push(format!(
+                "wallet.descriptor {descriptor} is not ranged, it needs a /* step"
+            ));
+        }
+    }
+    if let Err(e) = config.server_signing_keys() {
+        problems.push(e.to_string());
+    }
+    if let Some(webhook) = config.notifications_webhook() {
+        if Url::parse(&webhook).is_err() {
+            problems.push(format!("notifications.webhook {webhook} is not a URL"));
+        }
+    }
+    if let Err(e) = super::Notifier::new(config) {
+        problems.push(e.to_string());
+    }
+    if let Some(script) = config.server_records_script() {
+        if let Err(e) = RecordScript::load(&script, config.server_script_timeout()) {
+            problems.push(e.to_string());
+        }
+    }
+    if let Some(cookie) = &config.file.rpc.cookie {
+        if config.cli.cookie.is_none() && !cookie.is_file() {
+            problems.push(format!("rpc.cookie {} does not exist", cookie.display()));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(format!("network {}", config.network()))
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+async fn check_rpc(config: &Config) -> Check {
+    let rpc = config
+        .rpc()
+        .map_err(|e| format!("{e} (check the [rpc] section)"))?;
+    let info = rpc.get_blockchain_info().await.map_err(|e| {
+            format!("{e} (is the node running, and are rpc.host, rpc.port and the cookie or user/password right?)")
+        })?;
+
+    let expected = config.network().to_core_arg();
+    if info.chain != expected {
+        return Err(format!(
+            "Node is on {}, but the configured network is {expected} (set rpc.network or --network)",
+            info.chain
+        ));
+    }
+    Ok(format!("{} at height {}", info.chain, info.blocks))
+}
+
+async fn check_database(config: &Config) -> Check {
+    let path = config.data();
+    if config.data_in_memory() {
+        return Ok("in memory, nothing is written to disk".to_string());
+    }
+    if !path.exists() {
+        return Ok(format!("{} will be created", path.display()));
+    }
+
+    let pool = config.sqlite().await.map_err(|e| e.to_string())?;
+    // Create a table in a transaction that is rolled back, so nothing is left behind.
+    let writable = async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("CREATE TABLE nomen_check_config (x);")
+            .execute(&mut tx)
+            .await?;
+        tx.rollback().await
+    }
+    .await;
+    pool.close().await;
+
+    match writable {
+        Ok(()) => Ok(format!("{} is writable", path.display())),
+        Err(e) => Err(format!("{}: {e}", path.display())),
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ClaimUtxo {
+    txid: String,
+    vout: u32,
+    /// In sats.
+    value: u64,
+    address: Option<String>,
+    confirmations: u32,
+}
+
+pub async fn list_utxos(
+    config: &Config,
+    fee_rate: Option<f64>,
+    min_conf: usize,
+    json: bool,
+) -> anyhow::Result<()> {
+    let rpc = config.rpc()?;
+    let fee_rate = match fee_rate {
+        Some(rate) => rate,
+        None => rpc.estimate_fee_rate(6).await?.unwrap_or(FALLBACK_FEE_RATE),
+    };
+    let minimum = claim_minimum(fee_rate);
+    let utxos = claim_utxos(rpc.list_unspent(min_conf).await?, minimum);
+
+    if json {
+        println!("{}", serde_json::to_string(&utxos)?);
+        return Ok(());
+    }
+    tag_print("Fee rate", &format!("{fee_rate} sat/vB"));
+    tag_print("Minimum value", &format!("{minimum} sats"));
+    if utxos.is_empty() {
+        println!("No UTXO in the wallet is large enough for a claim.");
+    }
+    for utxo in utxos {
+        println!(
+            "{}:{} {} sats {}",
+            utxo.txid,
+            utxo.vout,
+            utxo.value,
+            utxo.address.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+/// Least value a UTXO needs to pay for a claim at `fee_rate` and still leave change above dust.
+fn claim_minimum(fee_rate: f64) -> u64 {
+    DUST_LIMIT + (fee_rate * CLAIM_VSIZE as f64).ceil() as u64
+}
+
+/// The spendable and safe UTXOs worth at least `minimum` sats, largest first.
+fn claim_utxos(unspent: Vec<ListUnspentResultEntry>, minimum: u64) -> Vec<ClaimUtxo> {
+    unspent
+        .into_iter()
+        .filter(|u| u.spendable && u.safe && u.amount.to_sat() >= minimum)
+        .sorted_by_key(|u| std::cmp::Reverse(u.amount))
+        .map(|u| ClaimUtxo {
+            txid: u.txid.to_string(),
+            vout: u.vout,
+            value: u.amount.to_sat(),
+            address: u.address.map(|a| a.assume_checked().to_string()),
+            confirmations: u.confirmations,
+        })
+        .collect()
+}
+
+#[derive(serde::Serialize)]
+struct MergePsbtOutput {
+    fee: u64,
+    unsigned_tx: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signed_tx: Option<String>,
+}
+
+/// Add the sponsor's inputs to a sponsored claim, and sign them.
+pub async fn merge_psbt(config: &Config, args: &MergePsbtSubcommand) -> anyhow::Result<()> {
+    let claim = parse_psbt(&args.claim)?;
+    let sponsor = parse_psbt(&args.sponsor)?;
+    let mut psbt = sponsor_claim(claim, sponsor)?;
+    let fee = util::input_value(&psbt)?
+        .checked_sub(util::output_value(&psbt))
+        .ok_or_else(|| {
+            NomenError::Validation("The sponsor's inputs must pay at least the fee".into())
+        })?;
+
+    let unsigned_tx = psbt.to_string();
+    let signed_tx = if args.hwi.hwi {
+        psbt = hwi::sign(config, &args.hwi, &psbt).await?;
+        Some(hwi::finalize(config, &psbt, args.hwi.broadcast_tx).await?)
+    } else {
+        None
+    };
+
+    let output = MergePsbtOutput {
+        fee,
+        unsigned_tx,
+        signed_tx,
+    };
+    if args.json {
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        tag_print("Fee", &format!("{} sats", output.fee));
+        tag_print("Unsigned Tx", &output.unsigned_tx);
+        if let Some(signed_tx) = &output.signed_tx {
+            tag_print("Signed Tx", signed_tx);
+        }
+    }
+
+    if let Some(output) = &args.output {
+        std::fs::write(output, psbt.serialize())?;
+    }
+
+    Ok(())
+}
+
+/// `claim` with the inputs and outputs of `sponsor` added. Signed inputs of the claim must be
+/// `SIGHASH_ALL|SIGHASH_ANYONECANPAY`, and then the sponsor can't add outputs.
+fn sponsor_claim(mut claim: Psbt, sponsor: Psbt) -> anyhow::Result<Psbt> {
+    let mut signed = false;
+    for (txin, input) in claim.unsigned_tx.input.iter().zip(&claim.inputs) {
+        let is_signed = input.final_script_witness.is_some()
+            || input.final_script_sig.is_some()
+            || input.tap_key_sig.is_some()
+            || !input.partial_sigs.is_empty();
+        if is_signed && !util::signed_anyone_can_pay(input) {
+            bail!(NomenError::Validation(format!(
+                "Claim input {} is not signed with SIGHASH_ALL|SIGHASH_ANYONECANPAY, use `name new --sponsor-psbt`",
+                txin.previous_output
+            )));
+        }
+        signed |= is_signed;
+    }
+    if signed && !sponsor.unsigned_tx.output.is_empty() {
+        bail!(NomenError::Validation(
+            "The sponsor's transaction must not have outputs, as the claim is signed".into()
+        ));
+    }
+    for txin in &sponsor.unsigned_tx.input {
+        if claim
+            .unsigned_tx
+            .input
+            .iter()
+            .any(|i| i.previous_output == txin.previous_output)
+        {
+            bail!(NomenError::Validation(format!(
+                "Input {} is in both transactions",
+                txin.previous_output
+            )));
+        }
+    }
+
+    claim.unsigned_tx.input.extend(sponsor.unsigned_tx.input);
+    claim.inputs.extend(sponsor.inputs);
+    claim.unsigned_tx.output.extend(sponsor.unsigned_tx.output);
+    claim.outputs.extend(sponsor.outputs);
+    Ok(claim)
+}
+
+pub(crate) fn decode_op_return(data: &str) -> anyhow::Result<()> {
+    let bytes = hex::decode(data.trim())
+        .map_err(|e| NomenError::Validation(format!("Invalid hex: {e}")))?;
+    let payload = op_return_payload(&bytes)?;
+    let tx = NomenTx::try_from(payload.as_ref())
+        .map_err(|e| NomenError::Validation(format!("Invalid Nomen OP_RETURN: {e}")))?;
+
+    println!("{}{}", Paint::cyan("Version:     "), 0);
+    println!("{}{}", Paint::cyan("Kind:        "), tx.kind);
+    println!(
+        "{}{}",
+        Paint::cyan("Fingerprint: "),
+        hex::encode(tx.fingerprint)
+    );
+    println!("{}{}", Paint::cyan("Nsid:        "), tx.nsid);
+    println!("{}{}", Paint::cyan("             "), tx.nsid.to_bech32());
+    if let Some(commitment) = tx.commitment {
+        println!(
+            "{}{}",
+            Paint::cyan("Commitment:  "),
+            hex::encode(commitment)
+        );
+    }
+
+    Ok(())
+}
+
+pub(crate) fn test_vectors(
+    verify: &Option<PathBuf>,
+    output: &Option<PathBuf>,
+) -> anyhow::Result<()> {
+    if let Some(path) = verify {
+        let vectors: TestVectors = serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| NomenError::Validation(format!("Invalid test vectors: {e}")))?;
+        let errors = vectors.verify()?;
+        if !errors.is_empty() {
+            for error in &errors {
+                println!("{} {error}", Paint::red("✗"));
+            }
+            bail!(NomenError::Validation(format!(
+                "{} test vector values do not match",
+                errors.len()
+            )));
+        }
+        println!(
+            "{} All {} test vectors match",
+            Paint::green("✓"),
+            vectors.vectors.len()
+        );
+        return Ok(());
+    }
+
+    let json = serde_json::to_string_pretty(&TestVectors::generate()?)?;
+    match output {
+        Some(path) => std::fs::write(path, json + "\n")?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
+
+pub(crate) fn completions(shell: clap_complete::Shell) -> anyhow::Result<()> {
+    // Generated into a buffer, as writing to a closed pipe would panic.
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "nomen", &mut script);
+    std::io::stdout().write_all(&script)?;
+    Ok(())
+}
+
+pub(crate) fn manpages(dir: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut cmd = Cli::command();
+    // Building names each subcommand after its parents, e.g. `nomen-name-new`.
+    cmd.build();
+    let count = write_manpages(&cmd, dir)?;
+    println!("Wrote {count} man pages to {}", dir.display());
+    Ok(())
+}
+
+/// Write the man page of `cmd` and of each of its visible subcommands, returning how many.
+fn write_manpages(cmd: &Command, dir: &Path) -> anyhow::Result<usize> {
+    clap_mangen::Man::new(cmd.clone()).generate_to(dir)?;
+    let mut count = 1;
+    for sub in cmd
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+    {
+        count += write_manpages(sub, dir)?;
+    }
+    Ok(count)
+}
+
+pub(crate) fn example(kind: DocumentKind, schema: bool) -> anyhow::Result<()> {
+    let json = if schema {
+        serde_json::to_string_pretty(&kind.schema())?
+    } else {
+        kind.example()?
+    };
+    println!("{json}");
+    Ok(())
+}
+
+/// Strip the `OP_RETURN <push>` wrapper if `bytes` is a full output script.
+fn op_return_payload(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if bytes.starts_with(b"NOM") {
+        return Ok(bytes.to_vec());
+    }
+
+    let script = bitcoin::Script::from_bytes(bytes);
+    if !script.is_op_return() {
+        bail!(NomenError::Validation(
+            "Expected a Nomen payload or an OP_RETURN script".to_string()
+        ));
+    }
+    match script.instructions().nth(1) {
+        Some(Ok(bitcoin::script::Instruction::PushBytes(push))) => Ok(push.as_bytes().to_vec()),
+        _ => bail!(NomenError::Validation(
+            "OP_RETURN script has no data push".to_string()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manpages() {
+        let dir = std::env::temp_dir().join(format!("nomen-manpages-{}", std::process::id()));
+        manpages(&dir).unwrap();
+        assert!(dir.join("nomen.1").exists());
+        assert!(dir.join("nomen-name-new.1").exists());
+        assert!(!dir.join("nomen-help.1").exists());
+        let new = std::fs::read_to_string(dir.join("nomen-name-new.1")).unwrap();
+        assert!(new.contains("\\-\\-privkey"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_op_return_payload() {
+        let payload = hex::decode("4e4f4d00000102030405").unwrap();
+        assert_eq!(op_return_payload(&payload).unwrap(), payload);
+
+        let script = hex::decode("6a0a4e4f4d00000102030405").unwrap();
+        assert_eq!(op_return_payload(&script).unwrap(), payload);
+
+        assert!(op_return_payload(&hex::decode("0014").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_claim_utxos() {
+        let utxo = |vout: u32, sats: u64, spendable: bool| ListUnspentResultEntry {
+            txid: "aa".repeat(32).parse().unwrap(),
+            vout,
+            address: None,
+            label: None,
+            redeem_script: None,
+            witness_script: None,
+            script_pub_key: bitcoin::ScriptBuf::new(),
+            amount: bitcoin::Amount::from_sat(sats),
+            confirmations: 6,
+            spendable,
+            solvable: true,
+            descriptor: None,
+            safe: true,
+        };
+        assert_eq!(claim_minimum(1.0), 746);
+        assert_eq!(claim_minimum(2.5), 1046);
+
+        let utxos = claim_utxos(
+            vec![
+                utxo(0, 745, true),
+                utxo(1, 746, true),
+                utxo(2, 100_000, true),
+                utxo(3, 500_000, false),
+            ],
+            claim_minimum(1.0),
+        );
+        let vouts: Vec<u32> = utxos.iter().map(|u| u.vout).collect();
+        assert_eq!(vouts, [2, 1]);
+    }
+
+    #[test]
+    fn test_sponsor_claim() {
+        use bitcoin::{
+            absolute::LockTime, hashes::Hash, OutPoint, Transaction, TxIn, TxOut, Txid, Witness,
+        };
+
+        let psbt = |inputs: &[u8], outputs: usize| {
+            let tx = Transaction {
+                version: 2,
+                lock_time: LockTime::ZERO,
+                input: inputs
+                    .iter()
+                    .map(|n| TxIn {
+                        previous_output: OutPoint::new(Txid::from_byte_array([*n; 32]), 0),
+                        ..Default::default()
+                    })
+                    .collect(),
+                output: vec![TxOut::default(); outputs],
+            };
+            Psbt::from_unsigned_tx(tx).unwrap()
+        };
+        let signed = |sighash: u8| {
+            let mut claim = psbt(&[1], 2);
+            claim.inputs[0].final_script_witness =
+                Some(Witness::from_slice(&[vec![0x30, sighash], vec![0x02]]));
+            claim
+        };
+
+        // Before the claim is signed, the sponsor may add change.
+        let merged = sponsor_claim(psbt(&[1], 2), psbt(&[2], 1)).unwrap();
+        assert_eq!(merged.unsigned_tx.input.len(), 2);
+        assert_eq!(merged.inputs.len(), 2);
+        assert_eq!(merged.unsigned_tx.output.len(), 3);
+        assert_eq!(merged.outputs.len(), 3);
+
+        // Once signed for anyone to add inputs, only inputs.
+        assert!(sponsor_claim(signed(0x81), psbt(&[2], 0)).is_ok());
+        assert!(sponsor_claim(signed(0x81), psbt(&[2], 1)).is_err());
+        assert!(sponsor_claim(signed(0x01), psbt(&[2], 0)).is_err());
+
+        assert!(sponsor_claim(psbt(&[1], 2), psbt(&[1], 0)).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use sqlx::SqlitePool;
+use time::{macros::format_description, OffsetDateTime};
+use yansi::Paint;
+
+use crate::{db, util::Name};
+
+pub async fn add(pool: &SqlitePool, name: &Name) -> anyhow::Result<()> {
+    db::insert_watch(pool, name).await?;
+    db::audit(pool, db::LOCAL_ACTOR, "watch add", name.as_ref()).await?;
+    println!("Watching {}.", Paint::yellow(name));
+    Ok(())
+}
+
+pub async fn remove(pool: &SqlitePool, name: &Name) -> anyhow::Result<()> {
+    if db::delete_watch(pool, name).await? {
+        db::audit(pool, db::LOCAL_ACTOR, "watch remove", name.as_ref()).await?;
+        println!("Stopped watching {}.", Paint::yellow(name));
+    } else {
+        println!("{} is not on the watch list.", Paint::yellow(name));
+    }
+    Ok(())
+}
+
+pub async fn list(pool: &SqlitePool) -> anyhow::Result<()> {
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+    for watched in db::watch_list(pool).await? {
+        let added = OffsetDateTime::from_unix_timestamp(watched.added_at)?.format(format)?;
+        println!("{}\tadded {added}", Paint::yellow(&watched.name));
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, bail};
+use nostr_sdk::{Event, EventBuilder, EventId, Tag, UnsignedEvent};
+use secp256k1::XOnlyPublicKey;
+
+use crate::{error::NomenError, protocol::Grammar};
+
+/// Nostr event kind of a parent owner's approval of a subname.
+pub const APPROVAL_KIND: u16 = 38305;
+
+/// The owner of a parent name approving `pubkey` as the owner of the subname `name`.
+///
+/// With `protocol.subname_approval`, a subname is only approved while every name from it up to its
+/// top level name has an approval by the current owner of its parent, for its current owner.
+#[derive(Debug, Clone)]
+pub struct Approval {
+    pub event_id: EventId,
+    pub name: String,
+    pub parent: String,
+    pub pubkey: XOnlyPublicKey,
+    pub approver: XOnlyPublicKey,
+    pub created_at: i64,
+    pub raw_event: String,
+}
+
+impl Approval {
+    pub fn kind() -> nostr_sdk::Kind {
+        nostr_sdk::Kind::ParameterizedReplaceable(APPROVAL_KIND)
+    }
+
+    /// The unsigned approval event, published by the owner of the parent of `name`.
+    pub fn event(
+        approver: XOnlyPublicKey,
+        name: &str,
+        pubkey: &XOnlyPublicKey,
+    ) -> anyhow::Result<UnsignedEvent> {
+        parent(name)?;
+        Ok(EventBuilder::new(
+            Approval::kind(),
+            "",
+            &[
+                Tag::Identifier(name.to_string()),
+                Tag::PubKey(*pubkey, None),
+            ],
+        )
+        .to_unsigned_event(approver))
+    }
+
+    /// Parse and check an approval event. This does not check the approver owns the parent.
+    pub fn from_event(event: &Event) -> anyhow::Result<Approval> {
+        if event.kind != Approval::kind() {
+            bail!(NomenError::Validation("Not an approval event".into()));
+        }
+        event
+            .verify()
+            .map_err(|_| NomenError::Validation("Invalid approval signature".into()))?;
+        let name = event
+            .tags
+            .iter()
+            .find_map(|t| match t {
+                Tag::Identifier(d) => Some(d.clone()),
+                _ => None,
+            })
+            .ok_or_else(|| NomenError::Validation("Missing 'd' tag".into()))?;
+        let pubkey = event
+            .tags
+            .iter()
+            .find_map(|t| match t {
+                Tag::PubKey(pk, _) => Some(*pk),
+                _ => None,
+            })
+            .ok_or_else(|| NomenError::Validation("Missing 'p' tag".into()))?;
+
+        Ok(Approval {
+            event_id: event.id,
+            parent: parent(&name)?.to_string(),
+            name,
+            pubkey,
+            approver: event.pubkey,
+            created_at: event.created_at.as_i64(),
+            raw_event: event.as_json(),
+        })
+    }
+}
+
+/// The parent of the subname `name`, if it is a valid subname.
+pub fn parent(name: &str) -> anyhow::Result<&str> {
+    Grammar::SUBNAME.validate(name)?;
+    match name.split_once(Grammar::SUBNAME.label_separator) {
+        Some((_, parent)) => Ok(parent),
+        None => bail!(NomenError::Validation(format!("{name} is not a subname"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_sdk::Keys;
+
+    use super::*;
+
+    #[test]
+    fn test_approval_event() {
+        let parent_owner = Keys::generate();
+        let child_owner = Keys::generate();
+        let event = Approval::event(
+            parent_owner.public_key(),
+            "bob.smith",
+            &child_owner.public_key(),
+        )
+        .unwrap()
+        .sign(&parent_owner)
+        .unwrap();
+
+        let approval = Approval::from_event(&event).unwrap();
+        assert_eq!(approval.name, "bob.smith");
+        assert_eq!(approval.parent, "smith");
+        assert_eq!(approval.pubkey, child_owner.public_key());
+        assert_eq!(approval.approver, parent_owner.public_key());
+
+        assert!(Approval::event(
+            parent_owner.public_key(),
+            "smith",
+            &child_owner.public_key()
+        )
+        .is_err());
+        assert!(Approval::event(
+            parent_owner.public_key(),
+            "Bob.smith",
+            &child_owner.public_key()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::bail;
+use nostr_sdk::{Event, EventBuilder, Kind, Tag, UnsignedEvent};
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+use crate::error::NomenError;
+
+/// Nostr event kind of a signed names checkpoint.
+pub const CHECKPOINT_KIND: u16 = 38304;
+
+/// What an indexer operator attests about the names in its index at a block: the Merkle root of
+/// all of them, sorted, so anyone can check a `nomen_core::AbsenceProof` against it.
+///
+/// Like a snapshot manifest, it is the content of a Nostr event signed by the operator, with the
+/// network as its `d` tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u8,
+    pub network: String,
+    pub index_height: i64,
+    pub index_blockhash: String,
+    /// Number of names in the tree.
+    pub count: u64,
+    /// Hex encoded `nomen_core::names_root` of the names.
+    pub root: String,
+}
+
+impl Checkpoint {
+    pub fn kind() -> Kind {
+        Kind::ParameterizedReplaceable(CHECKPOINT_KIND)
+    }
+
+    /// The unsigned checkpoint event, published by the operator.
+    pub fn event(&self, operator: XOnlyPublicKey) -> anyhow::Result<UnsignedEvent> {
+        Ok(EventBuilder::new(
+            Checkpoint::kind(),
+            serde_json::to_string(self)?,
+            &[Tag::Identifier(self.network.clone())],
+        )
+        .to_unsigned_event(operator))
+    }
+
+    /// Parse a checkpoint event and check its signature. Whether the signer is trusted is up to the caller.
+    pub fn from_event(event: &Event) -> anyhow::Result<Checkpoint> {
+        if event.kind != Checkpoint::kind() {
+            bail!(NomenError::Validation("Not a checkpoint event".into()));
+        }
+        event
+            .verify()
+            .map_err(|_| NomenError::Validation("Invalid checkpoint signature".into()))?;
+        let checkpoint: Checkpoint = serde_json::from_str(&event.content)?;
+        let network = event.tags.iter().find_map(|t| match t {
+            Tag::Identifier(d) => Some(d),
+            _ => None,
+        });
+        if network != Some(&checkpoint.network) {
+            bail!(NomenError::Validation(
+                "Checkpoint 'd' tag does not match its network".into()
+            ));
+        }
+        Ok(checkpoint)
+    }
+}
@@ -0,0 +1,145 @@
+use std::str::FromStr;
+
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::error::NomenError;
+
+/// A point in time given on the command line, as RFC 3339 (`2024-05-01T12:00:00Z`) or Unix seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime(OffsetDateTime);
+
+impl DateTime {
+    pub fn unix_timestamp(&self) -> i64 {
+        self.0.unix_timestamp()
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = match s.parse::<i64>() {
+            Ok(seconds) => OffsetDateTime::from_unix_timestamp(seconds).ok(),
+            Err(_) => OffsetDateTime::parse(s, &Rfc3339).ok(),
+        };
+        parsed.map(DateTime).ok_or_else(|| {
+            NomenError::Validation(format!(
+                "Invalid date and time {s}, expected RFC 3339 (2024-05-01T12:00:00Z) or Unix seconds"
+            ))
+            .into()
+        })
+    }
+}
+
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let formatted = self.0.format(&Rfc3339).map_err(|_| std::fmt::Error)?;
+        f.write_str(&formatted)
+    }
+}
+
+/// Numbers below this are block heights, like a transaction's lock time.
+const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+
+/// A point in a name's history: a block height, or a date and time. As with a transaction's lock
+/// time, numbers below 500,000,000 are heights and larger ones Unix seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Moment {
+    Height(i64),
+    Time(DateTime),
+}
+
+impl FromStr for Moment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<i64>() {
+            Ok(height) if (0..LOCKTIME_THRESHOLD).contains(&height) => Ok(Moment::Height(height)),
+            _ => Ok(Moment::Time(s.parse()?)),
+        }
+    }
+}
+
+impl std::fmt::Display for Moment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Moment::Height(height) => write!(f, "height {height}"),
+            Moment::Time(time) => write!(f, "{time}"),
+        }
+    }
+}
+
+/// A length of time given on the command line, as a number and a unit: `90s`, `30m`, `12h`, `7d` or
+/// `2w`. A bare number is seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lifetime(i64);
+
+impl Lifetime {
+    pub fn as_secs(&self) -> i64 {
+        self.0
+    }
+}
+
+impl FromStr for Lifetime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+            Some(i) => s.split_at(i),
+            None => (s, "s"),
+        };
+        let unit = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => 0,
+        };
+        match number.parse::<i64>() {
+            Ok(n) if n > 0 && unit > 0 => n
+                .checked_mul(unit)
+                .map(Lifetime)
+                .ok_or_else(|| NomenError::Validation(format!("{s} is too long")).into()),
+            _ => Err(NomenError::Validation(format!(
+                "Invalid duration {s}, expected a number and a unit (90s, 30m, 12h, 7d, 2w)"
+            ))
+            .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datetime() {
+        let at: DateTime = "2024-05-01T12:00:00Z".parse().unwrap();
+        assert_eq!(at.unix_timestamp(), 1714564800);
+        let offset: DateTime = "2024-05-01T14:00:00+02:00".parse().unwrap();
+        assert_eq!(offset, at);
+        let seconds: DateTime = "1714564800".parse().unwrap();
+        assert_eq!(seconds, at);
+        assert_eq!(at.to_string(), "2024-05-01T12:00:00Z");
+        assert!("2024-05-01".parse::<DateTime>().is_err());
+
+        assert_eq!("800000".parse::<Moment>().unwrap(), Moment::Height(800000));
+        assert_eq!("1714564800".parse::<Moment>().unwrap(), Moment::Time(at));
+        assert_eq!(
+            "2024-05-01T12:00:00Z".parse::<Moment>().unwrap(),
+            Moment::Time(at)
+        );
+    }
+
+    #[test]
+    fn test_lifetime() {
+        assert_eq!("90".parse::<Lifetime>().unwrap().as_secs(), 90);
+        assert_eq!("30m".parse::<Lifetime>().unwrap().as_secs(), 1800);
+        assert_eq!("7d".parse::<Lifetime>().unwrap().as_secs(), 604800);
+        assert_eq!("2w".parse::<Lifetime>().unwrap().as_secs(), 1209600);
+        assert!("0h".parse::<Lifetime>().is_err());
+        assert!("5y".parse::<Lifetime>().is_err());
+        assert!("h".parse::<Lifetime>().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::{anyhow, bail};
+use nostr_sdk::{Event, EventBuilder, EventId, Tag, UnsignedEvent};
+use secp256k1::XOnlyPublicKey;
+
+use crate::{error::NomenError, protocol::Grammar};
+
+/// Nostr event kind of an owner deactivating their name.
+pub const DEACTIVATION_KIND: u16 = 38306;
+
+/// The owner of a name deactivating it: its records are no longer served, but stay in the
+/// history. A records event published after the deactivation reactivates the name.
+#[derive(Debug, Clone)]
+pub struct Deactivation {
+    pub event_id: EventId,
+    pub name: String,
+    pub pubkey: XOnlyPublicKey,
+    /// Why the name was deactivated, from the event's content. Empty if no reason was given.
+    pub reason: String,
+    pub created_at: i64,
+    pub raw_event: String,
+}
+
+impl Deactivation {
+    pub fn kind() -> nostr_sdk::Kind {
+        nostr_sdk::Kind::ParameterizedReplaceable(DEACTIVATION_KIND)
+    }
+
+    /// The unsigned deactivation event, published by the owner of `name`.
+    pub fn event(pubkey: XOnlyPublicKey, name: &str, reason: &str) -> UnsignedEvent {
+        EventBuilder::new(
+            Deactivation::kind(),
+            reason,
+            &[Tag::Identifier(name.to_string())],
+        )
+        .to_unsigned_event(pubkey)
+    }
+
+    /// Parse and check a deactivation event. This does not check the signer owns the name.
+    pub fn from_event(event: &Event) -> anyhow::Result<Deactivation> {
+        if event.kind != Deactivation::kind() {
+            bail!(NomenError::Validation("Not a deactivation event".into()));
+        }
+        event
+            .verify()
+            .map_err(|_| NomenError::Validation("Invalid deactivation signature".into()))?;
+        let name = event
+            .tags
+            .iter()
+            .find_map(|t| match t {
+                Tag::Identifier(d) => Some(d.clone()),
+                _ => None,
+            })
+            .ok_or_else(|| NomenError::Validation("Missing 'd' tag".into()))?;
+        Grammar::SUBNAME.validate(&name)?;
+
+        Ok(Deactivation {
+            event_id: event.id,
+            name,
+            pubkey: event.pubkey,
+            reason: event.content.clone(),
+            created_at: event.created_at.as_i64(),
+            raw_event: event.as_json(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_sdk::Keys;
+
+    use super::*;
+
+    #[test]
+    fn test_deactivation_event() {
+        let owner = Keys::generate();
+        let event = Deactivation::event(owner.public_key(), "smith", "Moved to jones")
+            .sign(&owner)
+            .unwrap();
+
+        let deactivation = Deactivation::from_event(&event).unwrap();
+        assert_eq!(deactivation.name, "smith");
+        assert_eq!(deactivation.pubkey, owner.public_key());
+        assert_eq!(deactivation.reason, "Moved to jones");
+
+        let event = Deactivation::event(owner.public_key(), "Not A Name", "")
+            .sign(&owner)
+            .unwrap();
+        assert!(Deactivation::from_event(&event).is_err());
+    }
+}
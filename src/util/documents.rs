@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+use super::{Hash160, NomenKind, NsidBuilder};
+
+/// Owner of the example documents: the x coordinate of the secp256k1 generator.
+const EXAMPLE_PUBKEY: &str = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+/// New owner in the example transfer.
+const EXAMPLE_NEW_PUBKEY: &str = "d57b873363d2233d3cd54453416deff9546df50d963bb1208da37f10a4c23d6f";
+
+const EXAMPLE_NAME: &str = "smith";
+
+/// A JSON document of the protocol, with an example that `util example` prints.
+pub trait ExampleDocument: Serialize + JsonSchema {
+    fn example() -> anyhow::Result<Self>
+    where
+        Self: Sized;
+}
+
+/// Document types `util example` can print.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Create,
+    Transfer,
+    Records,
+}
+
+impl DocumentKind {
+    /// The example document, as pretty printed JSON.
+    pub fn example(self) -> anyhow::Result<String> {
+        Ok(match self {
+            DocumentKind::Create => serde_json::to_string_pretty(&CreateDocument::example()?)?,
+            DocumentKind::Transfer => serde_json::to_string_pretty(&TransferDocument::example()?)?,
+            DocumentKind::Records => serde_json::to_string_pretty(&RecordsDocument::example()?)?,
+        })
+    }
+
+    pub fn schema(self) -> RootSchema {
+        match self {
+            DocumentKind::Create => schema_for!(CreateDocument),
+            DocumentKind::Transfer => schema_for!(TransferDocument),
+            DocumentKind::Records => schema_for!(RecordsDocument),
+        }
+    }
+}
+
+/// A name claim: the values a `create` OP_RETURN commits to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CreateDocument {
+    /// Name being claimed.
+    pub name: String,
+    /// Hex x-only public key of the owner.
+    pub pubkey: String,
+    /// Hex first 5 bytes of the HASH160 of the name.
+    pub fingerprint: String,
+    /// Hex HASH160 of the name and owner's public key.
+    pub nsid: String,
+    /// Hex SHA-256 of the content of the owner's first records event, if the claim commits to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+    /// Hex OP_RETURN payload of the claim transaction.
+    pub op_return: String,
+}
+
+/// A name transfer: the `transfer` OP_RETURN, and the transfer event's content, signed by the
+/// current owner.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TransferDocument {
+    /// Name being transferred.
+    pub name: String,
+    /// Hex x-only public key of the current owner, who signs the transfer event.
+    pub pubkey: String,
+    /// Hex x-only public key of the new owner, also the content of the transfer event.
+    pub new_pubkey: String,
+    /// Hex first 5 bytes of the HASH160 of the name.
+    pub fingerprint: String,
+    /// Hex HASH160 of the name and new owner's public key.
+    pub nsid: String,
+    /// Hex OP_RETURN payload of the transfer transaction.
+    pub op_return: String,
+}
+
+/// Content of a records event: record keys (e.g. `IP4`, `WEB`) and their values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RecordsDocument(pub BTreeMap<String, String>);
+
+impl ExampleDocument for CreateDocument {
+    fn example() -> anyhow::Result<Self> {
+        let pubkey: XOnlyPublicKey = EXAMPLE_PUBKEY.parse()?;
+        let fingerprint = fingerprint(EXAMPLE_NAME);
+        let nsid = NsidBuilder::new(EXAMPLE_NAME, &pubkey).finalize();
+        Ok(CreateDocument {
+            name: EXAMPLE_NAME.into(),
+            pubkey: pubkey.to_string(),
+            fingerprint: hex::encode(fingerprint),
+            nsid: nsid.to_string(),
+            commitment: None,
+            op_return: hex::encode(crate::subcommands::op_return(
+                fingerprint,
+                nsid,
+                NomenKind::Create,
+                None,
+            )),
+        })
+    }
+}
+
+impl ExampleDocument for TransferDocument {
+    fn example() -> anyhow::Result<Self> {
+        let new_pubkey: XOnlyPublicKey = EXAMPLE_NEW_PUBKEY.parse()?;
+        let fingerprint = fingerprint(EXAMPLE_NAME);
+        let nsid = NsidBuilder::new(EXAMPLE_NAME, &new_pubkey).finalize();
+        Ok(TransferDocument {
+            name: EXAMPLE_NAME.into(),
+            pubkey: EXAMPLE_PUBKEY.into(),
+            new_pubkey: new_pubkey.to_string(),
+            fingerprint: hex::encode(fingerprint),
+            nsid: nsid.to_string(),
+            op_return: hex::encode(crate::subcommands::op_return(
+                fingerprint,
+                nsid,
+                NomenKind::Transfer,
+                None,
+            )),
+        })
+    }
+}
+
+impl ExampleDocument for RecordsDocument {
+    fn example() -> anyhow::Result<Self> {
+        Ok(RecordsDocument(BTreeMap::from([
+            ("IP4".to_string(), "192.0.2.1".to_string()),
+            ("NPUB".to_string(), EXAMPLE_PUBKEY.to_string()),
+            ("WEB".to_string(), format!("https://{EXAMPLE_NAME}.example")),
+        ])))
+    }
+}
+
+fn fingerprint(name: &str) -> [u8; 5] {
+    Hash160::default()
+        .chain_update(name.as_bytes())
+        .fingerprint()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::NomenTx;
+
+    #[test]
+    fn test_examples() {
+        let create = CreateDocument::example().unwrap();
+        let tx = NomenTx::try_from(hex::decode(&create.op_return).unwrap().as_ref()).unwrap();
+        assert_eq!(tx.kind, NomenKind::Create);
+        assert_eq!(tx.nsid.to_string(), create.nsid);
+        assert_eq!(hex::encode(tx.fingerprint), create.fingerprint);
+
+        let transfer = TransferDocument::example().unwrap();
+        let tx = NomenTx::try_from(hex::decode(&transfer.op_return).unwrap().as_ref()).unwrap();
+        assert_eq!(tx.kind, NomenKind::Transfer);
+        assert_eq!(tx.nsid.to_string(), transfer.nsid);
+
+        // Every example has the fields its schema requires.
+        for kind in [
+            DocumentKind::Create,
+            DocumentKind::Transfer,
+            DocumentKind::Records,
+        ] {
+            let example: serde_json::Value =
+                serde_json::from_str(&kind.example().unwrap()).unwrap();
+            let schema = kind.schema().schema;
+            for field in schema.object.map(|o| o.required).unwrap_or_default() {
+                assert!(example.get(&field).is_some(), "{kind:?} is missing {field}");
+            }
+        }
+        let records = DocumentKind::Records.example().unwrap();
+        assert_eq!(
+            serde_json::from_str::<RecordsDocument>(&records).unwrap(),
+            RecordsDocument::example().unwrap()
+        );
+    }
+}
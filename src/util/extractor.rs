@@ -5,7 +5,8 @@ use itertools::Itertools;
 use nostr_sdk::Event;
 use secp256k1::XOnlyPublicKey;
 
-use super::Nsid;
+use super::{Nsid, RecordAnchor};
+use crate::error::NomenError;
 
 pub trait EventExtractor {
     fn extract_children(&self, name: &str) -> anyhow::Result<Vec<(String, XOnlyPublicKey)>>;
@@ -13,6 +14,8 @@ pub trait EventExtractor {
     fn extract_name(&self) -> anyhow::Result<String>;
     fn extract_nsid(&self) -> anyhow::Result<Nsid>;
     fn extract_prev_nsid(&self) -> anyhow::Result<Option<Nsid>>;
+    fn extract_anchor(&self) -> anyhow::Result<Option<RecordAnchor>>;
+    fn extract_expiration(&self) -> Option<i64>;
 }
 
 impl EventExtractor for Event {
@@ -44,7 +47,7 @@ impl EventExtractor for Event {
                 _ => None,
             })
             .next()
-            .ok_or_else(|| anyhow!("Missing or invalid 'nom' tag"))?;
+            .ok_or_else(|| NomenError::Validation("Missing or invalid 'nom' tag".into()))?;
         Ok(name)
     }
 
@@ -57,8 +60,8 @@ impl EventExtractor for Event {
                 _ => None,
             })
             .next()
-            .ok_or_else(|| anyhow!("Missing 'd' tag"))?;
-        nsid.parse()
+            .ok_or_else(|| NomenError::Validation("Missing 'd' tag".into()))?;
+        Nsid::from_hex(&nsid)
     }
 
     fn extract_prev_nsid(&self) -> anyhow::Result<Option<Nsid>> {
@@ -74,7 +77,36 @@ impl EventExtractor for Event {
                 },
                 _ => None,
             })
-            .and_then(|s| s.parse::<Nsid>().ok());
+            .and_then(|s| Nsid::from_hex(&s).ok());
         Ok(nn)
     }
+
+    fn extract_anchor(&self) -> anyhow::Result<Option<RecordAnchor>> {
+        let Some(values) = self.tags.iter().find_map(|t| match t {
+            nostr_sdk::Tag::Generic(nostr_sdk::prelude::TagKind::Custom(tn), values)
+                if tn == "anchor" =>
+            {
+                Some(values)
+            }
+            _ => None,
+        }) else {
+            return Ok(None);
+        };
+        match values.as_slice() {
+            [txid, height, ..] => Ok(Some(RecordAnchor {
+                txid: txid.parse().context("Invalid 'anchor' tag txid")?,
+                height: height.parse().context("Invalid 'anchor' tag height")?,
+            })),
+            _ => Err(anyhow!(NomenError::Validation(
+                "Invalid 'anchor' tag".into()
+            ))),
+        }
+    }
+
+    fn extract_expiration(&self) -> Option<i64> {
+        self.tags.iter().find_map(|t| match t {
+            nostr_sdk::Tag::Expiration(at) => Some(at.as_i64()),
+            _ => None,
+        })
+    }
 }
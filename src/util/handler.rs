@@ -0,0 +1,93 @@
+use nostr_sdk::{prelude::TagKind, EventBuilder, Kind, Tag, UnsignedEvent};
+use secp256k1::XOnlyPublicKey;
+use serde::Serialize;
+
+use super::NameKind;
+
+/// Nostr event kind of a NIP-89 handler information event.
+pub const HANDLER_KIND: u16 = 31990;
+
+/// `d` tag of an indexer's handler event, so announcing again replaces the last one.
+const HANDLER_ID: &str = "nomen";
+
+/// An indexer announcing itself as a resolver of Nomen names (NIP-89), so Nostr clients can
+/// discover one instead of having it configured.
+///
+/// The content is kind 0 style metadata. Tags list the name kinds it handles (`k`), the name
+/// page to open for an nsid (`web`, with `<bech32>` replaced by the client) and the base URL
+/// of the HTTP API (`api`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Handler {
+    /// Shown to users choosing between resolvers.
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub about: Option<String>,
+    /// Public URL of the server, e.g. `https://nomen.example`.
+    #[serde(rename = "website")]
+    pub url: String,
+}
+
+impl Handler {
+    pub fn kind() -> Kind {
+        Kind::ParameterizedReplaceable(HANDLER_KIND)
+    }
+
+    /// The unsigned handler event, published by the operator.
+    pub fn event(&self, operator: XOnlyPublicKey) -> anyhow::Result<UnsignedEvent> {
+        let url = self.url.trim_end_matches('/');
+        let mut tags = vec![Tag::Identifier(HANDLER_ID.into())];
+        tags.extend([NameKind::Name, NameKind::Transfer].map(|kind| {
+            Tag::Generic(TagKind::Custom("k".into()), vec![(kind as u16).to_string()])
+        }));
+        tags.push(Tag::Generic(
+            TagKind::Custom("web".into()),
+            vec![format!("{url}/explorer/<bech32>"), "nsid".into()],
+        ));
+        tags.push(Tag::Generic(
+            TagKind::Custom("api".into()),
+            vec![format!("{url}/api")],
+        ));
+        Ok(
+            EventBuilder::new(Handler::kind(), serde_json::to_string(self)?, &tags)
+                .to_unsigned_event(operator),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_sdk::Keys;
+
+    use super::*;
+
+    #[test]
+    fn test_handler_event() {
+        let operator = Keys::generate();
+        let handler = Handler {
+            name: "Example indexer".into(),
+            about: None,
+            url: "https://nomen.example/".into(),
+        };
+        let event = handler
+            .event(operator.public_key())
+            .unwrap()
+            .sign(&operator)
+            .unwrap();
+
+        assert_eq!(event.kind, Handler::kind());
+        let content: serde_json::Value = serde_json::from_str(&event.content).unwrap();
+        assert_eq!(content["name"], "Example indexer");
+        assert_eq!(content["website"], "https://nomen.example/");
+        assert!(content.get("about").is_none());
+
+        let tags: Vec<Vec<String>> = event.tags.iter().map(|t| t.as_vec()).collect();
+        assert_eq!(tags[0], ["d", "nomen"]);
+        assert_eq!(tags[1], ["k", "38300"]);
+        assert_eq!(tags[2], ["k", "38301"]);
+        assert_eq!(
+            tags[3],
+            ["web", "https://nomen.example/explorer/<bech32>", "nsid"]
+        );
+        assert_eq!(tags[4], ["api", "https://nomen.example/api"]);
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+pub use nomen_core::api_types::{RecordChange, RecordsDiff};
+use nostr_sdk::EventId;
+use sqlx::SqlitePool;
+
+use crate::{config::Config, db, error::NomenError};
+
+use super::Moment;
+
+/// What the records of `name` were at `moment`, from the records and ownership history. A height
+/// is resolved to the time of its block, which records events must not be newer than.
+pub async fn records_at(
+    config: &Config,
+    pool: &SqlitePool,
+    name: &str,
+    moment: Moment,
+) -> anyhow::Result<db::HistoricalRecords> {
+    let (height, time) = match moment {
+        Moment::Height(height) => {
+            let tip = db::index_tip(pool).await?.map_or(0, |(tip, _)| tip);
+            if height > tip {
+                bail!(NomenError::Validation(format!(
+                    "Height {height} is past the index tip {tip}"
+                )));
+            }
+            let time = config.rpc()?.get_block_time(height as u64).await?;
+            (Some(height), time as i64)
+        }
+        Moment::Time(time) => (None, time.unix_timestamp()),
+    };
+    db::records_at(pool, name, height, time)
+        .await?
+        .ok_or_else(|| {
+            NomenError::NotFound(format!("{name} was not registered at {moment}")).into()
+        })
+}
+
+/// A records event of `name` from the history: its records and creation time.
+pub async fn history_records(
+    pool: &SqlitePool,
+    name: &str,
+    event_id: EventId,
+) -> anyhow::Result<(HashMap<String, String>, i64)> {
+    let (records, created_at) = db::history_records(pool, name, &event_id.to_hex())
+        .await?
+        .ok_or_else(|| {
+            NomenError::NotFound(format!("{name} has no records event {}", event_id.to_hex()))
+        })?;
+    Ok((serde_json::from_str(&records)?, created_at))
+}
@@ -2,6 +2,8 @@ use std::str::FromStr;
 
 use anyhow::anyhow;
 
+use crate::error::NomenError;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct KeyVal(String, String);
 
@@ -17,7 +19,7 @@ impl FromStr for KeyVal {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (key, val) = s
             .split_once('=')
-            .ok_or_else(|| anyhow!("Invalid key=value"))?;
+            .ok_or_else(|| NomenError::Validation("Invalid key=value".into()))?;
         Ok(KeyVal(key.to_string().to_uppercase(), val.to_string()))
     }
 }
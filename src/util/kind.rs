@@ -1,106 +1,27 @@
-use std::{fmt::Display, str::FromStr};
-
-use anyhow::{anyhow, bail};
+pub use nomen_core::NomenKind;
 
 use super::Nsid;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum NomenKind {
-    Create,
-    Transfer,
-}
-
-impl Display for NomenKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            NomenKind::Create => "create",
-            NomenKind::Transfer => "transfer",
-        };
-        write!(f, "{s}")
-    }
-}
-
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct NomenTx {
     pub kind: NomenKind,
     pub fingerprint: [u8; 5],
     pub nsid: Nsid,
-}
-
-impl NomenTx {
-    fn create(fingerprint: [u8; 5], nsid: Nsid) -> NomenTx {
-        NomenTx {
-            kind: NomenKind::Create,
-            fingerprint,
-            nsid,
-        }
-    }
-
-    fn transfer(fingerprint: [u8; 5], nsid: Nsid) -> NomenTx {
-        NomenTx {
-            kind: NomenKind::Transfer,
-            fingerprint,
-            nsid,
-        }
-    }
-
-    fn parse_create(value: &[u8]) -> anyhow::Result<NomenTx> {
-        Ok(NomenTx::create(
-            value[..5].try_into()?,
-            value[5..].try_into()?,
-        ))
-    }
-
-    fn parse_transfer(value: &[u8]) -> anyhow::Result<NomenTx> {
-        Ok(NomenTx::transfer(
-            value[..5].try_into()?,
-            value[5..].try_into()?,
-        ))
-    }
-}
-
-impl From<NomenKind> for u8 {
-    fn from(value: NomenKind) -> Self {
-        match value {
-            NomenKind::Create => 0x00,
-            NomenKind::Transfer => 0x01,
-        }
-    }
-}
-
-impl FromStr for NomenKind {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "create" => Ok(NomenKind::Create),
-            "transfer" => Ok(NomenKind::Transfer),
-            _ => Err(anyhow!("Unrecognized Nomen transaction type")),
-        }
-    }
+    /// Commitment to the owner's first records event, if the claim has one.
+    pub commitment: Option<[u8; 32]>,
 }
 
 impl TryFrom<&[u8]> for NomenTx {
     type Error = anyhow::Error;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        if !value.starts_with(b"NOM") {
-            bail!("Not an Nomen transaction")
-        }
-        let value = &value[3..];
-
-        if !value.starts_with(&[0x00]) {
-            bail!("Unsupported Nomen version")
-        }
-        let value = &value[1..];
-
-        let kind = match value.first() {
-            Some(0x00) => NomenTx::parse_create(&value[1..])?,
-            Some(0x01) => NomenTx::parse_transfer(&value[1..])?,
-            _ => bail!("Unexpected blockchain tx type"),
-        };
-
-        Ok(kind)
+        let op_return = nomen_core::OpReturn::parse(value)?;
+        Ok(NomenTx {
+            kind: op_return.kind,
+            fingerprint: op_return.fingerprint,
+            nsid: op_return.nsid.into(),
+            commitment: op_return.commitment,
+        })
     }
 }
 
@@ -124,7 +45,12 @@ mod tests {
             .collect_vec();
         assert_eq!(
             NomenTx::try_from(create.as_ref()).unwrap(),
-            NomenTx::create(fp.try_into().unwrap(), nsid)
+            NomenTx {
+                kind: NomenKind::Create,
+                fingerprint: fp.try_into().unwrap(),
+                nsid,
+                commitment: None,
+            }
         );
     }
 
@@ -0,0 +1,47 @@
+use std::{future::Future, io::Write};
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Log like `env_logger` does by default, with the id of the server request being handled, if any,
+/// after the target: `[2024-05-01T12:00:00Z INFO  nomen::db 1b4e28ba-...] message`.
+pub fn init_logger() {
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            let level = buf.default_styled_level(record.level());
+            write!(buf, "[{} {level:<5} {}", buf.timestamp(), record.target())?;
+            if let Some(id) = request_id() {
+                write!(buf, " {id}")?;
+            }
+            writeln!(buf, "] {}", record.args())
+        })
+        .init();
+}
+
+/// Run `f` with `id` as the request id of every line it logs.
+pub async fn with_request_id<F: Future>(id: String, f: F) -> F::Output {
+    REQUEST_ID.scope(id, f).await
+}
+
+/// The id of the server request being handled by the current task.
+pub fn request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_id() {
+        assert_eq!(request_id(), None);
+        let id = with_request_id("abc".into(), async {
+            tokio::task::yield_now().await;
+            request_id()
+        })
+        .await;
+        assert_eq!(id.as_deref(), Some("abc"));
+        assert_eq!(request_id(), None);
+    }
+}
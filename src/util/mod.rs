@@ -1,28 +1,59 @@
+mod approval;
+mod checkpoint;
+mod datetime;
+mod deactivation;
+mod documents;
 mod extractor;
-mod hash160;
+mod handler;
+mod history;
 mod keyval;
 mod kind;
+mod logging;
 mod name;
 mod nostr;
 mod nsid;
 mod nsid_builder;
+mod offer;
+mod quorum;
+mod record;
+mod script;
+mod similar;
+mod snapshot;
+mod vectors;
 
 use anyhow::bail;
+pub use approval::*;
+pub use checkpoint::*;
+pub use datetime::*;
+pub use deactivation::*;
+pub use documents::*;
 pub use extractor::*;
-pub use hash160::*;
+pub use handler::*;
+pub use history::*;
 pub use keyval::*;
 pub use kind::*;
+pub use logging::*;
 pub use name::*;
+pub use nomen_core::Hash160;
 pub use nostr::*;
 pub use nsid::*;
 pub use nsid_builder::*;
+pub use offer::*;
+pub use quorum::*;
+pub use record::*;
+pub use script::*;
+pub use similar::*;
+pub use snapshot::*;
+pub use vectors::*;
 use yansi::Paint;
 
 use crate::{
     config::{Cli, Config},
     db,
+    error::NomenError,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NameKind {
     Name = 38300,
     Transfer = 38301,
@@ -41,7 +72,7 @@ impl TryFrom<nostr_sdk::Kind> for NameKind {
         let nk = match value {
             nostr_sdk::Kind::ParameterizedReplaceable(38300) => NameKind::Name,
             nostr_sdk::Kind::ParameterizedReplaceable(38301) => NameKind::Transfer,
-            _ => bail!("Invalid Event kind"),
+            _ => bail!(NomenError::Validation("Invalid Event kind".into())),
         };
         Ok(nk)
     }
@@ -52,10 +83,13 @@ pub fn tag_print(tag: &str, message: &str) {
 }
 
 pub async fn check_name_availability(config: &Config, name: &str) -> anyhow::Result<()> {
+    config.grammar().validate(name)?;
     let conn = config.sqlite().await?;
     let available = db::name_available(&conn, name).await?;
     if !available {
-        bail!("Name {name} already exists");
+        bail!(NomenError::Validation(format!(
+            "Name {name} already exists"
+        )));
     }
     Ok(())
 }
@@ -1,8 +1,8 @@
 use std::str::FromStr;
 
-use anyhow::bail;
 use derive_more::{AsRef, Display, Into};
-use regex::Regex;
+
+use crate::protocol::Grammar;
 
 #[derive(Display, AsRef, Debug, Clone, PartialEq, Eq, Default)]
 pub struct Name(String);
@@ -11,12 +11,8 @@ impl FromStr for Name {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let r = Regex::new(r#"\A[0-9a-z\-]{3,256}\z"#)?;
-        if r.is_match(s) {
-            return Ok(Name(s.into()));
-        }
-
-        bail!("Invalid name format")
+        Grammar::SUBNAME.validate(s)?;
+        Ok(Name(s.into()))
     }
 }
 
@@ -32,6 +28,12 @@ mod tests {
         assert_eq!(s.unwrap(), Name("smith".to_string()))
     }
 
+    #[test]
+    fn test_subname() {
+        assert!("bob.smith".parse::<Name>().is_ok());
+        assert!("bob..smith".parse::<Name>().is_err());
+    }
+
     #[test]
     fn test_invalid() {
         let s: anyhow::Result<Name> = "Smith".parse();
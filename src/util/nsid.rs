@@ -4,11 +4,14 @@ use std::{
     str::FromStr,
 };
 
-use bitcoin::secp256k1::XOnlyPublicKey;
+use anyhow::bail;
+use bitcoin::bech32::{self, FromBase32, ToBase32, Variant};
 use derive_more::{AsMut, AsRef, Deref, DerefMut, From};
-use nostr_sdk::Event;
 
-use super::{EventExtractor, NameKind, NsidBuilder};
+use crate::error::NomenError;
+
+/// Human readable part of bech32 encoded nsids.
+pub const NSID_HRP: &str = "nom";
 
 #[derive(Clone, Copy, Deref, DerefMut, AsRef, AsMut, From, Eq, PartialEq)]
 pub struct Nsid([u8; 20]);
@@ -18,40 +21,47 @@ impl Nsid {
     pub fn from_slice(bytes: &[u8]) -> anyhow::Result<Nsid> {
         Ok(Nsid(bytes.try_into()?))
     }
-}
 
-impl TryFrom<&[u8]> for Nsid {
-    type Error = anyhow::Error;
+    /// Parse the hex encoding, which is the only one used in events and OP_RETURNs.
+    pub fn from_hex(s: &str) -> anyhow::Result<Nsid> {
+        let mut out = [0u8; 20];
+        hex::decode_to_slice(s, &mut out)?;
+        Ok(Nsid(out))
+    }
 
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        Nsid::from_slice(value)
+    /// Parse a bech32 encoded nsid, e.g. `nom1…`.
+    pub fn from_bech32(s: &str) -> anyhow::Result<Nsid> {
+        let (hrp, data, variant) = bech32::decode(s)?;
+        if hrp != NSID_HRP || variant != Variant::Bech32 {
+            bail!(NomenError::Validation(format!("Not a bech32 nsid: {s}")));
+        }
+        Nsid::from_slice(&Vec::<u8>::from_base32(&data)?)
+    }
+
+    /// The bech32 encoding, for display. Hex remains the canonical encoding.
+    pub fn to_bech32(self) -> String {
+        bech32::encode(NSID_HRP, self.0.to_base32(), Variant::Bech32).expect("nsid HRP is valid")
     }
 }
 
-impl TryFrom<Event> for Nsid {
+impl TryFrom<&[u8]> for Nsid {
     type Error = anyhow::Error;
 
-    fn try_from(event: Event) -> Result<Self, Self::Error> {
-        let nk: NameKind = event.kind.try_into()?;
-        let name = event.extract_name()?;
-        let builder = match nk {
-            NameKind::Name => NsidBuilder::new(&name, &event.pubkey),
-            NameKind::Transfer => {
-                let nextpk: XOnlyPublicKey = event.content.parse()?;
-                NsidBuilder::new(&name, &nextpk)
-            }
-        };
-        Ok(builder.finalize())
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Nsid::from_slice(value)
     }
 }
 
 impl FromStr for Nsid {
     type Err = anyhow::Error;
 
+    /// Accepts either the hex or the bech32 encoding.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut out = [0u8; 20];
-        hex::decode_to_slice(s, &mut out)?;
-        Ok(Nsid(out))
+        if s.starts_with(NSID_HRP) {
+            Nsid::from_bech32(s)
+        } else {
+            Nsid::from_hex(s)
+        }
     }
 }
 
@@ -66,3 +76,26 @@ impl Display for Nsid {
         write!(f, "{}", hex::encode(self.0))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bech32() {
+        let hex = "08c695d6d7c615fda7ea2b415502ce3de57ef010";
+        let nsid: Nsid = hex.parse().unwrap();
+        let bech32 = nsid.to_bech32();
+        assert!(bech32.starts_with("nom1"));
+        assert_eq!(bech32.parse::<Nsid>().unwrap(), nsid);
+        assert_eq!(Nsid::from_bech32(&bech32).unwrap().to_string(), hex);
+
+        assert!(Nsid::from_hex(&bech32).is_err());
+        let npub = bech32::encode("npub", nsid.to_base32(), Variant::Bech32).unwrap();
+        assert!(Nsid::from_bech32(&npub).is_err());
+        let mut typo = bech32.clone();
+        typo.pop();
+        typo.push(if bech32.ends_with('q') { 'p' } else { 'q' });
+        assert!(typo.parse::<Nsid>().is_err());
+    }
+}
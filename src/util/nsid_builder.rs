@@ -1,7 +1,5 @@
 use bitcoin::secp256k1::XOnlyPublicKey;
 
-use crate::util::Hash160;
-
 use super::Nsid;
 
 pub struct NsidBuilder {
@@ -18,10 +16,7 @@ impl NsidBuilder {
     }
 
     pub fn finalize(self) -> Nsid {
-        let mut hasher = Hash160::default();
-        hasher.update(self.root_name.as_bytes());
-        hasher.update(&self.pk.serialize());
-        hasher.finalize().into()
+        nomen_core::derive_nsid(&self.root_name, &self.pk.serialize()).into()
     }
 }
 
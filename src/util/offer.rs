@@ -0,0 +1,245 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use bitcoin::{
+    psbt::{Input, Psbt, PsbtSighashType},
+    sighash::{EcdsaSighashType, TapSighashType},
+};
+use nostr_sdk::{prelude::TagKind, Event, EventBuilder, EventId, Tag, UnsignedEvent};
+use secp256k1::XOnlyPublicKey;
+
+use super::{EventExtractor, Hash160, Name, NomenKind, Nsid, NsidBuilder};
+use crate::error::NomenError;
+
+/// Nostr event kind of an offer to sell a name.
+pub const OFFER_KIND: u16 = 38302;
+
+/// An offer to sell a name to a buyer.
+///
+/// The seller's PSBT pays the seller and contains the transfer OP_RETURN for the buyer.
+/// Its inputs are signed with `SIGHASH_ALL|SIGHASH_ANYONECANPAY`, so the buyer can add inputs
+/// but cannot change the outputs. The price is whatever the outputs pay above the seller's inputs.
+#[derive(Debug, Clone)]
+pub struct Offer {
+    pub event_id: EventId,
+    pub name: Name,
+    pub nsid: Nsid,
+    pub seller: XOnlyPublicKey,
+    pub buyer: XOnlyPublicKey,
+    pub price: u64,
+    pub psbt: Psbt,
+    pub created_at: i64,
+    pub raw_event: String,
+}
+
+impl Offer {
+    pub fn kind() -> nostr_sdk::Kind {
+        nostr_sdk::Kind::ParameterizedReplaceable(OFFER_KIND)
+    }
+
+    /// The unsigned offer event for `psbt`, published by the seller.
+    pub fn event(
+        seller: XOnlyPublicKey,
+        name: &Name,
+        buyer: &XOnlyPublicKey,
+        psbt: &Psbt,
+    ) -> UnsignedEvent {
+        let nsid = NsidBuilder::new(name.as_ref(), buyer).finalize();
+        EventBuilder::new(
+            Offer::kind(),
+            psbt.to_string(),
+            &[
+                Tag::Identifier(nsid.to_string()),
+                Tag::Generic(TagKind::Custom("nom".to_owned()), vec![name.to_string()]),
+                Tag::PubKey(*buyer, None),
+            ],
+        )
+        .to_unsigned_event(seller)
+    }
+
+    /// Parse and check an offer event. This does not check the seller owns the name.
+    pub fn from_event(event: &Event) -> anyhow::Result<Offer> {
+        if event.kind != Offer::kind() {
+            bail!(NomenError::Validation("Not an offer event".into()));
+        }
+        let name: Name = event.extract_name()?.parse()?;
+        let nsid = event.extract_nsid()?;
+        let buyer = event
+            .tags
+            .iter()
+            .find_map(|t| match t {
+                Tag::PubKey(pk, _) => Some(*pk),
+                _ => None,
+            })
+            .ok_or_else(|| NomenError::Validation("Missing 'p' tag".into()))?;
+        let psbt = Psbt::from_str(&event.content)?;
+
+        if nsid != NsidBuilder::new(name.as_ref(), &buyer).finalize() {
+            bail!(NomenError::Validation("Invalid nsid".into()));
+        }
+        let fingerprint = Hash160::default()
+            .chain_update(name.as_ref().as_bytes())
+            .fingerprint();
+        if !has_transfer_output(&psbt, fingerprint, nsid) {
+            bail!(NomenError::Validation(
+                "Offer transaction does not transfer the name to the buyer".into()
+            ));
+        }
+        if !psbt.inputs.iter().all(signed_anyone_can_pay) {
+            bail!(NomenError::Validation(
+                "Offer inputs must be signed with SIGHASH_ALL|SIGHASH_ANYONECANPAY".into()
+            ));
+        }
+
+        Ok(Offer {
+            event_id: event.id,
+            name,
+            nsid,
+            seller: event.pubkey,
+            buyer,
+            price: price(&psbt)?,
+            psbt,
+            created_at: event.created_at.as_i64(),
+            raw_event: serde_json::to_string(event)?,
+        })
+    }
+}
+
+/// The sighash type every seller input is signed with.
+pub fn offer_sighash_type() -> PsbtSighashType {
+    EcdsaSighashType::AllPlusAnyoneCanPay.into()
+}
+
+/// Amount the outputs of `psbt` pay above the value of its inputs.
+pub fn price(psbt: &Psbt) -> anyhow::Result<u64> {
+    output_value(psbt)
+        .checked_sub(input_value(psbt)?)
+        .ok_or_else(|| {
+            anyhow!(NomenError::Validation(
+                "Offer transaction pays less than its inputs".into()
+            ))
+        })
+}
+
+/// Total value of the inputs of `psbt`, which must all have their previous output.
+pub fn input_value(psbt: &Psbt) -> anyhow::Result<u64> {
+    let mut total = 0;
+    for (txin, input) in psbt.unsigned_tx.input.iter().zip(&psbt.inputs) {
+        let vout = txin.previous_output.vout as usize;
+        total += match (&input.witness_utxo, &input.non_witness_utxo) {
+            (Some(utxo), _) => utxo.value,
+            (None, Some(tx)) if vout < tx.output.len() => tx.output[vout].value,
+            _ => bail!(NomenError::Validation(format!(
+                "Missing input amount for {}",
+                txin.previous_output
+            ))),
+        };
+    }
+    Ok(total)
+}
+
+pub fn output_value(psbt: &Psbt) -> u64 {
+    psbt.unsigned_tx.output.iter().map(|o| o.value).sum()
+}
+
+/// Whether `psbt` has the transfer OP_RETURN for `nsid`.
+pub fn has_transfer_output(psbt: &Psbt, fingerprint: [u8; 5], nsid: Nsid) -> bool {
+    let op_return = crate::subcommands::op_return(fingerprint, nsid, NomenKind::Transfer, None);
+    psbt.unsigned_tx
+        .output
+        .iter()
+        .any(|o| o.script_pubkey.is_op_return() && o.script_pubkey.as_bytes().ends_with(&op_return))
+}
+
+/// Whether `input` is signed, and every signature is `SIGHASH_ALL|SIGHASH_ANYONECANPAY`.
+pub fn signed_anyone_can_pay(input: &Input) -> bool {
+    const ACP: u8 = EcdsaSighashType::AllPlusAnyoneCanPay as u8;
+    if let Some(witness) = &input.final_script_witness {
+        // The signature is the first witness element, with the sighash type as its last byte.
+        return witness.nth(0).and_then(|sig| sig.last()) == Some(&ACP);
+    }
+    if let Some(sig) = &input.tap_key_sig {
+        return sig.hash_ty == TapSighashType::AllPlusAnyoneCanPay;
+    }
+    !input.partial_sigs.is_empty()
+        && input
+            .partial_sigs
+            .values()
+            .all(|sig| sig.hash_ty == EcdsaSighashType::AllPlusAnyoneCanPay)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{
+        absolute::LockTime, psbt::Output, script::PushBytesBuf, OutPoint, ScriptBuf, Transaction,
+        TxIn, TxOut,
+    };
+    use nostr_sdk::Keys;
+
+    use super::*;
+
+    fn offer_psbt(name: &str, buyer: &XOnlyPublicKey) -> Psbt {
+        let fingerprint = Hash160::default()
+            .chain_update(name.as_bytes())
+            .fingerprint();
+        let nsid = NsidBuilder::new(name, buyer).finalize();
+        let op_return: PushBytesBuf =
+            crate::subcommands::op_return(fingerprint, nsid, NomenKind::Transfer, None)
+                .try_into()
+                .unwrap();
+        let tx = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                ..Default::default()
+            }],
+            output: vec![
+                TxOut {
+                    value: 150_000,
+                    script_pubkey: ScriptBuf::new(),
+                },
+                TxOut {
+                    value: 0,
+                    script_pubkey: ScriptBuf::new_op_return(&op_return),
+                },
+            ],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 50_000,
+            script_pubkey: ScriptBuf::new(),
+        });
+        psbt.outputs = vec![Output::default(), Output::default()];
+        psbt
+    }
+
+    #[test]
+    fn test_price() {
+        let buyer = Keys::generate().public_key();
+        let psbt = offer_psbt("smith", &buyer);
+        assert_eq!(price(&psbt).unwrap(), 100_000);
+
+        let fingerprint = Hash160::default().chain_update(b"smith").fingerprint();
+        let nsid = NsidBuilder::new("smith", &buyer).finalize();
+        assert!(has_transfer_output(&psbt, fingerprint, nsid));
+        let other = NsidBuilder::new("smith", &Keys::generate().public_key()).finalize();
+        assert!(!has_transfer_output(&psbt, fingerprint, other));
+    }
+
+    #[test]
+    fn test_unsigned_offer_rejected() {
+        let seller = Keys::generate();
+        let buyer = Keys::generate().public_key();
+        let psbt = offer_psbt("smith", &buyer);
+        let event = Offer::event(
+            seller.public_key(),
+            &"smith".parse().unwrap(),
+            &buyer,
+            &psbt,
+        )
+        .sign(&seller)
+        .unwrap();
+        assert!(Offer::from_event(&event).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+use anyhow::bail;
+use nostr_sdk::{Client, Event, Filter, Keys, Options, Tag};
+
+use crate::{
+    config::{Config, RelayUse},
+    error::NomenError,
+};
+
+/// How long each relay has to answer a quorum read.
+const QUORUM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The latest event matching `filter` that at least `nostr.read_quorum` relays agree on.
+///
+/// Every relay is asked in parallel for its latest matching event. The answer is the newest event
+/// that `read_quorum` of them returned as their latest, or `None` if that many relays have no
+/// matching event at all. A single relay serving a stale or withheld event cannot outvote the
+/// others; if no answer has enough votes, this fails.
+pub async fn quorum_latest(config: &Config, filter: Filter) -> anyhow::Result<Option<Event>> {
+    let relays = config.relays_for(&filter.kinds.clone().unwrap_or_default(), RelayUse::Read);
+    let quorum = config.nostr_read_quorum();
+    if quorum > relays.len() {
+        bail!(NomenError::Config(format!(
+            "nostr.read_quorum is {quorum}, but only {} relays are configured",
+            relays.len()
+        )));
+    }
+    let answers = futures::future::join_all(
+        relays
+            .into_iter()
+            .map(|url| relay_latest(url, filter.clone(), config.network_proxy())),
+    )
+    .await;
+    agreed(answers, quorum)
+}
+
+/// Events that would replace the parameterized replaceable `event`: same kind, author and `d` tag.
+pub fn replacements_filter(event: &Event) -> Filter {
+    let identifier = event.tags.iter().find_map(|t| match t {
+        Tag::Identifier(d) => Some(d.clone()),
+        _ => None,
+    });
+    let mut custom = serde_json::Map::new();
+    custom.insert("#d".into(), vec![identifier.unwrap_or_default()].into());
+    Filter::new()
+        .kind(event.kind)
+        .author(event.pubkey.to_string())
+        .custom(custom)
+}
+
+/// The latest event matching `filter` on the relay at `url`, or `None` if it has none.
+async fn relay_latest(
+    url: String,
+    filter: Filter,
+    proxy: Option<SocketAddr>,
+) -> anyhow::Result<Option<Event>> {
+    let client = Client::with_opts(&Keys::generate(), Options::new());
+    client.add_relay(url.as_str(), proxy).await?;
+    client.connect().await;
+    let events = client
+        .get_events_of(vec![filter], Some(QUORUM_TIMEOUT))
+        .await;
+    client.disconnect().await.ok();
+    Ok(events?
+        .into_iter()
+        .filter(|e| e.verify().is_ok())
+        .max_by_key(|e| (e.created_at, e.id)))
+}
+
+/// The newest answer given by at least `quorum` relays. Relays that failed to answer don't vote.
+fn agreed(
+    answers: Vec<anyhow::Result<Option<Event>>>,
+    quorum: usize,
+) -> anyhow::Result<Option<Event>> {
+    let answered = answers.len();
+    let mut votes: HashMap<Option<String>, (usize, Option<Event>)> = HashMap::new();
+    for answer in answers {
+        match answer {
+            Ok(event) => {
+                let vote = votes
+                    .entry(event.as_ref().map(|e| e.id.to_hex()))
+                    .or_insert((0, event));
+                vote.0 += 1;
+            }
+            Err(err) => log::debug!("Relay failed to answer a quorum read: {err}"),
+        }
+    }
+    let Some((_, latest)) = votes
+        .into_values()
+        .filter(|(count, _)| *count >= quorum)
+        .max_by_key(|(_, event)| event.as_ref().map(|e| (e.created_at, e.id)))
+    else {
+        bail!(NomenError::Relay(format!(
+            "Fewer than {quorum} of {answered} relays agree on the latest event"
+        )));
+    };
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use nostr_sdk::{EventBuilder, Timestamp};
+
+    use super::*;
+
+    #[test]
+    fn test_agreed() {
+        let keys = Keys::generate();
+        let event = |created_at: u64| {
+            let mut unsigned =
+                EventBuilder::new_text_note("records", &[]).to_unsigned_event(keys.public_key());
+            unsigned.created_at = Timestamp::from(created_at);
+            unsigned.id = nostr_sdk::EventId::new(
+                &unsigned.pubkey,
+                unsigned.created_at,
+                &unsigned.kind,
+                &unsigned.tags,
+                &unsigned.content,
+            );
+            unsigned.sign(&keys).unwrap()
+        };
+        let (old, new) = (event(100), event(200));
+
+        // One relay serving an old event is outvoted.
+        let answers = vec![
+            Ok(Some(new.clone())),
+            Ok(Some(new.clone())),
+            Ok(Some(old.clone())),
+        ];
+        assert_eq!(agreed(answers, 2).unwrap().unwrap().id, new.id);
+
+        // Two relays hiding the new event win over one that has it.
+        let answers = vec![Ok(Some(new.clone())), Ok(None), Ok(None)];
+        assert!(agreed(answers, 2).unwrap().is_none());
+
+        // With a quorum of one, the newest answer wins.
+        let answers = vec![Ok(Some(old.clone())), Ok(Some(new.clone())), Ok(None)];
+        assert_eq!(agreed(answers, 1).unwrap().unwrap().id, new.id);
+
+        // Failed relays don't count.
+        let answers = vec![
+            Ok(Some(new.clone())),
+            Err(anyhow!("timeout")),
+            Ok(Some(old)),
+        ];
+        assert!(agreed(answers, 2).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use anyhow::bail;
+use bitcoin::Txid;
+use nostr_sdk::{prelude::TagKind, Tag};
+use serde::{Deserialize, Serialize};
+
+use crate::error::NomenError;
+
+/// Record keys whose values are JSON documents instead of plain strings.
+///
+/// Records are always stored and published as strings. Typed values are checked when a record
+/// event is created, and parsed for `/api/v1` responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordType {
+    /// `DNS`: a hostname, as before, or a JSON array of [`DnsRecord`].
+    Dns,
+}
+
+impl RecordType {
+    /// The type of `key`, if its value is JSON.
+    pub fn of(key: &str) -> Option<RecordType> {
+        match key {
+            "DNS" => Some(RecordType::Dns),
+            _ => None,
+        }
+    }
+
+    /// Parse and check `value` against the type's schema.
+    pub fn parse(&self, value: &str) -> anyhow::Result<serde_json::Value> {
+        match self {
+            RecordType::Dns if !value.trim_start().starts_with('[') => {
+                Ok(serde_json::Value::String(value.to_string()))
+            }
+            RecordType::Dns => {
+                let records: Vec<DnsRecord> = serde_json::from_str(value)?;
+                for record in &records {
+                    record.validate()?;
+                }
+                Ok(serde_json::to_value(records)?)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Ns,
+    Txt,
+    Srv,
+    Caa,
+}
+
+/// One entry of a `DNS` record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DnsRecord {
+    #[serde(rename = "type")]
+    pub kind: DnsRecordType,
+
+    /// Host relative to the name, e.g. `www`. The name itself if absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    pub value: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+}
+
+impl DnsRecord {
+    fn validate(&self) -> anyhow::Result<()> {
+        let valid = match self.kind {
+            DnsRecordType::A => self.value.parse::<Ipv4Addr>().is_ok(),
+            DnsRecordType::Aaaa => self.value.parse::<Ipv6Addr>().is_ok(),
+            _ => !self.value.is_empty(),
+        };
+        if !valid {
+            bail!(NomenError::Validation(format!(
+                "Invalid {:?} DNS record value {:?}",
+                self.kind, self.value
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The transaction a records event is bound to, by its `["anchor", <txid>, <height>]` tag.
+///
+/// A bound event is only served while its anchor is the name's latest transaction for the owner,
+/// so an owner's old records don't come back if the name is transferred away and back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordAnchor {
+    pub txid: Txid,
+    pub height: i64,
+}
+
+impl RecordAnchor {
+    pub fn tag(&self) -> Tag {
+        Tag::Generic(
+            TagKind::Custom("anchor".into()),
+            vec![self.txid.to_string(), self.height.to_string()],
+        )
+    }
+}
+
+/// Bounds on the records of one event, set in `[protocol]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordLimits {
+    /// Largest records JSON, in bytes.
+    pub max_size: usize,
+    pub max_records: usize,
+    /// Largest record value, in bytes.
+    pub max_value: usize,
+}
+
+impl Default for RecordLimits {
+    fn default() -> Self {
+        RecordLimits {
+            max_size: 8192,
+            max_records: 64,
+            max_value: 2048,
+        }
+    }
+}
+
+impl RecordLimits {
+    /// Check the records JSON `content` of an event, and its parsed `records` if it has any.
+    pub fn check(
+        &self,
+        content: &str,
+        records: Option<&HashMap<String, String>>,
+    ) -> anyhow::Result<()> {
+        if content.len() > self.max_size {
+            bail!(NomenError::Validation(format!(
+                "Records are {} bytes, more than the limit of {}",
+                content.len(),
+                self.max_size
+            )));
+        }
+        let Some(records) = records else {
+            return Ok(());
+        };
+        if records.len() > self.max_records {
+            bail!(NomenError::Validation(format!(
+                "{} records, more than the limit of {}",
+                records.len(),
+                self.max_records
+            )));
+        }
+        if let Some((key, value)) = records.iter().find(|(_, v)| v.len() > self.max_value) {
+            bail!(NomenError::Validation(format!(
+                "Record {key} is {} bytes, more than the limit of {}",
+                value.len(),
+                self.max_value
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Check the value of `key` if it is a typed record. Plain records are always valid.
+pub fn validate_record(key: &str, value: &str) -> anyhow::Result<()> {
+    if let Some(kind) = RecordType::of(key) {
+        kind.parse(value).map_err(|e| {
+            NomenError::Validation(format!("Invalid {key} record: {}", e.root_cause()))
+        })?;
+    }
+    Ok(())
+}
+
+/// Records from a JSON object, e.g. `{"WEB": "https://smith.example"}`, with keys uppercased like
+/// `key=value` arguments. Typed records may be given as JSON (a `DNS` array), and are published as
+/// its text; every other value must be a string. Each record is checked like `validate_record`.
+pub fn records_from_json(json: &str) -> anyhow::Result<HashMap<String, String>> {
+    let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)
+        .map_err(|e| NomenError::Validation(format!("Records must be a JSON object: {e}")))?;
+    let mut records = HashMap::new();
+    for (key, value) in object {
+        let key = key.to_uppercase();
+        let value = match value {
+            serde_json::Value::String(value) => value,
+            value if RecordType::of(&key).is_some() => value.to_string(),
+            value => bail!(NomenError::Validation(format!(
+                "Record {key} must be a string, not {value}"
+            ))),
+        };
+        validate_record(&key, &value)?;
+        if records.insert(key.clone(), value).is_some() {
+            bail!(NomenError::Validation(format!(
+                "Record {key} is given twice"
+            )));
+        }
+    }
+    Ok(records)
+}
+
+/// The JSON value of a record: parsed if it is a valid typed record, otherwise the raw string.
+pub fn typed_value(key: &str, value: String) -> serde_json::Value {
+    RecordType::of(key)
+        .and_then(|kind| kind.parse(&value).ok())
+        .unwrap_or(serde_json::Value::String(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_dns_record() {
+        let value = r#"[{"type":"A","value":"192.0.2.1"},{"type":"CNAME","host":"www","value":"smith","ttl":300}]"#;
+        assert!(validate_record("DNS", value).is_ok());
+        assert_eq!(
+            typed_value("DNS", value.to_string()),
+            json!([
+                {"type": "A", "value": "192.0.2.1"},
+                {"type": "CNAME", "host": "www", "value": "smith", "ttl": 300}
+            ])
+        );
+
+        assert_eq!(
+            typed_value("DNS", "smith.example".into()),
+            json!("smith.example")
+        );
+
+        let bad = r#"[{"type":"A","value":"not an ip"}]"#;
+        assert!(validate_record("DNS", bad).is_err());
+        assert_eq!(typed_value("DNS", bad.to_string()), json!(bad));
+    }
+
+    #[test]
+    fn test_records_from_json() {
+        let records = records_from_json(
+            r#"{"web": "https://smith.example/?a=b c", "DNS": [{"type": "A", "value": "192.0.2.1"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(records["WEB"], "https://smith.example/?a=b c");
+        assert_eq!(records["DNS"], r#"[{"type":"A","value":"192.0.2.1"}]"#);
+
+        assert!(records_from_json(r#"["WEB"]"#).is_err());
+        assert!(records_from_json(r#"{"PORT": 80}"#).is_err());
+        assert!(records_from_json(r#"{"DNS": [{"type": "A", "value": "nope"}]}"#).is_err());
+        assert!(records_from_json(r#"{"web": "a", "WEB": "b"}"#).is_err());
+    }
+
+    #[test]
+    fn test_plain_record() {
+        assert!(validate_record("WEB", "[not json").is_ok());
+        assert_eq!(
+            typed_value("WEB", "https://example.com".into()),
+            json!("https://example.com")
+        );
+    }
+
+    #[test]
+    fn test_record_limits() {
+        let limits = RecordLimits {
+            max_size: 64,
+            max_records: 2,
+            max_value: 8,
+        };
+        let records = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let ok = records(&[("IP4", "1.2.3.4")]);
+        assert!(limits
+            .check(&serde_json::to_string(&ok).unwrap(), Some(&ok))
+            .is_ok());
+
+        let long_value = records(&[("WEB", "https://example.com")]);
+        assert!(limits.check("{}", Some(&long_value)).is_err());
+        let too_many = records(&[("A", "1"), ("B", "2"), ("C", "3")]);
+        assert!(limits.check("{}", Some(&too_many)).is_err());
+        assert!(limits.check(&"x".repeat(65), None).is_err());
+    }
+}
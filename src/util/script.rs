@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+#[cfg(not(feature = "scripting"))]
+use anyhow::bail;
+
+use crate::error::NomenError;
+
+/// An operator supplied Rhai script that rewrites a name's records before they are served.
+///
+/// The script defines `fn transform(name, records)`, taking and returning a map of records.
+/// Scripts have no access to the filesystem or network, and are stopped after `timeout`.
+pub struct RecordScript {
+    #[cfg(feature = "scripting")]
+    ast: rhai::AST,
+    timeout: Duration,
+}
+
+#[cfg(feature = "scripting")]
+impl RecordScript {
+    pub fn load(path: &Path, timeout: Duration) -> anyhow::Result<RecordScript> {
+        let source = std::fs::read_to_string(path)?;
+        let ast = rhai::Engine::new()
+            .compile(source)
+            .map_err(|e| NomenError::Config(format!("Invalid script {}: {e}", path.display())))?;
+        Ok(RecordScript { ast, timeout })
+    }
+
+    pub fn transform(
+        &self,
+        name: &str,
+        records: HashMap<String, String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        let mut engine = rhai::Engine::new();
+        let (started, timeout) = (Instant::now(), self.timeout);
+        engine
+            .set_max_call_levels(32)
+            .set_max_string_size(64 * 1024)
+            .set_max_map_size(1024)
+            .set_max_array_size(1024)
+            .on_progress(move |_| (started.elapsed() > timeout).then(|| "Timed out".into()));
+
+        let records: rhai::Map = records
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        let result: rhai::Map = engine
+            .call_fn(
+                &mut rhai::Scope::new(),
+                &self.ast,
+                "transform",
+                (name.to_string(), records),
+            )
+            .map_err(|e| NomenError::Config(format!("Record script failed: {e}")))?;
+
+        Ok(result
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect())
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+impl RecordScript {
+    pub fn load(path: &Path, _timeout: Duration) -> anyhow::Result<RecordScript> {
+        bail!(NomenError::Config(format!(
+            "Record script {} configured, but scripting was not enabled at compile time",
+            path.display()
+        )))
+    }
+
+    pub fn transform(
+        &self,
+        _name: &str,
+        records: HashMap<String, String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        Ok(records)
+    }
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use super::*;
+
+    fn script(source: &str) -> RecordScript {
+        RecordScript {
+            ast: rhai::Engine::new().compile(source).unwrap(),
+            timeout: Duration::from_millis(100),
+        }
+    }
+
+    #[test]
+    fn test_transform() {
+        let script = script(
+            r#"
+            fn transform(name, records) {
+                if "ip" in records {
+                    records.IP4 = records.ip;
+                    records.remove("ip");
+                }
+                records.NAME = name;
+                records
+            }
+            "#,
+        );
+        let records = HashMap::from([("ip".to_string(), "127.0.0.1".to_string())]);
+        let records = script.transform("smith", records).unwrap();
+        assert_eq!(
+            records,
+            HashMap::from([
+                ("IP4".to_string(), "127.0.0.1".to_string()),
+                ("NAME".to_string(), "smith".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let script = script("fn transform(name, records) { loop {} }");
+        assert!(script.transform("smith", HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+/// Letter sequences that look like a single letter.
+const CONFUSABLE_SEQUENCES: [(&str, &str); 3] = [("rn", "m"), ("vv", "w"), ("cl", "d")];
+
+/// What a name looks like, with case, hyphens and confusable characters folded away. Names with the
+/// same skeleton are easily mistaken for each other: `g00gle`, `Google` and `goo-gle` all have the
+/// skeleton `google`.
+pub fn skeleton(name: &str) -> String {
+    let mut folded = name.to_lowercase().replace('-', "");
+    for (sequence, letter) in CONFUSABLE_SEQUENCES {
+        folded = folded.replace(sequence, letter);
+    }
+    folded
+        .chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | 'i' => 'l',
+            '5' => 's',
+            c => c,
+        })
+        .collect()
+}
+
+/// How alike two names are, from 0 to 1: the share of characters that don't need an edit to turn
+/// one into the other.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (diagonal + usize::from(ca != *cb))
+                .min(above + 1)
+                .min(row[j] + 1);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skeleton() {
+        assert_eq!(skeleton("g00gle"), "google");
+        assert_eq!(skeleton("Google"), "google");
+        assert_eq!(skeleton("goo-gle"), "google");
+        assert_eq!(skeleton("rnodern"), skeleton("modem"));
+        assert_eq!(skeleton("paypa1"), skeleton("paypal"));
+        assert_ne!(skeleton("smith"), skeleton("smyth"));
+
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(similarity("smith", "smith"), 1.0);
+        assert_eq!(similarity("g00gle", "google"), 1.0 - 2.0 / 6.0);
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::bail;
+use nostr_sdk::{Event, EventBuilder, Kind, Tag, UnsignedEvent};
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+use crate::error::NomenError;
+
+/// Nostr event kind of a signed index snapshot manifest.
+pub const SNAPSHOT_KIND: u16 = 38303;
+
+/// What an indexer operator attests about an exported index snapshot.
+///
+/// The manifest is the content of a Nostr event signed by the operator, with the network as its
+/// `d` tag. Publishing it lets other operators and users find, and vouch for, an operator's snapshots.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub version: u8,
+    pub network: String,
+    pub index_height: i64,
+    pub index_blockhash: String,
+    pub schema_version: i64,
+    /// Hex encoded SHA-256 of the snapshot file.
+    pub sha256: String,
+    pub size: u64,
+}
+
+impl SnapshotManifest {
+    pub fn kind() -> Kind {
+        Kind::ParameterizedReplaceable(SNAPSHOT_KIND)
+    }
+
+    /// The unsigned manifest event, published by the operator.
+    pub fn event(&self, operator: XOnlyPublicKey) -> anyhow::Result<UnsignedEvent> {
+        Ok(EventBuilder::new(
+            SnapshotManifest::kind(),
+            serde_json::to_string(self)?,
+            &[Tag::Identifier(self.network.clone())],
+        )
+        .to_unsigned_event(operator))
+    }
+
+    /// Parse a manifest event and check its signature. Whether the signer is trusted is up to the caller.
+    pub fn from_event(event: &Event) -> anyhow::Result<SnapshotManifest> {
+        if event.kind != SnapshotManifest::kind() {
+            bail!(NomenError::Validation(
+                "Not a snapshot manifest event".into()
+            ));
+        }
+        event
+            .verify()
+            .map_err(|_| NomenError::Validation("Invalid snapshot manifest signature".into()))?;
+        let manifest: SnapshotManifest = serde_json::from_str(&event.content)?;
+        let network = event.tags.iter().find_map(|t| match t {
+            Tag::Identifier(d) => Some(d),
+            _ => None,
+        });
+        if network != Some(&manifest.network) {
+            bail!(NomenError::Validation(
+                "Snapshot manifest 'd' tag does not match its network".into()
+            ));
+        }
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_sdk::Keys;
+
+    use super::*;
+
+    #[test]
+    fn test_manifest_event() {
+        let operator = Keys::generate();
+        let manifest = SnapshotManifest {
+            version: 0,
+            network: "regtest".into(),
+            index_height: 120,
+            index_blockhash: "00".repeat(32),
+            schema_version: 9,
+            sha256: "11".repeat(32),
+            size: 4096,
+        };
+        let event = manifest
+            .event(operator.public_key())
+            .unwrap()
+            .sign(&operator)
+            .unwrap();
+        assert_eq!(SnapshotManifest::from_event(&event).unwrap(), manifest);
+
+        let mut tampered = event.clone();
+        tampered.content = tampered.content.replace("4096", "4097");
+        assert!(SnapshotManifest::from_event(&tampered).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use nostr_sdk::{prelude::TagKind, Event, EventId, Kind, Tag, Timestamp, UnsignedEvent};
+use secp256k1::{KeyPair, Message, Secp256k1, SecretKey, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{Hash160, NameKind, NomenKind, Nsid, NsidBuilder};
+use crate::{error::NomenError, subcommands::EventData};
+
+/// Names used by the test vectors, one per vector.
+const NAMES: [&str; 4] = ["smith", "alice", "bob-01", "satoshi"];
+
+/// `created_at` of the first vector's events. Each vector adds one second.
+const BASE_TIME: u64 = 1_700_000_000;
+
+/// Deterministic examples of every value a Nomen implementation derives,
+/// for checking other implementations against this one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub version: u8,
+    pub vectors: Vec<TestVector>,
+}
+
+/// Vector `i` is owned by the key `sha256(seed)`, and transfers its name to the key of vector `i + 1`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVector {
+    pub seed: String,
+    pub secret_key: String,
+    pub pubkey: String,
+    pub name: String,
+    pub fingerprint: String,
+    pub nsid: String,
+    pub create_op_return: String,
+    pub transfer_pubkey: String,
+    pub transfer_nsid: String,
+    pub transfer_op_return: String,
+    pub records_event: Event,
+    pub transfer_event: Event,
+}
+
+impl TestVectors {
+    pub fn generate() -> anyhow::Result<TestVectors> {
+        let keys = (0..NAMES.len())
+            .map(|i| seed_key(&seed(i)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let vectors = NAMES
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let keypair = &keys[i];
+                let new_owner = keys[(i + 1) % keys.len()].x_only_public_key().0;
+                vector(i, name, keypair, &new_owner)
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(TestVectors {
+            version: 0,
+            vectors,
+        })
+    }
+
+    /// Regenerate the vectors and list every value that differs from `self`.
+    /// Events are also checked the way the indexer checks them.
+    pub fn verify(&self) -> anyhow::Result<Vec<String>> {
+        let expected = TestVectors::generate()?;
+        let mut errors = Vec::new();
+
+        if self.version != expected.version {
+            errors.push(format!("version: expected {}", expected.version));
+        }
+        if self.vectors.len() != expected.vectors.len() {
+            errors.push(format!(
+                "expected {} vectors, found {}",
+                expected.vectors.len(),
+                self.vectors.len()
+            ));
+        }
+
+        for (i, (found, expected)) in self.vectors.iter().zip(&expected.vectors).enumerate() {
+            let fields = [
+                ("seed", &found.seed, &expected.seed),
+                ("secret_key", &found.secret_key, &expected.secret_key),
+                ("pubkey", &found.pubkey, &expected.pubkey),
+                ("name", &found.name, &expected.name),
+                ("fingerprint", &found.fingerprint, &expected.fingerprint),
+                ("nsid", &found.nsid, &expected.nsid),
+                (
+                    "create_op_return",
+                    &found.create_op_return,
+                    &expected.create_op_return,
+                ),
+                (
+                    "transfer_pubkey",
+                    &found.transfer_pubkey,
+                    &expected.transfer_pubkey,
+                ),
+                (
+                    "transfer_nsid",
+                    &found.transfer_nsid,
+                    &expected.transfer_nsid,
+                ),
+                (
+                    "transfer_op_return",
+                    &found.transfer_op_return,
+                    &expected.transfer_op_return,
+                ),
+            ];
+            for (field, found, expected) in fields {
+                if found != expected {
+                    errors.push(format!("vector {i}: {field}: expected {expected}"));
+                }
+            }
+
+            for (field, found, expected) in [
+                (
+                    "records_event",
+                    &found.records_event,
+                    &expected.records_event,
+                ),
+                (
+                    "transfer_event",
+                    &found.transfer_event,
+                    &expected.transfer_event,
+                ),
+            ] {
+                if let Err(err) = check_event(found) {
+                    errors.push(format!("vector {i}: {field}: {err}"));
+                } else if found != expected {
+                    errors.push(format!(
+                        "vector {i}: {field}: expected {}",
+                        expected.as_json()
+                    ));
+                }
+            }
+        }
+
+        Ok(errors)
+    }
+}
+
+fn seed(i: usize) -> String {
+    format!("nomen test vector {i}")
+}
+
+fn seed_key(seed: &str) -> anyhow::Result<KeyPair> {
+    let sk = SecretKey::from_slice(&Sha256::digest(seed.as_bytes()))?;
+    Ok(KeyPair::from_secret_key(&Secp256k1::signing_only(), &sk))
+}
+
+fn vector(
+    i: usize,
+    name: &str,
+    keypair: &KeyPair,
+    new_owner: &XOnlyPublicKey,
+) -> anyhow::Result<TestVector> {
+    let pubkey = keypair.x_only_public_key().0;
+    let fingerprint = Hash160::default()
+        .chain_update(name.as_bytes())
+        .fingerprint();
+    let nsid = NsidBuilder::new(name, &pubkey).finalize();
+    let transfer_nsid = NsidBuilder::new(name, new_owner).finalize();
+    let created_at = BASE_TIME + i as u64;
+
+    let records = BTreeMap::from([
+        ("IP4".to_string(), format!("192.0.2.{}", i + 1)),
+        ("WEB".to_string(), format!("https://{name}.example")),
+    ]);
+    let records_event = sign(
+        keypair,
+        NameKind::Name,
+        nsid,
+        name,
+        serde_json::to_string(&records)?,
+        created_at,
+    )?;
+    let transfer_event = sign(
+        keypair,
+        NameKind::Transfer,
+        transfer_nsid,
+        name,
+        new_owner.to_string(),
+        created_at,
+    )?;
+
+    Ok(TestVector {
+        seed: seed(i),
+        secret_key: hex::encode(keypair.secret_bytes()),
+        pubkey: pubkey.to_string(),
+        name: name.to_string(),
+        fingerprint: hex::encode(fingerprint),
+        nsid: nsid.to_string(),
+        create_op_return: hex::encode(crate::subcommands::op_return(
+            fingerprint,
+            nsid,
+            NomenKind::Create,
+            None,
+        )),
+        transfer_pubkey: new_owner.to_string(),
+        transfer_nsid: transfer_nsid.to_string(),
+        transfer_op_return: hex::encode(crate::subcommands::op_return(
+            fingerprint,
+            transfer_nsid,
+            NomenKind::Transfer,
+            None,
+        )),
+        records_event,
+        transfer_event,
+    })
+}
+
+/// Sign an event with a fixed `created_at`, and without auxiliary randomness, so the
+/// signature is deterministic.
+fn sign(
+    keypair: &KeyPair,
+    kind: NameKind,
+    nsid: Nsid,
+    name: &str,
+    content: String,
+    created_at: u64,
+) -> anyhow::Result<Event> {
+    let pubkey = keypair.x_only_public_key().0;
+    let kind: Kind = kind.into();
+    let created_at = Timestamp::from(created_at);
+    let tags = vec![
+        Tag::Identifier(nsid.to_string()),
+        Tag::Generic(TagKind::Custom("nom".to_owned()), vec![name.to_owned()]),
+    ];
+    let id = EventId::new(&pubkey, created_at, &kind, &tags, &content);
+    let sig = Secp256k1::signing_only()
+        .sign_schnorr_no_aux_rand(&Message::from_slice(id.as_bytes())?, keypair);
+
+    Ok(UnsignedEvent {
+        id,
+        pubkey,
+        created_at,
+        kind,
+        tags,
+        content,
+    }
+    .add_signature(sig)?)
+}
+
+fn check_event(event: &Event) -> anyhow::Result<()> {
+    NameKind::try_from(event.kind)?;
+    event.verify()?;
+    let ed = EventData::from_event(event)?;
+    ed.validate()?;
+    if ed.records.is_none() && NameKind::try_from(event.kind)? == NameKind::Name {
+        bail!(NomenError::Validation("Invalid records".into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors_file() {
+        let vectors: TestVectors =
+            serde_json::from_str(include_str!("../../docs/test-vectors.json")).unwrap();
+        assert_eq!(vectors, TestVectors::generate().unwrap());
+        assert!(vectors.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_vectors_mismatch() {
+        let mut vectors = TestVectors::generate().unwrap();
+        vectors.vectors[1].nsid = vectors.vectors[0].nsid.clone();
+        vectors.vectors[2].records_event.content = "{}".into();
+        let errors = vectors.verify().unwrap();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("vector 1: nsid"));
+        assert!(errors[1].starts_with("vector 2: records_event"));
+    }
+}